@@ -8,6 +8,14 @@ pub fn handle(params: &Parameters, options: DisplayOptions, _inner: Vec<String>)
     handle_model(&**tract, &params, options)
 }
 
+/// Saves the decluttered model in tract native format.
+pub fn save(params: &Parameters, path: &str) -> CliResult<()> {
+    let model = params.typed_model.as_ref().ok_or("No decluttered model to save")?;
+    tract_core::native::save_to_path(model, path)?;
+    info!("Model saved to {:?}", path);
+    Ok(())
+}
+
 pub fn handle_model(
     model: &dyn Model,
     params: &Parameters,
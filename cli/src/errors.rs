@@ -16,6 +16,7 @@ error_chain! {
         Fmt(::std::fmt::Error);
         Io(::std::io::Error);
         NumParseInt(::std::num::ParseIntError);
+        NumParseFloat(::std::num::ParseFloatError);
         NdarrayShape(ndarray::ShapeError);
         NdarrayNpyReadNpz(ndarray_npy::ReadNpzError);
     }
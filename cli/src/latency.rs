@@ -0,0 +1,53 @@
+use crate::display_graph::*;
+use crate::errors::*;
+use crate::{Model, Parameters};
+use tract_hir::internal::*;
+
+pub fn handle(
+    params: &Parameters,
+    options: DisplayOptions,
+    matches: &clap::ArgMatches,
+    m: &clap::ArgMatches,
+) -> CliResult<()> {
+    let pulsed =
+        params.pulsed_model.as_ref().ok_or("Latency report needs a pulsed network, use --pulse")?;
+    let pulse: usize = matches.value_of("pulse").unwrap().parse()?;
+    let frame_rate: Option<f32> = m.value_of("frame-rate").map(|s| s.parse()).transpose()?;
+
+    let mut display_graph =
+        DisplayGraph::from_model_and_options(pulsed as &dyn Model, options.into())?
+            .with_graph_def(&params.graph)?;
+    let mut buffer = 0;
+    for node in pulsed.latencies(pulse)? {
+        let mut rows = vec![];
+        for (ix, (d, s)) in node.delay.iter().zip(node.stream_delay.iter()).enumerate() {
+            rows.push(format!("output #{} delay: {} frames ({} in S)", ix, d, s));
+        }
+        if node.overlap > 0 {
+            rows.push(format!("overlap: {} frames", node.overlap));
+        }
+        if node.buffer > 0 {
+            rows.push(format!("buffer: {} bytes", node.buffer));
+        }
+        display_graph.add_node_section(&[node.node], rows)?;
+        buffer += node.buffer;
+    }
+    display_graph.render()?;
+
+    println!("Pulse: {}", pulse);
+    for (outlet, latency) in pulsed.output_outlets()?.iter().zip(pulsed.output_latencies(pulse)?) {
+        let name = &pulsed.node(outlet.node).name;
+        if let Some(rate) = frame_rate {
+            println!(
+                "Latency of {}: {} frames, {:.1} ms",
+                name,
+                latency,
+                latency as f32 * 1000.0 / rate
+            );
+        } else {
+            println!("Latency of {}: {} frames", name, latency);
+        }
+    }
+    println!("State buffers: {} bytes", buffer);
+    Ok(())
+}
@@ -41,8 +41,11 @@ mod draw;
 mod dump;
 mod errors;
 mod format;
+mod latency;
 mod optimize_check;
 mod profile;
+mod pulse_search;
+mod quantize;
 mod run;
 mod rusage;
 mod stream_check;
@@ -81,7 +84,7 @@ fn main() {
     (@arg model: +takes_value "Sets the model to use")
 
     (@arg format: -f +takes_value
-     "Hint the model format ('kaldi', 'onnx' or 'tf') instead of guess from extension.")
+     "Hint the model format ('kaldi', 'onnx', 'tf' or 'native') instead of guess from extension.")
 
     (@arg input: -i --input +takes_value +multiple number_of_values(1)
      "Set input shape and type (@file.pb or @file.npz:thing.npy or 3x4xi32).")
@@ -95,6 +98,9 @@ fn main() {
     (@arg stream_axis: -s --("stream-axis") +takes_value
      "Set Axis number to stream upon (first is 0)")
 
+    (@arg onnx_stream_dim: --("onnx-stream-dim") +takes_value
+     "Name of the ONNX named dimension (dim_param) to stream upon")
+
     (@arg kaldi_adjust_final_offset: --("kaldi-adjust-final-offset") +takes_value
      "Adjust value of final offset in network (for reproducibility)")
 
@@ -107,6 +113,9 @@ fn main() {
     (@arg kaldi_right_context: --("kaldi-right-context") +takes_value
      "Add lines of right context to input (dupping last time frame)")
 
+    (@arg signature: --signature +takes_value
+     "Signature to use in a TensorFlow SavedModel directory (serving_default otherwise).")
+
     (@arg input_node: --("input-node") +takes_value +multiple number_of_values(1)
      "Override input nodes names (auto-detects otherwise).")
 
@@ -137,6 +146,8 @@ fn main() {
 
     (@arg machine_friendly: --("machine-friendly") "Machine friendly output")
 
+    (@arg threads: --threads +takes_value "Run matrix multiplications on this many threads")
+
     (@arg list_ops: --("list-ops") "List all known operators")
     );
 
@@ -201,6 +212,12 @@ fn main() {
                 .multiple(true)
                 .long("inner")
                 .help("Navigate to a sub-model"),
+        )
+        .arg(
+            Arg::with_name("save")
+                .takes_value(true)
+                .long("save")
+                .help("Save the decluttered model in tract native format"),
         );
     app = app.subcommand(output_options(dump));
 
@@ -226,6 +243,23 @@ fn main() {
             );
     app = app.subcommand(output_options(profile));
 
+    let quantize = clap::SubCommand::with_name("quantize")
+        .long_about("Quantize the model to int8, calibrating it on npz samples")
+        .arg(
+            Arg::with_name("calibration")
+                .takes_value(true)
+                .required(true)
+                .multiple(true)
+                .help("Calibration samples (.npz, one array per input)"),
+        )
+        .arg(
+            Arg::with_name("save")
+                .takes_value(true)
+                .long("save")
+                .help("Save the quantized model in tract native format"),
+        );
+    app = app.subcommand(output_options(quantize));
+
     let run = clap::SubCommand::with_name("run")
         .long_about("Run the graph")
         .arg(Arg::with_name("dump").long("dump").help("Show output"))
@@ -270,6 +304,51 @@ fn main() {
         .long_about("Compare output of streamed and regular exec");
     app = app.subcommand(output_options(stream_check));
 
+    let latency = clap::SubCommand::with_name("latency")
+        .long_about("Report delays, overlaps and buffers of a pulsed network (needs --pulse).")
+        .arg(
+            Arg::with_name("frame-rate")
+                .takes_value(true)
+                .long("frame-rate")
+                .help("Frames of the streaming axis per second, to express latency in ms"),
+        );
+    app = app.subcommand(output_options(latency));
+
+    let pulse_search = clap::SubCommand::with_name("pulse-search")
+        .long_about("Benchmarks the network pulsified with a range of pulse sizes.")
+        .arg(
+            Arg::with_name("pulses")
+                .takes_value(true)
+                .long("pulses")
+                .help("Comma-separated pulse sizes to try [default: smallest pulse allowed by the strides times powers of two]"),
+        )
+        .arg(
+            Arg::with_name("max-pulse")
+                .takes_value(true)
+                .long("max-pulse")
+                .help("Largest pulse size to try [default: 256]"),
+        )
+        .arg(
+            Arg::with_name("frame-rate")
+                .takes_value(true)
+                .long("frame-rate")
+                .help("Frames of the streaming axis per second, to compute latency in ms and real-time factor"),
+        )
+        .arg(
+            Arg::with_name("max_iters")
+                .takes_value(true)
+                .long("max-iters")
+                .short("n")
+                .help("Sets the maximum number of iterations for each pulse size [default: 10_000]."),
+        )
+        .arg(
+            Arg::with_name("max-time")
+                .takes_value(true)
+                .long("max-time")
+                .help("Sets the maximum execution time for each pulse size (in ms) [default: 1000]."),
+        );
+    app = app.subcommand(output_options(pulse_search));
+
     let matches = app.get_matches();
 
     let probe = if matches.is_present("readings") {
@@ -368,6 +447,7 @@ pub struct Parameters {
     graph: SomeGraphDef,
     typed_model: Option<TypedModel>,
     normalized_model: Option<NormalizedModel>,
+    pulsed_model: Option<PulsedModel>,
     tract_model: Box<dyn Model>,
 
     output_names: Vec<String>,
@@ -395,9 +475,14 @@ impl Parameters {
         let name = matches.value_of("model").ok_or("Model argument required")?;
         let format = matches.value_of("format").unwrap_or(if name.ends_with(".onnx") {
             "onnx"
+        } else if name.ends_with(".tract") {
+            "native"
         } else {
             "tf"
         });
+        if format == "native" {
+            return Self::from_native(matches, name, probe);
+        }
         let (mut graph, mut raw_model) = match format {
             #[cfg(feature = "kaldi")]
             "kaldi" => {
@@ -412,10 +497,14 @@ impl Parameters {
             }
             #[cfg(feature = "onnx")]
             "onnx" => {
-                let onnx = tract_onnx::onnx();
+                let mut onnx = tract_onnx::onnx();
+                if let Some(dim) = matches.value_of("onnx_stream_dim") {
+                    onnx = onnx.with_streaming_dim_param(dim);
+                }
                 info_usage("load framework (onnx)", probe);
                 let graph = onnx.proto_model_for_path(&name)?;
-                let parsed = onnx.parse(&graph)?;
+                let parsed =
+                    onnx.parse_with_model_dir(&graph, std::path::Path::new(name).parent())?;
                 let tract = parsed.model.clone();
                 (SomeGraphDef::Onnx(graph, parsed), tract)
             }
@@ -423,11 +512,21 @@ impl Parameters {
             "tf" => {
                 let tf = tract_tensorflow::tensorflow();
                 info_usage("load framework (tf)", probe);
-                let mut graph = tf.proto_model_for_path(&name)?;
+                let (mut graph, signature) = if std::path::Path::new(name).is_dir() {
+                    let (graph, signature) =
+                        tf.read_saved_model_dir(name, matches.value_of("signature"))?;
+                    (graph, Some(signature))
+                } else {
+                    (tf.proto_model_for_path(&name)?, None)
+                };
                 if matches.is_present("determinize") {
                     tract_tensorflow::Tensorflow::determinize(&mut graph)?;
                 }
-                let tract = tf.model_for_proto_model(&graph)?;
+                let tract = if let Some(signature) = signature {
+                    tf.model_for_signature(&graph, &signature)?
+                } else {
+                    tf.model_for_proto_model(&graph)?
+                };
                 (SomeGraphDef::Tf(graph), tract)
             }
             _ => bail!(
@@ -611,6 +710,7 @@ impl Parameters {
         let pulse: Option<usize> = matches.value_of("pulse").map(|s| s.parse()).transpose()?;
         let mut typed_model = None;
         let normalized_model: Option<NormalizedModel> = None;
+        let mut pulsed_model = None;
 
         let mut analyse_error = None;
 
@@ -677,6 +777,8 @@ impl Parameters {
                     info_usage("after pulse-normalize", probe);
                     info!("Running 'pulse' ({})", pulse);
                     let pulsed = ::tract_core::pulse::PulsedModel::new(&normalized_model, pulse)?;
+                    info!("Pulsed output latencies: {:?}", pulsed.output_latencies(pulse)?);
+                    pulsed_model = Some(pulsed.clone());
                     if stop_at == "pulse" {
                         return Ok(Box::new(pulsed) as _);
                     }
@@ -708,6 +810,7 @@ impl Parameters {
             graph,
             typed_model,
             normalized_model,
+            pulsed_model,
             tract_model,
             tf_model,
             input_values,
@@ -716,6 +819,57 @@ impl Parameters {
             machine_friendly,
         })
     }
+
+    /// Loads a model saved in tract native format. The model is already
+    /// decluttered, so only the optimisation step can be applied.
+    fn from_native(
+        matches: &clap::ArgMatches,
+        name: &str,
+        probe: Option<&Probe>,
+    ) -> CliResult<Parameters> {
+        #[allow(unused_mut)]
+        let mut registry = tract_core::native::Registry::default();
+        #[cfg(feature = "onnx")]
+        tract_onnx::ops::register_native_ops(&mut registry);
+        let model = tract_core::native::load_from_path_with_registry(name, &registry)?;
+        info!("Model {:?} loaded", name);
+        info_usage("model loaded", probe);
+
+        let output_names = model
+            .output_outlets()?
+            .iter()
+            .map(|o| model.node(o.node).name.to_string())
+            .collect();
+
+        let tract_model: Box<dyn Model> = if matches.is_present("optimize") {
+            info!("Running 'optimize'");
+            Box::new(model.clone().codegen()?)
+        } else {
+            Box::new(model.clone())
+        };
+
+        info!("Model ready");
+        info_usage("model ready", probe);
+
+        #[cfg(feature = "conform")]
+        let tf_model = None;
+        #[cfg(not(feature = "conform"))]
+        let tf_model = ();
+
+        Ok(Parameters {
+            analyse_error: None,
+            graph: SomeGraphDef::NoGraphDef,
+            typed_model: Some(model),
+            normalized_model: None,
+            pulsed_model: None,
+            tract_model,
+            tf_model,
+            input_values: vec![],
+            output_names,
+            assertions: None,
+            machine_friendly: matches.is_present("machine_friendly"),
+        })
+    }
 }
 
 pub enum ProfilingMode {
@@ -812,7 +966,7 @@ fn handle(matches: clap::ArgMatches, probe: Option<&Probe>) -> CliResult<()> {
         #[cfg(feature = "onnx")]
         {
             let onnx = tract_onnx::onnx();
-            let names = onnx.op_register.0.keys().sorted().into_iter().join(", ");
+            let names = onnx.op_register.names().join(", ");
             println!("Onnx:\n");
             println!("{}", names);
             println!("\n");
@@ -828,6 +982,16 @@ fn handle(matches: clap::ArgMatches, probe: Option<&Probe>) -> CliResult<()> {
         return Ok(());
     }
 
+    if let Some(threads) = matches.value_of("threads") {
+        #[cfg(feature = "multithread-mmm")]
+        {
+            use tract_core::tract_linalg::multithread::*;
+            set_default_executor(Executor::multithread(threads.parse()?));
+        }
+        #[cfg(not(feature = "multithread-mmm"))]
+        bail!("Need multithread-mmm feature to run on {} threads", threads);
+    }
+
     let mut params = Parameters::from_clap(&matches, probe)?;
 
     match matches.subcommand() {
@@ -856,6 +1020,13 @@ fn handle(matches: clap::ArgMatches, probe: Option<&Probe>) -> CliResult<()> {
             display_options_from_clap(&matches, m)?,
         ),
 
+        ("quantize", Some(m)) => quantize::handle(
+            &params,
+            m.values_of("calibration").unwrap().collect(),
+            m.value_of("save"),
+            display_options_from_clap(&matches, m)?,
+        ),
+
         ("run", Some(m)) => {
             params.assertions = Some(Assertions::from_clap(m, &*params.output_names)?);
             run::handle(&params, m.is_present("dump"))
@@ -869,6 +1040,12 @@ fn handle(matches: clap::ArgMatches, probe: Option<&Probe>) -> CliResult<()> {
             stream_check::handle(&params, display_options_from_clap(&matches, m)?)
         }
 
+        ("latency", Some(m)) => {
+            latency::handle(&params, display_options_from_clap(&matches, m)?, &matches, m)
+        }
+
+        ("pulse-search", Some(m)) => pulse_search::handle(&params, m),
+
         ("cost", Some(m)) => {
             crate::cost::handle(&params, display_options_from_clap(&matches, m)?, m)
         }
@@ -883,7 +1060,11 @@ fn handle(matches: clap::ArgMatches, probe: Option<&Probe>) -> CliResult<()> {
                 .values_of("inner")
                 .map(|ss| ss.map(|s| s.to_string()).collect())
                 .unwrap_or(vec![]);
-            dump::handle(&params, display_options_from_clap(&matches, m)?, inner)
+            dump::handle(&params, display_options_from_clap(&matches, m)?, inner)?;
+            if let Some(path) = m.value_of("save") {
+                dump::save(&params, path)?;
+            }
+            Ok(())
         }
 
         ("profile", Some(m)) => {
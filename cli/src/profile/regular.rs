@@ -220,6 +220,13 @@ where
 
     println!("Entire network performance: {}", dur_avg_oneline(entire));
     println!("Accounted by ops: {}", dur_avg_oneline_ratio(profile.summed(), entire));
+    let memory = &SimplePlan::new(model)?.memory_plan;
+    println!(
+        "Peak memory of intermediate values: {} bytes, arena: {} bytes in {} buffers",
+        memory.peak_memory,
+        memory.arena_size(),
+        memory.slots.len()
+    );
 
     if log_enabled!(Info) {
         println!(
@@ -0,0 +1,108 @@
+use crate::errors::*;
+use crate::rusage::{Duration, Instant};
+use crate::tensor::make_inputs;
+use crate::Parameters;
+use tract_hir::internal::*;
+
+/// Benchmark figures of the network pulsified for one pulse size.
+struct Candidate {
+    pulse: usize,
+    delay: usize,
+    per_pulse: std::time::Duration,
+}
+
+pub fn handle(params: &Parameters, m: &clap::ArgMatches) -> CliResult<()> {
+    let source = params
+        .typed_model
+        .as_ref()
+        .ok_or("Pulse search needs a decluttered network")?
+        .clone()
+        .into_normalized()?;
+    let max_pulse: usize = m.value_of("max-pulse").map(|s| s.parse()).transpose()?.unwrap_or(256);
+    if max_pulse == 0 {
+        bail!("--max-pulse must be at least 1");
+    }
+    let frame_rate: Option<f32> = m.value_of("frame-rate").map(|s| s.parse()).transpose()?;
+    let max_iters: u64 = m.value_of("max_iters").map(|s| s.parse()).transpose()?.unwrap_or(10_000);
+    if max_iters == 0 {
+        bail!("--max-iters must be at least 1");
+    }
+    let max_time = m
+        .value_of("max-time")
+        .map(|s| s.parse())
+        .transpose()?
+        .map(std::time::Duration::from_millis)
+        .unwrap_or(std::time::Duration::from_secs(1));
+
+    let pulses: Vec<usize> = if let Some(pulses) = m.value_of("pulses") {
+        let pulses: Vec<usize> = pulses.split(",").map(|s| s.parse()).collect::<Result<_, _>>()?;
+        if pulses.contains(&0) {
+            bail!("--pulses must all be at least 1");
+        }
+        pulses
+    } else {
+        let smallest = tract_core::pulse::stride_multiple(&source);
+        if smallest > max_pulse {
+            bail!(
+                "The network strides require a pulse multiple of {}, above --max-pulse {}",
+                smallest,
+                max_pulse
+            );
+        }
+        std::iter::successors(Some(smallest), |p| Some(p * 2))
+            .take_while(|&p| p <= max_pulse)
+            .collect()
+    };
+
+    let mut candidates = vec![];
+    for pulse in pulses {
+        let pulsed = match PulsedModel::new(&source, pulse) {
+            Ok(pulsed) => pulsed,
+            Err(e) => {
+                println!("pulse {}: incompatible ({})", pulse, e);
+                continue;
+            }
+        };
+        let delay = pulsed.output_latencies(pulse)?.into_iter().max().unwrap_or(0);
+        info!("Benching pulse {}", pulse);
+        let model = pulsed.into_typed()?.declutter()?.codegen()?;
+        let inputs = make_inputs(
+            &*model
+                .input_outlets()?
+                .iter()
+                .map(|&o| model.outlet_fact(o).map(|f| f.clone()))
+                .collect::<TractResult<Vec<_>>>()?,
+        )?;
+        let plan = SimplePlan::new(&model)?;
+        let mut state = SimpleState::new(plan)?;
+        let mut iters = 0;
+        let start = Instant::now();
+        while iters < max_iters && start.elapsed_real() < max_time {
+            state.run(inputs.clone())?;
+            iters += 1;
+        }
+        let mut dur = Duration::since(&start);
+        dur /= iters as f64;
+        candidates.push(Candidate { pulse, delay, per_pulse: dur.avg_real() });
+    }
+
+    for c in &candidates {
+        let throughput = c.pulse as f64 / c.per_pulse.as_secs_f64();
+        let mut line = format!(
+            "pulse {:5}: delay {:5} frames, {:8.3} ms/pulse, {:10.0} frames/s",
+            c.pulse,
+            c.delay,
+            c.per_pulse.as_secs_f64() * 1000.0,
+            throughput
+        );
+        if let Some(rate) = frame_rate {
+            // a frame waits for the rest of its pulse, the network delay,
+            // then the pulse computation
+            let latency = (c.pulse + c.delay) as f64 * 1000.0 / rate as f64
+                + c.per_pulse.as_secs_f64() * 1000.0;
+            line += &format!(", latency {:8.1} ms, RTF {:.3}", latency, rate as f64 / throughput);
+        }
+        println!("{}", line);
+    }
+    Ok(())
+}
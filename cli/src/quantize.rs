@@ -0,0 +1,38 @@
+use crate::display_graph::DisplayOptions;
+use crate::errors::*;
+use crate::Parameters;
+use tract_core::quantize::Calibrator;
+use tract_hir::internal::{tvec, TVec, Tensor};
+
+/// Calibrates the decluttered model on the samples and quantizes it.
+///
+/// Each calibration file is an npz holding one sample, with one array per
+/// model input, named after the input node.
+pub fn handle(
+    params: &Parameters,
+    calibration: Vec<&str>,
+    save: Option<&str>,
+    options: DisplayOptions,
+) -> CliResult<()> {
+    let model = params.typed_model.as_ref().ok_or("Quantization needs a decluttered model")?;
+    let mut calibrator = Calibrator::new(model)?;
+    for path in calibration {
+        let mut npz = ndarray_npy::NpzReader::new(std::fs::File::open(path)?)?;
+        let mut inputs: TVec<Tensor> = tvec!();
+        for input in model.input_outlets()? {
+            let name = format!("{}.npy", model.node(input.node).name);
+            let t = crate::tensor::for_npz(&mut npz, &name)
+                .chain_err(|| format!("Reading {} from {}", name, path))?;
+            inputs.push(t);
+        }
+        calibrator.observe(inputs)?;
+        info!("Calibrated on {:?}", path);
+    }
+    let quantized = calibrator.quantize()?;
+    crate::dump::handle_model(&quantized, params, options)?;
+    if let Some(path) = save {
+        tract_core::native::save_to_path(&quantized, path)?;
+        info!("Quantized model saved to {:?}", path);
+    }
+    Ok(())
+}
@@ -45,54 +45,16 @@ fn run_regular(tract: &dyn Model, params: &Parameters) -> CliResult<TVec<Arc<Ten
 }
 
 fn run_pulse_t(model: &PulsedModel, params: &Parameters) -> CliResult<TVec<Arc<Tensor>>> {
-    let input_fact = model.input_fact(0)?;
-    let output_fact = model.output_fact(0)?;
-
-    let output_pulse = output_fact.pulse();
-    //    println!("output_fact: {:?}", output_fact);
-    let axis = input_fact.axis;
     let input: &Tensor = &params.input_values[0].as_ref().unwrap();
-    //    println!("input_shape: {:?}", input.shape());
-    let input_dim = input.shape()[axis];
-    //    println!("output_fact: {:?}", output_fact);
-    let output_dim = output_fact.dim.eval(input_dim as i32).unwrap() as i32;
-    let mut output_shape = output_fact.shape.to_vec();
-    output_shape[output_fact.axis] =
-        output_dim as usize + output_fact.delay + 4 * output_fact.pulse();
-    let plan = SimplePlan::new(model)?;
-    let mut state = ::tract_core::plan::SimpleState::new(&plan)?;
-    //    println!("output_shape: {:?}", output_shape);
-    let pulse = input_fact.pulse();
-    let mut result = tract_ndarray::ArrayD::<f32>::default(output_shape);
-    let input = input.to_array_view::<f32>()?;
+    let mut session = ::tract_core::pulse::StreamingSession::new(model.clone())?;
+    let axis = session.output_fact().axis;
+    let pulse = session.input_fact().pulse();
+    let input_dim = input.shape()[session.input_fact().axis];
+    let mut outputs = vec![];
     for ix in 0..input_dim.div_ceil(pulse) {
-        let chunk =
-            input.slice_axis(tract_ndarray::Axis(axis), (ix * pulse..(ix + 1) * pulse).into());
-        let input = if chunk.shape()[input_fact.axis] < pulse {
-            let mut chunk_shape = chunk.shape().to_vec();
-            chunk_shape[input_fact.axis] = pulse;
-            let mut padded_chunk = tract_ndarray::ArrayD::<f32>::default(chunk_shape);
-            padded_chunk
-                .slice_axis_mut(
-                    tract_ndarray::Axis(input_fact.axis),
-                    (..chunk.shape()[input_fact.axis]).into(),
-                )
-                .assign(&chunk);
-            padded_chunk
-        } else {
-            chunk.to_owned()
-        };
-        let outputs = state.run(tvec!(input.into()))?;
-        let result_chunk = outputs[0].to_array_view::<f32>()?;
-        result
-            .slice_axis_mut(
-                tract_ndarray::Axis(output_fact.axis),
-                ((output_pulse * ix)..(output_pulse * (ix + 1))).into(),
-            )
-            .assign(&result_chunk);
+        let end = ((ix + 1) * pulse).min(input_dim);
+        outputs.push(session.push(input.slice(session.input_fact().axis, ix * pulse, end)?)?);
     }
-    result.slice_axis_inplace(tract_ndarray::Axis(output_fact.axis), (output_fact.delay..).into());
-    result
-        .slice_axis_inplace(tract_ndarray::Axis(output_fact.axis), (..output_dim as usize).into());
-    Ok(tvec!(result.into_arc_tensor()))
+    outputs.push(session.finish()?);
+    Ok(tvec!(Tensor::stack_tensors(axis, &outputs)?.into_arc_tensor()))
 }
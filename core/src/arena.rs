@@ -0,0 +1,231 @@
+//! Reuse of intermediate tensor memory across runs.
+//!
+//! The `MemoryPlan` is computed once per plan: it follows the lifetime of
+//! each intermediate value along the evaluation order and assigns the values
+//! to slots so that two values alive at the same time never share a slot.
+//! The `Arena` keeps one buffer per slot in the state, hands them out to the
+//! operators opting in with `StatelessOp::eval_with_buffers`, and takes them
+//! back when the values are flushed.
+
+use std::fmt::{Debug, Display};
+
+use crate::internal::*;
+use crate::model::{Fact, ModelImpl, OutletId};
+
+/// Alignment of the arena buffers, enough for the tract-linalg kernels.
+const ALIGNMENT: usize = 32;
+
+/// Where and how a value lives in the arena.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Allocation {
+    pub slot: usize,
+    pub datum_type: DatumType,
+    pub shape: TVec<usize>,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct MemoryPlan {
+    /// Size in bytes of each slot.
+    pub slots: Vec<usize>,
+    /// Allocation of each value stored in the arena.
+    pub allocations: HashMap<OutletId, Allocation>,
+    /// Maximum number of bytes held by values alive at the same time.
+    pub peak_memory: usize,
+}
+
+impl MemoryPlan {
+    pub fn new<F, O>(
+        model: &ModelImpl<F, O>,
+        order: &[usize],
+        outputs: &[OutletId],
+    ) -> TractResult<MemoryPlan>
+    where
+        F: Fact + Clone + 'static + Hash,
+        O: Debug + Display + AsRef<dyn Op> + AsMut<dyn Op> + Clone + 'static + DynHash,
+    {
+        let mut step_of = vec![None; model.nodes().len()];
+        for (step, &n) in order.iter().enumerate() {
+            step_of[n] = Some(step);
+        }
+        // values: (outlet, datum type, shape, bytes, first step, last step)
+        let mut values = vec![];
+        for (step, &n) in order.iter().enumerate() {
+            let node = model.node(n);
+            for ix in 0..node.outputs.len() {
+                let outlet = OutletId::new(n, ix);
+                let fact = if let Ok(fact) = model.outlet_fact(outlet)?.to_typed_fact() {
+                    fact
+                } else {
+                    continue;
+                };
+                let shape: TVec<usize> = if let Some(shape) = fact.shape.as_finite() {
+                    shape.into()
+                } else {
+                    continue;
+                };
+                let bytes = fact.datum_type.size_of() * shape.iter().product::<usize>();
+                let last = if outputs.contains(&outlet) {
+                    order.len()
+                } else {
+                    node.outputs[ix]
+                        .successors
+                        .iter()
+                        .filter_map(|s| step_of[s.node])
+                        .max()
+                        .unwrap_or(step)
+                };
+                values.push((outlet, fact.datum_type, shape, bytes, step, last));
+            }
+        }
+
+        let mut live = vec![0isize; order.len() + 2];
+        for v in &values {
+            live[v.4] += v.3 as isize;
+            live[v.5 + 1] -= v.3 as isize;
+        }
+        let peak_memory = live
+            .iter()
+            .scan(0isize, |acc, d| {
+                *acc += d;
+                Some(*acc)
+            })
+            .max()
+            .unwrap_or(0) as usize;
+
+        let mut slots: Vec<usize> = vec![];
+        let mut busy_until: Vec<usize> = vec![];
+        let mut allocations = HashMap::new();
+        for (outlet, datum_type, shape, bytes, first, last) in values {
+            let node = model.node(outlet.node);
+            if node.inputs.len() == 0
+                || outputs.contains(&outlet)
+                || [DatumType::String, DatumType::TDim, DatumType::Blob].contains(&datum_type)
+            {
+                continue;
+            }
+            let free = (0..slots.len()).filter(|&s| busy_until[s] < first);
+            let best_fit = free.clone().filter(|&s| slots[s] >= bytes).min_by_key(|&s| slots[s]);
+            let slot = if let Some(slot) = best_fit.or_else(|| free.max_by_key(|&s| slots[s])) {
+                slot
+            } else {
+                slots.push(0);
+                busy_until.push(0);
+                slots.len() - 1
+            };
+            slots[slot] = slots[slot].max(bytes);
+            busy_until[slot] = last;
+            allocations.insert(outlet, Allocation { slot, datum_type, shape });
+        }
+        Ok(MemoryPlan { slots, allocations, peak_memory })
+    }
+
+    /// Total size of the arena buffers, in bytes.
+    pub fn arena_size(&self) -> usize {
+        self.slots.iter().sum()
+    }
+}
+
+/// The buffers of a memory plan, kept from one run to the next.
+#[derive(Debug, Default)]
+pub struct Arena {
+    buffers: Vec<Option<Tensor>>,
+}
+
+impl Arena {
+    /// Preallocate the buffers of all slots.
+    pub fn new(plan: &MemoryPlan) -> TractResult<Arena> {
+        let buffers = plan
+            .slots
+            .iter()
+            .map(|&bytes| Ok(Some(Self::allocate(bytes)?)))
+            .collect::<TractResult<_>>()?;
+        Ok(Arena { buffers })
+    }
+
+    fn allocate(bytes: usize) -> TractResult<Tensor> {
+        unsafe { Tensor::uninitialized_aligned_dt(DatumType::U8, &[bytes], ALIGNMENT) }
+    }
+
+    /// Take the buffer of a slot, shaped for an allocation.
+    pub fn buffer(&mut self, plan: &MemoryPlan, allocation: &Allocation) -> TractResult<Tensor> {
+        if self.buffers.len() <= allocation.slot {
+            self.buffers.resize_with(allocation.slot + 1, || None);
+        }
+        let reused = self.buffers[allocation.slot]
+            .take()
+            .and_then(|t| unsafe { t.reuse_as(allocation.datum_type, &allocation.shape) });
+        if let Some(t) = reused {
+            return Ok(t);
+        }
+        let t = Self::allocate(plan.slots[allocation.slot])?;
+        Ok(unsafe { t.reuse_as(allocation.datum_type, &allocation.shape) }
+            .ok_or("Arena slot too small for its allocation")?)
+    }
+
+    /// Give back a buffer to a slot.
+    pub fn give_back(&mut self, allocation: &Allocation, tensor: Tensor) {
+        if self.buffers.len() <= allocation.slot {
+            self.buffers.resize_with(allocation.slot + 1, || None);
+        }
+        self.buffers[allocation.slot] = Some(tensor);
+    }
+
+    /// Recover the memory of a value when nothing else refers to it.
+    pub fn recycle(&mut self, allocation: &Allocation, value: Arc<Tensor>) {
+        if let Ok(tensor) = Arc::try_unwrap(value) {
+            self.give_back(allocation, tensor)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ops::math;
+
+    #[test]
+    fn chain_reuses_slots() -> TractResult<()> {
+        let mut model = TypedModel::default();
+        let mut wire =
+            model.add_source("x", TypedFact::dt_shape(f32::datum_type(), [16usize].as_ref())?)?;
+        for i in 0..6 {
+            wire = model.wire_node(format!("exp-{}", i), math::exp(), &[wire])?[0];
+        }
+        model.set_output_outlets(&[wire])?;
+        let plan = SimplePlan::new(&model)?;
+        let memory = &plan.memory_plan;
+        // 5 intermediate values, alternating between two slots
+        assert_eq!(memory.allocations.len(), 5);
+        assert_eq!(memory.slots, vec!(64, 64));
+        assert_eq!(memory.peak_memory, 128);
+        Ok(())
+    }
+
+    #[test]
+    fn optimized_matmuls_reuse_arena_buffers() -> TractResult<()> {
+        let mut model = TypedModel::default();
+        let mut wire =
+            model.add_source("x", TypedFact::dt_shape(f32::datum_type(), [4usize, 4].as_ref())?)?;
+        for i in 0..4 {
+            let a = rctensor2(&[
+                [1f32, 0., 0., 1.],
+                [0., 2., 0., 0.],
+                [0., 0., 1., 0.],
+                [1., 0., 0., 1.],
+            ]);
+            let op = crate::ops::matmul::MatMulUnary::new(a, false, false, false, None);
+            wire = model.wire_node(format!("mm-{}", i), op, &[wire])?[0];
+        }
+        model.set_output_outlets(&[wire])?;
+        let optimized = model.clone().into_optimized()?;
+        let reference = SimplePlan::new(&model)?;
+        let plan = SimplePlan::new(&optimized)?;
+        assert!(plan.memory_plan.allocations.len() > 0);
+        let mut state = SimpleState::new(&plan)?;
+        for i in 0..3 {
+            let input = || tensor2(&[[i as f32; 4]; 4]);
+            assert_eq!(state.run(tvec!(input()))?, reference.run(tvec!(input()))?);
+        }
+        Ok(())
+    }
+}
@@ -3,6 +3,10 @@ use std::fmt;
 use std::ops;
 use std::str::FromStr;
 
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+
 use num_traits::cast::AsPrimitive;
 use num_traits::Zero;
 
@@ -10,6 +14,7 @@ mod stack;
 mod tree;
 
 use self::stack::Stack;
+use crate::native::{Decode, Decoder, Encode, Encoder};
 use crate::TractResult;
 
 /// A super-trait for value acting as tensor dimensions in tract.
@@ -17,7 +22,7 @@ use crate::TractResult;
 /// Implemented by:
 ///
 /// * `usize` for regular dimensions
-/// * `TDim` supporting regular and streaming dimensions
+/// * `TDim` supporting regular, streaming and symbolic dimensions
 pub trait DimLike:
     Clone
     + Default
@@ -40,6 +45,8 @@ pub trait DimLike:
     + Sync
     + 'static
     + std::iter::Sum
+    + Encode
+    + Decode
 {
     fn maybe_mul(&self, other: &Self) -> TractResult<Self>;
 
@@ -51,6 +58,9 @@ pub trait DimLike:
     /// Convert to regular integer.
     fn to_integer(&self) -> TractResult<i32>;
 
+    /// Convert to regular integer, giving values to the symbols.
+    fn eval_with(&self, values: &SymbolValues) -> TractResult<i32>;
+
     /// do not use num_traits::Mul as it implies a regular Mul
     fn one() -> Self;
 }
@@ -62,7 +72,7 @@ impl DimLike for TDim {
         } else if let Ok(a) = self.to_integer() {
             Ok(other.clone() * a)
         } else {
-            bail!("product with too many symbols")
+            bail!("product of symbolic dimensions {} and {}", self, other)
         }
     }
 
@@ -70,6 +80,10 @@ impl DimLike for TDim {
         TDim::to_integer(self)
     }
 
+    fn eval_with(&self, values: &SymbolValues) -> TractResult<i32> {
+        TDim::eval_with(self, values)
+    }
+
     fn one() -> Self {
         Self::from(1)
     }
@@ -84,6 +98,10 @@ impl DimLike for usize {
         Ok(*self as i32)
     }
 
+    fn eval_with(&self, _values: &SymbolValues) -> TractResult<i32> {
+        Ok(*self as i32)
+    }
+
     fn one() -> usize {
         1
     }
@@ -99,8 +117,84 @@ impl<D: DimLike, A: std::borrow::Borrow<D>, I: Iterator<Item = A>> MaybeProduct<
     }
 }
 
-/// An arithmetic expression built with integer and the special value S for
-/// the streaming dimension.
+/// Values given to the symbols of dimension expressions.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct SymbolValues(HashMap<char, i32>);
+
+impl SymbolValues {
+    /// Add or replace the value of a symbol.
+    pub fn with(mut self, symbol: char, value: i32) -> SymbolValues {
+        self.set(symbol, value);
+        self
+    }
+
+    pub fn set(&mut self, symbol: char, value: i32) {
+        self.0.insert(symbol, value);
+    }
+
+    pub fn get(&self, symbol: char) -> Option<i32> {
+        self.0.get(&symbol).cloned()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+}
+
+/// Unicode private use range where data-dependent symbols are picked.
+const DATA_DEPENDENT_SYMBOLS: std::ops::Range<u32> = 0xF0000..0xFFFFE;
+
+/// Stands for a data-dependent dimension until a model gives it a symbol.
+const UNASSIGNED_DATA_DEPENDENT_SYMBOL: char = '\u{FFFFE}';
+
+/// Is `symbol` a data-dependent symbol, or the placeholder made by
+/// `TDim::data_dependent` ?
+pub fn is_data_dependent_symbol(symbol: char) -> bool {
+    DATA_DEPENDENT_SYMBOLS.contains(&(symbol as u32)) || symbol == UNASSIGNED_DATA_DEPENDENT_SYMBOL
+}
+
+/// The data-dependent symbols drawn for a model.
+///
+/// Clones share the table, so a model and the patches tapping it never
+/// draw the same symbol twice.
+#[derive(Clone, Debug, Default)]
+pub struct SymbolTable(Arc<AtomicU32>);
+
+impl SymbolTable {
+    /// A data-dependent symbol no dimension of the model uses yet.
+    pub fn new_data_dependent(&self) -> TractResult<TDim> {
+        let range = DATA_DEPENDENT_SYMBOLS.end - DATA_DEPENDENT_SYMBOLS.start;
+        let ix = self
+            .0
+            .fetch_update(Ordering::Relaxed, Ordering::Relaxed, |ix| {
+                if ix < range {
+                    Some(ix + 1)
+                } else {
+                    None
+                }
+            })
+            .map_err(|_| format!("Ran out of data-dependent symbols ({} are in use)", range))?;
+        Ok(TDim::sym(std::char::from_u32(DATA_DEPENDENT_SYMBOLS.start + ix).unwrap()))
+    }
+
+    /// Make sure the data-dependent symbols of `dim` are not drawn again.
+    pub fn reserve(&self, dim: &TDim) {
+        for symbol in dim.symbols() {
+            if DATA_DEPENDENT_SYMBOLS.contains(&(symbol as u32)) {
+                self.0
+                    .fetch_max(symbol as u32 - DATA_DEPENDENT_SYMBOLS.start + 1, Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+/// An arithmetic expression built with integers and symbols.
+///
+/// Symbols are single characters: `S` is the streaming dimension, other
+/// letters can be used for any dimension unknown until runtime (`N` for a
+/// batch size, `T` for a sequence length...). Dimensions only known after
+/// evaluating the operator producing them get a symbol of their own (see
+/// `TDim::data_dependent`).
 #[derive(Clone, PartialEq, Eq, Hash)]
 #[cfg_attr(feature = "serialize", derive(Serialize))]
 pub struct TDim(Stack);
@@ -131,7 +225,38 @@ impl TDim {
 
     /// The special value S, for streaming.
     pub fn s() -> TDim {
-        TDim(Stack::sym('S'))
+        TDim::sym('S')
+    }
+
+    /// A symbolic dimension.
+    pub fn sym(symbol: char) -> TDim {
+        TDim(Stack::sym(symbol))
+    }
+
+    /// A placeholder for a dimension only known after the operator producing
+    /// it has run, like the number of non-zero elements of a tensor.
+    ///
+    /// Operators make it when they are built and expose it in
+    /// `TypedOp::data_dependent_dims_mut`. The model they are wired in then
+    /// replaces it by a symbol from its `SymbolTable`, and draws a new one if
+    /// a copy of the operator is wired again.
+    pub fn data_dependent() -> TDim {
+        TDim::sym(UNASSIGNED_DATA_DEPENDENT_SYMBOL)
+    }
+
+    /// Is the value a placeholder made by `TDim::data_dependent` ?
+    pub fn is_unassigned_data_dependent(&self) -> bool {
+        self.symbols().contains(&UNASSIGNED_DATA_DEPENDENT_SYMBOL)
+    }
+
+    /// Does the value depend on a data-dependent symbol ?
+    pub fn is_data_dependent(&self) -> bool {
+        self.symbols().into_iter().any(is_data_dependent_symbol)
+    }
+
+    /// Symbols appearing in the expression, sorted.
+    pub fn symbols(&self) -> Vec<char> {
+        self.0.symbols()
     }
 
     /// The special value S, for streaming.
@@ -139,7 +264,7 @@ impl TDim {
         Self::s()
     }
 
-    /// Try to convert the value to an integer, if it does not contains S.
+    /// Try to convert the value to an integer, if it does not contain any symbol.
     pub fn as_const(&self) -> Option<i32> {
         self.to_integer().ok()
     }
@@ -149,8 +274,31 @@ impl TDim {
         self.0.eval(&hashmap!('S' => s)).ok()
     }
 
+    /// A multiple of all the divisors in the expression.
+    ///
+    /// Divisions are rounded, so an expression may only be linear on the
+    /// multiples of its divisor, like S/4 on the multiples of 4.
+    pub fn divisor(&self) -> u32 {
+        self.0.divisor()
+    }
+
+    /// Replace the symbols with a known value, leaving the others untouched.
+    pub fn substitute(&self, values: &SymbolValues) -> TDim {
+        TDim(self.0.substitute(&values.0))
+    }
+
+    /// Eval the value given values for all its symbols.
+    pub fn eval_with(&self, values: &SymbolValues) -> TractResult<i32> {
+        self.0.eval(&values.0)
+    }
+
     /// Is the value dependend on S ?
     pub fn is_stream(&self) -> bool {
+        self.symbols().contains(&'S')
+    }
+
+    /// Is the value dependent on any symbol ?
+    pub fn is_symbolic(&self) -> bool {
         self.as_const().is_none()
     }
 
@@ -169,6 +317,18 @@ impl TDim {
     }
 }
 
+impl Encode for TDim {
+    fn encode(&self, encoder: &mut Encoder) -> TractResult<()> {
+        self.0.encode(encoder)
+    }
+}
+
+impl Decode for TDim {
+    fn decode(decoder: &mut Decoder) -> TractResult<Self> {
+        Ok(TDim(Stack::decode(decoder)?))
+    }
+}
+
 impl Zero for TDim {
     fn zero() -> Self {
         Self::from(0)
@@ -333,14 +493,35 @@ impl<'a> From<&'a usize> for TDim {
 impl FromStr for TDim {
     type Err = std::num::ParseIntError;
     fn from_str(s: &str) -> Result<TDim, Self::Err> {
-        if s == "S" {
-            Ok(TDim::s())
-        } else if s.ends_with("S") {
-            let number: String = s.chars().take_while(|c| c.is_digit(10)).collect();
-            let number: i32 = number.parse::<i32>().map(|i| i.into())?;
-            Ok(TDim::s() * number)
-        } else {
-            s.parse::<i32>().map(|i| i.into())
+        match s.chars().last() {
+            Some(symbol) if symbol.is_ascii_alphabetic() => {
+                let number = &s[..s.len() - 1];
+                let number: i32 = if number.len() > 0 { number.parse::<i32>()? } else { 1 };
+                Ok(TDim::sym(symbol) * number)
+            }
+            _ => s.parse::<i32>().map(|i| i.into()),
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn symbol_table_does_not_wrap() {
+        let range = DATA_DEPENDENT_SYMBOLS.end - DATA_DEPENDENT_SYMBOLS.start;
+        let table = SymbolTable(Arc::new(AtomicU32::new(range - 1)));
+        let last = table.new_data_dependent().unwrap();
+        assert!(last.is_data_dependent() && !last.is_unassigned_data_dependent());
+        assert!(table.new_data_dependent().is_err());
+    }
+
+    #[test]
+    fn symbol_table_skips_reserved_symbols() {
+        let first = SymbolTable::default().new_data_dependent().unwrap();
+        let table = SymbolTable::default();
+        table.reserve(&(first.clone() * 2));
+        assert_ne!(table.new_data_dependent().unwrap(), first);
+    }
+}
@@ -1,9 +1,10 @@
 use super::tree::ExpNode;
+use crate::native::{Decode, Decoder, Encode, Encoder};
 use crate::TractResult;
 use std::{fmt, ops};
 
-use std::collections::HashMap;
 use crate::errors::TractResultExt;
+use std::collections::HashMap;
 
 use ExpNode::*;
 
@@ -34,6 +35,18 @@ impl ::serde::Serialize for Stack {
     }
 }
 
+impl Encode for Stack {
+    fn encode(&self, encoder: &mut Encoder) -> TractResult<()> {
+        self.it.encode(encoder)
+    }
+}
+
+impl Decode for Stack {
+    fn decode(decoder: &mut Decoder) -> TractResult<Self> {
+        Ok(Stack::new(ExpNode::decode(decoder)?))
+    }
+}
+
 impl Eq for Stack {}
 
 impl PartialEq for Stack {
@@ -47,6 +60,21 @@ impl Stack {
         self.it.eval(values).chain_err(|| format!("Evaluating {}", self.it))
     }
 
+    pub fn symbols(&self) -> Vec<char> {
+        let mut symbols = self.it.symbols();
+        symbols.sort();
+        symbols.dedup();
+        symbols
+    }
+
+    pub fn divisor(&self) -> u32 {
+        self.it.divisor()
+    }
+
+    pub fn substitute(&self, values: &HashMap<char, i32>) -> Stack {
+        Stack::new(self.it.substitute(values).reduce())
+    }
+
     pub fn sym(s: char) -> Stack {
         Stack::new(ExpNode::Sym(s))
     }
@@ -204,6 +232,14 @@ mod tests {
         assert_eq!(e.eval(&hashmap! {'x' => 2}).unwrap(), 5);
     }
 
+    #[test]
+    fn partial_substitution() {
+        let e = Stack::sym('N') * 2 + Stack::sym('S');
+        assert_eq!(e.symbols(), vec!('N', 'S'));
+        assert_eq!(e.substitute(&hashmap! {'N' => 3}), Stack::sym('S') + 6);
+        assert_eq!(e.substitute(&hashmap! {'N' => 3, 'S' => 1}), Stack::from(7));
+    }
+
     #[test]
     fn reduce_adds() {
         let e: Stack = Stack::from(2) + 1;
@@ -1,3 +1,4 @@
+use crate::native::{Decode, Decoder, Encode, Encoder};
 use crate::prelude::TractResult;
 use itertools::Itertools;
 use std::collections::HashMap;
@@ -19,6 +20,10 @@ use ExpNode::*;
 impl fmt::Display for ExpNode {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
         match &self {
+            Sym(it) if *it == super::UNASSIGNED_DATA_DEPENDENT_SYMBOL => write!(fmt, "?"),
+            Sym(it) if super::is_data_dependent_symbol(*it) => {
+                write!(fmt, "?{}", *it as u32 - super::DATA_DEPENDENT_SYMBOLS.start)
+            }
             Sym(it) => write!(fmt, "{}", it),
             Val(it) => write!(fmt, "{}", it),
             Add(it) => write!(fmt, "{}", it.iter().map(|x| format!("{}", x)).join("+")),
@@ -28,6 +33,51 @@ impl fmt::Display for ExpNode {
     }
 }
 
+impl Encode for ExpNode {
+    fn encode(&self, encoder: &mut Encoder) -> TractResult<()> {
+        match self {
+            Sym(s) => {
+                0u8.encode(encoder)?;
+                (*s as u32).encode(encoder)
+            }
+            Val(v) => {
+                1u8.encode(encoder)?;
+                v.encode(encoder)
+            }
+            Add(terms) => {
+                2u8.encode(encoder)?;
+                terms.encode(encoder)
+            }
+            Mul(a, b) => {
+                3u8.encode(encoder)?;
+                a.encode(encoder)?;
+                b.encode(encoder)
+            }
+            Div(a, b) => {
+                4u8.encode(encoder)?;
+                a.encode(encoder)?;
+                b.encode(encoder)
+            }
+        }
+    }
+}
+
+impl Decode for ExpNode {
+    fn decode(decoder: &mut Decoder) -> TractResult<Self> {
+        Ok(match u8::decode(decoder)? {
+            0 => {
+                let code = u32::decode(decoder)?;
+                Sym(std::char::from_u32(code).ok_or_else(|| format!("Invalid symbol {}", code))?)
+            }
+            1 => Val(i32::decode(decoder)?),
+            2 => Add(Vec::decode(decoder)?),
+            3 => Mul(i32::decode(decoder)?, Box::new(ExpNode::decode(decoder)?)),
+            4 => Div(Box::new(ExpNode::decode(decoder)?), u32::decode(decoder)?),
+            code => bail!("Invalid dimension expression code {}", code),
+        })
+    }
+}
+
 impl ExpNode {
     pub fn eval(&self, values: &HashMap<char, i32>) -> TractResult<i32> {
         Ok(match self {
@@ -41,9 +91,36 @@ impl ExpNode {
         })
     }
 
+    pub fn symbols(&self) -> Vec<char> {
+        match self {
+            Sym(s) => vec![*s],
+            Val(_) => vec![],
+            Add(terms) => terms.iter().flat_map(|t| t.symbols()).collect(),
+            Mul(_, a) | Div(a, _) => a.symbols(),
+        }
+    }
+
+    pub fn divisor(&self) -> u32 {
+        match self {
+            Sym(_) | Val(_) => 1,
+            Add(terms) => terms.iter().map(|t| t.divisor()).product(),
+            Mul(_, a) => a.divisor(),
+            Div(a, q) => a.divisor() * q,
+        }
+    }
+
+    pub fn substitute(&self, values: &HashMap<char, i32>) -> ExpNode {
+        match self {
+            Sym(s) => values.get(s).map(|v| Val(*v)).unwrap_or(Sym(*s)),
+            Val(_) => self.clone(),
+            Add(terms) => Add(terms.iter().map(|t| t.substitute(values)).collect()),
+            Mul(p, a) => Mul(*p, b!(a.substitute(values))),
+            Div(a, q) => Div(b!(a.substitute(values)), *q),
+        }
+    }
+
     pub fn reduce(self) -> ExpNode {
-        self
-            .simplify()
+        self.simplify()
             .wiggle()
             .into_iter()
             .sorted()
@@ -341,6 +418,16 @@ mod tests {
         assert_eq!(mul(-2, &mul(-1, &Sym('S'))).reduce(), mul(2, &Sym('S')))
     }
 
+    #[test]
+    fn substitute_some_symbols() {
+        let e = add(&mul(2, &Sym('N')), &div(&Sym('S'), 2));
+        assert_eq!(
+            e.substitute(&hashmap!('N' => 3)).reduce(),
+            add(&Val(6), &div(&Sym('S'), 2)).reduce()
+        );
+        assert_eq!(e.symbols(), vec!('N', 'S'));
+    }
+
     #[test]
     fn reduce_mul_div_1() {
         assert_eq!(mul(2, &div(&mul(-1, &Sym('S')), 3)).reduce(), mul(-2, &div(&Sym('S'), 3)))
@@ -82,15 +82,18 @@ pub mod macros;
 #[macro_use]
 pub mod ops;
 
+pub mod arena;
 pub mod broadcast;
 pub mod datum;
 pub mod dim;
 pub mod errors;
 pub mod hash;
 pub mod model;
+pub mod native;
 mod optim;
 pub mod plan;
 pub mod pulse;
+pub mod quantize;
 pub mod tensor;
 
 pub use crate::errors::*;
@@ -99,10 +102,10 @@ pub use dyn_clone;
 /// This prelude is meant for code using tract.
 pub mod prelude {
     pub use crate::datum::{Blob, Datum, DatumType};
-    pub use crate::dim::TDim;
+    pub use crate::dim::{SymbolTable, SymbolValues, TDim};
     pub use crate::errors::*;
     pub use crate::model::*;
-    pub use crate::plan::{SimplePlan, SimpleState};
+    pub use crate::plan::{ConcurrentState, SimplePlan, SimpleState};
     pub use crate::tensor::litteral::*;
     pub use crate::tensor::{IntoArcTensor, IntoTensor, Tensor};
     pub use crate::tvec;
@@ -116,9 +119,10 @@ pub mod prelude {
 
 /// This prelude is meant for code extending tract (like implementing new ops).
 pub mod internal {
-    pub use crate::dim::{DimLike, TDim, ToDim, MaybeProduct};
+    pub use crate::dim::{DimLike, MaybeProduct, SymbolValues, TDim, ToDim};
     pub use tract_linalg::hash::{ hash_f32, DynHash };
     pub use crate::model::*;
+    pub use crate::native::{Decode, Decoder, Encode, Encoder};
     pub use crate::ops::change_axes::*;
     pub use crate::ops::element_wise::ElementWiseMiniOp;
     pub use crate::ops::invariants::*;
@@ -138,6 +142,7 @@ pub mod internal {
     pub use tvec;
     pub use {args_1, args_2, args_3, args_4, args_5, args_6, args_7, args_8};
     pub use {as_op, not_a_pulsed_op, not_a_typed_op, op_as_typed_op, pulsed_op_to_typed_op};
+    pub use {impl_native_codec, native_save};
     pub use {bin_to_super_type, element_wise, element_wise_oop};
     pub use {dispatch_copy, dispatch_datum, dispatch_datum_by_size, dispatch_floatlike, dispatch_numbers};
 }
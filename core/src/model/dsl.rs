@@ -181,19 +181,16 @@ impl ModelWireNode<TypedFact, Box<dyn TypedOp>> for TypedModel {
         op: impl Into<Box<dyn TypedOp>>,
         inputs: &[OutletId],
     ) -> TractResult<TVec<OutletId>> {
-        let op = op.into();
-        let output_facts = {
-            let input_facts =
-                inputs.iter().map(|o| self.outlet_fact(*o)).collect::<TractResult<TVec<_>>>()?;
-            if input_facts.iter().all(|f| f.konst.is_some()) && op.as_stateless().is_some() {
-                let tensors =
-                    input_facts.iter().map(|f| f.konst.clone().unwrap()).collect::<TVec<_>>();
-                let outputs = op.as_stateless().unwrap().eval(tensors)?;
-                outputs.into_iter().map(|t| TypedFact::from(t)).collect()
-            } else {
-                op.output_facts(&*input_facts)?
-            }
-        };
+        let mut op = op.into();
+        assign_data_dependent_dims(self, &mut *op)?;
+        let output_facts = typed_output_facts(self, &*op, inputs)?;
+        if output_facts
+            .iter()
+            .flat_map(|f| f.shape.iter())
+            .any(|d| d.is_unassigned_data_dependent())
+        {
+            bail!("{} makes up data-dependent dimensions it does not expose", op.name())
+        }
         let id = self.add_node(name, op, output_facts)?;
         inputs
             .iter()
@@ -203,6 +200,49 @@ impl ModelWireNode<TypedFact, Box<dyn TypedOp>> for TypedModel {
     }
 }
 
+fn typed_output_facts(
+    model: &TypedModel,
+    op: &dyn TypedOp,
+    inputs: &[OutletId],
+) -> TractResult<TVec<TypedFact>> {
+    let input_facts =
+        inputs.iter().map(|o| model.outlet_fact(*o)).collect::<TractResult<TVec<_>>>()?;
+    if input_facts.iter().all(|f| f.konst.is_some()) && op.as_stateless().is_some() {
+        let tensors = input_facts.iter().map(|f| f.konst.clone().unwrap()).collect::<TVec<_>>();
+        let outputs = op.as_stateless().unwrap().eval(tensors)?;
+        Ok(outputs.into_iter().map(|t| TypedFact::from(t)).collect())
+    } else {
+        op.output_facts(&*input_facts)
+    }
+}
+
+/// Give symbols from the model table to the data-dependent dimensions of an
+/// op. An op keeps its symbols unless they are already in use in the model,
+/// like when a node is copied.
+fn assign_data_dependent_dims(model: &TypedModel, op: &mut dyn TypedOp) -> TractResult<()> {
+    let mut dims = op.data_dependent_dims_mut();
+    if dims.len() == 0 {
+        return Ok(());
+    }
+    let in_use = |dim: &TDim| {
+        let symbols = dim.symbols();
+        model
+            .nodes()
+            .iter()
+            .flat_map(|n| n.outputs.iter())
+            .flat_map(|o| o.fact.shape.iter())
+            .any(|d| d.symbols().iter().any(|s| symbols.contains(s)))
+    };
+    if dims.iter().any(|d| d.is_unassigned_data_dependent() || in_use(d)) {
+        for dim in dims.iter_mut() {
+            **dim = model.symbol_table.new_data_dependent()?;
+        }
+    } else {
+        dims.iter().for_each(|d| model.symbol_table.reserve(d));
+    }
+    Ok(())
+}
+
 impl ModelWireNode<PulsedFact, Box<dyn PulsedOp>> for PulsedModel {
     fn wire_node(
         &mut self,
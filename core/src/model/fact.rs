@@ -31,131 +31,121 @@ pub struct StreamFact {
 
 /// Fully determined dimension of a tensor.
 ///
-/// Tensors in tract can have one streaming dimension. TDim generalize the
-/// regular tensor dimensions (usize) to arithmetic expressions of `S`, the
-/// (sometimes hypothetical) tensor length on the streaming axis.
+/// Tensors in tract can have symbolic dimensions. TDim generalize the
+/// regular tensor dimensions (usize) to arithmetic expressions of symbols
+/// like `S`, the (sometimes hypothetical) tensor length on the streaming axis,
+/// or `N` for a batch size only known at runtime. At most one axis can depend
+/// on the streaming symbol. Dimensions only known once the operator producing
+/// the tensor has run use data-dependent symbols (see `TDim::data_dependent`).
 #[derive(Clone, Hash)]
 pub struct ShapeFact {
-    shape: TVec<usize>,
+    dims: TVec<TDim>,
+    concrete: Option<TVec<usize>>,
     /// Optional information for streaming tensors. None for regular tensors.
     pub stream_info: Option<StreamFact>,
 }
 
 impl PartialEq for ShapeFact {
     fn eq(&self, other: &ShapeFact) -> bool {
-        self.shape.len() == other.shape.len() && self.iter().zip(other.iter()).all(|(a, b)| a == b)
+        self.dims == other.dims
     }
 }
 
 impl ShapeFact {
+    fn compute_cache(&mut self) -> TractResult<()> {
+        self.concrete = self
+            .dims
+            .iter()
+            .map(|d| d.to_integer().map(|i| i as usize))
+            .collect::<TractResult<TVec<_>>>()
+            .ok();
+        let mut streaming = self.dims.iter().enumerate().filter(|(_, d)| d.is_stream());
+        self.stream_info = streaming.next().map(|(axis, d)| StreamFact { axis, len: d.clone() });
+        if streaming.next().is_some() {
+            bail!("Shape with two streaming dims are invalid: {:?}", self.dims)
+        }
+        Ok(())
+    }
+
     /// Rank of the tensor.
     pub fn rank(&self) -> usize {
-        self.shape.len()
+        self.dims.len()
     }
 
     /// Extended dimension of the i-th axis.
     ///
-    /// The TDim will wrap a plain integer for regular (non-streaming) tensors.
+    /// The TDim will wrap a plain integer for regular (non-symbolic) dimensions.
     pub fn dim(&self, i: usize) -> TDim {
-        if let Some(ref stream) = self.stream_info {
-            if stream.axis == i {
-                return stream.len.clone();
-            }
-        }
-        self.shape[i].to_dim()
+        self.dims[i].clone()
     }
 
     /// Set the i-th axis dimension.
     pub fn set_dim(&mut self, i: usize, dim: TDim) -> TractResult<()> {
-        if let Some(ref stream) = self.stream_info {
-            if let Ok(int) = dim.to_integer() {
-                self.shape[i] = int as _;
-                if stream.axis == i {
-                    self.stream_info = None;
-                }
-            } else {
-                if stream.axis != i {
-                    bail!("Attempt at building a shape with two streaming dim")
-                } else {
-                    self.stream_info = Some(StreamFact { len: dim, axis: i })
-                }
-            }
-        } else {
-            if let Ok(int) = dim.to_integer() {
-                self.shape[i] = int as _;
-            } else {
-                self.shape[i] = 0;
-                self.stream_info = Some(StreamFact { len: dim, axis: i })
-            }
-        }
-        Ok(())
+        self.dims[i] = dim;
+        self.compute_cache()
     }
 
     pub fn insert_axis(&mut self, axis: usize) -> TractResult<()> {
-        self.shape.insert(axis, 1);
-        if let Some(s) = self.stream_info.as_mut() {
-            if s.axis >= axis {
-                s.axis += 1;
-            }
-        }
-        Ok(())
+        self.dims.insert(axis, 1.to_dim());
+        self.compute_cache()
     }
 
     pub fn remove_axis(&mut self, axis: usize) -> TractResult<()> {
-        self.shape.remove(axis);
-        if let Some(s) = self.stream_info.as_mut() {
-            if s.axis > axis {
-                s.axis -= 1;
-            }
-        }
-        Ok(())
+        self.dims.remove(axis);
+        self.compute_cache()
     }
 
-    /// Shape of the tensor, unless it is streaming.
+    /// Shape of the tensor, unless it has symbolic dimensions.
     pub fn as_finite(&self) -> Option<&[usize]> {
-        match self.stream_info {
-            None => Some(&*self.shape),
-            _ => None,
-        }
+        self.concrete.as_ref().map(|c| &**c)
     }
 
     /// Iterator over dimension of the shape.
     pub fn iter<'a>(&'a self) -> impl Iterator<Item = TDim> + 'a {
-        self.shape.clone().into_iter().enumerate().map(move |(ix, d)| {
-            if let Some(ref info) = self.stream_info {
-                if ix == info.axis {
-                    return info.len.clone();
-                }
-            }
-            (d as i64).to_dim()
-        })
+        self.dims.iter().cloned()
     }
 
     /// Convert the shape to an array of extended dimensions.
     pub fn to_tvec(&self) -> TVec<TDim> {
-        self.iter().collect::<TVec<TDim>>()
+        self.dims.clone()
     }
 
-    pub fn from_dims<T: AsRef<[TDim]> + std::fmt::Debug>(it: T) -> TractResult<ShapeFact> {
-        let count = it.as_ref().iter().filter(|t| t.is_stream()).count();
-        if count > 1 {
-            bail!("Shape with two streaming dims are invalid: {:?}", it)
-        } else {
-            let stream_info = it
-                .as_ref()
-                .iter()
-                .enumerate()
-                .find(|(_ix, d)| d.is_stream())
-                .map(|(ix, d)| StreamFact { axis: ix, len: d.clone() });
-            Ok(ShapeFact {
-                shape: it
-                    .as_ref()
-                    .iter()
-                    .map(|t| t.to_integer().map(|i| i as usize).unwrap_or(0))
-                    .collect(),
-                stream_info,
+    /// Symbols appearing in the shape, sorted.
+    pub fn symbols(&self) -> Vec<char> {
+        let mut symbols: Vec<char> = self.dims.iter().flat_map(|d| d.symbols()).collect();
+        symbols.sort();
+        symbols.dedup();
+        symbols
+    }
+
+    /// Does any dimension depend on a data-dependent symbol ?
+    pub fn is_data_dependent(&self) -> bool {
+        self.dims.iter().any(|d| d.is_data_dependent())
+    }
+
+    /// Check a concrete shape against this one, given the known symbol
+    /// values. Dimensions depending on unknown symbols match anything.
+    pub fn matches_with(&self, shape: &[usize], values: &SymbolValues) -> bool {
+        self.rank() == shape.len()
+            && self.dims.iter().zip(shape.iter()).all(|(d, &s)| {
+                d.substitute(values).to_integer().map(|d| d as usize == s).unwrap_or(true)
             })
-        }
+    }
+
+    /// Replace symbols with known values, leaving the others untouched.
+    pub fn substitute(&self, values: &SymbolValues) -> TractResult<ShapeFact> {
+        ShapeFact::from_dims(self.dims.iter().map(|d| d.substitute(values)).collect::<TVec<_>>())
+    }
+
+    /// Concrete shape given values for all the symbols.
+    pub fn eval_with(&self, values: &SymbolValues) -> TractResult<TVec<usize>> {
+        self.dims.iter().map(|d| Ok(d.eval_with(values)? as usize)).collect()
+    }
+
+    pub fn from_dims<T: AsRef<[TDim]> + std::fmt::Debug>(it: T) -> TractResult<ShapeFact> {
+        let mut shape = ShapeFact { dims: it.as_ref().into(), concrete: None, stream_info: None };
+        shape.compute_cache()?;
+        Ok(shape)
     }
 }
 
@@ -176,7 +166,11 @@ impl TryFrom<&[TDim]> for ShapeFact {
 impl TryFrom<&[usize]> for ShapeFact {
     type Error = TractError;
     fn try_from(it: &[usize]) -> TractResult<ShapeFact> {
-        Ok(ShapeFact { shape: it.into(), stream_info: None })
+        Ok(ShapeFact {
+            dims: it.iter().map(|d| d.to_dim()).collect(),
+            concrete: Some(it.into()),
+            stream_info: None,
+        })
     }
 }
 
@@ -231,7 +225,7 @@ impl Fact for TypedFact {
     }
 
     fn matches(&self, t: &Tensor) -> TractResult<bool> {
-        Ok(self.datum_type == t.datum_type() && t.shape() == &*self.shape.shape)
+        Ok(self.datum_type == t.datum_type() && Some(t.shape()) == self.shape.as_finite())
     }
 
     fn same_as(&self, other: &dyn Fact) -> bool {
@@ -259,7 +253,7 @@ impl From<Arc<Tensor>> for TypedFact {
     fn from(t: Arc<Tensor>) -> TypedFact {
         TypedFact {
             datum_type: t.datum_type(),
-            shape: ShapeFact { shape: t.shape().into(), stream_info: None },
+            shape: ShapeFact::try_from(t.shape()).unwrap(),
             konst: Some(t),
         }
     }
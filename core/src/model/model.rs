@@ -1,4 +1,5 @@
 use super::*;
+use crate::dim::SymbolTable;
 use crate::ops::Op;
 use std::fmt;
 use std::hash::Hash;
@@ -27,6 +28,9 @@ where
     /// outlet labels
     #[educe(Hash(method="hash_outlet_labels"))]
     pub outlet_labels: HashMap<OutletId, String>,
+    /// data-dependent symbols drawn for the model
+    #[educe(Hash(ignore))]
+    pub symbol_table: SymbolTable,
 }
 
 fn hash_outlet_labels<H: std::hash::Hasher>(it: &HashMap<OutletId, String>, state: &mut H) {
@@ -46,6 +50,7 @@ where
             inputs: vec![],
             outputs: vec![],
             outlet_labels: HashMap::new(),
+            symbol_table: SymbolTable::default(),
         }
     }
 }
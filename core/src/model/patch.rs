@@ -75,13 +75,15 @@ where
 
     /// Draw a tap from a preexisting node.
     ///
-    /// returns an OutletId usable in the little "patch" model
+    /// returns an OutletId usable in the little "patch" model, which shares
+    /// the symbol table of the patched model.
     pub fn tap_model(
         &mut self,
         model: &ModelImpl<F, O>,
         outlet: OutletId,
     ) -> TractResult<OutletId> {
         let fact = model.outlet_fact(outlet)?;
+        self.model.symbol_table = model.symbol_table.clone();
         let id = self.add_source(
             format!("incoming-{}/{}", outlet.node, outlet.slot),
             dyn_clone::clone(fact),
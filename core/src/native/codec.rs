@@ -0,0 +1,438 @@
+//! Binary encoding primitives for the native model format.
+//!
+//! All integers are little-endian, sizes and lengths are written as u64.
+//! Since version 2, operator payloads and plain tensor data start on an
+//! `ALIGNMENT` boundary, so that tensors can be read in place from a
+//! memory mapped file.
+use crate::internal::*;
+use crate::native::{Registry, FORMAT_VERSION};
+use crate::tensor::mmap::Mmap;
+use tract_linalg::lut::Lut;
+
+/// Alignment of payloads and tensor data, relative to the beginning of the
+/// enclosing payload.
+pub const ALIGNMENT: usize = 16;
+
+/// Accumulates the binary representation of a model.
+#[derive(Default, Debug, Clone)]
+pub struct Encoder {
+    buffer: Vec<u8>,
+}
+
+impl Encoder {
+    pub fn write_bytes(&mut self, bytes: &[u8]) {
+        self.buffer.extend_from_slice(bytes)
+    }
+
+    pub fn write_blob(&mut self, bytes: &[u8]) -> TractResult<()> {
+        bytes.len().encode(self)?;
+        self.write_bytes(bytes);
+        Ok(())
+    }
+
+    /// Pad with zeroes up to the next `ALIGNMENT` boundary.
+    pub fn align(&mut self) {
+        let padding = (ALIGNMENT - self.buffer.len() % ALIGNMENT) % ALIGNMENT;
+        self.buffer.resize(self.buffer.len() + padding, 0);
+    }
+
+    /// Write a nested payload, aligned so that its own alignments are
+    /// preserved in the final stream.
+    pub fn write_payload(&mut self, payload: Encoder) -> TractResult<()> {
+        payload.buffer.len().encode(self)?;
+        self.align();
+        self.write_bytes(&payload.buffer);
+        Ok(())
+    }
+
+    pub fn into_bytes(self) -> Vec<u8> {
+        self.buffer
+    }
+}
+
+/// Reads back what an `Encoder` produced.
+///
+/// The decoder carries the operator `Registry` used to instantiate the
+/// operators found in the stream. When the data comes from a memory mapped
+/// file, tensors are borrowed from the mapping instead of being copied.
+pub struct Decoder<'a> {
+    data: &'a [u8],
+    pos: usize,
+    pub registry: &'a Registry,
+    pub version: u32,
+    mmap: Option<&'a Arc<Mmap>>,
+}
+
+impl<'a> Decoder<'a> {
+    pub fn new(data: &'a [u8], registry: &'a Registry) -> Decoder<'a> {
+        Decoder { data, pos: 0, registry, version: FORMAT_VERSION, mmap: None }
+    }
+
+    /// Decode the content of `mmap`.
+    pub fn for_mmap(mmap: &'a Arc<Mmap>, registry: &'a Registry) -> Decoder<'a> {
+        Decoder { mmap: Some(mmap), ..Decoder::new(mmap.as_bytes(), registry) }
+    }
+
+    /// Skip the padding up to the next `ALIGNMENT` boundary.
+    pub fn align(&mut self) -> TractResult<()> {
+        if self.version >= 2 {
+            let padding = (ALIGNMENT - self.pos % ALIGNMENT) % ALIGNMENT;
+            self.read_bytes(padding)?;
+        }
+        Ok(())
+    }
+
+    /// Read a payload written by `Encoder::write_payload`, returning a
+    /// decoder for its content.
+    pub fn read_payload(&mut self) -> TractResult<Decoder<'a>> {
+        let len = usize::decode(self)?;
+        self.align()?;
+        let data = self.read_bytes(len)?;
+        Ok(Decoder { data, pos: 0, ..*self })
+    }
+
+    /// Read plain data of `dt` as a tensor, in place if possible.
+    fn read_tensor(&mut self, dt: DatumType, shape: &[usize]) -> TractResult<Tensor> {
+        self.align()?;
+        let bytes = self.read_bytes(shape.iter().product::<usize>() * dt.size_of())?;
+        unsafe {
+            if let Some(mmap) = self.mmap {
+                let offset = bytes.as_ptr() as usize - mmap.as_bytes().as_ptr() as usize;
+                Tensor::from_mmap(dt, shape, mmap.clone(), offset)
+            } else {
+                Tensor::from_raw_dt(dt, shape, bytes)
+            }
+        }
+    }
+
+    pub fn read_bytes(&mut self, len: usize) -> TractResult<&'a [u8]> {
+        if self.data.len() - self.pos < len {
+            bail!(
+                "Unexpected end of native model data (wanted {} bytes at offset {}, {} available)",
+                len,
+                self.pos,
+                self.data.len() - self.pos
+            )
+        }
+        let slice = &self.data[self.pos..][..len];
+        self.pos += len;
+        Ok(slice)
+    }
+
+    pub fn read_blob(&mut self) -> TractResult<&'a [u8]> {
+        let len = usize::decode(self)?;
+        self.read_bytes(len)
+    }
+
+    pub fn is_exhausted(&self) -> bool {
+        self.pos == self.data.len()
+    }
+}
+
+pub trait Encode {
+    fn encode(&self, encoder: &mut Encoder) -> TractResult<()>;
+}
+
+pub trait Decode: Sized {
+    fn decode(decoder: &mut Decoder) -> TractResult<Self>;
+}
+
+macro_rules! codec_le {
+    ($($t: ty),*) => {
+        $(
+        impl Encode for $t {
+            fn encode(&self, encoder: &mut Encoder) -> TractResult<()> {
+                encoder.write_bytes(&self.to_le_bytes());
+                Ok(())
+            }
+        }
+
+        impl Decode for $t {
+            fn decode(decoder: &mut Decoder) -> TractResult<Self> {
+                let mut bytes = [0u8; std::mem::size_of::<$t>()];
+                bytes.copy_from_slice(decoder.read_bytes(std::mem::size_of::<$t>())?);
+                Ok(<$t>::from_le_bytes(bytes))
+            }
+        }
+        )*
+    }
+}
+
+codec_le!(u8, i8, u16, i16, u32, i32, u64, i64, f32, f64);
+
+impl Encode for usize {
+    fn encode(&self, encoder: &mut Encoder) -> TractResult<()> {
+        (*self as u64).encode(encoder)
+    }
+}
+
+impl Decode for usize {
+    fn decode(decoder: &mut Decoder) -> TractResult<Self> {
+        Ok(u64::decode(decoder)? as usize)
+    }
+}
+
+impl Encode for bool {
+    fn encode(&self, encoder: &mut Encoder) -> TractResult<()> {
+        (*self as u8).encode(encoder)
+    }
+}
+
+impl Decode for bool {
+    fn decode(decoder: &mut Decoder) -> TractResult<Self> {
+        match u8::decode(decoder)? {
+            0 => Ok(false),
+            1 => Ok(true),
+            b => bail!("Invalid boolean value {}", b),
+        }
+    }
+}
+
+impl Encode for str {
+    fn encode(&self, encoder: &mut Encoder) -> TractResult<()> {
+        encoder.write_blob(self.as_bytes())
+    }
+}
+
+impl Encode for String {
+    fn encode(&self, encoder: &mut Encoder) -> TractResult<()> {
+        self.as_str().encode(encoder)
+    }
+}
+
+impl Decode for String {
+    fn decode(decoder: &mut Decoder) -> TractResult<Self> {
+        Ok(std::str::from_utf8(decoder.read_blob()?)?.to_string())
+    }
+}
+
+impl<T: Encode> Encode for Option<T> {
+    fn encode(&self, encoder: &mut Encoder) -> TractResult<()> {
+        match self {
+            None => false.encode(encoder),
+            Some(it) => {
+                true.encode(encoder)?;
+                it.encode(encoder)
+            }
+        }
+    }
+}
+
+impl<T: Decode> Decode for Option<T> {
+    fn decode(decoder: &mut Decoder) -> TractResult<Self> {
+        if bool::decode(decoder)? {
+            Ok(Some(T::decode(decoder)?))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+impl<T: Encode> Encode for [T] {
+    fn encode(&self, encoder: &mut Encoder) -> TractResult<()> {
+        self.len().encode(encoder)?;
+        self.iter().try_for_each(|it| it.encode(encoder))
+    }
+}
+
+impl<T: Encode> Encode for Vec<T> {
+    fn encode(&self, encoder: &mut Encoder) -> TractResult<()> {
+        self.as_slice().encode(encoder)
+    }
+}
+
+impl<T: Decode> Decode for Vec<T> {
+    fn decode(decoder: &mut Decoder) -> TractResult<Self> {
+        let len = usize::decode(decoder)?;
+        (0..len).map(|_| T::decode(decoder)).collect()
+    }
+}
+
+impl<T: Encode> Encode for TVec<T> {
+    fn encode(&self, encoder: &mut Encoder) -> TractResult<()> {
+        self.as_slice().encode(encoder)
+    }
+}
+
+impl<T: Decode> Decode for TVec<T> {
+    fn decode(decoder: &mut Decoder) -> TractResult<Self> {
+        let len = usize::decode(decoder)?;
+        (0..len).map(|_| T::decode(decoder)).collect()
+    }
+}
+
+impl<A: Encode, B: Encode> Encode for (A, B) {
+    fn encode(&self, encoder: &mut Encoder) -> TractResult<()> {
+        self.0.encode(encoder)?;
+        self.1.encode(encoder)
+    }
+}
+
+impl<A: Decode, B: Decode> Decode for (A, B) {
+    fn decode(decoder: &mut Decoder) -> TractResult<Self> {
+        Ok((A::decode(decoder)?, B::decode(decoder)?))
+    }
+}
+
+impl<T: Encode> Encode for Box<T> {
+    fn encode(&self, encoder: &mut Encoder) -> TractResult<()> {
+        (**self).encode(encoder)
+    }
+}
+
+impl<T: Decode> Decode for Box<T> {
+    fn decode(decoder: &mut Decoder) -> TractResult<Self> {
+        Ok(Box::new(T::decode(decoder)?))
+    }
+}
+
+impl<T: Encode> Encode for Arc<T> {
+    fn encode(&self, encoder: &mut Encoder) -> TractResult<()> {
+        (**self).encode(encoder)
+    }
+}
+
+impl<T: Decode> Decode for Arc<T> {
+    fn decode(decoder: &mut Decoder) -> TractResult<Self> {
+        Ok(Arc::new(T::decode(decoder)?))
+    }
+}
+
+impl Encode for DatumType {
+    fn encode(&self, encoder: &mut Encoder) -> TractResult<()> {
+        use DatumType::*;
+        let code: u8 = match self {
+            Bool => 0,
+            U8 => 1,
+            U16 => 2,
+            I8 => 3,
+            I16 => 4,
+            I32 => 5,
+            I64 => 6,
+            F16 => 7,
+            F32 => 8,
+            F64 => 9,
+            TDim => 10,
+            Blob => 11,
+            String => 12,
+        };
+        code.encode(encoder)
+    }
+}
+
+impl Decode for DatumType {
+    fn decode(decoder: &mut Decoder) -> TractResult<Self> {
+        use DatumType::*;
+        Ok(match u8::decode(decoder)? {
+            0 => Bool,
+            1 => U8,
+            2 => U16,
+            3 => I8,
+            4 => I16,
+            5 => I32,
+            6 => I64,
+            7 => F16,
+            8 => F32,
+            9 => F64,
+            10 => TDim,
+            11 => Blob,
+            12 => String,
+            code => bail!("Invalid datum type code {}", code),
+        })
+    }
+}
+
+impl Encode for Blob {
+    fn encode(&self, encoder: &mut Encoder) -> TractResult<()> {
+        encoder.write_blob(&self.0)
+    }
+}
+
+impl Decode for Blob {
+    fn decode(decoder: &mut Decoder) -> TractResult<Self> {
+        Ok(Blob(decoder.read_blob()?.to_vec()))
+    }
+}
+
+fn raw_bytes<T: Datum>(t: &Tensor) -> TractResult<&[u8]> {
+    let slice = t.as_slice::<T>()?;
+    Ok(unsafe {
+        std::slice::from_raw_parts(
+            slice.as_ptr() as *const u8,
+            slice.len() * T::datum_type().size_of(),
+        )
+    })
+}
+
+fn encode_items<T: Datum + Encode>(t: &Tensor, encoder: &mut Encoder) -> TractResult<()> {
+    t.as_slice::<T>()?.iter().try_for_each(|it| it.encode(encoder))
+}
+
+fn decode_items<T: Datum + Decode>(shape: &[usize], decoder: &mut Decoder) -> TractResult<Tensor> {
+    let len = shape.iter().product::<usize>();
+    let items = (0..len).map(|_| T::decode(decoder)).collect::<TractResult<Vec<T>>>()?;
+    Ok(tract_ndarray::ArrayD::from_shape_vec(shape, items)?.into_tensor())
+}
+
+impl Encode for Tensor {
+    fn encode(&self, encoder: &mut Encoder) -> TractResult<()> {
+        self.datum_type().encode(encoder)?;
+        self.shape().encode(encoder)?;
+        match self.datum_type() {
+            DatumType::TDim => encode_items::<TDim>(self, encoder),
+            DatumType::String => encode_items::<String>(self, encoder),
+            DatumType::Blob => encode_items::<Blob>(self, encoder),
+            dt => {
+                if cfg!(target_endian = "big") {
+                    bail!("Native format serialization is not supported on big endian targets")
+                }
+                encoder.align();
+                if self.len() > 0 {
+                    encoder.write_bytes(dispatch_copy!(raw_bytes(dt)(self))?);
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+impl Decode for Tensor {
+    fn decode(decoder: &mut Decoder) -> TractResult<Self> {
+        let dt = DatumType::decode(decoder)?;
+        let shape = TVec::<usize>::decode(decoder)?;
+        match dt {
+            DatumType::TDim => decode_items::<TDim>(&shape, decoder),
+            DatumType::String => decode_items::<String>(&shape, decoder),
+            DatumType::Blob => decode_items::<Blob>(&shape, decoder),
+            dt => decoder.read_tensor(dt, &shape),
+        }
+    }
+}
+
+impl Encode for Box<dyn Lut> {
+    fn encode(&self, encoder: &mut Encoder) -> TractResult<()> {
+        encoder.write_blob(self.table())
+    }
+}
+
+impl Decode for Box<dyn Lut> {
+    fn decode(decoder: &mut Decoder) -> TractResult<Self> {
+        Ok((tract_linalg::ops().lut_u8)(decoder.read_blob()?))
+    }
+}
+
+impl Encode for ShapeFact {
+    fn encode(&self, encoder: &mut Encoder) -> TractResult<()> {
+        self.to_tvec().encode(encoder)
+    }
+}
+
+impl Decode for ShapeFact {
+    fn decode(decoder: &mut Decoder) -> TractResult<Self> {
+        ShapeFact::from_dims(TVec::<TDim>::decode(decoder)?)
+    }
+}
+
+impl_native_codec!(TypedFact { datum_type, shape, konst });
+
+impl_native_codec!(OutletId { node, slot });
@@ -0,0 +1,485 @@
+//! # Native model format
+//!
+//! Serialization of decluttered TypedModel to a self-contained binary
+//! format that can be loaded back with tract-core alone, skipping the
+//! framework parsing, analysis and decluttering steps.
+//!
+//! A native file is made of a magic header, a format version, then the
+//! model: graph structure, output facts and operators. Each operator is
+//! stored as a tag looked up in the `Registry` at load time, and an opaque
+//! payload containing its attributes (including constant tensors).
+//!
+//! Plain tensor data is aligned in the file, so models loaded with
+//! `load_from_path` borrow their constants from a read-only memory mapping
+//! of the file instead of copying them to the heap.
+//!
+//! Only operators implementing `TypedOp::save` can be serialized. These are
+//! the tract-core operators found in decluttered networks (including scan
+//! bodies and pulsed networks translated back to typed). Optimized models
+//! (after codegen) and framework-specific operators are rejected with an
+//! explicit error.
+use crate::internal::*;
+use crate::ops::binary::BinMiniOp;
+use crate::ops::element_wise::ElementWiseMiniOp;
+use crate::tensor::mmap::Mmap;
+use std::io::{Read, Write};
+use std::path::Path;
+
+mod codec;
+mod registry;
+
+pub use self::codec::{Decode, Decoder, Encode, Encoder, ALIGNMENT};
+pub use self::registry::{BinMiniOpBuilder, ElementWiseLoader, OpLoader, Registry};
+
+/// Leading bytes of a native model file.
+pub const MAGIC: &[u8; 8] = b"TRACTNAT";
+
+/// Version of the format written by this version of tract. Files written
+/// with a more recent version are rejected.
+///
+/// Version 2 introduced the alignment of payloads and tensor data.
+pub const FORMAT_VERSION: u32 = 2;
+
+/// Serialize a decluttered model.
+pub fn save(model: &TypedModel, write: &mut dyn Write) -> TractResult<()> {
+    let mut encoder = Encoder::default();
+    encoder.write_bytes(MAGIC);
+    FORMAT_VERSION.encode(&mut encoder)?;
+    model.encode(&mut encoder)?;
+    write.write_all(&encoder.into_bytes())?;
+    Ok(())
+}
+
+/// Serialize a decluttered model to a file.
+pub fn save_to_path(model: &TypedModel, path: impl AsRef<Path>) -> TractResult<()> {
+    let mut file = std::fs::File::create(path.as_ref())
+        .chain_err(|| format!("Creating {:?}", path.as_ref()))?;
+    save(model, &mut file)
+}
+
+/// Load a model using the tract-core operators registry.
+pub fn load(read: &mut dyn Read) -> TractResult<TypedModel> {
+    load_with_registry(read, &Registry::default())
+}
+
+/// Load a model, looking up operators in `registry`.
+pub fn load_with_registry(read: &mut dyn Read, registry: &Registry) -> TractResult<TypedModel> {
+    let mut data = vec![];
+    read.read_to_end(&mut data)?;
+    decode_model(&mut Decoder::new(&data, registry))
+}
+
+/// Load a model from a file using the tract-core operators registry.
+///
+/// The file is memory mapped, and the model constants are read in place.
+pub fn load_from_path(path: impl AsRef<Path>) -> TractResult<TypedModel> {
+    load_from_path_with_registry(path, &Registry::default())
+}
+
+/// Load a model from a file, looking up operators in `registry`.
+pub fn load_from_path_with_registry(
+    path: impl AsRef<Path>,
+    registry: &Registry,
+) -> TractResult<TypedModel> {
+    let mmap = Arc::new(Mmap::open(path)?);
+    decode_model(&mut Decoder::for_mmap(&mmap, registry))
+}
+
+fn decode_model(decoder: &mut Decoder) -> TractResult<TypedModel> {
+    if decoder.read_bytes(MAGIC.len()).ok() != Some(&MAGIC[..]) {
+        bail!("Not a tract native model (wrong magic)")
+    }
+    let version = u32::decode(decoder)?;
+    if version > FORMAT_VERSION {
+        bail!(
+            "Native model format version {} is not supported (this tract supports up to {})",
+            version,
+            FORMAT_VERSION
+        )
+    }
+    decoder.version = version;
+    let model = TypedModel::decode(decoder)?;
+    if !decoder.is_exhausted() {
+        bail!("Trailing data after native model")
+    }
+    Ok(model)
+}
+
+impl Encode for TypedModel {
+    fn encode(&self, encoder: &mut Encoder) -> TractResult<()> {
+        self.label.encode(encoder)?;
+        self.nodes.len().encode(encoder)?;
+        for node in &self.nodes {
+            node.name.encode(encoder)?;
+            node.inputs.encode(encoder)?;
+            let mut payload = Encoder::default();
+            let tag = node.op.save(&mut payload)?.ok_or_else(|| {
+                format!(
+                    "{} can not be saved in native format (only decluttered core operators are supported)",
+                    node
+                )
+            })?;
+            tag.encode(encoder)?;
+            encoder.write_payload(payload)?;
+            node.outputs.len().encode(encoder)?;
+            node.outputs.iter().try_for_each(|o| o.fact.encode(encoder))?;
+        }
+        self.inputs.encode(encoder)?;
+        self.outputs.encode(encoder)?;
+        let mut labels =
+            self.outlet_labels.iter().map(|(o, l)| (*o, l.clone())).collect::<Vec<_>>();
+        labels.sort();
+        labels.encode(encoder)
+    }
+}
+
+impl Decode for TypedModel {
+    fn decode(decoder: &mut Decoder) -> TractResult<Self> {
+        let mut model = TypedModel::default();
+        model.label = Option::<String>::decode(decoder)?;
+        let node_count = usize::decode(decoder)?;
+        let mut wires = vec![];
+        for _ in 0..node_count {
+            let name = String::decode(decoder)?;
+            let inputs = Vec::<OutletId>::decode(decoder)?;
+            let tag = String::decode(decoder)?;
+            let mut payload = decoder.read_payload()?;
+            let mut op = decoder
+                .registry
+                .load_op(&tag, &mut payload)
+                .chain_err(|| format!("Loading node {} ({})", name, tag))?;
+            op.data_dependent_dims_mut().into_iter().for_each(|d| model.symbol_table.reserve(d));
+            let facts = TVec::<TypedFact>::decode(decoder)?;
+            facts.iter().flat_map(|f| f.shape.iter()).for_each(|d| model.symbol_table.reserve(&d));
+            let id = model.add_node(name, op, facts)?;
+            wires.push((id, inputs));
+        }
+        for (id, inputs) in wires {
+            for (slot, input) in inputs.into_iter().enumerate() {
+                if input.node >= model.nodes.len()
+                    || input.slot >= model.nodes[input.node].outputs.len()
+                {
+                    bail!("Node #{} refers to a missing outlet {:?}", id, input)
+                }
+                model.add_edge(input, InletId::new(id, slot))?;
+            }
+        }
+        let inputs = Vec::<OutletId>::decode(decoder)?;
+        model.set_input_outlets(&inputs)?;
+        let outputs = Vec::<OutletId>::decode(decoder)?;
+        model.set_output_outlets(&outputs)?;
+        for (outlet, label) in Vec::<(OutletId, String)>::decode(decoder)? {
+            model.set_outlet_label(outlet, label);
+        }
+        Ok(model)
+    }
+}
+
+impl Encode for Box<dyn ElementWiseMiniOp> {
+    fn encode(&self, encoder: &mut Encoder) -> TractResult<()> {
+        let mut payload = Encoder::default();
+        let tag = self.save(&mut payload)?.ok_or_else(|| {
+            format!("Element wise operator {} can not be saved in native format", self.name())
+        })?;
+        tag.encode(encoder)?;
+        encoder.write_payload(payload)
+    }
+}
+
+impl Decode for Box<dyn ElementWiseMiniOp> {
+    fn decode(decoder: &mut Decoder) -> TractResult<Self> {
+        let tag = String::decode(decoder)?;
+        let mut payload = decoder.read_payload()?;
+        decoder.registry.load_element_wise(&tag, &mut payload)
+    }
+}
+
+impl Encode for Box<dyn BinMiniOp> {
+    fn encode(&self, encoder: &mut Encoder) -> TractResult<()> {
+        self.name().encode(encoder)
+    }
+}
+
+impl Decode for Box<dyn BinMiniOp> {
+    fn decode(decoder: &mut Decoder) -> TractResult<Self> {
+        let name = String::decode(decoder)?;
+        decoder.registry.bin_mini_op(&name)
+    }
+}
+
+/// Operators nested in other operators, encoded like the model nodes.
+impl Encode for Box<dyn TypedOp> {
+    fn encode(&self, encoder: &mut Encoder) -> TractResult<()> {
+        let mut payload = Encoder::default();
+        let tag = self
+            .save(&mut payload)?
+            .ok_or_else(|| format!("{} can not be saved in native format", self.name()))?;
+        tag.encode(encoder)?;
+        encoder.write_payload(payload)
+    }
+}
+
+impl Decode for Box<dyn TypedOp> {
+    fn decode(decoder: &mut Decoder) -> TractResult<Self> {
+        let tag = String::decode(decoder)?;
+        let mut payload = decoder.read_payload()?;
+        decoder.registry.load_op(&tag, &mut payload)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ops;
+    use crate::ops::cnn::*;
+    use crate::ops::matmul::MatMulUnary;
+    use crate::ops::nn::DataFormat;
+
+    fn round_trip(model: &TypedModel) -> TypedModel {
+        let mut buffer = vec![];
+        save(model, &mut buffer).unwrap();
+        load(&mut &*buffer).unwrap()
+    }
+
+    fn check_same_graph(model: &TypedModel, reloaded: &TypedModel) {
+        assert_eq!(model.nodes().len(), reloaded.nodes().len());
+        for (a, b) in model.nodes().iter().zip(reloaded.nodes().iter()) {
+            assert_eq!(a.name, b.name);
+            assert_eq!(a.inputs, b.inputs);
+            if let Some(scan) = a.op_as::<ops::scan::TypedScan>() {
+                let other = b.op_as::<ops::scan::TypedScan>().unwrap();
+                check_same_graph(&scan.body, &other.body);
+                assert_eq!(
+                    format!("{:?}", scan.input_mapping),
+                    format!("{:?}", other.input_mapping)
+                );
+                assert_eq!(
+                    format!("{:?}", scan.output_mapping),
+                    format!("{:?}", other.output_mapping)
+                );
+            } else {
+                assert_eq!(format!("{:?}", a.op), format!("{:?}", b.op));
+            }
+            assert_eq!(
+                a.outputs.iter().map(|o| &o.fact).collect::<Vec<_>>(),
+                b.outputs.iter().map(|o| &o.fact).collect::<Vec<_>>()
+            );
+        }
+        assert_eq!(model.input_outlets().unwrap(), reloaded.input_outlets().unwrap());
+        assert_eq!(model.output_outlets().unwrap(), reloaded.output_outlets().unwrap());
+    }
+
+    fn check_same_outputs(model: &TypedModel, inputs: TVec<Tensor>) {
+        let reloaded = round_trip(model);
+        check_same_graph(model, &reloaded);
+        let expected = SimplePlan::new(model).unwrap().run(inputs.clone()).unwrap();
+        let found = SimplePlan::new(&reloaded).unwrap().run(inputs).unwrap();
+        assert_eq!(expected, found);
+    }
+
+    #[test]
+    fn tdim_round_trip() {
+        let registry = Registry::default();
+        for d in &[TDim::from(12), TDim::s(), (TDim::s() * 3 + 4).div_ceil(2), -TDim::s()] {
+            let mut encoder = Encoder::default();
+            d.encode(&mut encoder).unwrap();
+            let bytes = encoder.into_bytes();
+            let mut decoder = Decoder::new(&bytes, &registry);
+            assert_eq!(&TDim::decode(&mut decoder).unwrap(), d);
+            assert!(decoder.is_exhausted());
+        }
+    }
+
+    #[test]
+    fn tensor_round_trip() {
+        let registry = Registry::default();
+        for t in &[
+            tensor0(1.5f32),
+            tensor1(&[1u8, 2, 3]),
+            tensor2(&[[1i64, 2], [3, 4]]),
+            tensor1(&[true, false]),
+            tensor1(&["foo".to_string(), "bar".to_string()]),
+            tensor1(&[TDim::s(), 2.into()]),
+            Tensor::from(tract_ndarray::ArrayD::<f32>::zeros(&[0, 3][..])),
+        ] {
+            let mut encoder = Encoder::default();
+            t.encode(&mut encoder).unwrap();
+            let bytes = encoder.into_bytes();
+            let mut decoder = Decoder::new(&bytes, &registry);
+            assert_eq!(&Tensor::decode(&mut decoder).unwrap(), t);
+            assert!(decoder.is_exhausted());
+        }
+    }
+
+    #[test]
+    fn load_from_path_maps_constants() {
+        let mut model = TypedModel::default();
+        let input = model
+            .add_source("input", TypedFact::dt_shape(f32::datum_type(), [3].as_ref()).unwrap())
+            .unwrap();
+        let two = model.add_const("two", tensor1(&[2f32, 3., 4.])).unwrap();
+        let mul = model.wire_node("mul", ops::math::mul::bin_typed(), &[input, two]).unwrap();
+        model.set_output_outlets(&mul).unwrap();
+        let path = std::env::temp_dir().join(format!("tract-native-{}.tract", std::process::id()));
+        save_to_path(&model, &path).unwrap();
+        let reloaded = load_from_path(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        check_same_graph(&model, &reloaded);
+        let konst = reloaded.node_by_name("two").unwrap().op_as::<ops::konst::Const>().unwrap();
+        assert!(konst.value.is_mmapped());
+        let found =
+            SimplePlan::new(&reloaded).unwrap().run(tvec!(tensor1(&[1f32, 1., 1.]))).unwrap();
+        assert_eq!(*found[0], tensor1(&[2f32, 3., 4.]));
+    }
+
+    #[test]
+    fn decode_unaligned_version_1_tensor() {
+        let registry = Registry::default();
+        let mut encoder = Encoder::default();
+        DatumType::I64.encode(&mut encoder).unwrap();
+        [2usize][..].encode(&mut encoder).unwrap();
+        encoder.write_bytes(&1i64.to_le_bytes());
+        encoder.write_bytes(&2i64.to_le_bytes());
+        let bytes = encoder.into_bytes();
+        let mut decoder = Decoder::new(&bytes, &registry);
+        decoder.version = 1;
+        assert_eq!(Tensor::decode(&mut decoder).unwrap(), tensor1(&[1i64, 2]));
+        assert!(decoder.is_exhausted());
+    }
+
+    #[test]
+    fn element_wise_and_binary() {
+        let mut model = TypedModel::default();
+        let input = model
+            .add_source("input", TypedFact::dt_shape(f32::datum_type(), [3].as_ref()).unwrap())
+            .unwrap();
+        let two = model.add_const("two", tensor1(&[2f32])).unwrap();
+        let mul = model.wire_node("mul", ops::math::mul::bin_typed(), &[input, two]).unwrap();
+        let exp = model.wire_node("exp", ops::math::exp(), &mul).unwrap();
+        let add = model.wire_node("add", ops::math::add::unary(rctensor1(&[1f32])), &exp).unwrap();
+        let min = model
+            .wire_node("min", ops::math::scalar_min_max(tensor0(1f32), tensor0(4f32)), &add)
+            .unwrap();
+        model.set_output_outlets(&min).unwrap();
+        model.set_outlet_label(min[0], "output".to_string());
+        check_same_outputs(&model, tvec!(tensor1(&[-1f32, 0., 1.])));
+        assert_eq!(round_trip(&model).outlet_labels, model.outlet_labels);
+    }
+
+    #[test]
+    fn conv_unary() {
+        let mut model = TypedModel::default();
+        let input = model
+            .add_source(
+                "input",
+                TypedFact::dt_shape(f32::datum_type(), [1, 2, 5].as_ref()).unwrap(),
+            )
+            .unwrap();
+        let kernel = tract_ndarray::Array3::<f32>::from_shape_fn((3, 2, 2), |(a, b, c)| {
+            (a * 4 + b * 2 + c) as f32
+        });
+        let pool_spec = PoolSpec::new(
+            DataFormat::NCHW,
+            tvec!(2),
+            PaddingSpec::Explicit(tvec!(1), tvec!(0)),
+            None,
+            Some(tvec!(2)),
+            Some(3),
+        );
+        let conv = ConvUnary::new(
+            pool_spec,
+            KernelFormat::OIHW,
+            kernel.into_arc_tensor(),
+            1,
+            Some(rctensor1(&[1f32, 2., 3.])),
+            None,
+        );
+        let conv = model.wire_node("conv", conv, &[input]).unwrap();
+        model.set_output_outlets(&conv).unwrap();
+        let input =
+            tract_ndarray::Array3::<f32>::from_shape_fn((1, 2, 5), |(_, b, c)| (b * 5 + c) as f32);
+        check_same_outputs(&model, tvec!(input.into_tensor()));
+    }
+
+    #[test]
+    fn mat_mul_unary() {
+        let mut model = TypedModel::default();
+        let input = model
+            .add_source("input", TypedFact::dt_shape(f32::datum_type(), [3, 2].as_ref()).unwrap())
+            .unwrap();
+        let a = rctensor2(&[[1f32, 2., 3.], [4., 5., 6.]]);
+        let mm = model
+            .wire_node("mm", MatMulUnary::new(a, false, false, false, None), &[input])
+            .unwrap();
+        model.set_output_outlets(&mm).unwrap();
+        check_same_outputs(&model, tvec!(tensor2(&[[1f32, 0.], [0., 1.], [1., 1.]])));
+    }
+
+    #[test]
+    fn typed_scan() {
+        use crate::ops::scan::*;
+        let mut body = TypedModel::default();
+        let x = body
+            .add_source("x", TypedFact::dt_shape(f32::datum_type(), [1].as_ref()).unwrap())
+            .unwrap();
+        let acc = body
+            .add_source("acc", TypedFact::dt_shape(f32::datum_type(), [1].as_ref()).unwrap())
+            .unwrap();
+        let sum = body.wire_node("sum", ops::math::add::bin_typed(), &[x, acc]).unwrap();
+        body.set_output_outlets(&[sum[0], sum[0]]).unwrap();
+        let scan = TypedScan::new(
+            body,
+            vec![
+                InputMapping::Scan { slot: 0, axis: 0, chunk: 1.into() },
+                InputMapping::State { initializer: StateInitializer::Value(rctensor1(&[0f32])) },
+            ],
+            vec![
+                OutputMapping {
+                    state: true,
+                    full_slot: None,
+                    last_value_slot: Some(1),
+                    axis: 0,
+                    chunk: 1.into(),
+                    full_dim_hint: None,
+                },
+                OutputMapping {
+                    state: false,
+                    full_slot: Some(0),
+                    last_value_slot: None,
+                    axis: 0,
+                    chunk: 1.into(),
+                    full_dim_hint: None,
+                },
+            ],
+            None,
+        )
+        .unwrap();
+        let mut model = TypedModel::default();
+        let input = model
+            .add_source("input", TypedFact::dt_shape(f32::datum_type(), [4].as_ref()).unwrap())
+            .unwrap();
+        let scan = model.wire_node("scan", scan, &[input]).unwrap();
+        model.set_output_outlets(&scan).unwrap();
+        check_same_outputs(&model, tvec!(tensor1(&[1f32, 2., 3., 4.])));
+    }
+
+    #[test]
+    fn reject_codegen_ops() {
+        let mut model = TypedModel::default();
+        let input = model
+            .add_source("input", TypedFact::dt_shape(f32::datum_type(), [3, 2].as_ref()).unwrap())
+            .unwrap();
+        let a = rctensor2(&[[1f32, 2., 3.], [4., 5., 6.]]);
+        let mm = model
+            .wire_node("mm", MatMulUnary::new(a, false, false, false, None), &[input])
+            .unwrap();
+        model.set_output_outlets(&mm).unwrap();
+        let optimized = model.into_optimized().unwrap();
+        assert!(save(&optimized, &mut vec![]).is_err());
+    }
+
+    #[test]
+    fn reject_bad_magic() {
+        assert!(load(&mut &b"NOTTRACT\x01\0\0\0"[..]).is_err());
+    }
+}
@@ -0,0 +1,218 @@
+use crate::internal::*;
+use crate::ops::binary::BinMiniOp;
+use crate::ops::element_wise::ElementWiseMiniOp;
+use crate::ops::{self, TypedOp};
+
+pub type OpLoader = fn(&mut Decoder) -> TractResult<Box<dyn TypedOp>>;
+pub type ElementWiseLoader = fn(&mut Decoder) -> TractResult<Box<dyn ElementWiseMiniOp>>;
+pub type BinMiniOpBuilder = fn() -> Box<dyn BinMiniOp>;
+
+/// Maps the tags found in native files to operator constructors.
+///
+/// `Registry::default()` knows about all tract-core operators. Crates
+/// defining their own operators can extend it before calling
+/// `native::load_with_registry`.
+pub struct Registry {
+    pub ops: HashMap<String, OpLoader>,
+    pub element_wise: HashMap<String, ElementWiseLoader>,
+    pub bin_mini_ops: HashMap<String, BinMiniOpBuilder>,
+}
+
+fn load<O: TypedOp + Decode>(decoder: &mut Decoder) -> TractResult<Box<dyn TypedOp>> {
+    Ok(Box::new(O::decode(decoder)?))
+}
+
+fn load_element_wise<O: ElementWiseMiniOp + Decode>(
+    decoder: &mut Decoder,
+) -> TractResult<Box<dyn ElementWiseMiniOp>> {
+    Ok(Box::new(O::decode(decoder)?))
+}
+
+impl Registry {
+    pub fn register_op<O: TypedOp + Decode>(&mut self, tag: &str) {
+        self.ops.insert(tag.to_string(), load::<O>);
+    }
+
+    pub fn register_element_wise<O: ElementWiseMiniOp + Decode>(&mut self, tag: &str) {
+        self.element_wise.insert(tag.to_string(), load_element_wise::<O>);
+    }
+
+    pub fn register_bin_mini_op(&mut self, name: &str, builder: BinMiniOpBuilder) {
+        self.bin_mini_ops.insert(name.to_string(), builder);
+    }
+
+    pub fn load_op(&self, tag: &str, payload: &mut Decoder) -> TractResult<Box<dyn TypedOp>> {
+        let loader = self.ops.get(tag).ok_or_else(|| format!("Unknown operator tag {}", tag))?;
+        let op = loader(payload)?;
+        if !payload.is_exhausted() {
+            bail!("Trailing data after {} attributes", tag)
+        }
+        Ok(op)
+    }
+
+    pub fn load_element_wise(
+        &self,
+        tag: &str,
+        payload: &mut Decoder,
+    ) -> TractResult<Box<dyn ElementWiseMiniOp>> {
+        let loader = self
+            .element_wise
+            .get(tag)
+            .ok_or_else(|| format!("Unknown element wise operator tag {}", tag))?;
+        let op = loader(payload)?;
+        if !payload.is_exhausted() {
+            bail!("Trailing data after {} attributes", tag)
+        }
+        Ok(op)
+    }
+
+    pub fn bin_mini_op(&self, name: &str) -> TractResult<Box<dyn BinMiniOp>> {
+        let builder = self
+            .bin_mini_ops
+            .get(name)
+            .ok_or_else(|| format!("Unknown binary operator {}", name))?;
+        Ok(builder())
+    }
+}
+
+impl Default for Registry {
+    fn default() -> Registry {
+        let mut reg = Registry {
+            ops: HashMap::new(),
+            element_wise: HashMap::new(),
+            bin_mini_ops: HashMap::new(),
+        };
+
+        reg.register_op::<ops::array::MultiBroadcastTo>("MultiBroadcastTo");
+        reg.register_op::<ops::array::Compress>("Compress");
+        reg.register_op::<ops::array::TypedConcat>("TypedConcat");
+        reg.register_op::<ops::array::concat::PulsedSameAxisConcat>("PulsedSameAxisConcat");
+        reg.register_op::<ops::array::Flatten>("Flatten");
+        reg.register_op::<ops::array::Gather>("Gather");
+        reg.register_op::<ops::array::NonZero>("NonZero");
+        reg.register_op::<ops::array::Pad>("Pad");
+        reg.register_op::<ops::array::PulsePad>("PulsePad");
+        reg.register_op::<ops::array::TypedReshape>("TypedReshape");
+        reg.register_op::<ops::array::FiniteReshape>("FiniteReshape");
+        reg.register_op::<ops::array::Resize>("Resize");
+        reg.register_op::<ops::array::Shape>("Shape");
+        reg.register_op::<ops::array::Size>("Size");
+        reg.register_op::<ops::array::Slice<usize>>("Slice<usize>");
+        reg.register_op::<ops::array::Slice<TDim>>("Slice<TDim>");
+        reg.register_op::<ops::array::Tile>("Tile");
+        reg.register_op::<ops::array::TopK>("TopK");
+        reg.register_op::<ops::array::Unique>("Unique");
+        reg.register_op::<ops::binary::TypedBinOp>("TypedBinOp");
+        reg.register_op::<ops::binary::UnaryOp>("UnaryOp");
+        reg.register_op::<ops::binary::MergeOp>("MergeOp");
+        reg.register_op::<ops::binary::MergeOpUnicast>("MergeOpUnicast");
+        reg.register_op::<AxisOp>("AxisOp");
+        reg.register_op::<ops::cnn::AvgPool>("AvgPool");
+        reg.register_op::<ops::cnn::MaxPool>("MaxPool");
+        reg.register_op::<ops::cnn::ConvUnary>("ConvUnary");
+        reg.register_op::<ops::cnn::DeconvUnary>("DeconvUnary");
+        reg.register_op::<ops::cnn::PulsedDeconv>("PulsedDeconv");
+        reg.register_op::<ops::control_flow::TypedIf>("TypedIf");
+        reg.register_op::<ops::control_flow::TypedLoop>("TypedLoop");
+        reg.register_op::<ops::downsample::Downsample>("Downsample");
+        reg.register_op::<ops::dummy::Dummy>("Dummy");
+        reg.register_op::<ops::element_wise::ElementWiseOp>("ElementWiseOp");
+        reg.register_op::<ops::identity::Identity>("Identity");
+        reg.register_op::<ops::konst::Const>("Const");
+        reg.register_op::<ops::logic::Iff>("Iff");
+        reg.register_op::<ops::matmul::MatMul>("MatMul");
+        reg.register_op::<ops::matmul::MatMulUnary>("MatMulUnary");
+        reg.register_op::<ops::nn::ArgMaxMin>("ArgMaxMin");
+        reg.register_op::<ops::nn::GlobalAvgPool>("GlobalAvgPool");
+        reg.register_op::<ops::nn::GlobalLpPool>("GlobalLpPool");
+        reg.register_op::<ops::nn::GlobalMaxPool>("GlobalMaxPool");
+        reg.register_op::<ops::nn::NonMaxSuppression>("NonMaxSuppression");
+        reg.register_op::<ops::nn::Reduce>("Reduce");
+        reg.register_op::<ops::nn::RoiAlign>("RoiAlign");
+        reg.register_op::<ops::quant::DequantizeLinearF32>("DequantizeLinearF32");
+        reg.register_op::<ops::scan::TypedScan>("TypedScan");
+        reg.register_op::<ops::source::TypedSource>("TypedSource");
+        reg.register_op::<crate::pulse::delay::Delay>("Delay");
+
+        macro_rules! element_wise {
+            ($module: ident, [$($op: ident),*]) => {
+                $( reg.register_element_wise::<ops::$module::$op>(stringify!($op)); )*
+            }
+        }
+        element_wise!(cast, [Cast]);
+        element_wise!(logic, [Not]);
+        element_wise!(
+            math,
+            [
+                Abs,
+                Exp,
+                Ln,
+                Square,
+                Sqrt,
+                Recip,
+                Rsqrt,
+                Ceil,
+                Floor,
+                Round,
+                ScalarMinMax,
+                ScalarMin,
+                ScalarMax,
+                Cos,
+                Sin,
+                Tan,
+                Acos,
+                Asin,
+                Atan,
+                Cosh,
+                Sinh,
+                Tanh,
+                Acosh,
+                Asinh,
+                Atanh,
+                Neg,
+                Sign
+            ]
+        );
+        element_wise!(
+            nn,
+            [
+                Softplus,
+                Softsign,
+                Sigmoid,
+                Elu,
+                HardSigmoid,
+                LeakyRelu,
+                ParametricSoftplus,
+                ScaledTanh,
+                Selu,
+                ThresholdRelu
+            ]
+        );
+        element_wise!(quant, [QuantizeLinearU8, QuantizeLinearI8, LookupTable]);
+
+        macro_rules! bin {
+            ($module: ident, [$($op: ident),*]) => {
+                $( reg.register_bin_mini_op(stringify!($op), || Box::new(ops::$module::$op)); )*
+            }
+        }
+        bin!(
+            math,
+            [
+                Add,
+                Sub,
+                Mul,
+                Div,
+                Rem,
+                Min,
+                Max,
+                Pow,
+                ShiftLeft,
+                ShiftRight,
+                FlippedShiftLeft,
+                FlippedShiftRight
+            ]
+        );
+        bin!(logic, [And, Or, Xor, Equals, Lesser, LesserEqual, Greater, GreaterEqual]);
+        reg
+    }
+}
@@ -5,6 +5,8 @@ pub struct MultiBroadcastTo {
     shape: TVec<TDim>,
 }
 
+impl_native_codec!(MultiBroadcastTo { shape });
+
 impl MultiBroadcastTo {
     pub fn eval_t<T: Datum>(input: &Tensor, shape: &[usize]) -> TractResult<TVec<Arc<Tensor>>> {
         let input = input.to_array_view::<T>()?;
@@ -24,10 +26,21 @@ impl Op for MultiBroadcastTo {
 }
 
 impl StatelessOp for MultiBroadcastTo {
-    fn eval(&self, mut inputs: TVec<Arc<Tensor>>) -> TractResult<TVec<Arc<Tensor>>> {
+    fn eval(&self, inputs: TVec<Arc<Tensor>>) -> TractResult<TVec<Arc<Tensor>>> {
+        self.eval_in_session(&SessionState::default(), inputs)
+    }
+
+    fn eval_in_session(
+        &self,
+        session: &SessionState,
+        mut inputs: TVec<Arc<Tensor>>,
+    ) -> TractResult<TVec<Arc<Tensor>>> {
         let input = args_1!(inputs);
-        let dims: Vec<usize> =
-            self.shape.iter().map(|d| Ok(d.to_integer()? as usize)).collect::<TractResult<_>>()?;
+        let dims: Vec<usize> = self
+            .shape
+            .iter()
+            .map(|d| Ok(d.eval_with(&session.resolved_symbols)? as usize))
+            .collect::<TractResult<_>>()?;
         dispatch_datum!(Self::eval_t(input.datum_type())(&*input, &*dims))
     }
 }
@@ -37,5 +50,6 @@ impl TypedOp for MultiBroadcastTo {
         Ok(tvec!(TypedFact::dt_shape(inputs[0].datum_type, &*self.shape)?))
     }
 
+    native_save!("MultiBroadcastTo");
     as_op!();
 }
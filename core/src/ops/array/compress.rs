@@ -0,0 +1,103 @@
+use crate::internal::*;
+use ndarray::*;
+
+/// Select the slices along an axis (or the elements of the flattened input
+/// if there is no axis) for which the condition input is true.
+///
+/// The output dimension on the axis is the number of true conditions, only
+/// known after evaluation unless the conditions are constant.
+#[derive(Debug, Clone, Hash)]
+pub struct Compress {
+    pub axis: Option<usize>,
+    len: TDim,
+}
+
+impl_native_codec!(Compress { axis, len });
+
+impl Compress {
+    pub fn new(axis: Option<usize>) -> Compress {
+        Compress { axis, len: TDim::data_dependent() }
+    }
+
+    unsafe fn eval_t<T: Datum>(&self, input: &Tensor, conds: &[bool], output: &mut Tensor) {
+        let input = input.to_array_view_unchecked::<T>();
+        if let Some(ax) = self.axis {
+            for (ixo, ixi) in
+                conds.iter().enumerate().filter(|(_, c)| **c).map(|(ix, _)| ix).enumerate()
+            {
+                output
+                    .to_array_view_mut_unchecked::<T>()
+                    .index_axis_mut(Axis(ax), ixo)
+                    .assign(&input.index_axis(Axis(ax), ixi));
+            }
+        } else {
+            let output = output.as_slice_mut_unchecked::<T>();
+            let mut ix = 0;
+            for (c, i) in conds.iter().zip(input.iter()) {
+                if *c {
+                    output[ix] = i.clone();
+                    ix += 1;
+                }
+            }
+        }
+    }
+}
+
+impl Op for Compress {
+    fn name(&self) -> Cow<str> {
+        "Compress".into()
+    }
+
+    op_as_typed_op!();
+    not_a_pulsed_op!();
+}
+
+impl StatelessOp for Compress {
+    fn eval(&self, mut inputs: TVec<Arc<Tensor>>) -> TractResult<TVec<Arc<Tensor>>> {
+        let (input, conds) = args_2!(inputs);
+        let conds = conds.as_slice()?;
+        let compressed_dim = conds.iter().filter(|c| **c).count();
+        let shape = if let Some(axis) = self.axis {
+            let mut shape: TVec<usize> = input.shape().into();
+            shape[axis] = compressed_dim;
+            shape
+        } else {
+            tvec!(compressed_dim)
+        };
+        unsafe {
+            let mut output = Tensor::uninitialized_dt(input.datum_type(), &*shape)?;
+            dispatch_datum_by_size!(Self::eval_t(input.datum_type())(
+                self,
+                &input,
+                conds,
+                &mut output
+            ));
+            Ok(tvec!(output.into_arc_tensor()))
+        }
+    }
+}
+
+impl TypedOp for Compress {
+    fn output_facts(&self, inputs: &[&TypedFact]) -> TractResult<TVec<TypedFact>> {
+        let len = if let Some(conds) = &inputs[1].konst {
+            conds.as_slice::<bool>()?.iter().filter(|c| **c).count().to_dim()
+        } else {
+            self.len.clone()
+        };
+        let shape = if let Some(axis) = self.axis {
+            let mut shape = inputs[0].shape.to_tvec();
+            shape[axis] = len;
+            shape
+        } else {
+            tvec!(len)
+        };
+        Ok(tvec!(TypedFact::dt_shape(inputs[0].datum_type, &*shape)?))
+    }
+
+    fn data_dependent_dims_mut(&mut self) -> TVec<&mut TDim> {
+        tvec!(&mut self.len)
+    }
+
+    native_save!("Compress");
+    as_op!();
+}
@@ -11,6 +11,27 @@ pub enum ConcatSlice {
     Var,
 }
 
+impl Encode for ConcatSlice {
+    fn encode(&self, encoder: &mut Encoder) -> TractResult<()> {
+        match self {
+            ConcatSlice::Const(t) => {
+                0u8.encode(encoder)?;
+                t.encode(encoder)
+            }
+            ConcatSlice::Var => 1u8.encode(encoder),
+        }
+    }
+}
+
+impl Decode for ConcatSlice {
+    fn decode(decoder: &mut Decoder) -> TractResult<Self> {
+        Ok(match u8::decode(decoder)? {
+            0 => ConcatSlice::Const(Arc::decode(decoder)?),
+            1 => ConcatSlice::Var,
+            code => bail!("Invalid ConcatSlice code {}", code),
+        })
+    }
+}
 impl ConcatSlice {
     pub fn as_const(&self) -> Option<&Tensor> {
         match self {
@@ -33,6 +54,8 @@ pub struct TypedConcat {
     pub slices: TVec<ConcatSlice>,
 }
 
+impl_native_codec!(TypedConcat { axis, slices });
+
 impl TypedConcat {
     pub fn offsets(&self, inputs: &[&TypedFact]) -> TractResult<Vec<TDim>> {
         let mut offsets = vec![0.to_dim()];
@@ -58,10 +81,11 @@ impl Op for TypedConcat {
     }
 
     op_as_typed_op!();
-    not_a_pulsed_op!();
+    op_as_pulsed_op!();
 }
 
 impl TypedOp for TypedConcat {
+    native_save!("TypedConcat");
     as_op!();
 
     fn output_facts(&self, inputs: &[&TypedFact]) -> TractResult<TVec<TypedFact>> {
@@ -155,17 +179,13 @@ impl TypedOp for TypedConcat {
         mapping: &HashMap<OutletId, OutletId>,
         _pulse: usize,
     ) -> TractResult<TVec<OutletId>> {
-        if node.inputs.len() > 1 {
-            bail!("Pulsification not implemented for more than one input to Concat")
-        }
-
         let input = mapping[&node.inputs[0]];
         let fact = target.outlet_fact(input)?;
 
         if fact.axis == self.axis {
             self.pulsify_along_concat_axis(source, node, target, mapping)
         } else {
-            bail!("Pulsify for Concat on a separate axis is not implemented (but possible)");
+            self.pulsify_across_concat_axis(node, target, mapping)
         }
     }
 }
@@ -225,6 +245,32 @@ impl TypedConcat {
     }
 }
 
+impl TypedConcat {
+    fn pulsify_across_concat_axis(
+        &self,
+        node: &NormalizedNode,
+        target: &mut PulsedModel,
+        mapping: &HashMap<OutletId, OutletId>,
+    ) -> TractResult<TVec<OutletId>> {
+        if self.slices.iter().any(|s| !s.is_var()) {
+            bail!("Pulsify for Concat with constants on a separate axis is not implemented")
+        }
+        let inputs = crate::pulse::sync_inputs(node, target, mapping)?;
+        target.wire_node(&*node.name, self.clone(), &*inputs)
+    }
+}
+
+impl PulsedOp for TypedConcat {
+    fn pulsed_output_facts(&self, inputs: &[&PulsedFact]) -> TractResult<TVec<PulsedFact>> {
+        let mut fact = inputs[0].clone();
+        fact.shape[self.axis] = inputs.iter().map(|f| f.shape[self.axis]).sum();
+        Ok(tvec!(fact))
+    }
+
+    as_op!();
+    pulsed_op_to_typed_op!();
+}
+
 #[derive(Copy, Clone, Debug)]
 enum RangeInRange {
     /// ----[--]<-->HHHH----
@@ -312,6 +358,8 @@ pub struct PulsedSameAxisConcat {
     input_len: TDim,
 }
 
+impl_native_codec!(PulsedSameAxisConcat { axis, pre_slice, post_slice, input_delay, input_len });
+
 impl Op for PulsedSameAxisConcat {
     fn name(&self) -> Cow<str> {
         "PulsedSameAxisConcat".into()
@@ -332,6 +380,7 @@ impl StatefullOp for PulsedSameAxisConcat {
 }
 
 impl TypedOp for PulsedSameAxisConcat {
+    native_save!("PulsedSameAxisConcat");
     as_op!();
 
     fn output_facts(&self, inputs: &[&TypedFact]) -> TractResult<TVec<TypedFact>> {
@@ -7,6 +7,8 @@ pub struct Flatten {
     axis: usize,
 }
 
+impl_native_codec!(Flatten { axis });
+
 impl Flatten {
     /// Evaluates the operation given the input tensors.
     fn eval_t<T: Datum>(
@@ -43,6 +45,7 @@ impl StatelessOp for Flatten {
 }
 
 impl TypedOp for Flatten {
+    native_save!("Flatten");
     as_op!();
 
     fn output_facts(&self, inputs: &[&TypedFact]) -> TractResult<TVec<TypedFact>> {
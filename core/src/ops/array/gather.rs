@@ -6,6 +6,8 @@ pub struct Gather {
     axis: i64,
 }
 
+impl_native_codec!(Gather { axis });
+
 impl Op for Gather {
     fn name(&self) -> Cow<str> {
         "Gather".into()
@@ -83,6 +85,7 @@ impl Gather {
 }
 
 impl TypedOp for Gather {
+    native_save!("Gather");
     as_op!();
 
     fn output_facts(&self, inputs: &[&TypedFact]) -> TractResult<TVec<TypedFact>> {
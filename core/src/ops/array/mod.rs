@@ -1,22 +1,33 @@
 /// # Operators on array and shapes
 mod broadcast;
+mod compress;
 pub(crate) mod concat;
 mod flatten;
 mod gather;
+mod non_zero;
 mod pad;
 mod reshape;
+mod resize;
 mod shape;
 mod size;
 mod slice;
 mod tile;
+mod topk;
+mod unique;
 
 pub use self::broadcast::MultiBroadcastTo;
+pub use self::compress::Compress;
 pub use self::concat::{ConcatSlice, TypedConcat};
 pub use self::flatten::Flatten;
 pub use self::gather::Gather;
+pub use self::non_zero::NonZero;
 pub use self::pad::{Pad, PadMode};
+pub(crate) use self::pad::PulsePad;
 pub use self::reshape::{FiniteReshape, TypedReshape};
+pub use self::resize::{CoordTransformer, Interpolator, Nearest, Resize};
 pub use self::shape::Shape;
 pub use self::size::Size;
 pub use self::slice::Slice;
 pub use self::tile::Tile;
+pub use self::topk::TopK;
+pub use self::unique::Unique;
@@ -0,0 +1,85 @@
+use crate::internal::*;
+
+/// Coordinates of the non-zero elements of the input, one row per input
+/// axis.
+///
+/// The number of columns is only known after evaluation.
+#[derive(Debug, Clone, Hash)]
+pub struct NonZero {
+    len: TDim,
+}
+
+impl_native_codec!(NonZero { len });
+
+impl Default for NonZero {
+    fn default() -> NonZero {
+        NonZero { len: TDim::data_dependent() }
+    }
+}
+
+impl NonZero {
+    fn eval_t<T: Datum>(input: &Tensor) -> TractResult<Tensor> {
+        let zero = T::default();
+        let input = input.to_array_view::<T>()?;
+        let coords: Vec<_> =
+            input.indexed_iter().filter(|(_, x)| **x != zero).map(|(coords, _)| coords).collect();
+        let output = ndarray::Array2::from_shape_fn((input.ndim(), coords.len()), |(axis, ix)| {
+            coords[ix][axis] as i64
+        });
+        Ok(output.into_tensor())
+    }
+}
+
+impl Op for NonZero {
+    fn name(&self) -> Cow<str> {
+        "NonZero".into()
+    }
+
+    op_as_typed_op!();
+    not_a_pulsed_op!();
+}
+
+impl StatelessOp for NonZero {
+    fn eval(&self, mut inputs: TVec<Arc<Tensor>>) -> TractResult<TVec<Arc<Tensor>>> {
+        let input = args_1!(inputs);
+        let output = dispatch_datum!(Self::eval_t(input.datum_type())(&input))?;
+        Ok(tvec!(output.into_arc_tensor()))
+    }
+}
+
+impl TypedOp for NonZero {
+    fn output_facts(&self, inputs: &[&TypedFact]) -> TractResult<TVec<TypedFact>> {
+        let shape = [inputs[0].rank().to_dim(), self.len.clone()];
+        Ok(tvec!(TypedFact::dt_shape(i64::datum_type(), shape.as_ref())?))
+    }
+
+    fn data_dependent_dims_mut(&mut self) -> TVec<&mut TDim> {
+        tvec!(&mut self.len)
+    }
+
+    native_save!("NonZero");
+    as_op!();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn non_zero_coords() {
+        let mut model = TypedModel::default();
+        let x = model
+            .add_source("x", TypedFact::dt_shape(f32::datum_type(), [2, 3].as_ref()).unwrap())
+            .unwrap();
+        let nz = model.wire_node("nz", NonZero::default(), &[x]).unwrap();
+        model.set_output_outlets(&nz).unwrap();
+        let fact = model.outlet_fact(nz[0]).unwrap();
+        assert_eq!(fact.shape.dim(0), 2.to_dim());
+        assert!(fact.shape.dim(1).is_data_dependent());
+        let output = SimplePlan::new(model)
+            .unwrap()
+            .run(tvec!(tensor2(&[[0f32, 1., 0.], [2., 0., 3.]])))
+            .unwrap();
+        assert_eq!(*output[0], tensor2(&[[0i64, 1, 1], [1, 0, 2]]));
+    }
+}
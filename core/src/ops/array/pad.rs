@@ -9,6 +9,29 @@ pub enum PadMode {
     Edge,
 }
 
+impl Encode for PadMode {
+    fn encode(&self, encoder: &mut Encoder) -> TractResult<()> {
+        match self {
+            PadMode::Constant(t) => {
+                0u8.encode(encoder)?;
+                t.encode(encoder)
+            }
+            PadMode::Reflect => 1u8.encode(encoder),
+            PadMode::Edge => 2u8.encode(encoder),
+        }
+    }
+}
+
+impl Decode for PadMode {
+    fn decode(decoder: &mut Decoder) -> TractResult<Self> {
+        Ok(match u8::decode(decoder)? {
+            0 => PadMode::Constant(Arc::decode(decoder)?),
+            1 => PadMode::Reflect,
+            2 => PadMode::Edge,
+            code => bail!("Invalid PadMode code {}", code),
+        })
+    }
+}
 impl Default for PadMode {
     fn default() -> PadMode {
         PadMode::Constant(Arc::new(0.0f32.into()))
@@ -21,6 +44,8 @@ pub struct Pad {
     mode: PadMode,
 }
 
+impl_native_codec!(Pad { pads, mode });
+
 impl Pad {
     fn eval_t<T>(&self, input: Arc<Tensor>) -> TractResult<Arc<Tensor>>
     where
@@ -106,6 +131,7 @@ impl StatelessOp for Pad {
 }
 
 impl TypedOp for Pad {
+    native_save!("Pad");
     as_op!();
 
     fn output_facts(&self, inputs: &[&TypedFact]) -> TractResult<TVec<TypedFact>> {
@@ -274,7 +300,7 @@ impl PulsePadOpState {
 }
 
 #[derive(Debug, Clone, Default, new, Hash)]
-struct PulsePad {
+pub(crate) struct PulsePad {
     axis: usize,
     pulse: usize,
     before: usize,
@@ -284,6 +310,8 @@ struct PulsePad {
     mode: PadMode,
 }
 
+impl_native_codec!(PulsePad { axis, pulse, before, after, begin_input, end_input, mode });
+
 impl Op for PulsePad {
     fn name(&self) -> Cow<str> {
         "PulsePad".into()
@@ -316,6 +344,18 @@ impl TypedOp for PulsePad {
         Ok(tvec!(inputs[0].clone()))
     }
 
+    fn cost(&self, inputs: &[&TypedFact]) -> TractResult<TVec<(Cost, TDim)>> {
+        if self.mode == PadMode::Edge && self.after > 0 {
+            // the last valid frame is kept to pad after the end of the stream
+            let mut shape = inputs[0].shape.to_tvec();
+            shape[self.axis] = 1.to_dim();
+            Ok(tvec!((Cost::Buffer(inputs[0].datum_type), shape.iter().maybe_product()?)))
+        } else {
+            Ok(tvec!())
+        }
+    }
+
+    native_save!("PulsePad");
     as_op!();
 }
 
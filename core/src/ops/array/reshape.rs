@@ -8,6 +8,8 @@ pub struct TypedReshape {
     shape: TVec<TDim>,
 }
 
+impl_native_codec!(TypedReshape { shape });
+
 impl Op for TypedReshape {
     fn name(&self) -> Cow<str> {
         "TypedReshape".into()
@@ -22,16 +24,28 @@ impl Op for TypedReshape {
 }
 
 impl StatelessOp for TypedReshape {
-    fn eval(&self, mut inputs: TVec<Arc<Tensor>>) -> TractResult<TVec<Arc<Tensor>>> {
+    fn eval(&self, inputs: TVec<Arc<Tensor>>) -> TractResult<TVec<Arc<Tensor>>> {
+        self.eval_in_session(&SessionState::default(), inputs)
+    }
+
+    fn eval_in_session(
+        &self,
+        session: &SessionState,
+        mut inputs: TVec<Arc<Tensor>>,
+    ) -> TractResult<TVec<Arc<Tensor>>> {
         let input = args_1!(inputs);
-        let shape: TVec<usize> =
-            self.shape.iter().map(|d| Ok(d.to_integer()? as usize)).collect::<TractResult<_>>()?;
+        let shape: TVec<usize> = self
+            .shape
+            .iter()
+            .map(|d| Ok(d.eval_with(&session.resolved_symbols)? as usize))
+            .collect::<TractResult<_>>()?;
         let o = unsafe { input.into_tensor().into_shape(&*shape)?.into_arc_tensor() };
         Ok(tvec!(o))
     }
 }
 
 impl TypedOp for TypedReshape {
+    native_save!("TypedReshape");
     as_op!();
 
     fn output_facts(&self, inputs: &[&TypedFact]) -> TractResult<TVec<TypedFact>> {
@@ -64,6 +78,8 @@ pub struct FiniteReshape {
     pub shape: TVec<usize>,
 }
 
+impl_native_codec!(FiniteReshape { shape });
+
 impl Op for FiniteReshape {
     fn name(&self) -> Cow<str> {
         "FiniteReshape".into()
@@ -90,5 +106,31 @@ impl TypedOp for FiniteReshape {
         Ok(tvec!(TypedFact::dt_shape(inputs[0].datum_type, &*self.shape)?))
     }
 
+    native_save!("FiniteReshape");
     as_op!();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn reshape_with_symbols_resolved_from_input() {
+        let mut model = TypedModel::default();
+        let n = TDim::sym('N');
+        let source = model
+            .add_source(
+                "input",
+                TypedFact::dt_shape(f32::datum_type(), &[n.clone(), 2.into()][..]).unwrap(),
+            )
+            .unwrap();
+        model.wire_node("reshape", TypedReshape::new(tvec!(n * 2)), &[source]).unwrap();
+        model.auto_outputs().unwrap();
+        let plan = SimplePlan::new(model).unwrap();
+        for &len in &[3usize, 5] {
+            let input = Tensor::from(ndarray::Array2::<f32>::zeros((len, 2)));
+            let outputs = plan.run(tvec!(input)).unwrap();
+            assert_eq!(outputs[0].shape(), &[2 * len]);
+        }
+    }
+}
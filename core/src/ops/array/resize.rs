@@ -0,0 +1,470 @@
+use crate::internal::*;
+use ndarray::*;
+use num_traits::Float;
+
+/// How output coordinates map to input coordinates, along each axis.
+#[derive(Clone, Copy, Debug, Hash, PartialEq)]
+pub enum CoordTransformer {
+    HalfPixel,
+    AlignCorners,
+    Asymmetric,
+    PytorchHalfPixel,
+    TfHalfPixelForNn,
+    TfCropAndResize,
+}
+
+impl CoordTransformer {
+    /// Input coordinate of the output index `x_out`, on an axis going from
+    /// `len_in` to `len_out` with `scale`. `roi` is only used for cropping.
+    pub fn transform(
+        &self,
+        x_out: usize,
+        scale: f32,
+        len_in: usize,
+        len_out: usize,
+        roi: (f32, f32),
+    ) -> f32 {
+        let x = x_out as f32;
+        match self {
+            CoordTransformer::HalfPixel => (x + 0.5) / scale - 0.5,
+            CoordTransformer::PytorchHalfPixel if len_out > 1 => (x + 0.5) / scale - 0.5,
+            CoordTransformer::PytorchHalfPixel => 0.0,
+            CoordTransformer::AlignCorners if len_out > 1 => {
+                x * (len_in - 1) as f32 / (len_out - 1) as f32
+            }
+            CoordTransformer::AlignCorners => 0.0,
+            CoordTransformer::Asymmetric => x / scale,
+            CoordTransformer::TfHalfPixelForNn => (x + 0.5) / scale,
+            CoordTransformer::TfCropAndResize if len_out > 1 => {
+                roi.0 * (len_in - 1) as f32
+                    + x * (roi.1 - roi.0) * (len_in - 1) as f32 / (len_out - 1) as f32
+            }
+            CoordTransformer::TfCropAndResize => 0.5 * (roi.0 + roi.1) * (len_in - 1) as f32,
+        }
+    }
+}
+
+/// Rounding of the input coordinate in nearest mode.
+#[derive(Clone, Copy, Debug, Hash, PartialEq)]
+pub enum Nearest {
+    RoundPreferFloor,
+    RoundPreferCeil,
+    Floor,
+    Ceil,
+}
+
+impl Nearest {
+    fn round(&self, x: f32) -> f32 {
+        match self {
+            Nearest::RoundPreferFloor if x.fract() == 0.5 => x.floor(),
+            Nearest::RoundPreferFloor => x.round(),
+            Nearest::RoundPreferCeil => x.round(),
+            Nearest::Floor => x.floor(),
+            Nearest::Ceil => x.ceil(),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Hash, PartialEq)]
+pub enum Interpolator {
+    Nearest,
+    Linear,
+    Cubic,
+}
+
+macro_rules! impl_enum_codec {
+    ($Enum: ident { $($variant: ident),* }) => {
+        impl Encode for $Enum {
+            fn encode(&self, encoder: &mut Encoder) -> TractResult<()> {
+                let variants = [$($Enum::$variant),*];
+                (variants.iter().position(|v| v == self).unwrap() as u8).encode(encoder)
+            }
+        }
+
+        impl Decode for $Enum {
+            fn decode(decoder: &mut Decoder) -> TractResult<Self> {
+                let variants = [$($Enum::$variant),*];
+                let code = u8::decode(decoder)? as usize;
+                variants.get(code).cloned().ok_or_else(|| {
+                    format!("Invalid {} code {}", stringify!($Enum), code).into()
+                })
+            }
+        }
+    };
+}
+
+impl_enum_codec!(CoordTransformer {
+    HalfPixel,
+    AlignCorners,
+    Asymmetric,
+    PytorchHalfPixel,
+    TfHalfPixelForNn,
+    TfCropAndResize
+});
+impl_enum_codec!(Nearest { RoundPreferFloor, RoundPreferCeil, Floor, Ceil });
+impl_enum_codec!(Interpolator { Nearest, Linear, Cubic });
+
+/// Input contributions to one output index along an axis: pairs of input
+/// index and weight, or None for the extrapolation value.
+type Contributions = Option<TVec<(usize, f32)>>;
+
+/// Resize a tensor along all its axes.
+///
+/// The first input is the data. Output dimensions come from either an
+/// optional `scales` input (f32, one per axis) or an optional `sizes` input
+/// (one per axis). An optional `roi` input gives the starts then the ends of
+/// the region of interest, in normalized coordinates, for the crop and resize
+/// coordinate transformation.
+#[derive(Clone, Debug, Educe)]
+#[educe(Hash)]
+pub struct Resize {
+    pub coord_transformer: CoordTransformer,
+    pub interpolator: Interpolator,
+    pub nearest: Nearest,
+    #[educe(Hash(method = "hash_f32"))]
+    pub cubic_coeff_a: f32,
+    pub exclude_outside: bool,
+    #[educe(Hash(method = "hash_f32"))]
+    pub extrapolation_value: f32,
+    pub optional_roi_input: Option<usize>,
+    pub optional_scales_input: Option<usize>,
+    pub optional_sizes_input: Option<usize>,
+}
+
+impl_native_codec!(Resize {
+    coord_transformer,
+    interpolator,
+    nearest,
+    cubic_coeff_a,
+    exclude_outside,
+    extrapolation_value,
+    optional_roi_input,
+    optional_scales_input,
+    optional_sizes_input
+});
+
+impl Resize {
+    fn scales<'t>(&self, inputs: &'t [Arc<Tensor>]) -> TractResult<Option<&'t [f32]>> {
+        if let Some(ix) = self.optional_scales_input {
+            let scales = inputs[ix].as_slice::<f32>()?;
+            if scales.len() > 0 {
+                return Ok(Some(scales));
+            }
+        }
+        Ok(None)
+    }
+
+    fn sizes(&self, inputs: &[Arc<Tensor>]) -> TractResult<Option<TVec<usize>>> {
+        if let Some(ix) = self.optional_sizes_input {
+            if inputs[ix].len() > 0 {
+                let sizes = inputs[ix].cast_to::<i64>()?;
+                return Ok(Some(sizes.as_slice::<i64>()?.iter().map(|&d| d as usize).collect()));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Output dimensions, given the input shape and the scales or sizes.
+    pub fn compute_output_shape<D: DimLike>(
+        &self,
+        input_shape: &[D],
+        scales: Option<&[f32]>,
+        sizes: Option<&[usize]>,
+    ) -> TractResult<TVec<D>> {
+        if let Some(sizes) = sizes {
+            if sizes.len() != input_shape.len() {
+                bail!("Resize sizes {:?} do not match input rank {}", sizes, input_shape.len())
+            }
+            Ok(sizes.iter().map(|&d| D::from(d)).collect())
+        } else if let Some(scales) = scales {
+            if scales.len() != input_shape.len() {
+                bail!("Resize scales {:?} do not match input rank {}", scales, input_shape.len())
+            }
+            input_shape
+                .iter()
+                .zip(scales.iter())
+                .map(|(d, &s)| {
+                    if s == 1.0 {
+                        Ok(d.clone())
+                    } else {
+                        let d = d.to_integer()? as f32;
+                        Ok(D::from((d * s).floor() as usize))
+                    }
+                })
+                .collect()
+        } else {
+            bail!("Resize needs either scales or sizes")
+        }
+    }
+
+    fn contributions(
+        &self,
+        len_in: usize,
+        len_out: usize,
+        scale: f32,
+        roi: (f32, f32),
+    ) -> Vec<Contributions> {
+        let last = len_in as isize - 1;
+        let crop = self.coord_transformer == CoordTransformer::TfCropAndResize;
+        (0..len_out)
+            .map(|x_out| {
+                let x = self.coord_transformer.transform(x_out, scale, len_in, len_out, roi);
+                if crop && (x < 0.0 || x > last as f32) {
+                    return None;
+                }
+                Some(match self.interpolator {
+                    Interpolator::Nearest => {
+                        let x = (self.nearest.round(x) as isize).max(0).min(last);
+                        tvec!((x as usize, 1.0))
+                    }
+                    Interpolator::Linear => {
+                        let x = x.max(0.0).min(last as f32);
+                        let x0 = x.floor();
+                        let x1 = (x0 as isize + 1).min(last);
+                        let w = x - x0;
+                        tvec!((x0 as usize, 1.0 - w), (x1 as usize, w))
+                    }
+                    Interpolator::Cubic => {
+                        let x0 = x.floor();
+                        let t = x - x0;
+                        let a = self.cubic_coeff_a;
+                        let far = |d: f32| ((a * d - 5.0 * a) * d + 8.0 * a) * d - 4.0 * a;
+                        let near = |d: f32| ((a + 2.0) * d - (a + 3.0)) * d * d + 1.0;
+                        let coeffs = [far(t + 1.0), near(t), near(1.0 - t), far(2.0 - t)];
+                        let mut contribs: TVec<(isize, f32)> = coeffs
+                            .iter()
+                            .enumerate()
+                            .map(|(ix, &w)| (x0 as isize + ix as isize - 1, w))
+                            .collect();
+                        if self.exclude_outside {
+                            contribs.retain(|(x, _)| *x >= 0 && *x <= last);
+                            let sum: f32 = contribs.iter().map(|(_, w)| w).sum();
+                            contribs.iter_mut().for_each(|(_, w)| *w /= sum);
+                        }
+                        contribs
+                            .into_iter()
+                            .map(|(x, w)| (x.max(0).min(last) as usize, w))
+                            .collect()
+                    }
+                })
+            })
+            .collect()
+    }
+
+    fn resize_axis_nearest<T: Datum>(
+        input: ArrayViewD<T>,
+        axis: usize,
+        contributions: &[Contributions],
+        extrapolation: &T,
+    ) -> ArrayD<T> {
+        let mut shape = input.shape().to_vec();
+        shape[axis] = contributions.len();
+        let mut output = ArrayD::from_elem(shape, extrapolation.clone());
+        for (x, c) in contributions.iter().enumerate() {
+            if let Some(c) = c {
+                output.index_axis_mut(Axis(axis), x).assign(&input.index_axis(Axis(axis), c[0].0));
+            }
+        }
+        output
+    }
+
+    fn resize_axis_float<T: Datum + Float + LinalgScalar>(
+        input: ArrayViewD<T>,
+        axis: usize,
+        contributions: &[Contributions],
+        extrapolation: T,
+    ) -> ArrayD<T> {
+        let mut shape = input.shape().to_vec();
+        shape[axis] = contributions.len();
+        let mut output = ArrayD::from_elem(shape, extrapolation);
+        for (x, c) in contributions.iter().enumerate() {
+            if let Some(c) = c {
+                let mut slice = output.index_axis_mut(Axis(axis), x);
+                slice.fill(T::zero());
+                for &(ix, w) in c {
+                    slice.scaled_add(T::from(w).unwrap(), &input.index_axis(Axis(axis), ix));
+                }
+            }
+        }
+        output
+    }
+
+    fn eval_nearest<T: Datum>(
+        &self,
+        input: &Tensor,
+        contributions: &[Vec<Contributions>],
+    ) -> TractResult<Tensor> {
+        let extrapolation = Tensor::from(self.extrapolation_value).cast_to::<T>()?.into_owned();
+        let extrapolation = extrapolation.to_scalar::<T>()?;
+        let mut data = input.to_array_view::<T>()?.to_owned();
+        for (axis, c) in contributions.iter().enumerate() {
+            data = Self::resize_axis_nearest(data.view(), axis, c, extrapolation);
+        }
+        Ok(data.into_tensor())
+    }
+
+    fn eval_float<T: Datum + Float + LinalgScalar>(
+        &self,
+        input: &Tensor,
+        contributions: &[Vec<Contributions>],
+    ) -> TractResult<Tensor> {
+        let extrapolation = T::from(self.extrapolation_value).unwrap();
+        let mut data = input.to_array_view::<T>()?.to_owned();
+        for (axis, c) in contributions.iter().enumerate() {
+            data = Self::resize_axis_float(data.view(), axis, c, extrapolation);
+        }
+        Ok(data.into_tensor())
+    }
+}
+
+impl Op for Resize {
+    fn name(&self) -> Cow<str> {
+        "Resize".into()
+    }
+
+    fn info(&self) -> TractResult<Vec<String>> {
+        Ok(vec![format!(
+            "{:?} {:?} (nearest: {:?})",
+            self.interpolator, self.coord_transformer, self.nearest
+        )])
+    }
+
+    op_as_typed_op!();
+    not_a_pulsed_op!();
+}
+
+impl StatelessOp for Resize {
+    fn eval(&self, inputs: TVec<Arc<Tensor>>) -> TractResult<TVec<Arc<Tensor>>> {
+        let input = &inputs[0];
+        let scales = self.scales(&inputs)?;
+        let sizes = self.sizes(&inputs)?;
+        let output_shape = self.compute_output_shape(input.shape(), scales, sizes.as_deref())?;
+        let roi = if let Some(ix) = self.optional_roi_input.filter(|&ix| inputs[ix].len() > 0) {
+            let roi = inputs[ix].cast_to::<f32>()?;
+            roi.as_slice::<f32>()?.to_vec()
+        } else {
+            vec![]
+        };
+        let contributions: Vec<Vec<Contributions>> = (0..input.rank())
+            .map(|axis| {
+                let (len_in, len_out) = (input.shape()[axis], output_shape[axis]);
+                let scale = scales.map(|s| s[axis]).unwrap_or(len_out as f32 / len_in as f32);
+                let roi = if roi.len() == 2 * input.rank() {
+                    (roi[axis], roi[axis + input.rank()])
+                } else {
+                    (0.0, 1.0)
+                };
+                self.contributions(len_in, len_out, scale, roi)
+            })
+            .collect();
+        let output = match (self.interpolator, input.datum_type()) {
+            (Interpolator::Nearest, dt) => {
+                dispatch_numbers!(Self::eval_nearest(dt)(self, input, &*contributions))?
+            }
+            (_, DatumType::F32) => self.eval_float::<f32>(input, &contributions)?,
+            (_, DatumType::F64) => self.eval_float::<f64>(input, &contributions)?,
+            (interpolator, dt) => {
+                bail!("Resize with {:?} interpolation does not support {:?}", interpolator, dt)
+            }
+        };
+        Ok(tvec!(output.into_arc_tensor()))
+    }
+}
+
+impl TypedOp for Resize {
+    native_save!("Resize");
+    as_op!();
+
+    fn output_facts(&self, inputs: &[&TypedFact]) -> TractResult<TVec<TypedFact>> {
+        let konst =
+            |ix: Option<usize>| ix.and_then(|ix| inputs[ix].konst.clone()).filter(|t| t.len() > 0);
+        let scales = konst(self.optional_scales_input);
+        let sizes = konst(self.optional_sizes_input);
+        let sizes = if let Some(sizes) = sizes {
+            let sizes = sizes.cast_to::<i64>()?;
+            Some(sizes.as_slice::<i64>()?.iter().map(|&d| d as usize).collect::<TVec<_>>())
+        } else {
+            None
+        };
+        let scales = scales.as_ref().map(|s| s.as_slice::<f32>()).transpose()?;
+        if scales.is_none() && sizes.is_none() {
+            bail!("Resize needs constant scales or sizes in a typed model")
+        }
+        let input_shape = inputs[0].shape.to_tvec();
+        let shape = self.compute_output_shape(&*input_shape, scales, sizes.as_deref())?;
+        Ok(tvec!(TypedFact::dt_shape(inputs[0].datum_type, &*shape)?))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn resize(interpolator: Interpolator, coord_transformer: CoordTransformer) -> Resize {
+        Resize {
+            coord_transformer,
+            interpolator,
+            nearest: Nearest::RoundPreferFloor,
+            cubic_coeff_a: -0.75,
+            exclude_outside: false,
+            extrapolation_value: 0.0,
+            optional_roi_input: None,
+            optional_scales_input: Some(1),
+            optional_sizes_input: None,
+        }
+    }
+
+    fn run(op: Resize, input: Tensor, scales: &[f32]) -> Arc<Tensor> {
+        op.eval(tvec!(input.into(), rctensor1(scales))).unwrap().remove(0)
+    }
+
+    #[test]
+    fn nearest_asymmetric_upsample() {
+        let mut op = resize(Interpolator::Nearest, CoordTransformer::Asymmetric);
+        op.nearest = Nearest::Floor;
+        let output = run(op, tensor2(&[[1f32, 2.], [3., 4.]]), &[1.0, 2.0]);
+        assert_eq!(output, rctensor2(&[[1f32, 1., 2., 2.], [3., 3., 4., 4.]]));
+    }
+
+    #[test]
+    fn linear_half_pixel_upsample() {
+        let op = resize(Interpolator::Linear, CoordTransformer::HalfPixel);
+        let output = run(op, tensor2(&[[1f32, 2.], [3., 4.]]), &[2.0, 2.0]);
+        let expected = tensor2(&[
+            [1.0f32, 1.25, 1.75, 2.0],
+            [1.5, 1.75, 2.25, 2.5],
+            [2.5, 2.75, 3.25, 3.5],
+            [3.0, 3.25, 3.75, 4.0],
+        ]);
+        output.close_enough(&expected, true).unwrap();
+    }
+
+    #[test]
+    fn linear_align_corners_downsample() {
+        let op = resize(Interpolator::Linear, CoordTransformer::AlignCorners);
+        let output = run(op, tensor2(&[[1f32, 2., 3., 4.], [5., 6., 7., 8.]]), &[1.0, 0.6]);
+        output.close_enough(&tensor2(&[[1f32, 4.], [5., 8.]]), true).unwrap();
+    }
+
+    #[test]
+    fn cubic_is_exact_on_linear_data() {
+        let mut op = resize(Interpolator::Cubic, CoordTransformer::AlignCorners);
+        op.cubic_coeff_a = -0.5;
+        let input = tensor1(&[0f32, 1., 2., 3., 4., 5., 6., 7.]);
+        let output = run(op, input, &[2.0]);
+        let output = output.as_slice::<f32>().unwrap();
+        // away from the clamped borders, Keys cubic (a = -0.5) reproduces a ramp
+        for x in 4..12 {
+            assert!((output[x] - x as f32 * 7. / 15.).abs() < 1e-4, "{:?}", output);
+        }
+    }
+
+    #[test]
+    fn typed_output_shape_from_constant_scales() {
+        let op = resize(Interpolator::Nearest, CoordTransformer::HalfPixel);
+        let input = TypedFact::dt_shape(f32::datum_type(), [1usize, 3, 5, 7].as_ref()).unwrap();
+        let scales: TypedFact = rctensor1(&[1f32, 1., 2., 0.5]).into();
+        let facts = op.output_facts(&[&input, &scales]).unwrap();
+        assert_eq!(facts[0].shape.as_finite(), Some(&[1usize, 3, 10, 3][..]));
+    }
+}
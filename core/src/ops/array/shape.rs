@@ -5,6 +5,8 @@ pub struct Shape {
     pub dt: DatumType,
 }
 
+impl_native_codec!(Shape { dt });
+
 impl Op for Shape {
     fn name(&self) -> Cow<str> {
         "Shape".into()
@@ -33,5 +35,6 @@ impl TypedOp for Shape {
         Ok(tvec!(TypedFact::from(tensor)))
     }
 
+    native_save!("Shape");
     as_op!();
 }
@@ -5,6 +5,8 @@ pub struct Size {
     pub dt: DatumType,
 }
 
+impl_native_codec!(Size { dt });
+
 impl Op for Size {
     fn name(&self) -> Cow<str> {
         "Size".into()
@@ -28,5 +30,6 @@ impl TypedOp for Size {
         Ok(tvec!(TypedFact::dt_shape(self.dt, [0usize; 0].as_ref())?))
     }
 
+    native_save!("Size");
     as_op!();
 }
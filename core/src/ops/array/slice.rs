@@ -8,15 +8,44 @@ pub struct Slice<D: DimLike + ToDim + Hash> {
     pub end: D,
 }
 
+impl<D: DimLike + ToDim + Hash> Encode for Slice<D> {
+    fn encode(&self, encoder: &mut Encoder) -> TractResult<()> {
+        self.axis.encode(encoder)?;
+        self.start.encode(encoder)?;
+        self.end.encode(encoder)
+    }
+}
+
+impl<D: DimLike + ToDim + Hash> Decode for Slice<D> {
+    fn decode(decoder: &mut Decoder) -> TractResult<Self> {
+        Ok(Slice {
+            axis: usize::decode(decoder)?,
+            start: D::decode(decoder)?,
+            end: D::decode(decoder)?,
+        })
+    }
+}
 impl<D: DimLike + ToDim + Hash> Slice<D> {
-    unsafe fn eval_t<T: Datum>(&self, input: &Tensor) -> TractResult<Tensor> {
+    unsafe fn eval_t<T: Datum>(&self, input: &Tensor, start: i32, end: i32) -> TractResult<Tensor> {
         let mut input = input.to_array_view_unchecked::<T>();
-        input.slice_axis_inplace(
-            Axis(self.axis),
-            ::ndarray::Slice::from((self.start.to_integer()?)..(self.end.to_integer()?)),
-        );
+        input.slice_axis_inplace(Axis(self.axis), ::ndarray::Slice::from(start..end));
         Ok(Tensor::from(input.to_owned()).into())
     }
+
+    fn eval_range(
+        &self,
+        input: Arc<Tensor>,
+        start: i32,
+        end: i32,
+    ) -> TractResult<TVec<Arc<Tensor>>> {
+        unsafe {
+            let mut tensor = dispatch_datum_by_size!(Self::eval_t(input.datum_type())(
+                self, &input, start, end
+            ))?;
+            tensor.set_datum_type(input.datum_type());
+            Ok(tvec!(tensor.into_arc_tensor()))
+        }
+    }
 }
 
 impl<D: DimLike + ToDim + Hash> Op for Slice<D> {
@@ -37,11 +66,17 @@ impl<D: DimLike + ToDim + Hash> StatelessOp for Slice<D> {
     /// Evaluates the operation given the input tensors.
     fn eval(&self, mut inputs: TVec<Arc<Tensor>>) -> TractResult<TVec<Arc<Tensor>>> {
         let input = args_1!(inputs);
-        unsafe {
-            let mut tensor = dispatch_datum_by_size!(Self::eval_t(input.datum_type())(self, &input))?;
-            tensor.set_datum_type(input.datum_type());
-            Ok(tvec!(tensor.into_arc_tensor()))
-        }
+        self.eval_range(input, self.start.to_integer()?, self.end.to_integer()?)
+    }
+
+    fn eval_in_session(
+        &self,
+        session: &SessionState,
+        mut inputs: TVec<Arc<Tensor>>,
+    ) -> TractResult<TVec<Arc<Tensor>>> {
+        let input = args_1!(inputs);
+        let values = &session.resolved_symbols;
+        self.eval_range(input, self.start.eval_with(values)?, self.end.eval_with(values)?)
     }
 }
 
@@ -134,6 +169,15 @@ impl<D: DimLike + ToDim + Hash> TypedOp for Slice<D> {
         target.wire_node(&*node.name, op, &[input])
     }
 
+    fn save(&self, encoder: &mut Encoder) -> TractResult<Option<&'static str>> {
+        self.encode(encoder)?;
+        if std::any::TypeId::of::<D>() == std::any::TypeId::of::<TDim>() {
+            Ok(Some("Slice<TDim>"))
+        } else {
+            Ok(Some("Slice<usize>"))
+        }
+    }
+
     as_op!();
 }
 
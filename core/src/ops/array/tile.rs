@@ -6,6 +6,8 @@ pub struct Tile {
     multipliers: TVec<usize>,
 }
 
+impl_native_codec!(Tile { multipliers });
+
 impl Tile {
     fn eval_t<T: Datum>(&self, data: &Arc<Tensor>) -> TractResult<Arc<Tensor>> {
         let data = data.to_array_view::<T>()?;
@@ -42,6 +44,7 @@ impl StatelessOp for Tile {
 }
 
 impl TypedOp for Tile {
+    native_save!("Tile");
     as_op!();
 
     fn output_facts(&self, inputs: &[&TypedFact]) -> TractResult<TVec<TypedFact>> {
@@ -0,0 +1,142 @@
+use crate::internal::*;
+use ndarray::*;
+
+/// The `k` largest (or smallest) elements along an axis, and their indices.
+///
+/// `k` is the second input, a tensor with a single integer. Output
+/// dimension on the axis is only known after evaluation if it is not a
+/// constant.
+#[derive(Debug, Clone, Hash)]
+pub struct TopK {
+    pub axis: i64,
+    pub largest: bool,
+    pub sorted: bool,
+    len: TDim,
+}
+
+impl_native_codec!(TopK { axis, largest, sorted, len });
+
+impl TopK {
+    pub fn new(axis: i64, largest: bool, sorted: bool) -> TopK {
+        TopK { axis, largest, sorted, len: TDim::data_dependent() }
+    }
+
+    pub fn resolved_axis(&self, rank: usize) -> TractResult<usize> {
+        let axis = if self.axis < 0 { self.axis + rank as i64 } else { self.axis };
+        if axis < 0 || axis >= rank as i64 {
+            bail!("Invalid axis {} for rank {}", self.axis, rank)
+        }
+        Ok(axis as usize)
+    }
+
+    fn k(k: &Tensor) -> TractResult<usize> {
+        let k = k.cast_to::<i64>()?;
+        match k.as_slice::<i64>()? {
+            &[k] if k >= 0 => Ok(k as usize),
+            k => bail!("TopK expects a single non-negative k, got {:?}", k),
+        }
+    }
+
+    fn eval_t<T: Datum + PartialOrd>(
+        &self,
+        input: &Tensor,
+        k: usize,
+    ) -> TractResult<TVec<Arc<Tensor>>> {
+        use std::cmp::Ordering;
+        let input = input.to_array_view::<T>()?;
+        let axis = self.resolved_axis(input.ndim())?;
+        if k > input.shape()[axis] {
+            bail!("TopK k={} is bigger than axis dimension {}", k, input.shape()[axis])
+        }
+        let mut shape = input.shape().to_vec();
+        shape[axis] = k;
+        let mut values = ArrayD::<T>::default(&*shape);
+        let mut indices = ArrayD::<i64>::zeros(&*shape);
+        let largest = self.largest;
+        Zip::from(input.lanes(Axis(axis)))
+            .and(values.lanes_mut(Axis(axis)))
+            .and(indices.lanes_mut(Axis(axis)))
+            .apply(|lane, mut values, mut indices| {
+                let mut order: Vec<usize> = (0..lane.len()).collect();
+                // stable sort: ties keep the lowest index first
+                order.sort_by(|&a, &b| {
+                    let ord = lane[a].partial_cmp(&lane[b]).unwrap_or(Ordering::Equal);
+                    if largest {
+                        ord.reverse()
+                    } else {
+                        ord
+                    }
+                });
+                for (ix, &pos) in order.iter().take(k).enumerate() {
+                    values[ix] = lane[pos].clone();
+                    indices[ix] = pos as i64;
+                }
+            });
+        Ok(tvec!(values.into_arc_tensor(), indices.into_arc_tensor()))
+    }
+}
+
+impl Op for TopK {
+    fn name(&self) -> Cow<str> {
+        "TopK".into()
+    }
+
+    op_as_typed_op!();
+    not_a_pulsed_op!();
+}
+
+impl StatelessOp for TopK {
+    fn eval(&self, mut inputs: TVec<Arc<Tensor>>) -> TractResult<TVec<Arc<Tensor>>> {
+        let (input, k) = args_2!(inputs);
+        let k = Self::k(&k)?;
+        dispatch_numbers!(Self::eval_t(input.datum_type())(self, &input, k))
+    }
+}
+
+impl TypedOp for TopK {
+    fn output_facts(&self, inputs: &[&TypedFact]) -> TractResult<TVec<TypedFact>> {
+        let axis = self.resolved_axis(inputs[0].rank())?;
+        let mut shape = inputs[0].shape.to_tvec();
+        shape[axis] = match &inputs[1].konst {
+            Some(k) => Self::k(k)?.to_dim(),
+            None => self.len.clone(),
+        };
+        Ok(tvec!(
+            TypedFact::dt_shape(inputs[0].datum_type, &*shape)?,
+            TypedFact::dt_shape(i64::datum_type(), &*shape)?
+        ))
+    }
+
+    fn data_dependent_dims_mut(&mut self) -> TVec<&mut TDim> {
+        tvec!(&mut self.len)
+    }
+
+    native_save!("TopK");
+    as_op!();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn top_k_on_last_axis() {
+        let op = TopK::new(-1, true, true);
+        let input = tensor2(&[[1f32, 3., 2., 3.], [4., 0., 5., 1.]]);
+        let output = op.eval(tvec!(input.into(), rctensor1(&[2i64]))).unwrap();
+        assert_eq!(*output[0], tensor2(&[[3f32, 3.], [5., 4.]]));
+        assert_eq!(*output[1], tensor2(&[[1i64, 3], [2, 0]]));
+    }
+
+    #[test]
+    fn top_k_dynamic_k_is_data_dependent() {
+        let op = TopK::new(0, false, true);
+        let x = TypedFact::dt_shape(f32::datum_type(), [5].as_ref()).unwrap();
+        let k = TypedFact::dt_shape(i64::datum_type(), [1].as_ref()).unwrap();
+        let facts = op.output_facts(&[&x, &k]).unwrap();
+        assert!(facts[0].shape.dim(0).is_data_dependent());
+        let k = TypedFact::from(rctensor1(&[3i64]));
+        let facts = op.output_facts(&[&x, &k]).unwrap();
+        assert_eq!(facts[1].shape.dim(0), 3.to_dim());
+    }
+}
@@ -0,0 +1,175 @@
+use crate::internal::*;
+use ndarray::*;
+use std::cmp::Ordering;
+
+/// Unique elements of the flattened input, or unique slices along an axis.
+///
+/// Outputs are the unique values, the index of their first occurrence in
+/// the input, the index in the unique values of each input element (or
+/// slice), and the number of occurrences of each unique value. The number of
+/// unique values is only known after evaluation.
+#[derive(Debug, Clone, Hash)]
+pub struct Unique {
+    pub axis: Option<usize>,
+    /// Unique values are in ascending order if true, in order of first
+    /// occurrence otherwise.
+    pub sorted: bool,
+    len: TDim,
+}
+
+impl_native_codec!(Unique { axis, sorted, len });
+
+impl Unique {
+    pub fn new(axis: Option<usize>, sorted: bool) -> Unique {
+        Unique { axis, sorted, len: TDim::data_dependent() }
+    }
+
+    fn eval_t<T: Datum + PartialOrd>(&self, input: &Tensor) -> TractResult<TVec<Arc<Tensor>>> {
+        let (input, axis) = match self.axis {
+            Some(axis) => (input.to_array_view::<T>()?, axis),
+            None => (input.to_array_view::<T>()?.into_shape(IxDyn(&[input.len()]))?, 0),
+        };
+        let slices: Vec<ArrayViewD<T>> = input.axis_iter(Axis(axis)).collect();
+        let cmp = |a: &ArrayViewD<T>, b: &ArrayViewD<T>| {
+            a.iter()
+                .zip(b.iter())
+                .map(|(a, b)| a.partial_cmp(b).unwrap_or(Ordering::Equal))
+                .find(|o| *o != Ordering::Equal)
+                .unwrap_or(Ordering::Equal)
+        };
+        // stable sort: the first slice of each run of equals is its first
+        // occurrence
+        let mut order: Vec<usize> = (0..slices.len()).collect();
+        order.sort_by(|&a, &b| cmp(&slices[a], &slices[b]));
+        let mut groups: Vec<Vec<usize>> = vec![];
+        for ix in order {
+            match groups.last_mut() {
+                Some(group) if cmp(&slices[group[0]], &slices[ix]) == Ordering::Equal => {
+                    group.push(ix)
+                }
+                _ => groups.push(vec![ix]),
+            }
+        }
+        if !self.sorted {
+            groups.sort_by_key(|group| group[0]);
+        }
+        let firsts: Vec<usize> = groups.iter().map(|group| group[0]).collect();
+        let mut inverse = vec![0i64; slices.len()];
+        for (unique, group) in groups.iter().enumerate() {
+            for &ix in group {
+                inverse[ix] = unique as i64;
+            }
+        }
+        let mut shape = input.shape().to_vec();
+        shape[axis] = firsts.len();
+        let values = ArrayD::from_shape_fn(shape, |coords| {
+            let mut coords = coords.slice().to_vec();
+            coords[axis] = firsts[coords[axis]];
+            input[&*coords].clone()
+        });
+        let indices: Vec<i64> = firsts.iter().map(|&ix| ix as i64).collect();
+        let counts: Vec<i64> = groups.iter().map(|group| group.len() as i64).collect();
+        Ok(tvec!(
+            values.into_arc_tensor(),
+            rctensor1(&indices),
+            rctensor1(&inverse),
+            rctensor1(&counts)
+        ))
+    }
+}
+
+impl Op for Unique {
+    fn name(&self) -> Cow<str> {
+        "Unique".into()
+    }
+
+    op_as_typed_op!();
+    not_a_pulsed_op!();
+}
+
+impl StatelessOp for Unique {
+    fn eval(&self, mut inputs: TVec<Arc<Tensor>>) -> TractResult<TVec<Arc<Tensor>>> {
+        let input = args_1!(inputs);
+        match input.datum_type() {
+            DatumType::Bool => self.eval_t::<bool>(&input),
+            DatumType::String => self.eval_t::<String>(&input),
+            dt => dispatch_numbers!(Self::eval_t(dt)(self, &input)),
+        }
+    }
+}
+
+impl TypedOp for Unique {
+    fn output_facts(&self, inputs: &[&TypedFact]) -> TractResult<TVec<TypedFact>> {
+        let (mut shape, inverse_len) = match self.axis {
+            Some(axis) => (inputs[0].shape.to_tvec(), inputs[0].shape.dim(axis)),
+            None => (tvec!(0.to_dim()), inputs[0].shape.iter().maybe_product()?),
+        };
+        shape[self.axis.unwrap_or(0)] = self.len.clone();
+        Ok(tvec!(
+            TypedFact::dt_shape(inputs[0].datum_type, &*shape)?,
+            TypedFact::dt_shape(i64::datum_type(), [self.len.clone()].as_ref())?,
+            TypedFact::dt_shape(i64::datum_type(), [inverse_len].as_ref())?,
+            TypedFact::dt_shape(i64::datum_type(), [self.len.clone()].as_ref())?
+        ))
+    }
+
+    fn data_dependent_dims_mut(&mut self) -> TVec<&mut TDim> {
+        tvec!(&mut self.len)
+    }
+
+    native_save!("Unique");
+    as_op!();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn unique_flattened() {
+        let input = tensor2(&[[2f32, 1.], [1., 3.]]);
+        let output = Unique::new(None, true).eval(tvec!(input.clone().into())).unwrap();
+        assert_eq!(*output[0], tensor1(&[1f32, 2., 3.]));
+        assert_eq!(*output[1], tensor1(&[1i64, 0, 3]));
+        assert_eq!(*output[2], tensor1(&[1i64, 0, 0, 2]));
+        assert_eq!(*output[3], tensor1(&[2i64, 1, 1]));
+        let output = Unique::new(None, false).eval(tvec!(input.into())).unwrap();
+        assert_eq!(*output[0], tensor1(&[2f32, 1., 3.]));
+        assert_eq!(*output[2], tensor1(&[0i64, 1, 1, 2]));
+    }
+
+    #[test]
+    fn copies_get_their_own_len() -> TractResult<()> {
+        let mut model = TypedModel::default();
+        let x =
+            model.add_source("x", TypedFact::dt_shape(f32::datum_type(), [4usize].as_ref())?)?;
+        let op = Unique::new(None, true);
+        let a = model.wire_node("a", op.clone(), &[x])?;
+        let b = model.wire_node("b", op.clone(), &[x])?;
+        let c = model.wire_node("c", op.clone(), &b[0..1])?;
+        let len = |outlet: OutletId| model.outlet_fact(outlet).unwrap().shape.dim(0);
+        assert!(len(a[0]).is_data_dependent() && !len(a[0]).is_unassigned_data_dependent());
+        assert_ne!(len(a[0]), len(b[0]));
+        assert_eq!(len(b[0]), len(b[1]));
+        assert!(len(c[0]) != len(a[0]) && len(c[0]) != len(b[0]));
+        // a patch keeps the symbols of the ops it rewires, and draws new ones
+        // from the table of the patched model
+        let mut patch = TypedModelPatch::default();
+        let tap = patch.tap_model(&model, x)?;
+        let d = patch.wire_node("d", model.node(a[0].node).op.clone(), &[tap])?;
+        let e = patch.wire_node("e", op, &[tap])?;
+        let len_in_patch = |outlet: OutletId| patch.outlet_fact(outlet).unwrap().shape.dim(0);
+        assert_eq!(len_in_patch(d[0]), len(a[0]));
+        assert!([a[0], b[0], c[0]].iter().all(|o| len(*o) != len_in_patch(e[0])));
+        Ok(())
+    }
+
+    #[test]
+    fn unique_rows() {
+        let input = tensor2(&[[1i32, 1], [0, 1], [1, 1]]);
+        let output = Unique::new(Some(0), true).eval(tvec!(input.into())).unwrap();
+        assert_eq!(*output[0], tensor2(&[[0i32, 1], [1, 1]]));
+        assert_eq!(*output[2], tensor1(&[1i64, 0, 1]));
+        assert_eq!(*output[3], tensor1(&[1i64, 2]));
+    }
+}
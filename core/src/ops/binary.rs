@@ -97,6 +97,8 @@ impl StatelessOp for InferenceBinOp {
 #[derive(Debug, Clone, Hash)]
 pub struct TypedBinOp(pub Box<dyn BinMiniOp>);
 
+impl_native_codec!(TypedBinOp { 0 });
+
 impl Op for TypedBinOp {
     fn name(&self) -> Cow<str> {
         format!("{}TypedBinOp", self.0.name()).into()
@@ -237,6 +239,7 @@ impl TypedOp for TypedBinOp {
         pulsify_bin(node, self, target, mapping)
     }
 
+    native_save!("TypedBinOp");
     as_op!();
 }
 
@@ -259,25 +262,7 @@ fn pulsify_bin(
     target: &mut PulsedModel,
     mapping: &HashMap<OutletId, OutletId>,
 ) -> TractResult<TVec<OutletId>> {
-    use crate::pulse::delay::Delay;
-    let delay = (0..2)
-        .map(|ix| target.outlet_fact(mapping[&node.inputs[ix]]).unwrap().delay)
-        .max()
-        .unwrap();
-    let mut inputs = tvec!();
-    for ix in 0..2 {
-        let mut input = mapping[&node.inputs[ix]];
-        let fact = target.outlet_fact(input)?.clone();
-        if fact.delay < delay {
-            let add_delay = delay - fact.delay;
-            input = target.wire_node(
-                format!("{}/Delay", &*node.name),
-                Delay::new(&fact, add_delay, 0),
-                &[input],
-            )?[0];
-        }
-        inputs.push(input);
-    }
+    let inputs = crate::pulse::sync_inputs(node, target, mapping)?;
     target.wire_node(&*node.name, dyn_clone::clone_box(op), &*inputs)
 }
 
@@ -287,6 +272,8 @@ pub struct UnaryOp {
     pub a: Arc<Tensor>,
 }
 
+impl_native_codec!(UnaryOp { mini_op, a });
+
 impl Op for UnaryOp {
     fn name(&self) -> Cow<str> {
         format!("{}Unary", self.mini_op.name()).into()
@@ -423,6 +410,7 @@ impl TypedOp for UnaryOp {
         target.wire_node(&*node.name, self.clone(), &[input])
     }
 
+    native_save!("UnaryOp");
     as_op!();
 }
 
@@ -443,6 +431,8 @@ impl PulsedOp for UnaryOp {
 #[derive(Debug, Clone, Hash)]
 pub struct MergeOp(pub Box<dyn BinMiniOp>);
 
+impl_native_codec!(MergeOp { 0 });
+
 impl Op for MergeOp {
     fn name(&self) -> Cow<str> {
         format!("{}Merge", self.0.name()).into()
@@ -589,6 +579,7 @@ impl TypedOp for MergeOp {
         pulsify_bin(node, self, target, mapping)
     }
 
+    native_save!("MergeOp");
     as_op!();
 }
 
@@ -608,6 +599,8 @@ impl PulsedOp for MergeOp {
 #[derive(Debug, Clone, Hash)]
 pub struct MergeOpUnicast(pub Box<dyn BinMiniOp>);
 
+impl_native_codec!(MergeOpUnicast { 0 });
+
 impl Op for MergeOpUnicast {
     fn name(&self) -> Cow<str> {
         format!("{}MergeUnicast", self.0.name()).into()
@@ -641,6 +634,7 @@ impl TypedOp for MergeOpUnicast {
             .collect())
     }
 
+    native_save!("MergeOpUnicast");
     as_op!();
 }
 
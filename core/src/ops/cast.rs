@@ -10,6 +10,8 @@ pub struct Cast {
     to: DatumType,
 }
 
+impl_native_codec!(Cast { to });
+
 impl ElementWiseMiniOp for Cast {
     fn name(&self) -> String {
         "Cast".into()
@@ -34,4 +36,9 @@ impl ElementWiseMiniOp for Cast {
             Ok(None)
         }
     }
+
+    fn save(&self, encoder: &mut Encoder) -> TractResult<Option<&'static str>> {
+        Encode::encode(self, encoder)?;
+        Ok(Some("Cast"))
+    }
 }
@@ -8,7 +8,10 @@ pub enum InOut {
 }
 
 impl InOut {
-    pub fn as_outlet<F: Clone + Fact + Hash, O: Clone + DynHash>(&self, node: &BaseNode<F, O>) -> OutletId {
+    pub fn as_outlet<F: Clone + Fact + Hash, O: Clone + DynHash>(
+        &self,
+        node: &BaseNode<F, O>,
+    ) -> OutletId {
         match self {
             InOut::In(ix) => node.inputs[*ix],
             InOut::Out(ix) => OutletId::new(node.id, *ix),
@@ -23,6 +26,35 @@ pub enum AxisOp {
     Permute(TVec<usize>),
 }
 
+impl Encode for AxisOp {
+    fn encode(&self, encoder: &mut Encoder) -> TractResult<()> {
+        match self {
+            AxisOp::Add(axis) => {
+                0u8.encode(encoder)?;
+                axis.encode(encoder)
+            }
+            AxisOp::Rm(axis) => {
+                1u8.encode(encoder)?;
+                axis.encode(encoder)
+            }
+            AxisOp::Permute(axes) => {
+                2u8.encode(encoder)?;
+                axes.encode(encoder)
+            }
+        }
+    }
+}
+
+impl Decode for AxisOp {
+    fn decode(decoder: &mut Decoder) -> TractResult<Self> {
+        Ok(match u8::decode(decoder)? {
+            0 => AxisOp::Add(usize::decode(decoder)?),
+            1 => AxisOp::Rm(usize::decode(decoder)?),
+            2 => AxisOp::Permute(TVec::decode(decoder)?),
+            code => bail!("Invalid AxisOp code {}", code),
+        })
+    }
+}
 impl AxisOp {
     pub fn transform_axis(&self, axis: usize) -> Option<usize> {
         match self {
@@ -93,13 +125,10 @@ impl AxisOp {
             AxisOp::Add(ix) => shape.insert_axis(*ix),
             AxisOp::Rm(ix) => shape.remove_axis(*ix),
             AxisOp::Permute(perm) => {
-                let orig = shape.clone();
-                for (ix, &from) in perm.iter().enumerate() {
-                    shape.set_dim(ix, orig.dim(from).to_integer().unwrap_or(1).to_dim())?;
-                }
-                if let Some(info) = orig.stream_info {
-                    shape.set_dim(perm.iter().position(|&i| i == info.axis).unwrap(), info.len)?;
-                }
+                let orig = shape.to_tvec();
+                *shape = ShapeFact::from_dims(
+                    perm.iter().map(|&from| orig[from].clone()).collect::<TVec<_>>(),
+                )?;
                 Ok(())
             }
         }
@@ -202,6 +231,7 @@ impl StatelessOp for AxisOp {
 }
 
 impl TypedOp for AxisOp {
+    native_save!("AxisOp");
     as_op!();
 
     fn output_facts(&self, inputs: &[&TypedFact]) -> TractResult<TVec<TypedFact>> {
@@ -360,7 +390,8 @@ pub fn change_axes(
         if let Some(new_op) = changed_ops.remove(&node_id) {
             model.node_mut(node_id).op = new_op;
         }
-        let output_facts = model.node(node_id).op.output_facts(&model.node_input_facts(node_id)?)?;
+        let output_facts =
+            model.node(node_id).op.output_facts(&model.node_input_facts(node_id)?)?;
         for (ix, f) in output_facts.into_iter().enumerate() {
             model.set_outlet_fact(OutletId::new(node_id, ix), f)?;
         }
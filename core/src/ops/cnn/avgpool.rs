@@ -12,6 +12,8 @@ pub struct AvgPool {
     pub count_include_pad: bool,
 }
 
+impl_native_codec!(AvgPool { pool_spec, count_include_pad });
+
 impl AvgPool {
     fn to_fixed(
         &self,
@@ -79,6 +81,7 @@ impl TypedOp for AvgPool {
         Ok(None)
     }
 
+    native_save!("AvgPool");
     as_op!();
 }
 
@@ -83,9 +83,17 @@ impl<T: Copy + Datum + Zero> Im2Col<T> {
     }
 
     pub(super) fn im2col<'i>(&'i self, input: &'i ArrayViewD<'i, T>) -> TractResult<Tensor> {
-        let mut packed = unsafe {
+        let packed = unsafe {
             Tensor::uninitialized_aligned::<T>(&*self.output_shape.shape, self.b_pack.alignment())?
         };
+        self.im2col_into(input, packed)
+    }
+
+    fn im2col_into<'i>(
+        &'i self,
+        input: &'i ArrayViewD<'i, T>,
+        mut packed: Tensor,
+    ) -> TractResult<Tensor> {
         let pad_value = *self.pad_value.to_scalar()?;
         for i in 0..*self.input_shape.n_dim().unwrap_or(&1) {
             for g in 0..self.group {
@@ -128,6 +136,23 @@ impl<T: Copy + Datum + Zero> StatelessOp for Im2Col<T> {
         let tensor = self.im2col(&inputs[0].to_array_view()?)?;
         Ok(tvec!(tensor.into()))
     }
+
+    fn eval_with_buffers(
+        &self,
+        session: &SessionState,
+        inputs: TVec<Arc<Tensor>>,
+        buffers: &mut TVec<Option<Tensor>>,
+    ) -> TractResult<TVec<Arc<Tensor>>> {
+        let aligned = |t: &Tensor| {
+            t.as_ptr::<T>().ok().map(|p| p as usize % self.b_pack.alignment() == 0).unwrap_or(false)
+        };
+        if !buffers[0].as_ref().map(aligned).unwrap_or(false) {
+            return self.eval_in_session(session, inputs);
+        }
+        let packed = buffers[0].take().unwrap();
+        let tensor = self.im2col_into(&inputs[0].to_array_view()?, packed)?;
+        Ok(tvec!(tensor.into()))
+    }
 }
 
 impl<T: Copy + Datum + Zero> TypedOp for Im2Col<T> {
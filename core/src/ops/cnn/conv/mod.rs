@@ -1,3 +1,5 @@
+use crate::internal::*;
+
 mod depth_wise;
 mod im2col;
 mod unary;
@@ -11,6 +13,25 @@ pub enum KernelFormat {
     HWIO,
 }
 
+impl Encode for KernelFormat {
+    fn encode(&self, encoder: &mut Encoder) -> TractResult<()> {
+        let code: u8 = match self {
+            KernelFormat::OIHW => 0,
+            KernelFormat::HWIO => 1,
+        };
+        code.encode(encoder)
+    }
+}
+
+impl Decode for KernelFormat {
+    fn decode(decoder: &mut Decoder) -> TractResult<Self> {
+        Ok(match u8::decode(decoder)? {
+            0 => KernelFormat::OIHW,
+            1 => KernelFormat::HWIO,
+            code => bail!("Invalid KernelFormat code {}", code),
+        })
+    }
+}
 impl Default for KernelFormat {
     fn default() -> KernelFormat {
         KernelFormat::OIHW
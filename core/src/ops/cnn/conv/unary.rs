@@ -35,6 +35,8 @@ pub struct ConvUnary {
     pub q_params: Option<QParams>,
 }
 
+impl_native_codec!(ConvUnary { pool_spec, kernel_fmt, kernel, group, bias, q_params });
+
 impl ConvUnary {
     fn input_channels(&self) -> usize {
         match self.kernel_fmt {
@@ -101,28 +103,32 @@ impl ConvUnary {
         Ok(packed_as.insert_axis(Axis(0)))
     }
 
+    /// Scale factors the fixed point requantization of the matmul can not
+    /// apply: per channel factors, or a factor of at least 1.
+    fn float_requantization(&self) -> Option<&Arc<Tensor>> {
+        self.q_params
+            .as_ref()
+            .and_then(|q| q.scale_factor.as_ref())
+            .filter(|s| s.rank() == 1 || s.to_scalar::<f32>().map(|&f| f >= 1.0).unwrap_or(false))
+    }
+
     fn bias_as_non_linear<T>(&self) -> TractResult<Option<ArrayD<Vec<FusedSpec<T>>>>>
     where
         T: Datum + Copy,
     {
-        use crate::itertools::Itertools;
+        let per_group = self.output_channels() / self.group;
+        let mut ops: Vec<Vec<FusedSpec<T>>> = vec![vec![]; self.group];
         if let Some(bias) = &self.bias {
             let bias = bias.cast_to::<T>()?;
             let bias = bias.as_slice::<T>()?;
-            Ok(Some(
-                Array2::from_shape_vec(
-                    (1, self.group),
-                    bias.iter()
-                        .chunks(self.output_channels() / self.group)
-                        .into_iter()
-                        .map(|c| vec![FusedSpec::PerRowAdd(c.into_iter().cloned().collect())])
-                        .collect(),
-                )?
-                .into_dyn(),
-            ))
-        } else {
-            Ok(None)
+            for (ops, c) in ops.iter_mut().zip(bias.chunks(per_group)) {
+                ops.push(FusedSpec::PerRowAdd(c.to_vec()));
+            }
+        }
+        if ops.iter().all(|ops| ops.is_empty()) {
+            return Ok(None);
         }
+        Ok(Some(Array2::from_shape_vec((1, self.group), ops)?.into_dyn()))
     }
 
     pub unsafe fn wire_as_im2col_pair(
@@ -138,6 +144,16 @@ impl ConvUnary {
             return self.wire_as_im2col_pair_t(model, name, wire, direct, &|m, k, n| {
                 MMMWrapper::Plain((tract_linalg::ops().mmm_f32)(m, k, n))
             });
+        } else if self.float_requantization().is_some() {
+            if (a, b) == (u8::datum_type(), u8::datum_type()) {
+                return self.wire_as_im2col_pair_t(model, name, wire, direct, &|m, k, n| {
+                    MMMWrapper::Quant((tract_linalg::ops().qmmm_u8_i32)(m, k, n))
+                });
+            } else if (a, b) == (i8::datum_type(), i8::datum_type()) {
+                return self.wire_as_im2col_pair_t(model, name, wire, direct, &|m, k, n| {
+                    MMMWrapper::Quant((tract_linalg::ops().qmmm_i8_i32)(m, k, n))
+                });
+            }
         } else if (a, b) == (u8::datum_type(), u8::datum_type()) {
             return self.wire_as_im2col_pair_t(model, name, wire, direct, &|m, k, n| {
                 MMMWrapper::Quant((tract_linalg::ops().qmmm_u8_i32)(m, k, n))
@@ -191,7 +207,15 @@ impl ConvUnary {
         mmm.as_mmm_mut().c_from_data_and_strides(rsc, csc);
 
         if let Some(q) = self.q_params.as_ref() {
-            mmm.set_quant_params(q)?;
+            if self.float_requantization().is_some() {
+                mmm.set_quant_params(&QParams {
+                    scale_factor: None,
+                    zero_point_c: None,
+                    ..q.clone()
+                })?;
+            } else {
+                mmm.set_quant_params(q)?;
+            }
         }
 
         trace!(
@@ -265,6 +289,25 @@ impl ConvUnary {
             &[wire],
         )?[0];
 
+        if let (Some(q), Some(scales)) = (&self.q_params, self.float_requantization()) {
+            let zero_point = q
+                .zero_point_c
+                .as_ref()
+                .map(|zp| zp.cast_to_scalar::<i32>())
+                .transpose()?
+                .unwrap_or(0);
+            wire = model.wire_node(
+                format!("{}-requant", name),
+                crate::ops::quant::RequantizeF32::new(
+                    output_shape.c_axis(),
+                    scales.clone(),
+                    zero_point,
+                    q.c_datum_type,
+                ),
+                &[wire],
+            )?[0];
+        }
+
         Ok(wire)
     }
 
@@ -351,6 +394,10 @@ impl ConvUnary {
                 self.q_params.as_ref().map(|qp| qp.c_datum_type).unwrap_or(input_fact.datum_type);
             let must_split_quant =
                 self.bias.is_some() && self.bias.as_ref().unwrap().datum_type() != output_type;
+            if self.float_requantization().is_some() {
+                // the matmul can not requantize it, leave it to codegen
+                return Ok(None);
+            }
             let q_params = if must_split_quant {
                 Some(QParams {
                     c_datum_type: self.bias.as_ref().unwrap().datum_type(),
@@ -376,7 +423,12 @@ impl ConvUnary {
             if must_split_quant {
                 use crate::ops::quant::*;
                 let qp = self.q_params.as_ref().unwrap();
-                let scale = qp.scale_factor.unwrap_or(1.0);
+                let scale = qp
+                    .scale_factor
+                    .as_ref()
+                    .map(|s| s.to_scalar::<f32>().map(|&x| x))
+                    .transpose()?
+                    .unwrap_or(1.0);
                 let op = match output_type {
                     DatumType::I8 => quantize_linear_i8(
                         scale,
@@ -645,10 +697,13 @@ impl TypedOp for ConvUnary {
         if let Some(shape) = input_fact.shape.as_finite() {
             unsafe {
                 let dt = input_fact.datum_type;
+                // bias is added after the matmul, too late for a requantized output
                 if kernel_spatial_shape.iter().product::<usize>() == 1
                     && (0..spatial_rank)
                         .all(|i| self.pool_spec.stride(i) == 1 && self.pool_spec.dilation(i) == 1)
                     && self.group == 1
+                    && (self.q_params.is_none() || self.bias.is_none())
+                    && self.float_requantization().is_none()
                 {
                     use crate::ops::matmul::MatMulUnary;
                     let mut patch = TypedModelPatch::default();
@@ -707,7 +762,10 @@ impl TypedOp for ConvUnary {
                     let wire = self.wire_as_im2col_pair(&mut patch, &*node.name, wire, true)?;
                     patch.shunt_outside(model, OutletId::new(node.id, 0), wire)?;
                     return Ok(Some(patch));
-                } else if self.group != 1 && self.group == self.output_channels() {
+                } else if self.group != 1
+                    && self.group == self.output_channels()
+                    && self.q_params.is_none()
+                {
                     return Ok(Some(TypedModelPatch::single_unary_op(
                         model,
                         node,
@@ -725,6 +783,7 @@ impl TypedOp for ConvUnary {
         Ok(None)
     }
 
+    native_save!("ConvUnary");
     as_op!();
 }
 
@@ -736,3 +795,144 @@ impl PulsedOp for ConvUnary {
     as_op!();
     pulsed_op_to_typed_op!();
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ops::cnn::PaddingSpec;
+
+    // onnxruntime requantizes in float and rounds ties to even
+    fn requantize(acc: i32, scale: f32) -> i8 {
+        let x = acc as f32 * scale;
+        let r = x.round();
+        let r = if (r - x).abs() == 0.5 && r % 2.0 != 0.0 { r - x.signum() } else { r };
+        r.max(-128.0).min(127.0) as i8
+    }
+
+    fn per_channel_requantization(group: usize) -> TractResult<()> {
+        let scales = [0.5f32, 0.1, 0.03, 0.25];
+        let (ci, co) = (2, scales.len());
+        let kernel =
+            Array3::from_shape_fn((co, ci / group, 2), |(o, i, x)| (o * 3 + i * 2 + x) as i8 - 5);
+        let input = Array3::from_shape_fn((1, ci, 5), |(_, c, x)| (c * 5 + x) as i8 * 3 - 10);
+        let pool_spec = PoolSpec {
+            data_format: DataFormat::NCHW,
+            kernel_shape: tvec!(2),
+            padding: PaddingSpec::Valid,
+            dilations: None,
+            strides: None,
+            output_channel_override: Some(co),
+        };
+        let q_params = QParams::new(i8::datum_type()).with_scale_factors(&rctensor1(&scales))?;
+        let op = ConvUnary::new(
+            pool_spec,
+            KernelFormat::OIHW,
+            kernel.clone().into_arc_tensor(),
+            group,
+            None,
+            Some(q_params),
+        );
+        let found = op.eval(tvec!(input.clone().into_arc_tensor()))?.remove(0);
+        let expected = Array3::from_shape_fn((1, co, 4), |(_, o, x)| {
+            let g = o / (co / group);
+            let acc: i32 = (0..ci / group)
+                .flat_map(|i| (0..2).map(move |k| (i, k)))
+                .map(|(i, k)| {
+                    kernel[(o, i, k)] as i32 * input[(0, g * ci / group + i, x + k)] as i32
+                })
+                .sum();
+            requantize(acc, scales[o])
+        });
+        assert_eq!(*found, expected.into_tensor());
+        Ok(())
+    }
+
+    #[test]
+    fn per_channel_requantization_with_i32_bias_1x1() -> TractResult<()> {
+        let scales = [0.5f32, 0.1, 0.03];
+        let bias = [-20i32, 7, 100];
+        let (ci, co) = (2, scales.len());
+        let kernel = Array3::from_shape_fn((co, ci, 1), |(o, i, _)| (o * 3 + i * 2) as i8 - 5);
+        let input = Array3::from_shape_fn((1, ci, 5), |(_, c, x)| (c * 5 + x) as i8 * 3 - 10);
+        let pool_spec = PoolSpec {
+            data_format: DataFormat::NCHW,
+            kernel_shape: tvec!(1),
+            padding: PaddingSpec::Valid,
+            dilations: None,
+            strides: None,
+            output_channel_override: Some(co),
+        };
+        let q_params = QParams::new(i8::datum_type()).with_scale_factors(&rctensor1(&scales))?;
+        let op = ConvUnary::new(
+            pool_spec,
+            KernelFormat::OIHW,
+            kernel.clone().into_arc_tensor(),
+            1,
+            Some(rctensor1(&bias)),
+            Some(q_params),
+        );
+        let mut model = TypedModel::default();
+        let source = model
+            .add_source("source", TypedFact::dt_shape(i8::datum_type(), [1, 2, 5].as_ref())?)?;
+        let conv = model.wire_node("conv", op, &[source])?;
+        model.set_output_outlets(&conv)?;
+        let model = model.declutter()?;
+        let found = SimplePlan::new(model)?.run(tvec!(input.clone().into_tensor()))?.remove(0);
+        let expected = Array3::from_shape_fn((1, co, 5), |(_, o, x)| {
+            let acc: i32 =
+                (0..ci).map(|i| kernel[(o, i, 0)] as i32 * input[(0, i, x)] as i32).sum::<i32>()
+                    + bias[o];
+            requantize(acc, scales[o])
+        });
+        assert_eq!(*found, expected.into_tensor());
+        Ok(())
+    }
+
+    #[test]
+    fn scale_factor_above_one_1x1() -> TractResult<()> {
+        let kernel = Array3::from_shape_fn((2, 2, 1), |(o, i, _)| (o * 3 + i * 2) as i8 - 2);
+        let input = Array3::from_shape_fn((1, 2, 5), |(_, c, x)| (c * 5 + x) as i8 - 4);
+        let pool_spec = PoolSpec {
+            data_format: DataFormat::NCHW,
+            kernel_shape: tvec!(1),
+            padding: PaddingSpec::Valid,
+            dilations: None,
+            strides: None,
+            output_channel_override: Some(2),
+        };
+        let q_params = QParams::new(i8::datum_type())
+            .with_scale_factor(1.5)
+            .with_zero_point_c(&rctensor0(3i8));
+        let op = ConvUnary::new(
+            pool_spec,
+            KernelFormat::OIHW,
+            kernel.clone().into_arc_tensor(),
+            1,
+            None,
+            Some(q_params),
+        );
+        let mut model = TypedModel::default();
+        let source = model
+            .add_source("source", TypedFact::dt_shape(i8::datum_type(), [1, 2, 5].as_ref())?)?;
+        let conv = model.wire_node("conv", op, &[source])?;
+        model.set_output_outlets(&conv)?;
+        let model = model.into_optimized()?;
+        let found = SimplePlan::new(model)?.run(tvec!(input.clone().into_tensor()))?.remove(0);
+        let expected = Array3::from_shape_fn((1, 2, 5), |(_, o, x)| {
+            let acc: i32 = (0..2).map(|i| kernel[(o, i, 0)] as i32 * input[(0, i, x)] as i32).sum();
+            requantize(acc, 1.5).saturating_add(3)
+        });
+        assert_eq!(*found, expected.into_tensor());
+        Ok(())
+    }
+
+    #[test]
+    fn per_channel_requantization_1_group() -> TractResult<()> {
+        per_channel_requantization(1)
+    }
+
+    #[test]
+    fn per_channel_requantization_2_groups() -> TractResult<()> {
+        per_channel_requantization(2)
+    }
+}
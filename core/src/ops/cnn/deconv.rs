@@ -0,0 +1,582 @@
+use ndarray::*;
+use num_traits::Float;
+
+use crate::internal::*;
+use crate::ops::cnn::{KernelFormat, PaddingSpec, PatchSpec, PoolSpec};
+use crate::ops::nn::DataFormat;
+
+/// Padding before and after, and adjustment, of a spatial axis of a
+/// deconvolution.
+pub fn explicit_padding(
+    pool_spec: &PoolSpec,
+    adjustments: &[usize],
+    geo_axis: usize,
+) -> (usize, usize, usize) {
+    let stride = pool_spec.stride(geo_axis);
+    let dilation = pool_spec.dilation(geo_axis);
+    let kernel_field = (pool_spec.kernel_shape[geo_axis] - 1) * dilation + 1;
+    let adjustment = adjustments.get(geo_axis).cloned().unwrap_or(0);
+    match &pool_spec.padding {
+        PaddingSpec::Valid => (0, 0, adjustment),
+        PaddingSpec::Explicit(before, after) => (before[geo_axis], after[geo_axis], adjustment),
+        same => {
+            let padding = same.compute_one(
+                geo_axis,
+                &stride,
+                pool_spec.kernel_shape[geo_axis],
+                dilation,
+                stride,
+            );
+            (padding.pad_before, padding.pad_after, stride.saturating_sub(kernel_field))
+        }
+    }
+}
+
+/// Length of a spatial axis of the output of a deconvolution, given the
+/// input one.
+pub fn output_spatial_dim<D: DimLike>(
+    pool_spec: &PoolSpec,
+    adjustments: &[usize],
+    geo_axis: usize,
+    input: &D,
+) -> D {
+    let stride = pool_spec.stride(geo_axis);
+    let kernel_field = (pool_spec.kernel_shape[geo_axis] - 1) * pool_spec.dilation(geo_axis) + 1;
+    let (before, after, adjustment) = explicit_padding(pool_spec, adjustments, geo_axis);
+    input.clone() * stride + (kernel_field + adjustment) - (stride + before + after)
+}
+
+pub fn output_shape<D: DimLike>(
+    pool_spec: &PoolSpec,
+    adjustments: &[usize],
+    input_full_shape: &[D],
+    output_channels: usize,
+) -> TractResult<TVec<D>> {
+    let ishape = pool_spec.data_format.shape(input_full_shape)?;
+    let spatial: TVec<D> = ishape
+        .hw_dims()
+        .iter()
+        .enumerate()
+        .map(|(geo_axis, d)| output_spatial_dim(pool_spec, adjustments, geo_axis, d))
+        .collect();
+    Ok(pool_spec
+        .data_format
+        .from_n_c_hw(ishape.n().cloned().unwrap_or(1.into()), output_channels.into(), spatial)?
+        .shape)
+}
+
+/// Transposed convolution (aka deconvolution, or convolution backprop).
+///
+/// The pool spec and kernel are the ones of the forward convolution this op
+/// is the transpose of: this convolution maps the output of the
+/// deconvolution back to its input. In OIHW, the kernel is then
+/// `[input channels, output channels / group, spatial...]`, and in HWIO
+/// `[spatial..., output channels / group, input channels]`.
+///
+/// `adjustments` adds frames at the end of each spatial axis (ONNX
+/// `output_padding`), and must be smaller than the stride. It is ignored
+/// with the Same padding modes, where the output is the input times the
+/// stride.
+#[derive(Debug, Clone, new, Hash)]
+pub struct DeconvUnary {
+    pub pool_spec: PoolSpec,
+    pub kernel_format: KernelFormat,
+    pub kernel: Arc<Tensor>,
+    pub group: usize,
+    pub bias: Option<Arc<Tensor>>,
+    pub adjustments: TVec<usize>,
+}
+
+impl_native_codec!(DeconvUnary { pool_spec, kernel_format, kernel, group, bias, adjustments });
+
+impl DeconvUnary {
+    fn input_channels(&self) -> usize {
+        match self.kernel_format {
+            KernelFormat::OIHW => self.kernel.shape()[0],
+            KernelFormat::HWIO => self.kernel.shape()[self.kernel.rank() - 1],
+        }
+    }
+
+    pub fn output_channels(&self) -> usize {
+        let i = match self.kernel_format {
+            KernelFormat::OIHW => self.kernel.shape()[1],
+            KernelFormat::HWIO => self.kernel.shape()[self.kernel.rank() - 2],
+        };
+        i * self.group
+    }
+
+    pub fn explicit_padding(&self, geo_axis: usize) -> (usize, usize, usize) {
+        explicit_padding(&self.pool_spec, &self.adjustments, geo_axis)
+    }
+
+    pub fn output_spatial_dim<D: DimLike>(&self, geo_axis: usize, input: &D) -> D {
+        output_spatial_dim(&self.pool_spec, &self.adjustments, geo_axis, input)
+    }
+
+    pub fn output_shape<D: DimLike>(&self, input_full_shape: &[D]) -> TractResult<TVec<D>> {
+        output_shape(&self.pool_spec, &self.adjustments, input_full_shape, self.output_channels())
+    }
+
+    /// Kernel as (group, input channels / group, output channels / group * spatial).
+    fn kernel_as_group_i_ohw<T: Datum>(&self) -> TractResult<Array3<T>> {
+        let kernel = self.kernel.to_array_view::<T>()?;
+        let kernel = match self.kernel_format {
+            KernelFormat::OIHW => kernel,
+            KernelFormat::HWIO => {
+                let rank = kernel.ndim();
+                let mut permutation = vec![rank - 1, rank - 2];
+                permutation.extend(0..rank - 2);
+                kernel.permuted_axes(permutation)
+            }
+        };
+        let shape =
+            (self.group, self.input_channels() / self.group, kernel.len() / self.input_channels());
+        Ok(Array3::from_shape_vec(shape, kernel.iter().cloned().collect())?)
+    }
+
+    fn eval_t<T: Datum + Float + LinalgScalar>(&self, input: &Tensor) -> TractResult<Tensor> {
+        let data_format = self.pool_spec.data_format;
+        let input_shape = data_format.shape(input.shape())?;
+        let output_full_shape = self.output_shape(input.shape())?;
+        let output_shape = data_format.shape(&*output_full_shape)?;
+        let spatial_rank = input_shape.hw_rank();
+        for geo_axis in 0..spatial_rank {
+            if self.explicit_padding(geo_axis).2 >= self.pool_spec.stride(geo_axis) {
+                bail!("Deconvolution adjustments must be smaller than strides")
+            }
+        }
+
+        // the forward convolution maps the output onto the input
+        let (before, after): (TVec<usize>, TVec<usize>) =
+            (0..spatial_rank).map(|ax| self.explicit_padding(ax)).map(|p| (p.0, p.1)).unzip();
+        let mut single_channel_shape = tvec!(1);
+        single_channel_shape.extend(output_shape.hw_dims().iter().cloned());
+        let patch = PatchSpec::for_full_shape(DataFormat::CHW, &single_channel_shape)?
+            .with_kernel_shape(self.pool_spec.kernel_shape.clone())
+            .with_strides(self.pool_spec.strides.clone().unwrap_or(tvec!(1; spatial_rank)))
+            .with_dilations(self.pool_spec.dilations.clone().unwrap_or(tvec!(1; spatial_rank)))
+            .with_padding(PaddingSpec::Explicit(before, after))
+            .into_patch();
+        if &*patch.output_shape != input_shape.hw_dims() {
+            bail!(
+                "Inconsistent deconvolution geometry: input {:?}, output {:?}",
+                input.shape(),
+                output_full_shape
+            )
+        }
+
+        let mut input = input.to_array_view::<T>()?;
+        if input_shape.n_axis().is_none() {
+            input = input.insert_axis(Axis(0));
+        }
+        if input_shape.c_axis() == input_shape.rank() - 1 {
+            let rank = input.ndim();
+            let mut permutation = vec![0, rank - 1];
+            permutation.extend(1..rank - 1);
+            input = input.permuted_axes(permutation);
+        }
+        let n = input.shape()[0];
+        let x_len: usize = input_shape.hw_dims().iter().product();
+        let y_len: usize = output_shape.hw_dims().iter().product();
+        let kernel = self.kernel_as_group_i_ohw::<T>()?;
+        let kernel_len: usize = self.pool_spec.kernel_shape.iter().product();
+        let ci_per_group = self.input_channels() / self.group;
+        let co_per_group = self.output_channels() / self.group;
+        let mut output = Array3::<T>::zeros((n, self.output_channels(), y_len));
+        for i in 0..n {
+            for g in 0..self.group {
+                let x = input
+                    .index_axis(Axis(0), i)
+                    .slice_axis(Axis(0), (g * ci_per_group..(g + 1) * ci_per_group).into())
+                    .iter()
+                    .cloned()
+                    .collect::<Vec<T>>();
+                let x = Array2::from_shape_vec((ci_per_group, x_len), x)?;
+                let cols = kernel.index_axis(Axis(0), g).t().dot(&x);
+                let mut output = output.index_axis_mut(Axis(0), i);
+                let mut output = output
+                    .slice_axis_mut(Axis(0), (g * co_per_group..(g + 1) * co_per_group).into());
+                patch.visit_output(|scanner| {
+                    let x = scanner.output_offset as usize;
+                    for (k, y) in scanner.valid_offsets_with_indexes() {
+                        for co in 0..co_per_group {
+                            output[(co, y as usize)] =
+                                output[(co, y as usize)] + cols[(co * kernel_len + k, x)];
+                        }
+                    }
+                });
+            }
+        }
+        if let Some(bias) = &self.bias {
+            let bias = bias.cast_to::<T>()?;
+            for (mut channel, &b) in output.axis_iter_mut(Axis(1)).zip(bias.as_slice::<T>()?.iter())
+            {
+                channel.mapv_inplace(|v| v + b);
+            }
+        }
+
+        let mut nchw_shape: TVec<usize> = tvec!(n, self.output_channels());
+        nchw_shape.extend(output_shape.hw_dims().iter().cloned());
+        let mut output = output.into_shape(&*nchw_shape)?;
+        if output_shape.c_axis() == output_shape.rank() - 1 {
+            let rank = output.ndim();
+            let mut permutation = vec![0];
+            permutation.extend(2..rank);
+            permutation.push(1);
+            output = output.permuted_axes(permutation);
+        }
+        let output = Array::from_shape_vec(&*output_full_shape, output.iter().cloned().collect())?;
+        Ok(output.into_tensor())
+    }
+}
+
+impl Op for DeconvUnary {
+    fn name(&self) -> Cow<str> {
+        "DeconvUnary".into()
+    }
+
+    fn info(&self) -> TractResult<Vec<String>> {
+        let mut info = self.pool_spec.info();
+        info.push(format!(
+            "Kernel shape, {:?}: {:?} (groups:{}, adjustments:{:?})",
+            self.kernel_format,
+            self.kernel.shape(),
+            self.group,
+            self.adjustments
+        ));
+        if let Some(b) = &self.bias {
+            info.push(format!("Bias: {:?}", b))
+        }
+        Ok(info)
+    }
+
+    canonic!();
+    op_as_typed_op!();
+    op_as_pulsed_op!();
+}
+
+impl StatelessOp for DeconvUnary {
+    fn eval(&self, inputs: TVec<Arc<Tensor>>) -> TractResult<TVec<Arc<Tensor>>> {
+        let output = match inputs[0].datum_type() {
+            DatumType::F32 => self.eval_t::<f32>(&inputs[0])?,
+            DatumType::F64 => self.eval_t::<f64>(&inputs[0])?,
+            dt => bail!("Deconvolution is not implemented for {:?}", dt),
+        };
+        Ok(tvec!(output.into_arc_tensor()))
+    }
+}
+
+impl TypedOp for DeconvUnary {
+    fn output_facts(&self, inputs: &[&TypedFact]) -> TractResult<TVec<TypedFact>> {
+        let shape = self.output_shape(&*inputs[0].shape.to_tvec())?;
+        Ok(tvec!(TypedFact::dt_shape(inputs[0].datum_type, &*shape)?))
+    }
+
+    fn pulsify(
+        &self,
+        _source: &NormalizedModel,
+        node: &NormalizedNode,
+        target: &mut PulsedModel,
+        mapping: &HashMap<OutletId, OutletId>,
+        _pulse: usize,
+    ) -> TractResult<TVec<OutletId>> {
+        let input = mapping[&node.inputs[0]];
+        let fact = target.outlet_fact(input)?.clone();
+        let shape = self.pool_spec.data_format.shape(&*fact.shape)?;
+        if Some(fact.axis) == shape.n_axis() {
+            return target.wire_node(&*node.name, self.clone(), &[input]);
+        } else if fact.axis == shape.c_axis() {
+            bail!("Can not pulsify deconvolution along the input channel axis");
+        }
+        let geo_axis = fact.axis - shape.h_axis();
+        let spatial_rank = shape.hw_rank();
+        let (mut before, mut after, mut adjustments) = (tvec!(), tvec!(), tvec!());
+        for ax in 0..spatial_rank {
+            let (b, a, adj) = if ax == geo_axis { (0, 0, 0) } else { self.explicit_padding(ax) };
+            before.push(b);
+            after.push(a);
+            adjustments.push(adj);
+        }
+        let deconv = DeconvUnary {
+            pool_spec: PoolSpec {
+                padding: PaddingSpec::Explicit(before, after),
+                ..self.pool_spec.clone()
+            },
+            bias: None,
+            adjustments,
+            ..self.clone()
+        };
+        let stride = self.pool_spec.stride(geo_axis);
+        let kernel_field =
+            (self.pool_spec.kernel_shape[geo_axis] - 1) * self.pool_spec.dilation(geo_axis) + 1;
+        let op = PulsedDeconv {
+            deconv,
+            axis: fact.axis,
+            stride,
+            overlap: kernel_field.saturating_sub(stride),
+            input_delay: fact.delay,
+            end_input: fact.delay.to_dim() + &fact.dim,
+            output_pulse: fact.pulse() * stride,
+            output_delay: fact.delay * stride + self.explicit_padding(geo_axis).0,
+            output_dim: self.output_spatial_dim(geo_axis, &fact.dim),
+            bias: self.bias.clone(),
+        };
+        target.wire_node(&*node.name, op, &[input])
+    }
+
+    native_save!("DeconvUnary");
+    as_op!();
+}
+
+impl PulsedOp for DeconvUnary {
+    fn pulsed_output_facts(&self, inputs: &[&PulsedFact]) -> TractResult<TVec<PulsedFact>> {
+        let mut fact = inputs[0].clone();
+        fact.shape = self.output_shape(&*fact.shape)?;
+        Ok(tvec!(fact))
+    }
+
+    as_op!();
+    pulsed_op_to_typed_op!();
+}
+
+/// Deconvolution along a streaming axis.
+///
+/// Each pulse is deconvolved without padding, so it spreads over the next
+/// pulse by `overlap` frames: these are kept in the state and summed with the
+/// head of the next pulse output. The bias is added once the frames are
+/// complete.
+#[derive(Debug, Clone, Hash)]
+pub(crate) struct PulsedDeconv {
+    deconv: DeconvUnary,
+    axis: usize,
+    stride: usize,
+    overlap: usize,
+    input_delay: usize,
+    end_input: TDim,
+    output_pulse: usize,
+    output_delay: usize,
+    output_dim: TDim,
+    bias: Option<Arc<Tensor>>,
+}
+
+impl_native_codec!(PulsedDeconv {
+    deconv,
+    axis,
+    stride,
+    overlap,
+    input_delay,
+    end_input,
+    output_pulse,
+    output_delay,
+    output_dim,
+    bias
+});
+
+impl Op for PulsedDeconv {
+    fn name(&self) -> Cow<str> {
+        "PulsedDeconv".into()
+    }
+
+    fn info(&self) -> TractResult<Vec<String>> {
+        let mut info = self.deconv.info()?;
+        info.push(format!("Axis: {} overlap: {}", self.axis, self.overlap));
+        Ok(info)
+    }
+
+    canonic!();
+    op_as_typed_op!();
+    op_as_pulsed_op!();
+}
+
+impl StatefullOp for PulsedDeconv {
+    fn state(
+        &self,
+        _session: &mut SessionState,
+        _node_id: usize,
+    ) -> TractResult<Option<Box<dyn OpState>>> {
+        Ok(Some(Box::new(PulsedDeconvState::default())))
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+struct PulsedDeconvState {
+    current_pos: usize,
+    overlap: Option<Tensor>,
+}
+
+impl OpState for PulsedDeconvState {
+    fn eval(
+        &mut self,
+        session: &mut SessionState,
+        op: &dyn Op,
+        mut inputs: TVec<Arc<Tensor>>,
+    ) -> TractResult<TVec<Arc<Tensor>>> {
+        let input = args_1!(inputs).into_tensor();
+        let op = op.downcast_ref::<PulsedDeconv>().ok_or("Wrong Op type")?;
+        let output = match input.datum_type() {
+            DatumType::F32 => self.eval_t::<f32>(session, op, input)?,
+            DatumType::F64 => self.eval_t::<f64>(session, op, input)?,
+            dt => bail!("Deconvolution is not implemented for {:?}", dt),
+        };
+        Ok(tvec!(output.into_arc_tensor()))
+    }
+}
+
+impl PulsedDeconvState {
+    fn eval_t<T: Datum + Float + LinalgScalar>(
+        &mut self,
+        session: &SessionState,
+        op: &PulsedDeconv,
+        input: Tensor,
+    ) -> TractResult<Tensor> {
+        let axis = Axis(op.axis);
+        let mut input = input.into_array::<T>()?;
+        let pulse = input.shape()[op.axis];
+        // frames out of the stream are garbage, and would spread to valid frames
+        if self.current_pos < op.input_delay {
+            let garbage = (op.input_delay - self.current_pos).min(pulse);
+            input.slice_axis_mut(axis, (0..garbage).into()).fill(T::zero());
+        }
+        if let Some(len) = session.known_stream_len {
+            let end_input =
+                op.end_input.eval(len as i32).ok_or("Can not evaluate end of stream")? as usize;
+            if self.current_pos + pulse > end_input {
+                let valid = end_input.saturating_sub(self.current_pos);
+                input.slice_axis_mut(axis, (valid..).into()).fill(T::zero());
+            }
+        }
+        self.current_pos += pulse;
+
+        let output = op.deconv.eval_t::<T>(&input.into_tensor())?;
+        let mut output = output.into_array::<T>()?;
+        let len = output.shape()[op.axis];
+        if len < op.output_pulse + op.overlap {
+            let mut shape = output.shape().to_vec();
+            shape[op.axis] = op.output_pulse + op.overlap - len;
+            output = stack(axis, &[output.view(), ArrayD::zeros(shape).view()])?;
+        }
+        if let Some(previous) = self.overlap.take() {
+            let mut head = output.slice_axis_mut(axis, (0..op.overlap).into());
+            head.zip_mut_with(&previous.to_array_view::<T>()?, |h, &p| *h = *h + p);
+        }
+        if op.overlap > 0 {
+            let tail = output.slice_axis(axis, (op.output_pulse..).into());
+            self.overlap = Some(tail.to_owned().into_tensor());
+        }
+        let mut output = output.slice_axis(axis, (0..op.output_pulse).into()).to_owned();
+        if let Some(bias) = &op.bias {
+            let bias = bias.cast_to::<T>()?;
+            let c_axis = op.deconv.pool_spec.data_format.shape(output.shape())?.c_axis();
+            for (mut channel, &b) in
+                output.axis_iter_mut(Axis(c_axis)).zip(bias.as_slice::<T>()?.iter())
+            {
+                channel.mapv_inplace(|v| v + b);
+            }
+        }
+        Ok(output.into_tensor())
+    }
+}
+
+impl TypedOp for PulsedDeconv {
+    fn output_facts(&self, inputs: &[&TypedFact]) -> TractResult<TVec<TypedFact>> {
+        let mut shape = self.deconv.output_shape(&*inputs[0].shape.to_tvec())?;
+        shape[self.axis] = self.output_pulse.into();
+        Ok(tvec!(TypedFact::dt_shape(inputs[0].datum_type, &*shape)?))
+    }
+
+    fn cost(&self, inputs: &[&TypedFact]) -> TractResult<TVec<(Cost, TDim)>> {
+        let mut shape = self.deconv.output_shape(&*inputs[0].shape.to_tvec())?;
+        shape[self.axis] = self.overlap.into();
+        Ok(tvec!((Cost::Buffer(inputs[0].datum_type), shape.iter().maybe_product()?)))
+    }
+
+    native_save!("PulsedDeconv");
+    as_op!();
+}
+
+impl PulsedOp for PulsedDeconv {
+    fn pulsed_output_facts(&self, inputs: &[&PulsedFact]) -> TractResult<TVec<PulsedFact>> {
+        let mut fact = inputs[0].clone();
+        fact.shape = self.deconv.output_shape(&*fact.shape)?;
+        fact.shape[self.axis] = self.output_pulse;
+        fact.dim = self.output_dim.clone();
+        fact.delay = self.output_delay;
+        Ok(tvec!(fact))
+    }
+
+    as_op!();
+    pulsed_op_to_typed_op!();
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn deconv_1d(kernel: Tensor, stride: usize, padding: PaddingSpec) -> DeconvUnary {
+        let pool_spec = PoolSpec {
+            data_format: DataFormat::NCHW,
+            kernel_shape: tvec!(kernel.shape()[2]),
+            padding,
+            dilations: None,
+            strides: Some(tvec!(stride)),
+            output_channel_override: None,
+        };
+        DeconvUnary::new(pool_spec, KernelFormat::OIHW, kernel.into_arc_tensor(), 1, None, tvec!(0))
+    }
+
+    #[test]
+    fn deconv_1d_stride_2() -> TractResult<()> {
+        let op = deconv_1d(tensor3(&[[[1f32, 10., 100.]]]), 2, PaddingSpec::Valid);
+        let output = op.eval(tvec!(rctensor3(&[[[1f32, 2., 3.]]])))?;
+        assert_eq!(output[0], rctensor3(&[[[1f32, 10., 102., 20., 203., 30., 300.]]]));
+        Ok(())
+    }
+
+    #[test]
+    fn deconv_1d_explicit_padding_and_adjustment() -> TractResult<()> {
+        let mut op = deconv_1d(
+            tensor3(&[[[1f32, 10., 100.]]]),
+            2,
+            PaddingSpec::Explicit(tvec!(1), tvec!(2)),
+        );
+        op.adjustments = tvec!(1);
+        let output = op.eval(tvec!(rctensor3(&[[[1f32, 2., 3.]]])))?;
+        assert_eq!(output[0], rctensor3(&[[[10f32, 102., 20., 203., 30.]]]));
+        Ok(())
+    }
+
+    #[test]
+    fn deconv_2d_nhwc_matches_nchw() -> TractResult<()> {
+        let kernel = Tensor::from(arr4(&[[[[1f32, 2.], [3., 4.]]], [[[0.5f32, 1.], [1.5, 2.]]]]));
+        let pool_spec = PoolSpec {
+            data_format: DataFormat::NCHW,
+            kernel_shape: tvec!(2, 2),
+            padding: PaddingSpec::SameUpper,
+            dilations: None,
+            strides: Some(tvec!(2, 1)),
+            output_channel_override: None,
+        };
+        let nchw = DeconvUnary::new(
+            pool_spec,
+            KernelFormat::OIHW,
+            kernel.into_arc_tensor(),
+            1,
+            None,
+            tvec!(),
+        );
+        let input = Tensor::from(Array::from_shape_fn((1, 2, 2, 3), |(_, c, h, w)| {
+            (c * 6 + h * 3 + w) as f32
+        }));
+        let expected = nchw.eval(tvec!(input.clone().into()))?.remove(0);
+        assert_eq!(expected.shape(), &[1, 1, 4, 3]);
+        let nhwc = DeconvUnary {
+            pool_spec: PoolSpec { data_format: DataFormat::NHWC, ..nchw.pool_spec.clone() },
+            ..nchw.clone()
+        };
+        let input = input.into_array::<f32>()?.permuted_axes(vec![0, 2, 3, 1]).to_owned();
+        let output = nhwc.eval(tvec!(input.into_arc_tensor()))?.remove(0);
+        let output = output.to_array_view::<f32>()?.permuted_axes(vec![0, 3, 1, 2]).to_owned();
+        assert_eq!(output.into_tensor(), *expected);
+        Ok(())
+    }
+}
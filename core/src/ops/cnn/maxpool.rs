@@ -12,6 +12,8 @@ pub struct MaxPool {
     pub with_index_outputs: Option<DatumType>,
 }
 
+impl_native_codec!(MaxPool { pool_spec, with_index_outputs });
+
 impl MaxPool {
     fn to_fixed<T: Datum + Float>(&self, input_shape: &[usize]) -> TractResult<Box<dyn TypedOp>> {
         let (input_shape, patch, output_shape) = self.pool_spec.compute_geo(input_shape)?;
@@ -79,6 +81,7 @@ impl TypedOp for MaxPool {
         Ok(None)
     }
 
+    native_save!("MaxPool");
     as_op!();
 }
 
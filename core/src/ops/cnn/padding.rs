@@ -8,6 +8,32 @@ pub enum PaddingSpec {
     SameLower,
 }
 
+impl Encode for PaddingSpec {
+    fn encode(&self, encoder: &mut Encoder) -> TractResult<()> {
+        match self {
+            PaddingSpec::Explicit(before, after) => {
+                0u8.encode(encoder)?;
+                before.encode(encoder)?;
+                after.encode(encoder)
+            }
+            PaddingSpec::Valid => 1u8.encode(encoder),
+            PaddingSpec::SameUpper => 2u8.encode(encoder),
+            PaddingSpec::SameLower => 3u8.encode(encoder),
+        }
+    }
+}
+
+impl Decode for PaddingSpec {
+    fn decode(decoder: &mut Decoder) -> TractResult<Self> {
+        Ok(match u8::decode(decoder)? {
+            0 => PaddingSpec::Explicit(TVec::decode(decoder)?, TVec::decode(decoder)?),
+            1 => PaddingSpec::Valid,
+            2 => PaddingSpec::SameUpper,
+            3 => PaddingSpec::SameLower,
+            code => bail!("Invalid PaddingSpec code {}", code),
+        })
+    }
+}
 impl Default for PaddingSpec {
     fn default() -> PaddingSpec {
         PaddingSpec::Valid
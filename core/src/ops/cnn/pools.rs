@@ -13,6 +13,15 @@ pub struct PoolSpec {
     pub output_channel_override: Option<usize>,
 }
 
+impl_native_codec!(PoolSpec {
+    data_format,
+    kernel_shape,
+    padding,
+    dilations,
+    strides,
+    output_channel_override
+});
+
 impl PoolSpec {
     pub fn info(&self) -> Vec<String> {
         vec![
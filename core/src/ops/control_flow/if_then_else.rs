@@ -0,0 +1,402 @@
+use crate::internal::*;
+
+/// Runs one of two sub-models depending on a boolean scalar condition.
+///
+/// The condition is the first input of the operator. Each body input is fed
+/// by the outer input designated by the matching entry of its input mapping,
+/// the two bodies must produce the same number of outputs with matching
+/// types and ranks. Output dimensions on which the branches disagree, or
+/// which depend on the data inside a branch, are only known after
+/// evaluation.
+#[derive(Debug, Clone, Default, Hash)]
+pub struct TypedIf {
+    pub then_body: TypedModel,
+    pub then_input_mapping: Vec<usize>,
+    pub else_body: TypedModel,
+    pub else_input_mapping: Vec<usize>,
+    /// Data-dependent dimensions of the outputs, on the axes where the
+    /// branches disagree or make up a dimension.
+    output_dims: Vec<TVec<Option<TDim>>>,
+    decluttered: bool,
+    optimized: bool,
+}
+
+impl_native_codec!(TypedIf {
+    then_body,
+    then_input_mapping,
+    else_body,
+    else_input_mapping,
+    output_dims,
+    decluttered,
+    optimized
+});
+
+impl TypedIf {
+    pub fn new(
+        then_body: TypedModel,
+        then_input_mapping: Vec<usize>,
+        else_body: TypedModel,
+        else_input_mapping: Vec<usize>,
+    ) -> TractResult<TypedIf> {
+        if then_body.input_outlets()?.len() != then_input_mapping.len()
+            || else_body.input_outlets()?.len() != else_input_mapping.len()
+        {
+            bail!("If input mappings do not match branches inputs")
+        }
+        if then_body.output_outlets()?.len() != else_body.output_outlets()?.len() {
+            bail!("If branches have different numbers of outputs")
+        }
+        let mut output_dims = vec![];
+        for ix in 0..then_body.output_outlets()?.len() {
+            let then_fact = then_body.output_fact(ix)?;
+            let else_fact = else_body.output_fact(ix)?;
+            if then_fact.datum_type != else_fact.datum_type
+                || then_fact.shape.rank() != else_fact.shape.rank()
+            {
+                bail!("If branches output #{} mismatch: {:?} and {:?}", ix, then_fact, else_fact)
+            }
+            output_dims.push(
+                then_fact
+                    .shape
+                    .iter()
+                    .zip(else_fact.shape.iter())
+                    .map(|(t, e)| {
+                        let same = t == e
+                            && !super::made_up_in(&then_body, &t)?
+                            && !super::made_up_in(&else_body, &e)?;
+                        Ok(if same { None } else { Some(TDim::data_dependent()) })
+                    })
+                    .collect::<TractResult<_>>()?,
+            );
+        }
+        Ok(TypedIf {
+            then_body,
+            then_input_mapping,
+            else_body,
+            else_input_mapping,
+            output_dims,
+            decluttered: false,
+            optimized: false,
+        })
+    }
+
+    fn branch(&self, cond: bool) -> (&TypedModel, &[usize]) {
+        if cond {
+            (&self.then_body, &self.then_input_mapping)
+        } else {
+            (&self.else_body, &self.else_input_mapping)
+        }
+    }
+
+    fn declutter_const_condition(
+        &self,
+        model: &TypedModel,
+        node: &TypedNode,
+    ) -> TractResult<Option<TypedModelPatch>> {
+        let cond = if let Some(cond) = &model.outlet_fact(node.inputs[0])?.konst {
+            *cond.to_scalar::<bool>()?
+        } else {
+            return Ok(None);
+        };
+        let (body, mapping) = self.branch(cond);
+        let mut patch = TypedModelPatch::default();
+        let inputs = mapping
+            .iter()
+            .map(|slot| patch.tap_model(model, node.inputs[*slot]))
+            .collect::<TractResult<TVec<_>>>()?;
+        let outputs = super::wire_body(&mut patch, &node.name, body, &inputs)?;
+        for (ix, outlet) in outputs.into_iter().enumerate() {
+            patch.shunt_outside(model, OutletId::new(node.id, ix), outlet)?;
+        }
+        Ok(Some(patch))
+    }
+
+    fn declutter_bodies(
+        &self,
+        model: &TypedModel,
+        node: &TypedNode,
+    ) -> TractResult<Option<TypedModelPatch>> {
+        if !self.decluttered {
+            let mut new = self.clone();
+            new.then_body = self.then_body.clone().declutter()?;
+            new.else_body = self.else_body.clone().declutter()?;
+            new.decluttered = true;
+            Ok(Some(TypedModelPatch::replace_single_op(model, node, &node.inputs, new)?))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+impl Op for TypedIf {
+    fn name(&self) -> Cow<str> {
+        "If::Typed".into()
+    }
+
+    fn info(&self) -> TractResult<Vec<String>> {
+        Ok(vec![
+            format!("Then inputs: {:?}", self.then_input_mapping),
+            format!("Else inputs: {:?}", self.else_input_mapping),
+        ])
+    }
+
+    fn nested_models(&self) -> Vec<(Cow<str>, &dyn Model, Vec<String>, Vec<String>)> {
+        let outputs = |body: &TypedModel| {
+            (0..body.output_outlets().unwrap().len()).map(|ix| format!("Output #{}", ix)).collect()
+        };
+        vec![
+            (
+                "then".into(),
+                &self.then_body,
+                self.then_input_mapping.iter().map(|s| format!("Input #{}", s)).collect(),
+                outputs(&self.then_body),
+            ),
+            (
+                "else".into(),
+                &self.else_body,
+                self.else_input_mapping.iter().map(|s| format!("Input #{}", s)).collect(),
+                outputs(&self.else_body),
+            ),
+        ]
+    }
+
+    op_as_typed_op!();
+    not_a_pulsed_op!();
+}
+
+impl StatefullOp for TypedIf {
+    fn state(
+        &self,
+        _session: &mut SessionState,
+        _node_id: usize,
+    ) -> TractResult<Option<Box<dyn OpState>>> {
+        Ok(Some(Box::new(IfState {
+            then_plan: Arc::new(SimplePlan::new(self.then_body.clone())?),
+            then_input_mapping: self.then_input_mapping.clone(),
+            else_plan: Arc::new(SimplePlan::new(self.else_body.clone())?),
+            else_input_mapping: self.else_input_mapping.clone(),
+        })))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct IfState {
+    then_plan: Arc<TypedSimplePlan<TypedModel>>,
+    then_input_mapping: Vec<usize>,
+    else_plan: Arc<TypedSimplePlan<TypedModel>>,
+    else_input_mapping: Vec<usize>,
+}
+
+impl OpState for IfState {
+    fn eval(
+        &mut self,
+        _session: &mut SessionState,
+        _op: &dyn Op,
+        inputs: TVec<Arc<Tensor>>,
+    ) -> TractResult<TVec<Arc<Tensor>>> {
+        let cond = *inputs[0].to_scalar::<bool>()?;
+        let (plan, mapping) = if cond {
+            (&self.then_plan, &self.then_input_mapping)
+        } else {
+            (&self.else_plan, &self.else_input_mapping)
+        };
+        let body_inputs = mapping.iter().map(|slot| inputs[*slot].as_ref().clone()).collect();
+        plan.run(body_inputs)
+    }
+}
+
+impl TypedOp for TypedIf {
+    native_save!("TypedIf");
+    as_op!();
+
+    fn output_facts(&self, inputs: &[&TypedFact]) -> TractResult<TVec<TypedFact>> {
+        if inputs[0].datum_type != bool::datum_type() || inputs[0].shape.rank() != 0 {
+            bail!("If condition must be a boolean scalar, got {:?}", inputs[0])
+        }
+        let mut facts = tvec!();
+        for ix in 0..self.then_body.output_outlets()?.len() {
+            let then_fact = self.then_body.output_fact(ix)?;
+            let else_fact = self.else_body.output_fact(ix)?;
+            let shape = then_fact
+                .shape
+                .iter()
+                .zip(else_fact.shape.iter())
+                .zip(self.output_dims[ix].iter())
+                .map(|((t, e), dim)| match dim {
+                    Some(dim) => Ok(dim.clone()),
+                    None if t == e => Ok(t),
+                    None => bail!("If branches output #{} shapes diverged", ix),
+                })
+                .collect::<TractResult<TVec<_>>>()?;
+            facts.push(TypedFact::dt_shape(then_fact.datum_type, &*shape)?);
+        }
+        Ok(facts)
+    }
+
+    fn data_dependent_dims_mut(&mut self) -> TVec<&mut TDim> {
+        self.output_dims.iter_mut().flat_map(|dims| dims.iter_mut()).flatten().collect()
+    }
+
+    fn declutter(
+        &self,
+        model: &TypedModel,
+        node: &TypedNode,
+    ) -> TractResult<Option<TypedModelPatch>> {
+        for dec in &[Self::declutter_const_condition, Self::declutter_bodies] {
+            if let Some(r) = dec(&self, model, node)? {
+                return Ok(Some(r));
+            }
+        }
+        Ok(None)
+    }
+
+    fn nested_model_multipliers(&self, _inputs: &[&TypedFact]) -> Vec<(Cow<str>, f32)> {
+        vec![("then".into(), 1.0), ("else".into(), 1.0)]
+    }
+
+    fn codegen(
+        &self,
+        model: &TypedModel,
+        node: &TypedNode,
+    ) -> TractResult<Option<TypedModelPatch>> {
+        if self.optimized {
+            return Ok(None);
+        }
+        let mut new = self.clone();
+        new.then_body = self.then_body.clone().into_optimized()?;
+        new.else_body = self.else_body.clone().into_optimized()?;
+        new.optimized = true;
+        Ok(Some(TypedModelPatch::replace_single_op(model, node, &node.inputs, new)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ops;
+
+    fn branches() -> TypedIf {
+        let fact = TypedFact::dt_shape(f32::datum_type(), [2].as_ref()).unwrap();
+        let mut then_body = TypedModel::default();
+        let x = then_body.add_source("x", fact.clone()).unwrap();
+        let y = then_body.wire_node("exp", ops::math::exp(), &[x]).unwrap();
+        then_body.set_output_outlets(&y).unwrap();
+        let mut else_body = TypedModel::default();
+        let x = else_body.add_source("x", fact.clone()).unwrap();
+        let y = else_body.add_source("y", fact).unwrap();
+        let z = else_body.wire_node("add", ops::math::add::bin_typed(), &[x, y]).unwrap();
+        else_body.set_output_outlets(&z).unwrap();
+        TypedIf::new(then_body, vec![1], else_body, vec![1, 2]).unwrap()
+    }
+
+    fn model(cond: Option<bool>) -> TypedModel {
+        let fact = TypedFact::dt_shape(f32::datum_type(), [2].as_ref()).unwrap();
+        let mut model = TypedModel::default();
+        let cond = if let Some(c) = cond {
+            model.add_const("cond", tensor0(c)).unwrap()
+        } else {
+            model
+                .add_source(
+                    "cond",
+                    TypedFact::dt_shape(bool::datum_type(), [0usize; 0].as_ref()).unwrap(),
+                )
+                .unwrap()
+        };
+        let a = model.add_source("a", fact.clone()).unwrap();
+        let b = model.add_source("b", fact).unwrap();
+        let output = model.wire_node("if", branches(), &[cond, a, b]).unwrap();
+        model.set_output_outlets(&output).unwrap();
+        model
+    }
+
+    #[test]
+    fn eval_both_branches() {
+        let plan = SimplePlan::new(model(None)).unwrap();
+        let a = tensor1(&[0f32, 1.]);
+        let b = tensor1(&[2f32, 3.]);
+        let result = plan.run(tvec!(tensor0(true), a.clone(), b.clone())).unwrap();
+        assert_eq!(*result[0], tensor1(&[1f32, 1f32.exp()]));
+        let result = plan.run(tvec!(tensor0(false), a, b)).unwrap();
+        assert_eq!(*result[0], tensor1(&[2f32, 4.]));
+    }
+
+    #[test]
+    fn declutter_const_condition() {
+        let model = model(Some(false)).declutter().unwrap();
+        assert!(model.nodes().iter().all(|n| n.op_as::<TypedIf>().is_none()));
+        let result = SimplePlan::new(model)
+            .unwrap()
+            .run(tvec!(tensor1(&[0f32, 1.]), tensor1(&[2f32, 3.])))
+            .unwrap();
+        assert_eq!(*result[0], tensor1(&[2f32, 4.]));
+    }
+
+    #[test]
+    fn branches_with_different_shapes() {
+        let mut then_body = TypedModel::default();
+        let x = then_body
+            .add_source("x", TypedFact::dt_shape(f32::datum_type(), [2].as_ref()).unwrap())
+            .unwrap();
+        then_body.set_output_outlets(&[x]).unwrap();
+        let mut else_body = TypedModel::default();
+        let y = else_body
+            .add_source("y", TypedFact::dt_shape(f32::datum_type(), [3].as_ref()).unwrap())
+            .unwrap();
+        else_body.set_output_outlets(&[y]).unwrap();
+        let op = TypedIf::new(then_body, vec![1], else_body, vec![2]).unwrap();
+
+        let mut model = TypedModel::default();
+        let cond = model
+            .add_source(
+                "cond",
+                TypedFact::dt_shape(bool::datum_type(), [0usize; 0].as_ref()).unwrap(),
+            )
+            .unwrap();
+        let a = model
+            .add_source("a", TypedFact::dt_shape(f32::datum_type(), [2].as_ref()).unwrap())
+            .unwrap();
+        let b = model
+            .add_source("b", TypedFact::dt_shape(f32::datum_type(), [3].as_ref()).unwrap())
+            .unwrap();
+        let output = model.wire_node("if", op, &[cond, a, b]).unwrap();
+        model.set_output_outlets(&output).unwrap();
+        assert!(model.outlet_fact(output[0]).unwrap().shape.dim(0).is_data_dependent());
+
+        let plan = SimplePlan::new(model).unwrap();
+        let a = tensor1(&[0f32, 1.]);
+        let b = tensor1(&[2f32, 3., 4.]);
+        let result = plan.run(tvec!(tensor0(true), a.clone(), b.clone())).unwrap();
+        assert_eq!(*result[0], a);
+        let result = plan.run(tvec!(tensor0(false), a, b.clone())).unwrap();
+        assert_eq!(*result[0], b);
+    }
+
+    #[test]
+    fn branch_symbols_do_not_leak() {
+        let fact = TypedFact::dt_shape(f32::datum_type(), [4].as_ref()).unwrap();
+        let body = || {
+            let mut body = TypedModel::default();
+            let x = body.add_source("x", fact.clone()).unwrap();
+            let nz = body.wire_node("nz", ops::array::NonZero::default(), &[x]).unwrap();
+            body.set_output_outlets(&nz).unwrap();
+            body
+        };
+        let op = TypedIf::new(body(), vec![1], body(), vec![1]).unwrap();
+
+        let mut model = TypedModel::default();
+        let cond = model
+            .add_source(
+                "cond",
+                TypedFact::dt_shape(bool::datum_type(), [0usize; 0].as_ref()).unwrap(),
+            )
+            .unwrap();
+        let x = model.add_source("x", fact.clone()).unwrap();
+        let nz = model.wire_node("nz", ops::array::NonZero::default(), &[x]).unwrap();
+        let output = model.wire_node("if", op, &[cond, x]).unwrap();
+        let nz_len = model.outlet_fact(nz[0]).unwrap().shape.dim(1);
+        let if_len = model.outlet_fact(output[0]).unwrap().shape.dim(1);
+        assert_eq!(nz_len, body().output_fact(0).unwrap().shape.dim(1));
+        assert!(if_len.is_data_dependent());
+        assert_ne!(if_len, nz_len);
+    }
+}
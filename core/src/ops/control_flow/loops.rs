@@ -0,0 +1,493 @@
+use crate::internal::*;
+use crate::ops::scan::{InputMapping, OutputMapping, StateInitializer, TypedScan};
+
+/// Generic loop, iterating a body while a condition holds and an optional
+/// trip count is not exhausted.
+///
+/// The body inputs are: the iteration number (i64 scalar), the condition
+/// (boolean scalar), the loop carried values, then the values the body
+/// closes on. Its outputs are: the condition for the next iteration, the
+/// updated loop carried values, then the scan outputs.
+///
+/// The operator outputs the final loop carried values, then the scan
+/// outputs stacked along a new leading axis. Unless the number of iterations
+/// can be found from the facts, this axis is only known after evaluation.
+#[derive(Debug, Clone, Default, Hash)]
+pub struct TypedLoop {
+    pub body: TypedModel,
+    pub trip_count_slot: Option<usize>,
+    pub cond_slot: Option<usize>,
+    pub state_slots: Vec<usize>,
+    pub closure_slots: Vec<usize>,
+    iterations: TDim,
+    decluttered: bool,
+    optimized: bool,
+}
+
+impl_native_codec!(TypedLoop {
+    body,
+    trip_count_slot,
+    cond_slot,
+    state_slots,
+    closure_slots,
+    iterations,
+    decluttered,
+    optimized
+});
+
+impl TypedLoop {
+    pub fn new(
+        body: TypedModel,
+        trip_count_slot: Option<usize>,
+        cond_slot: Option<usize>,
+        state_slots: Vec<usize>,
+        closure_slots: Vec<usize>,
+    ) -> TractResult<TypedLoop> {
+        if body.input_outlets()?.len() != 2 + state_slots.len() + closure_slots.len() {
+            bail!("Loop body inputs do not match the loop carried values and closures")
+        }
+        if body.output_outlets()?.len() < 1 + state_slots.len() {
+            bail!("Loop body must output the condition and the loop carried values")
+        }
+        for ix in 1..body.output_outlets()?.len() {
+            for dim in body.output_fact(ix)?.shape.iter() {
+                if super::made_up_in(&body, &dim)? {
+                    bail!("Loop body output #{} has a data-dependent dimension ({})", ix, dim)
+                }
+            }
+        }
+        Ok(TypedLoop {
+            body,
+            trip_count_slot,
+            cond_slot,
+            state_slots,
+            closure_slots,
+            iterations: TDim::data_dependent(),
+            decluttered: false,
+            optimized: false,
+        })
+    }
+
+    fn scan_outputs(&self) -> TractResult<usize> {
+        Ok(self.body.output_outlets()?.len() - 1 - self.state_slots.len())
+    }
+
+    /// Number of iterations, if it can be determined from the facts.
+    ///
+    /// This requires a constant trip count, and a condition that can not
+    /// stop the loop early.
+    pub fn static_iterations(&self, inputs: &[&TypedFact]) -> TractResult<Option<usize>> {
+        let trip_count = match self.trip_count_slot.and_then(|s| inputs[s].konst.as_ref()) {
+            Some(trip_count) => trip_count.cast_to_scalar::<i64>()?,
+            None => return Ok(None),
+        };
+        if let Some(slot) = self.cond_slot {
+            match &inputs[slot].konst {
+                Some(cond) if *cond.to_scalar::<bool>()? => (),
+                _ => return Ok(None),
+            }
+        }
+        let mut cond_out = self.body.output_outlets()?[0];
+        while self.body.node(cond_out.node).op_is::<crate::ops::identity::Identity>() {
+            cond_out = self.body.node(cond_out.node).inputs[0];
+        }
+        let always_true = cond_out == self.body.input_outlets()?[1]
+            || match &self.body.outlet_fact(cond_out)?.konst {
+                Some(cond) => *cond.to_scalar::<bool>()?,
+                None => false,
+            };
+        Ok(if always_true { Some(trip_count.max(0) as usize) } else { None })
+    }
+
+    fn declutter_body(
+        &self,
+        model: &TypedModel,
+        node: &TypedNode,
+    ) -> TractResult<Option<TypedModelPatch>> {
+        if !self.decluttered {
+            let mut new = self.clone();
+            new.body = self.body.clone().declutter()?;
+            new.decluttered = true;
+            Ok(Some(TypedModelPatch::replace_single_op(model, node, &node.inputs, new)?))
+        } else {
+            Ok(None)
+        }
+    }
+
+    /// Lower a loop with a static number of iterations to a TypedScan,
+    /// scanning over the iteration numbers.
+    fn declutter_into_scan(
+        &self,
+        model: &TypedModel,
+        node: &TypedNode,
+    ) -> TractResult<Option<TypedModelPatch>> {
+        let iters = match self.static_iterations(&*model.node_input_facts(node.id)?)? {
+            Some(iters) if iters > 0 => iters,
+            _ => return Ok(None),
+        };
+        let carried = self.state_slots.len();
+        let closures = self.closure_slots.len();
+
+        let mut body = TypedModel::default();
+        let iteration = body.add_source(
+            format!("{}.iteration", node.name),
+            TypedFact::dt_shape(i64::datum_type(), [1].as_ref())?,
+        )?;
+        let iteration = body.wire_node(
+            format!("{}.iteration.rm_axis", node.name),
+            AxisOp::Rm(0),
+            &[iteration],
+        )?[0];
+        // the condition is known to stay true, so it is not carried
+        let cond = body.add_const(format!("{}.cond", node.name), tensor0(true))?;
+        let mut inputs = tvec!(iteration, cond);
+        for (ix, input) in self.body.input_outlets()?.iter().enumerate().skip(2) {
+            let name = format!("{}.{}", node.name, self.body.node(input.node).name);
+            inputs.push(body.add_source(name, self.body.input_fact(ix)?.clone())?);
+        }
+        let outputs = super::wire_body(&mut body, &node.name, &self.body, &inputs)?;
+        let mut body_outputs: TVec<OutletId> = outputs[1..1 + carried].into();
+        for (ix, output) in outputs[1 + carried..].iter().enumerate() {
+            body_outputs.push(
+                body.wire_node(
+                    format!("{}.scan_output_{}.add_axis", node.name, ix),
+                    AxisOp::Add(0),
+                    &[*output],
+                )?[0],
+            );
+        }
+        body.set_output_outlets(&body_outputs)?;
+
+        let mut input_mapping =
+            vec![InputMapping::Scan { slot: carried + closures, axis: 0, chunk: 1.into() }];
+        for ix in 0..carried {
+            input_mapping
+                .push(InputMapping::State { initializer: StateInitializer::FromInput(ix) });
+        }
+        for ix in 0..closures {
+            input_mapping.push(InputMapping::Full { slot: carried + ix });
+        }
+        let mut output_mapping = vec![];
+        for ix in 0..carried {
+            output_mapping.push(OutputMapping {
+                state: true,
+                full_slot: None,
+                last_value_slot: Some(ix),
+                axis: 0,
+                chunk: 1.into(),
+                full_dim_hint: None,
+            });
+        }
+        for ix in 0..self.scan_outputs()? {
+            output_mapping.push(OutputMapping {
+                state: false,
+                full_slot: Some(carried + ix),
+                last_value_slot: None,
+                axis: 0,
+                chunk: 1.into(),
+                full_dim_hint: None,
+            });
+        }
+        let scan = TypedScan::new(body, input_mapping, output_mapping, None)?;
+
+        let mut patch = TypedModelPatch::default();
+        let mut scan_inputs = self
+            .state_slots
+            .iter()
+            .chain(self.closure_slots.iter())
+            .map(|slot| patch.tap_model(model, node.inputs[*slot]))
+            .collect::<TractResult<TVec<_>>>()?;
+        let range = tensor1(&*(0..iters as i64).collect::<Vec<_>>());
+        scan_inputs.push(patch.add_const(format!("{}.iterations", node.name), range)?);
+        let outputs = patch.wire_node(&*node.name, scan, &scan_inputs)?;
+        for (ix, outlet) in outputs.into_iter().enumerate() {
+            patch.shunt_outside(model, OutletId::new(node.id, ix), outlet)?;
+        }
+        Ok(Some(patch))
+    }
+}
+
+impl Op for TypedLoop {
+    fn name(&self) -> Cow<str> {
+        "Loop::Typed".into()
+    }
+
+    fn info(&self) -> TractResult<Vec<String>> {
+        Ok(vec![
+            format!("Trip count input: {:?}", self.trip_count_slot),
+            format!("Condition input: {:?}", self.cond_slot),
+            format!("Loop carried inputs: {:?}", self.state_slots),
+            format!("Closure inputs: {:?}", self.closure_slots),
+        ])
+    }
+
+    fn nested_models(&self) -> Vec<(Cow<str>, &dyn Model, Vec<String>, Vec<String>)> {
+        let carried = self.state_slots.len();
+        let mut inputs = vec!["Iteration".to_string(), "Condition".to_string()];
+        inputs.extend(self.state_slots.iter().map(|s| format!("Loop carried from input #{}", s)));
+        inputs.extend(self.closure_slots.iter().map(|s| format!("Input #{}", s)));
+        let mut outputs = vec!["Condition".to_string()];
+        outputs.extend((0..carried).map(|ix| format!("Loop carried to output #{}", ix)));
+        outputs.extend(
+            (0..self.scan_outputs().unwrap_or(0))
+                .map(|ix| format!("Scan output to output #{}", carried + ix)),
+        );
+        vec![("loop".into(), &self.body, inputs, outputs)]
+    }
+
+    op_as_typed_op!();
+    not_a_pulsed_op!();
+}
+
+impl StatefullOp for TypedLoop {
+    fn state(
+        &self,
+        _session: &mut SessionState,
+        _node_id: usize,
+    ) -> TractResult<Option<Box<dyn OpState>>> {
+        Ok(Some(Box::new(LoopState {
+            op: Arc::new(self.clone()),
+            plan: Arc::new(SimplePlan::new(self.body.clone())?),
+        })))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct LoopState {
+    op: Arc<TypedLoop>,
+    plan: Arc<TypedSimplePlan<TypedModel>>,
+}
+
+impl OpState for LoopState {
+    fn eval(
+        &mut self,
+        _session: &mut SessionState,
+        _op: &dyn Op,
+        inputs: TVec<Arc<Tensor>>,
+    ) -> TractResult<TVec<Arc<Tensor>>> {
+        let op = &self.op;
+        let trip_count =
+            op.trip_count_slot.map(|s| inputs[s].cast_to_scalar::<i64>()).transpose()?;
+        let mut cond =
+            op.cond_slot.map(|s| inputs[s].to_scalar::<bool>().map(|b| *b)).transpose()?;
+        let mut carried: TVec<Tensor> =
+            op.state_slots.iter().map(|s| inputs[*s].as_ref().clone()).collect();
+        let mut scans: Vec<Vec<Tensor>> = vec![vec![]; op.scan_outputs()?];
+        let mut iteration = 0i64;
+        while cond.unwrap_or(true) && trip_count.map(|t| iteration < t).unwrap_or(true) {
+            let mut body_inputs = tvec!(tensor0(iteration), tensor0(cond.unwrap_or(true)));
+            body_inputs.extend(carried.drain(..));
+            body_inputs.extend(op.closure_slots.iter().map(|s| inputs[*s].as_ref().clone()));
+            let mut outputs = self.plan.run(body_inputs)?.into_iter();
+            cond = Some(*outputs.next().unwrap().to_scalar::<bool>()?);
+            for _ in 0..op.state_slots.len() {
+                carried.push(outputs.next().unwrap().into_tensor());
+            }
+            for (scan, output) in scans.iter_mut().zip(outputs) {
+                let mut output = output.into_tensor();
+                output.insert_axis(0)?;
+                scan.push(output);
+            }
+            iteration += 1;
+        }
+        let mut outputs: TVec<Arc<Tensor>> =
+            carried.into_iter().map(|t| t.into_arc_tensor()).collect();
+        for (ix, scan) in scans.into_iter().enumerate() {
+            let output = if scan.len() > 0 {
+                Tensor::stack_tensors(0, &scan)?
+            } else {
+                let fact = op.body.output_fact(1 + op.state_slots.len() + ix)?;
+                let mut shape: TVec<usize> = fact
+                    .shape
+                    .as_finite()
+                    .ok_or("Can not build an empty scan output with streaming dimensions")?
+                    .into();
+                shape.insert(0, 0);
+                unsafe { Tensor::uninitialized_dt(fact.datum_type, &shape)? }
+            };
+            outputs.push(output.into_arc_tensor());
+        }
+        Ok(outputs)
+    }
+}
+
+impl TypedOp for TypedLoop {
+    native_save!("TypedLoop");
+    as_op!();
+
+    fn output_facts(&self, inputs: &[&TypedFact]) -> TractResult<TVec<TypedFact>> {
+        let carried = self.state_slots.len();
+        let mut facts = tvec!();
+        for ix in 0..carried {
+            let fact = self.body.output_fact(1 + ix)?;
+            facts.push(TypedFact::dt_shape(fact.datum_type, fact.shape.clone())?);
+        }
+        let iters = match self.static_iterations(inputs)? {
+            Some(iters) => iters.to_dim(),
+            None => self.iterations.clone(),
+        };
+        for ix in 0..self.scan_outputs()? {
+            let fact = self.body.output_fact(1 + carried + ix)?;
+            let mut shape = fact.shape.clone();
+            shape.insert_axis(0)?;
+            shape.set_dim(0, iters.clone())?;
+            facts.push(TypedFact::dt_shape(fact.datum_type, shape)?);
+        }
+        Ok(facts)
+    }
+
+    fn data_dependent_dims_mut(&mut self) -> TVec<&mut TDim> {
+        tvec!(&mut self.iterations)
+    }
+
+    fn declutter(
+        &self,
+        model: &TypedModel,
+        node: &TypedNode,
+    ) -> TractResult<Option<TypedModelPatch>> {
+        for dec in &[Self::declutter_body, Self::declutter_into_scan] {
+            if let Some(r) = dec(&self, model, node)? {
+                return Ok(Some(r));
+            }
+        }
+        Ok(None)
+    }
+
+    fn nested_model_multipliers(&self, inputs: &[&TypedFact]) -> Vec<(Cow<str>, f32)> {
+        let iters = self.static_iterations(inputs).ok().flatten().unwrap_or(1);
+        vec![("loop".into(), iters as f32)]
+    }
+
+    fn codegen(
+        &self,
+        model: &TypedModel,
+        node: &TypedNode,
+    ) -> TractResult<Option<TypedModelPatch>> {
+        if self.optimized {
+            return Ok(None);
+        }
+        let mut new = self.clone();
+        new.body = self.body.clone().into_optimized()?;
+        new.optimized = true;
+        Ok(Some(TypedModelPatch::replace_single_op(model, node, &node.inputs, new)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ops;
+
+    // acc <- acc + x, output acc at each iteration, stop when acc > 5
+    fn accumulator() -> TypedLoop {
+        let scalar = |dt: DatumType| TypedFact::dt_shape(dt, [0usize; 0].as_ref()).unwrap();
+        let mut body = TypedModel::default();
+        let _iter = body.add_source("iter", scalar(i64::datum_type())).unwrap();
+        let _cond = body.add_source("cond", scalar(bool::datum_type())).unwrap();
+        let acc = body.add_source("acc", scalar(f32::datum_type())).unwrap();
+        let x = body.add_source("x", scalar(f32::datum_type())).unwrap();
+        let sum = body.wire_node("sum", ops::math::add::bin_typed(), &[acc, x]).unwrap()[0];
+        let five = body.add_const("five", tensor0(5f32)).unwrap();
+        let cond = body
+            .wire_node(
+                "cond_out",
+                ops::binary::TypedBinOp(Box::new(ops::logic::LesserEqual)),
+                &[sum, five],
+            )
+            .unwrap()[0];
+        body.set_output_outlets(&[cond, sum, sum]).unwrap();
+        TypedLoop::new(body, Some(0), None, vec![1], vec![2]).unwrap()
+    }
+
+    #[test]
+    fn eval_dynamic_condition() {
+        let mut op = accumulator();
+        let mut model = TypedModel::default();
+        let trip = model
+            .add_source(
+                "trip",
+                TypedFact::dt_shape(i64::datum_type(), [0usize; 0].as_ref()).unwrap(),
+            )
+            .unwrap();
+        let init = model
+            .add_source(
+                "init",
+                TypedFact::dt_shape(f32::datum_type(), [0usize; 0].as_ref()).unwrap(),
+            )
+            .unwrap();
+        let x = model
+            .add_source("x", TypedFact::dt_shape(f32::datum_type(), [0usize; 0].as_ref()).unwrap())
+            .unwrap();
+        let body_outputs = op.body.output_outlets().unwrap().to_vec();
+        op.body.set_output_outlets(&body_outputs[..2]).unwrap();
+        let output = model.wire_node("loop", op, &[trip, init, x]).unwrap();
+        model.set_output_outlets(&output).unwrap();
+        let plan = SimplePlan::new(model).unwrap();
+        let result = plan.run(tvec!(tensor0(10i64), tensor0(0f32), tensor0(2f32))).unwrap();
+        assert_eq!(*result[0], tensor0(6f32));
+        let result = plan.run(tvec!(tensor0(2i64), tensor0(0f32), tensor0(2f32))).unwrap();
+        assert_eq!(*result[0], tensor0(4f32));
+    }
+
+    #[test]
+    fn lower_static_loop_to_scan() {
+        let mut op = accumulator();
+        // make the condition trivially true
+        let body_outputs = op.body.output_outlets().unwrap().to_vec();
+        let cond_in = op.body.input_outlets().unwrap()[1];
+        op.body.set_output_outlets(&[cond_in, body_outputs[1], body_outputs[2]]).unwrap();
+        let mut model = TypedModel::default();
+        let trip = model.add_const("trip", tensor0(3i64)).unwrap();
+        let init = model
+            .add_source(
+                "init",
+                TypedFact::dt_shape(f32::datum_type(), [0usize; 0].as_ref()).unwrap(),
+            )
+            .unwrap();
+        let x = model
+            .add_source("x", TypedFact::dt_shape(f32::datum_type(), [0usize; 0].as_ref()).unwrap())
+            .unwrap();
+        let output = model.wire_node("loop", op, &[trip, init, x]).unwrap();
+        model.set_output_outlets(&output).unwrap();
+        let inputs = tvec!(tensor0(1f32), tensor0(2f32));
+        let expected = SimplePlan::new(&model).unwrap().run(inputs.clone()).unwrap();
+        assert_eq!(*expected[0], tensor0(7f32));
+        assert_eq!(*expected[1], tensor1(&[3f32, 5., 7.]));
+        let decluttered = model.declutter().unwrap();
+        assert!(decluttered.nodes().iter().any(|n| n.op_is::<TypedScan>()));
+        assert!(decluttered.nodes().iter().all(|n| !n.op_is::<TypedLoop>()));
+        let found = SimplePlan::new(&decluttered).unwrap().run(inputs).unwrap();
+        assert_eq!(expected, found);
+    }
+
+    #[test]
+    fn scan_outputs_of_dynamic_loop() {
+        let op = accumulator();
+        let scalar = |dt: DatumType| TypedFact::dt_shape(dt, [0usize; 0].as_ref()).unwrap();
+        let mut model = TypedModel::default();
+        let trip = model.add_source("trip", scalar(i64::datum_type())).unwrap();
+        let init = model.add_source("init", scalar(f32::datum_type())).unwrap();
+        let x = model.add_source("x", scalar(f32::datum_type())).unwrap();
+        let output = model.wire_node("loop", op, &[trip, init, x]).unwrap();
+        model.set_output_outlets(&output).unwrap();
+        assert!(model.outlet_fact(output[1]).unwrap().shape.dim(0).is_data_dependent());
+        let plan = SimplePlan::new(model).unwrap();
+        let result = plan.run(tvec!(tensor0(10i64), tensor0(0f32), tensor0(2f32))).unwrap();
+        assert_eq!(*result[0], tensor0(6f32));
+        assert_eq!(*result[1], tensor1(&[2f32, 4., 6.]));
+    }
+
+    #[test]
+    fn body_made_up_dims_are_rejected() {
+        let scalar = |dt: DatumType| TypedFact::dt_shape(dt, [0usize; 0].as_ref()).unwrap();
+        let mut body = TypedModel::default();
+        let _iter = body.add_source("iter", scalar(i64::datum_type())).unwrap();
+        let cond = body.add_source("cond", scalar(bool::datum_type())).unwrap();
+        let x = body
+            .add_source("x", TypedFact::dt_shape(f32::datum_type(), [4].as_ref()).unwrap())
+            .unwrap();
+        let nz = body.wire_node("nz", ops::array::NonZero::default(), &[x]).unwrap()[0];
+        body.set_output_outlets(&[cond, nz]).unwrap();
+        assert!(TypedLoop::new(body, Some(0), None, vec![], vec![2]).is_err());
+    }
+}
@@ -0,0 +1,55 @@
+use crate::internal::*;
+
+mod if_then_else;
+mod loops;
+
+pub use if_then_else::TypedIf;
+pub use loops::TypedLoop;
+
+/// Wire the nodes of `body` into `target`, feeding the body inputs with
+/// `inputs`.
+///
+/// Returns the outlets in `target` matching the body outputs.
+pub fn wire_body(
+    target: &mut TypedModel,
+    prefix: &str,
+    body: &TypedModel,
+    inputs: &[OutletId],
+) -> TractResult<TVec<OutletId>> {
+    let body_inputs = body.input_outlets()?;
+    if body_inputs.len() != inputs.len() {
+        bail!("Body expects {} inputs, got {}", body_inputs.len(), inputs.len())
+    }
+    let mut mapping: HashMap<OutletId, OutletId> =
+        body_inputs.iter().cloned().zip(inputs.iter().cloned()).collect();
+    for id in body.eval_order()? {
+        if body_inputs.iter().any(|i| i.node == id) {
+            continue;
+        }
+        let node = body.node(id);
+        let node_inputs = node.inputs.iter().map(|i| mapping[i]).collect::<TVec<_>>();
+        let outputs =
+            target.wire_node(format!("{}.{}", prefix, node.name), node.op.clone(), &node_inputs)?;
+        for (slot, outlet) in outputs.into_iter().enumerate() {
+            mapping.insert(OutletId::new(id, slot), outlet);
+        }
+    }
+    body.output_outlets()?.iter().map(|o| Ok(mapping[o])).collect()
+}
+
+/// Does `dim` use a data-dependent symbol drawn inside `body` ? Symbols of
+/// the body are not the ones of the outer model, only those coming from the
+/// body inputs can appear outside.
+fn made_up_in(body: &TypedModel, dim: &TDim) -> TractResult<bool> {
+    if !dim.is_data_dependent() {
+        return Ok(false);
+    }
+    let mut input_symbols = vec![];
+    for input in body.input_outlets()? {
+        input_symbols.extend(body.outlet_fact(*input)?.shape.symbols());
+    }
+    Ok(dim
+        .symbols()
+        .into_iter()
+        .any(|s| crate::dim::is_data_dependent_symbol(s) && !input_symbols.contains(&s)))
+}
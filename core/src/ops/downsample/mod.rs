@@ -13,6 +13,8 @@ pub struct Downsample {
     pub modulo: usize,
 }
 
+impl_native_codec!(Downsample { axis, stride, modulo });
+
 impl Downsample {
     fn eval_t<T: Datum>(&self, input: &Tensor) -> TractResult<Arc<Tensor>> {
         let input = input.to_array_view::<T>()?;
@@ -93,13 +95,16 @@ impl TypedOp for Downsample {
         _pulse: usize,
     ) -> TractResult<TVec<OutletId>> {
         let input = mapping[&node.inputs[0]];
-        let pulse = target.outlet_fact(input)?.pulse();
-        if pulse % self.stride != 0 {
+        let fact = target.outlet_fact(input)?;
+        if fact.pulse() % self.stride != 0 {
             bail!("Pulsificaton requires pulse to be a stride multiple")
         }
-        target.wire_node(&*node.name, self.clone(), &[input])
+        // the delayed frames shift the ones to pick
+        let modulo = (self.modulo + fact.delay) % self.stride;
+        target.wire_node(&*node.name, Downsample { modulo, ..self.clone() }, &[input])
     }
 
+    native_save!("Downsample");
     as_op!();
 }
 
@@ -108,6 +113,8 @@ impl PulsedOp for Downsample {
         let mut fact = inputs[0].clone();
         fact.shape[self.axis] /= self.stride;
         fact.dim = fact.dim.div_ceil(self.stride as u32);
+        // output frames picked before the first input frame out of the delay
+        fact.delay = (fact.delay + self.stride - 1 - self.modulo) / self.stride;
         Ok(tvec!(fact))
     }
 
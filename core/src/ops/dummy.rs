@@ -3,6 +3,8 @@ use crate::internal::*;
 #[derive(Debug, Clone, new, Hash)]
 pub struct Dummy;
 
+impl_native_codec!(Dummy {});
+
 impl Op for Dummy {
     fn name(&self) -> Cow<str> {
         "Dummy".into()
@@ -19,6 +21,7 @@ impl StatelessOp for Dummy {
 }
 
 impl TypedOp for Dummy {
+    native_save!("Dummy");
     as_op!();
 
     fn output_facts(&self, _inputs: &[&TypedFact]) -> TractResult<TVec<TypedFact>> {
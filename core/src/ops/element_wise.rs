@@ -50,6 +50,10 @@ pub trait ElementWiseMiniOp:
     fn info(&self) -> TractResult<Vec<String>> {
         Ok(vec![])
     }
+    #[allow(unused_variables)]
+    fn save(&self, encoder: &mut Encoder) -> TractResult<Option<&'static str>> {
+        Ok(None)
+    }
 }
 
 impl Hash for Box<dyn ElementWiseMiniOp> {
@@ -65,6 +69,18 @@ downcast_rs::impl_downcast!(ElementWiseMiniOp);
 #[derive(Debug, Clone, Hash)]
 pub struct ElementWiseOp(pub Box<dyn ElementWiseMiniOp>);
 
+impl Encode for ElementWiseOp {
+    fn encode(&self, encoder: &mut Encoder) -> TractResult<()> {
+        Encode::encode(&self.0, encoder)
+    }
+}
+
+impl Decode for ElementWiseOp {
+    fn decode(decoder: &mut Decoder) -> TractResult<Self> {
+        Ok(ElementWiseOp(Decode::decode(decoder)?))
+    }
+}
+
 impl Op for ElementWiseOp {
     fn name(&self) -> Cow<str> {
         format!("{}", self.0.name()).into()
@@ -163,6 +179,7 @@ impl TypedOp for ElementWiseOp {
         target.wire_node(&*node.name, self.clone(), &[input])
     }
 
+    native_save!("ElementWiseOp");
     as_op!();
 }
 
@@ -231,6 +248,26 @@ macro_rules! element_wise {
                 $validation
             }
             )?
+            fn save(
+                &self,
+                encoder: &mut $crate::native::Encoder
+            ) -> TractResult<Option<&'static str>> {
+                $crate::native::Encode::encode(self, encoder)?;
+                Ok(Some(stringify!($Op)))
+            }
+        }
+        impl $crate::native::Encode for $Op {
+            #[allow(unused_variables)]
+            fn encode(&self, encoder: &mut $crate::native::Encoder) -> TractResult<()> {
+                $( $( $crate::native::Encode::encode(&self.$var, encoder)?; )* )?
+                Ok(())
+            }
+        }
+        impl $crate::native::Decode for $Op {
+            #[allow(unused_variables)]
+            fn decode(decoder: &mut $crate::native::Decoder) -> TractResult<Self> {
+                Ok($Op { $( $( $var: $crate::native::Decode::decode(decoder)? ),* )? })
+            }
         }
         pub fn $func($( $($var: $var_typ),* )?) -> $crate::ops::element_wise::ElementWiseOp {
             $crate::ops::element_wise::ElementWiseOp(Box::new($Op { $( $($var),* )? } ))
@@ -305,6 +342,26 @@ macro_rules! element_wise_oop {
                 $validation
             }
             )?
+            fn save(
+                &self,
+                encoder: &mut $crate::native::Encoder
+            ) -> TractResult<Option<&'static str>> {
+                $crate::native::Encode::encode(self, encoder)?;
+                Ok(Some(stringify!($Op)))
+            }
+        }
+        impl $crate::native::Encode for $Op {
+            #[allow(unused_variables)]
+            fn encode(&self, encoder: &mut $crate::native::Encoder) -> TractResult<()> {
+                $( $( $crate::native::Encode::encode(&self.$var, encoder)?; )* )?
+                Ok(())
+            }
+        }
+        impl $crate::native::Decode for $Op {
+            #[allow(unused_variables)]
+            fn decode(decoder: &mut $crate::native::Decoder) -> TractResult<Self> {
+                Ok($Op { $( $( $var: $crate::native::Decode::decode(decoder)? ),* )? })
+            }
         }
         pub fn $func($( $($var: $var_typ),* )?) -> $crate::ops::element_wise::ElementWiseOp {
             $crate::ops::element_wise::ElementWiseOp(Box::new($Op { $( $($var),* )? } ))
@@ -3,6 +3,8 @@ use crate::internal::*;
 #[derive(Debug, Clone, Default, Hash)]
 pub struct Identity;
 
+impl_native_codec!(Identity {});
+
 impl Op for Identity {
     fn name(&self) -> Cow<str> {
         "Identity".into()
@@ -36,6 +38,7 @@ impl TypedOp for Identity {
         Ok(Some(TypedModelPatch::shunt_one_op(model, node)?))
     }
 
+    native_save!("Identity");
     as_op!();
 }
 
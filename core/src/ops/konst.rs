@@ -5,6 +5,8 @@ pub struct Const {
     pub value: Arc<Tensor>,
 }
 
+impl_native_codec!(Const { value });
+
 impl Const {
     pub fn for_tensor(tensor: Tensor) -> Const {
         Const { value: tensor.into() }
@@ -27,6 +29,7 @@ impl StatelessOp for Const {
 }
 
 impl TypedOp for Const {
+    native_save!("Const");
     as_op!();
 
     fn output_facts(&self, _inputs: &[&TypedFact]) -> TractResult<TVec<TypedFact>> {
@@ -27,6 +27,8 @@ element_wise!(not, Not, [bool] => |_, vs| {
 #[derive(Debug, Clone, new, Default, Hash)]
 pub struct Iff;
 
+impl_native_codec!(Iff {});
+
 impl Iff {
     pub unsafe fn eval_t<T: Datum>(
         cond: &ArrayViewD<bool>,
@@ -72,6 +74,7 @@ impl StatelessOp for Iff {
 }
 
 impl TypedOp for Iff {
+    native_save!("Iff");
     as_op!();
 
     fn output_facts(&self, inputs: &[&TypedFact]) -> TractResult<TVec<TypedFact>> {
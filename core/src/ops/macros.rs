@@ -65,6 +65,39 @@ macro_rules! canonic {
     }
 }
 
+#[macro_export]
+macro_rules! native_save {
+    ($tag: expr) => {
+        fn save(
+            &self,
+            encoder: &mut $crate::native::Encoder,
+        ) -> $crate::TractResult<Option<&'static str>> {
+            $crate::native::Encode::encode(self, encoder)?;
+            Ok(Some($tag))
+        }
+    };
+}
+
+#[macro_export]
+macro_rules! impl_native_codec {
+    ($Struct: ident { $($field: tt),* }) => {
+        impl $crate::native::Encode for $Struct {
+            #[allow(unused_variables)]
+            fn encode(&self, encoder: &mut $crate::native::Encoder) -> $crate::TractResult<()> {
+                $( $crate::native::Encode::encode(&self.$field, encoder)?; )*
+                Ok(())
+            }
+        }
+
+        impl $crate::native::Decode for $Struct {
+            #[allow(unused_variables)]
+            fn decode(decoder: &mut $crate::native::Decoder) -> $crate::TractResult<Self> {
+                Ok($Struct { $( $field: $crate::native::Decode::decode(decoder)? ),* })
+            }
+        }
+    };
+}
+
 #[macro_export]
 macro_rules! args_1 {
     ($inputs:expr) => {{
@@ -8,70 +8,114 @@ use crate::ops::quant::QParams;
 use ndarray::*;
 
 fn eval(
-    a: &Tensor,
-    b: &Tensor,
+    a: Arc<Tensor>,
+    b: Arc<Tensor>,
+    a_trans: bool,
+    b_trans: bool,
+    c_trans: bool,
+    q_params: Option<&QParams>,
+) -> TractResult<TVec<Arc<Tensor>>> {
+    let op = mat_mul_batched(
+        a.datum_type(),
+        b.datum_type(),
+        a.shape(),
+        b.shape(),
+        a_trans,
+        b_trans,
+        c_trans,
+        q_params,
+    )?;
+    op.as_op().as_stateless().unwrap().eval(tvec!(a, b))
+}
+
+/// Physical operator for the product of two variable operands with known
+/// shapes.
+fn mat_mul_batched(
+    a_dt: DatumType,
+    b_dt: DatumType,
+    a_shape: &[usize],
+    b_shape: &[usize],
     a_trans: bool,
     b_trans: bool,
     c_trans: bool,
     q_params: Option<&QParams>,
-) -> TractResult<Tensor> {
+) -> TractResult<Box<dyn TypedOp>> {
     if let Some(q) = q_params {
-        if (a.datum_type(), b.datum_type()) == (i8::datum_type(), i8::datum_type()) {
-            return eval_t(a, b, a_trans, b_trans, c_trans, q_params, &|m, k, n| {
-                MMMWrapper::Quant((tract_linalg::ops().qmmm_i8_i32)(m, k, n))
-            });
-        } else if (a.datum_type(), b.datum_type()) == (i8::datum_type(), i8::datum_type()) {
+        if (a_dt, b_dt) == (i8::datum_type(), i8::datum_type()) {
             if q.c_datum_type == i32::datum_type() {
-                return eval_t(a, b, a_trans, b_trans, c_trans, q_params, &|m, k, n| {
-                    MMMWrapper::Quant((tract_linalg::ops().qmmm_i8_i32)(m, k, n))
-                });
+                return new_mat_mul_batched(
+                    a_shape,
+                    b_shape,
+                    a_trans,
+                    b_trans,
+                    c_trans,
+                    q_params,
+                    &|m, k, n| MMMWrapper::Quant((tract_linalg::ops().qmmm_i8_i32)(m, k, n)),
+                );
             } else if q.c_datum_type == i8::datum_type() {
-                return eval_t(a, b, a_trans, b_trans, c_trans, q_params, &|m, k, n| {
-                    MMMWrapper::Quant((tract_linalg::ops().qmmm_i8_i8)(m, k, n))
-                });
+                return new_mat_mul_batched(
+                    a_shape,
+                    b_shape,
+                    a_trans,
+                    b_trans,
+                    c_trans,
+                    q_params,
+                    &|m, k, n| MMMWrapper::Quant((tract_linalg::ops().qmmm_i8_i8)(m, k, n)),
+                );
             }
-        } else if (a.datum_type(), b.datum_type()) == (u8::datum_type(), u8::datum_type()) {
+        } else if (a_dt, b_dt) == (u8::datum_type(), u8::datum_type()) {
             if q.c_datum_type == i32::datum_type() {
-                return eval_t(a, b, a_trans, b_trans, c_trans, q_params, &|m, k, n| {
-                    MMMWrapper::Quant((tract_linalg::ops().qmmm_u8_i32)(m, k, n))
-                });
+                return new_mat_mul_batched(
+                    a_shape,
+                    b_shape,
+                    a_trans,
+                    b_trans,
+                    c_trans,
+                    q_params,
+                    &|m, k, n| MMMWrapper::Quant((tract_linalg::ops().qmmm_u8_i32)(m, k, n)),
+                );
             } else if q.c_datum_type == u8::datum_type() {
-                return eval_t(a, b, a_trans, b_trans, c_trans, q_params, &|m, k, n| {
-                    MMMWrapper::Quant((tract_linalg::ops().qmmm_u8_u8)(m, k, n))
-                });
+                return new_mat_mul_batched(
+                    a_shape,
+                    b_shape,
+                    a_trans,
+                    b_trans,
+                    c_trans,
+                    q_params,
+                    &|m, k, n| MMMWrapper::Quant((tract_linalg::ops().qmmm_u8_u8)(m, k, n)),
+                );
             }
         }
-    } else if (a.datum_type(), b.datum_type()) == (f32::datum_type(), f32::datum_type()) {
-        return eval_t(a, b, a_trans, b_trans, c_trans, q_params, &|m, k, n| {
-            MMMWrapper::Plain((tract_linalg::ops().mmm_f32)(m, k, n))
-        });
+    } else if (a_dt, b_dt) == (f32::datum_type(), f32::datum_type()) {
+        return new_mat_mul_batched(
+            a_shape,
+            b_shape,
+            a_trans,
+            b_trans,
+            c_trans,
+            q_params,
+            &|m, k, n| MMMWrapper::Plain((tract_linalg::ops().mmm_f32)(m, k, n)),
+        );
     }
-    bail!(
-        "Unsupported combination for MatMul eval (a: {:?}, b:{:?} q:{:?})",
-        a.datum_type(),
-        b.datum_type(),
-        q_params
-    );
+    bail!("Unsupported combination for MatMul eval (a: {:?}, b:{:?} q:{:?})", a_dt, b_dt, q_params);
 }
 
-fn eval_t<TA, TB, TC, TI>(
-    a: &Tensor,
-    b: &Tensor,
+fn new_mat_mul_batched<TA, TB, TC, TI>(
+    a_shape: &[usize],
+    b_shape: &[usize],
     a_trans: bool,
     b_trans: bool,
     c_trans: bool,
     q_params: Option<&QParams>,
-    mmm: impl Fn(usize, usize, usize) -> MMMWrapper<TA, TB, TC, TI>,
-) -> TractResult<Tensor>
+    mmm: &impl Fn(usize, usize, usize) -> MMMWrapper<TA, TB, TC, TI>,
+) -> TractResult<Box<dyn TypedOp>>
 where
     TA: Datum + Copy + Zero,
     TB: Datum + Copy + Zero,
     TC: Datum + Copy + Zero + fmt::Debug,
     TI: Datum + Copy + Add + Mul + Zero + fmt::Debug,
 {
-    let a = a.to_array_view::<TA>()?;
-    let b = b.to_array_view::<TB>()?;
-    let mut geo = Geo::<TA, TB, TC, TI>::new(a.shape(), b.shape(), a_trans, b_trans, c_trans, mmm)?;
+    let mut geo = Geo::<TA, TB, TC, TI>::new(a_shape, b_shape, a_trans, b_trans, c_trans, mmm)?;
     unsafe {
         geo.mm.as_mmm_mut().c_from_data_and_strides(
             if c_trans { 1 } else { *geo.bc_c_shape.last().unwrap() as isize },
@@ -81,49 +125,34 @@ where
             geo.mm.set_quant_params(q)?;
         }
     }
-    let a = a.into_shape(&*geo.bc_a_shape)?;
-    let b = b.into_shape(&*geo.bc_b_shape)?;
-    let mut c = unsafe { Array::<TC, IxDyn>::uninitialized(&*geo.bc_c_shape) };
-
-    let b_pack = geo.mm.as_mmm().b_pack();
-
-    let mut pa = unsafe {
-        Tensor::uninitialized_aligned::<TA>(
-            &[geo.mm.as_mmm().a_pack().len()],
-            geo.mm.as_mmm().a_pack().alignment(),
-        )?
+    // broadcast axes get a zero stride, so the same matrix is used again
+    let prefix = geo.c_shape_prefix.len();
+    let prefix_strides = |shape: &[usize]| -> TVec<isize> {
+        let mut stride = shape[prefix..].iter().product::<usize>() as isize;
+        let mut strides: TVec<isize> = shape[..prefix]
+            .iter()
+            .rev()
+            .map(|&d| {
+                let s = if d == 1 { 0 } else { stride };
+                stride *= d as isize;
+                s
+            })
+            .collect();
+        strides.reverse();
+        strides
     };
-    let mut pb =
-        unsafe { Tensor::uninitialized_aligned::<TB>(&[b_pack.len()], b_pack.alignment())? };
-
-    for prefix in indices(&*geo.c_shape_prefix).into_iter() {
-        let mut a = a.view();
-        let mut b = b.view();
-        let mut c = c.view_mut();
-        for (axis, &dim) in prefix.slice().iter().enumerate() {
-            let d = dim.min(a.shape()[axis] - 1);
-            a.slice_axis_inplace(Axis(axis), (d..=d).into());
-            let d = dim.min(b.shape()[axis] - 1);
-            b.slice_axis_inplace(Axis(axis), (d..=d).into());
-            c.slice_axis_inplace(Axis(axis), (dim..=dim).into());
-        }
-        geo.mm.as_mmm().a_pack().pack(
-            pa.as_ptr_mut()?,
-            a.as_ptr(),
-            a.strides()[prefix.ndim() + a_trans as usize],
-            a.strides()[prefix.ndim() + !a_trans as usize],
-        );
-        b_pack.pack(
-            pb.as_ptr_mut()?,
-            b.as_ptr(),
-            b.strides()[prefix.ndim() + b_trans as usize],
-            b.strides()[prefix.ndim() + !b_trans as usize],
-        );
-        unsafe {
-            geo.mm.run(pa.as_ptr()?, pb.as_ptr()?, c.as_mut_ptr(), &[]);
-        }
-    }
-    unsafe { Ok(c.into_tensor().into_shape(&geo.final_c_shape)?) }
+    let a_cols = *geo.bc_a_shape.last().unwrap() as isize;
+    let b_cols = *geo.bc_b_shape.last().unwrap() as isize;
+    Ok(Box::new(phy::MatMatMulBatched {
+        a_prefix_strides: prefix_strides(&geo.bc_a_shape),
+        b_prefix_strides: prefix_strides(&geo.bc_b_shape),
+        c_prefix_strides: prefix_strides(&geo.bc_c_shape),
+        a_strides: if a_trans { (1, a_cols) } else { (a_cols, 1) },
+        b_strides: if b_trans { (1, b_cols) } else { (b_cols, 1) },
+        c_fact: TypedFact::dt_shape(TC::datum_type(), &*geo.final_c_shape)?,
+        c_prefix: geo.c_shape_prefix,
+        mmm: geo.mm,
+    }))
 }
 
 pub fn compute_shapes<D: DimLike>(
@@ -306,6 +335,8 @@ pub struct MatMul {
     pub q_params: Option<QParams>,
 }
 
+impl_native_codec!(MatMul { a_trans, b_trans, c_trans, q_params });
+
 impl MatMul {
     pub fn with_a_trans(self, a_trans: bool) -> MatMul {
         MatMul { a_trans, ..self }
@@ -334,16 +365,9 @@ impl Op for MatMul {
 }
 
 impl StatelessOp for MatMul {
-    fn eval(&self, inputs: TVec<Arc<Tensor>>) -> TractResult<TVec<Arc<Tensor>>> {
-        let t = eval(
-            &inputs[0],
-            &inputs[1],
-            self.a_trans,
-            self.b_trans,
-            self.c_trans,
-            self.q_params.as_ref(),
-        )?;
-        Ok(tvec!(t.into_arc_tensor()))
+    fn eval(&self, mut inputs: TVec<Arc<Tensor>>) -> TractResult<TVec<Arc<Tensor>>> {
+        let (a, b) = args_2!(inputs);
+        eval(a, b, self.a_trans, self.b_trans, self.c_trans, self.q_params.as_ref())
     }
 }
 
@@ -392,6 +416,29 @@ impl TypedOp for MatMul {
         return Ok(Some(patch));
     }
 
+    fn codegen(
+        &self,
+        model: &TypedModel,
+        node: &TypedNode,
+    ) -> TractResult<Option<TypedModelPatch>> {
+        let mut inputs = model.node_input_facts(node.id)?;
+        let (a, b) = args_2!(inputs);
+        if let (Some(a_shape), Some(b_shape)) = (a.shape.as_finite(), b.shape.as_finite()) {
+            let op = mat_mul_batched(
+                a.datum_type,
+                b.datum_type,
+                a_shape,
+                b_shape,
+                self.a_trans,
+                self.b_trans,
+                self.c_trans,
+                self.q_params.as_ref(),
+            )?;
+            return Ok(Some(TypedModelPatch::replace_single_op(model, node, &node.inputs, op)?));
+        }
+        Ok(None)
+    }
+
     fn cost(&self, inputs: &[&TypedFact]) -> TractResult<TVec<(Cost, TDim)>> {
         cost(
             &inputs[0].shape.to_tvec(),
@@ -402,18 +449,21 @@ impl TypedOp for MatMul {
         )
     }
 
+    native_save!("MatMul");
     as_op!();
 }
 
 #[derive(Debug, Clone, new, Hash)]
 pub struct MatMulUnary {
-    a: Arc<Tensor>,
-    a_trans: bool,
-    b_trans: bool,
-    c_trans: bool,
-    q_params: Option<QParams>,
+    pub a: Arc<Tensor>,
+    pub a_trans: bool,
+    pub b_trans: bool,
+    pub c_trans: bool,
+    pub q_params: Option<QParams>,
 }
 
+impl_native_codec!(MatMulUnary { a, a_trans, b_trans, c_trans, q_params });
+
 impl Op for MatMulUnary {
     fn name(&self) -> Cow<str> {
         "MatMulUnary".into()
@@ -439,16 +489,9 @@ impl Op for MatMulUnary {
 }
 
 impl StatelessOp for MatMulUnary {
-    fn eval(&self, inputs: TVec<Arc<Tensor>>) -> TractResult<TVec<Arc<Tensor>>> {
-        let t = eval(
-            &self.a,
-            &inputs[0],
-            self.a_trans,
-            self.b_trans,
-            self.c_trans,
-            self.q_params.as_ref(),
-        )?;
-        Ok(tvec!(t.into_arc_tensor()))
+    fn eval(&self, mut inputs: TVec<Arc<Tensor>>) -> TractResult<TVec<Arc<Tensor>>> {
+        let b = args_1!(inputs);
+        eval(self.a.clone(), b, self.a_trans, self.b_trans, self.c_trans, self.q_params.as_ref())
     }
 }
 
@@ -735,6 +778,7 @@ impl TypedOp for MatMulUnary {
         Ok(None)
     }
 
+    native_save!("MatMulUnary");
     as_op!();
 }
 
@@ -918,4 +962,62 @@ mod test {
         let c_found = op.eval(tvec!(b, a)).unwrap().pop().unwrap();
         c.close_enough(&c_found, true).unwrap();
     }
+
+    fn naive_batched(a: &ArrayD<f32>, b: &ArrayD<f32>, b_trans: bool) -> ArrayD<f32> {
+        let (heads, m, k) = (a.shape()[1], a.shape()[2], a.shape()[3]);
+        let n = b.shape()[2 + !b_trans as usize];
+        Array::from_shape_fn(vec![2, heads, m, n], |ix| {
+            (0..k)
+                .map(|kk| {
+                    let bb = (ix[0].min(b.shape()[0] - 1), ix[1].min(b.shape()[1] - 1));
+                    let b = if b_trans {
+                        b[[bb.0, bb.1, ix[3], kk]]
+                    } else {
+                        b[[bb.0, bb.1, kk, ix[3]]]
+                    };
+                    a[[ix[0], ix[1], ix[2], kk]] * b
+                })
+                .sum()
+        })
+    }
+
+    #[test]
+    fn batched_with_broadcast() {
+        let a = Array::from_shape_fn(vec![2, 3, 4, 5], |ix| {
+            (ix[0] * 7 + ix[1] * 5 + ix[2] * 3 + ix[3]) as f32 / 10.
+        });
+        let b = Array::from_shape_fn(vec![1, 3, 5, 2], |ix| (ix[1] + ix[2] * 2 + ix[3]) as f32);
+        let expected = naive_batched(&a, &b, false);
+        let c = MatMul::default()
+            .eval(tvec!(a.into_arc_tensor(), b.into_arc_tensor()))
+            .unwrap()
+            .pop()
+            .unwrap();
+        c.close_enough(&expected.into_tensor(), true).unwrap();
+    }
+
+    #[test]
+    fn batched_codegen() {
+        let mut model = TypedModel::default();
+        let q = model
+            .add_source("q", TypedFact::dt_shape(f32::datum_type(), [2, 3, 4, 5].as_ref()).unwrap())
+            .unwrap();
+        let k = model
+            .add_source("k", TypedFact::dt_shape(f32::datum_type(), [2, 3, 6, 5].as_ref()).unwrap())
+            .unwrap();
+        let c = model.wire_node("c", MatMul::default().with_b_trans(true), &[q, k]).unwrap();
+        model.set_output_outlets(&c).unwrap();
+        let model = model.into_optimized().unwrap();
+        assert!(model.nodes().iter().any(|n| n.op().name() == "MatMatMulBatched"));
+        let q = Array::from_shape_fn(vec![2, 3, 4, 5], |ix| (ix[0] + ix[1] * ix[3] + ix[2]) as f32);
+        let k = Array::from_shape_fn(vec![2, 3, 6, 5], |ix| (ix[0] * ix[2] + ix[1] + ix[3]) as f32);
+        let expected = naive_batched(&q, &k, true);
+        let c = SimplePlan::new(model)
+            .unwrap()
+            .run(tvec!(q.into_tensor(), k.into_tensor()))
+            .unwrap()
+            .pop()
+            .unwrap();
+        c.close_enough(&expected.into_tensor(), true).unwrap();
+    }
 }
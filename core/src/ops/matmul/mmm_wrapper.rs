@@ -82,8 +82,15 @@ where
             if let Some(t) = params.zero_point_c.as_ref() {
                 q.set_zero_point_c_scalar(t.cast_to_scalar()?)
             }
-            if let Some(factor) = params.scale_factor {
-                q.set_scale_factor(factor);
+            if let Some(t) = params.scale_factor.as_ref() {
+                if let Some(f) = t.as_slice::<f32>()?.iter().find(|&&f| !(f > 0.0 && f < 1.0)) {
+                    bail!("Can not requantize with scale factor {}, it must be in (0, 1)", f)
+                }
+                if t.rank() == 0 {
+                    q.set_scale_factor(*t.to_scalar()?)
+                } else {
+                    q.set_scale_factor_vector(t.as_slice()?.to_vec())
+                }
             }
         }
         Ok(())
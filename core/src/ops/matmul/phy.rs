@@ -134,55 +134,79 @@ where
     TC: Datum + Copy,
     TI: Datum + Copy + Add + Mul + Zero + fmt::Debug,
 {
-    fn eval(&self, mut inputs: TVec<Arc<Tensor>>) -> TractResult<TVec<Arc<Tensor>>> {
-        unsafe {
-            let b = args_1!(inputs);
-            let mut c = Tensor::uninitialized::<TC>(&*self.c_fact.shape.as_finite().unwrap())?;
-            if let Some((prefix_dim, prefix_strides)) = &self.c_prefix_dim_and_stride {
-                let b = b.to_array_view::<TB>()?;
-                let mut c = c.to_array_view_mut::<TC>()?;
-                for prefix in indices(&**prefix_dim).into_iter() {
-                    let mut a = self.packed_as.view();
-                    let mut b = b.view();
-                    let mut c: *mut TC = c.as_mut_ptr();
-                    for (ix, &dim) in prefix.slice().iter().enumerate() {
-                        let d = dim.min(a.shape()[0] - 1);
-                        a.index_axis_inplace(Axis(0), d);
-                        let d = dim.min(b.shape()[0] - 1);
-                        b.index_axis_inplace(Axis(0), d);
-                        c = c.offset(prefix_strides[ix] * dim as isize);
-                    }
-                    let pa: &Tensor = a.iter().next().unwrap();
-                    if let Some(fused) = &self.fused_ops {
-                        let mut fused = fused.view();
-                        for &dim in prefix.slice() {
-                            let d = dim.min(fused.shape()[0] - 1);
-                            fused.index_axis_inplace(Axis(0), d);
-                        }
-                        self.mmm.run(pa.as_ptr()?, b.as_ptr(), c, &fused.as_slice().unwrap()[0]);
-                    } else {
-                        self.mmm.run(pa.as_ptr()?, b.as_ptr(), c, &[]);
-                    }
+    fn eval(&self, inputs: TVec<Arc<Tensor>>) -> TractResult<TVec<Arc<Tensor>>> {
+        self.eval_with_buffers(&SessionState::default(), inputs, &mut tvec!(None))
+    }
+
+    fn eval_with_buffers(
+        &self,
+        _session: &SessionState,
+        mut inputs: TVec<Arc<Tensor>>,
+        buffers: &mut TVec<Option<Tensor>>,
+    ) -> TractResult<TVec<Arc<Tensor>>> {
+        let b = args_1!(inputs);
+        let c = if let Some(c) = buffers[0].take() {
+            c
+        } else {
+            unsafe { Tensor::uninitialized::<TC>(&*self.c_fact.shape.as_finite().unwrap())? }
+        };
+        let c = unsafe { self.eval_into(&b, c)? };
+        Ok(tvec!(c.into_arc_tensor()))
+    }
+}
+
+impl<TA, TB, TC, TI> MatMatMulUnaryFinite<TA, TB, TC, TI>
+where
+    TA: Datum + Copy + Zero,
+    TB: Datum + Copy + Zero,
+    TC: Datum + Copy,
+    TI: Datum + Copy + Add + Mul + Zero + fmt::Debug,
+{
+    unsafe fn eval_into(&self, b: &Tensor, mut c: Tensor) -> TractResult<Tensor> {
+        if let Some((prefix_dim, prefix_strides)) = &self.c_prefix_dim_and_stride {
+            let b = b.to_array_view::<TB>()?;
+            let mut c = c.to_array_view_mut::<TC>()?;
+            for prefix in indices(&**prefix_dim).into_iter() {
+                let mut a = self.packed_as.view();
+                let mut b = b.view();
+                let mut c: *mut TC = c.as_mut_ptr();
+                for (ix, &dim) in prefix.slice().iter().enumerate() {
+                    let d = dim.min(a.shape()[0] - 1);
+                    a.index_axis_inplace(Axis(0), d);
+                    let d = dim.min(b.shape()[0] - 1);
+                    b.index_axis_inplace(Axis(0), d);
+                    c = c.offset(prefix_strides[ix] * dim as isize);
                 }
-            } else {
+                let pa: &Tensor = a.iter().next().unwrap();
                 if let Some(fused) = &self.fused_ops {
-                    self.mmm.run(
-                        self.packed_as.as_slice().unwrap()[0].as_ptr()?,
-                        b.as_ptr()?,
-                        c.as_ptr_mut()?,
-                        &fused.as_slice().unwrap()[0],
-                    );
+                    let mut fused = fused.view();
+                    for &dim in prefix.slice() {
+                        let d = dim.min(fused.shape()[0] - 1);
+                        fused.index_axis_inplace(Axis(0), d);
+                    }
+                    self.mmm.run(pa.as_ptr()?, b.as_ptr(), c, &fused.as_slice().unwrap()[0]);
                 } else {
-                    self.mmm.run(
-                        self.packed_as.as_slice().unwrap()[0].as_ptr()?,
-                        b.as_ptr()?,
-                        c.as_ptr_mut()?,
-                        &[],
-                    );
+                    self.mmm.run(pa.as_ptr()?, b.as_ptr(), c, &[]);
                 }
             }
-            Ok(tvec!(c.into_arc_tensor()))
+        } else {
+            if let Some(fused) = &self.fused_ops {
+                self.mmm.run(
+                    self.packed_as.as_slice().unwrap()[0].as_ptr()?,
+                    b.as_ptr()?,
+                    c.as_ptr_mut()?,
+                    &fused.as_slice().unwrap()[0],
+                );
+            } else {
+                self.mmm.run(
+                    self.packed_as.as_slice().unwrap()[0].as_ptr()?,
+                    b.as_ptr()?,
+                    c.as_ptr_mut()?,
+                    &[],
+                );
+            }
         }
+        Ok(c)
     }
 }
 
@@ -260,3 +284,153 @@ where
 
     as_op!();
 }
+
+/// Product of two variable operands, looping over their (broadcast) batch
+/// axes with precomputed offsets. The packed A and B buffers are only
+/// refilled when the batch index moves to a different matrix, so an operand
+/// broadcast over the batch is packed once.
+#[derive(Debug, Clone, Educe)]
+#[educe(Hash)]
+pub(crate) struct MatMatMulBatched<TA, TB, TC, TI>
+where
+    TA: Datum + Copy + Zero,
+    TB: Datum + Copy + Zero,
+    TC: Datum + Copy,
+    TI: Datum + Copy + Add + Mul + Zero + fmt::Debug,
+{
+    pub(crate) c_prefix: TVec<usize>,
+    pub(crate) a_prefix_strides: TVec<isize>,
+    pub(crate) b_prefix_strides: TVec<isize>,
+    pub(crate) c_prefix_strides: TVec<isize>,
+    /// Row and column strides of each A matrix.
+    pub(crate) a_strides: (isize, isize),
+    /// Row and column strides of each B matrix.
+    pub(crate) b_strides: (isize, isize),
+    pub(crate) c_fact: TypedFact,
+    pub(crate) mmm: MMMWrapper<TA, TB, TC, TI>,
+}
+
+impl<TA, TB, TC, TI> Op for MatMatMulBatched<TA, TB, TC, TI>
+where
+    TA: Datum + Copy + Zero,
+    TB: Datum + Copy + Zero,
+    TC: Datum + Copy,
+    TI: Datum + Copy + Add + Mul + Zero + fmt::Debug,
+{
+    fn name(&self) -> Cow<str> {
+        "MatMatMulBatched".into()
+    }
+
+    fn info(&self) -> TractResult<Vec<String>> {
+        Ok(vec![
+            format!(
+                "batch: {:?} m:{} k:{} n:{}",
+                self.c_prefix,
+                self.mmm.as_mmm().m(),
+                self.mmm.as_mmm().k(),
+                self.mmm.as_mmm().n(),
+            ),
+            format!("Mult: {}", self.mmm),
+        ])
+    }
+
+    op_as_typed_op!();
+    not_a_pulsed_op!();
+}
+
+impl<TA, TB, TC, TI> StatelessOp for MatMatMulBatched<TA, TB, TC, TI>
+where
+    TA: Datum + Copy + Zero,
+    TB: Datum + Copy + Zero,
+    TC: Datum + Copy,
+    TI: Datum + Copy + Add + Mul + Zero + fmt::Debug,
+{
+    fn eval(&self, inputs: TVec<Arc<Tensor>>) -> TractResult<TVec<Arc<Tensor>>> {
+        self.eval_with_buffers(&SessionState::default(), inputs, &mut tvec!(None))
+    }
+
+    fn eval_with_buffers(
+        &self,
+        _session: &SessionState,
+        mut inputs: TVec<Arc<Tensor>>,
+        buffers: &mut TVec<Option<Tensor>>,
+    ) -> TractResult<TVec<Arc<Tensor>>> {
+        let (a, b) = args_2!(inputs);
+        let c = if let Some(c) = buffers[0].take() {
+            c
+        } else {
+            unsafe { Tensor::uninitialized::<TC>(&*self.c_fact.shape.as_finite().unwrap())? }
+        };
+        let c = unsafe { self.eval_into(&a, &b, c)? };
+        Ok(tvec!(c.into_arc_tensor()))
+    }
+}
+
+impl<TA, TB, TC, TI> MatMatMulBatched<TA, TB, TC, TI>
+where
+    TA: Datum + Copy + Zero,
+    TB: Datum + Copy + Zero,
+    TC: Datum + Copy,
+    TI: Datum + Copy + Add + Mul + Zero + fmt::Debug,
+{
+    unsafe fn eval_into(&self, a: &Tensor, b: &Tensor, mut c: Tensor) -> TractResult<Tensor> {
+        let a_pack = self.mmm.as_mmm().a_pack();
+        let b_pack = self.mmm.as_mmm().b_pack();
+        let mut pa = Tensor::uninitialized_aligned::<TA>(&[a_pack.len()], a_pack.alignment())?;
+        let mut pb = Tensor::uninitialized_aligned::<TB>(&[b_pack.len()], b_pack.alignment())?;
+        let (a_ptr, b_ptr, c_ptr) = (a.as_ptr::<TA>()?, b.as_ptr::<TB>()?, c.as_ptr_mut::<TC>()?);
+        let mut packed: (Option<isize>, Option<isize>) = (None, None);
+        for prefix in indices(&*self.c_prefix).into_iter() {
+            let offset = |strides: &[isize]| -> isize {
+                prefix.slice().iter().zip(strides.iter()).map(|(&d, &s)| d as isize * s).sum()
+            };
+            let a_offset = offset(&self.a_prefix_strides);
+            if packed.0 != Some(a_offset) {
+                a_pack.pack(
+                    pa.as_ptr_mut()?,
+                    a_ptr.offset(a_offset),
+                    self.a_strides.0,
+                    self.a_strides.1,
+                );
+                packed.0 = Some(a_offset);
+            }
+            let b_offset = offset(&self.b_prefix_strides);
+            if packed.1 != Some(b_offset) {
+                b_pack.pack(
+                    pb.as_ptr_mut()?,
+                    b_ptr.offset(b_offset),
+                    self.b_strides.0,
+                    self.b_strides.1,
+                );
+                packed.1 = Some(b_offset);
+            }
+            self.mmm.run(
+                pa.as_ptr()?,
+                pb.as_ptr()?,
+                c_ptr.offset(offset(&self.c_prefix_strides)),
+                &[],
+            );
+        }
+        Ok(c)
+    }
+}
+
+impl<TA, TB, TC, TI> TypedOp for MatMatMulBatched<TA, TB, TC, TI>
+where
+    TA: Datum + Copy + Zero,
+    TB: Datum + Copy + Zero,
+    TC: Datum + Copy,
+    TI: Datum + Copy + Add + Mul + Zero + fmt::Debug,
+{
+    fn output_facts(&self, _inputs: &[&TypedFact]) -> TractResult<TVec<TypedFact>> {
+        Ok(tvec!(self.c_fact.clone()))
+    }
+
+    fn cost(&self, _inputs: &[&TypedFact]) -> TractResult<TVec<(Cost, TDim)>> {
+        let mmm = self.mmm.as_mmm();
+        let mul: usize = self.c_prefix.iter().product();
+        Ok(tvec!((Cost::FMA(TI::datum_type()), (mul * mmm.m() * mmm.n() * mmm.k()).to_dim())))
+    }
+
+    as_op!();
+}
@@ -18,6 +18,7 @@ pub mod array;
 pub mod cast;
 pub mod change_axes;
 pub mod cnn;
+pub mod control_flow;
 pub mod downsample;
 pub mod dummy;
 pub mod identity;
@@ -65,6 +66,31 @@ pub trait OpState: fmt::Debug + Send + dyn_clone::DynClone {
 
 pub trait StatelessOp: Op {
     fn eval(&self, inputs: TVec<Arc<Tensor>>) -> TractResult<TVec<Arc<Tensor>>>;
+
+    /// Evaluates the operation in a running session, where the symbols of
+    /// the model dimensions have been resolved from the inputs.
+    fn eval_in_session(
+        &self,
+        _session: &SessionState,
+        inputs: TVec<Arc<Tensor>>,
+    ) -> TractResult<TVec<Arc<Tensor>>> {
+        self.eval(inputs)
+    }
+
+    /// Evaluates the operation, possibly writing its outputs in preallocated
+    /// buffers.
+    ///
+    /// `buffers` holds, for each output, an uninitialized tensor with the
+    /// datum type and shape of the output fact when the plan has one to
+    /// offer. Operators opt in by taking the buffers they use.
+    fn eval_with_buffers(
+        &self,
+        session: &SessionState,
+        inputs: TVec<Arc<Tensor>>,
+        _buffers: &mut TVec<Option<Tensor>>,
+    ) -> TractResult<TVec<Arc<Tensor>>> {
+        self.eval_in_session(session, inputs)
+    }
 }
 
 pub trait StatefullOp {
@@ -145,6 +171,14 @@ pub trait TypedOp:
     /// Deduce output facts from input facts.
     fn output_facts(&self, inputs: &[&TypedFact]) -> TractResult<TVec<TypedFact>>;
 
+    /// The data-dependent dimensions the op makes up (see
+    /// `TDim::data_dependent`).
+    ///
+    /// The model the op is wired in gives them symbols of its own.
+    fn data_dependent_dims_mut(&mut self) -> TVec<&mut TDim> {
+        tvec!()
+    }
+
     #[allow(unused_variables)]
     fn invariants(&self, model: &TypedModel, node: &TypedNode) -> TractResult<Invariants> {
         Ok(Invariants::default())
@@ -253,6 +287,15 @@ pub trait TypedOp:
     fn nested_model_multipliers(&self, inputs: &[&TypedFact]) -> Vec<(Cow<str>, f32)> {
         vec![]
     }
+
+    /// Encode the op attributes for the native model format.
+    ///
+    /// Returns the tag the op is registered under in `native::Registry`, or
+    /// None if the op can not be saved.
+    #[allow(unused_variables)]
+    fn save(&self, encoder: &mut Encoder) -> TractResult<Option<&'static str>> {
+        Ok(None)
+    }
 }
 
 pub trait PulsedOp:
@@ -8,6 +8,8 @@ pub struct ArgMaxMin {
     pub keepdims: bool,
 }
 
+impl_native_codec!(ArgMaxMin { max, axis, keepdims });
+
 impl ArgMaxMin {
     fn eval_t<T: Datum + PartialOrd>(&self, input: Arc<Tensor>) -> TractResult<Arc<Tensor>> {
         use std::cmp::Ordering;
@@ -53,5 +55,6 @@ impl TypedOp for ArgMaxMin {
         Ok(tvec!(TypedFact::dt_shape(i64::datum_type(), &*shape)?))
     }
 
+    native_save!("ArgMaxMin");
     as_op!();
 }
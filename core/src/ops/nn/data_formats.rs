@@ -9,6 +9,29 @@ pub enum DataFormat {
     HWC,
 }
 
+impl Encode for DataFormat {
+    fn encode(&self, encoder: &mut Encoder) -> TractResult<()> {
+        let code: u8 = match self {
+            DataFormat::NCHW => 0,
+            DataFormat::NHWC => 1,
+            DataFormat::CHW => 2,
+            DataFormat::HWC => 3,
+        };
+        code.encode(encoder)
+    }
+}
+
+impl Decode for DataFormat {
+    fn decode(decoder: &mut Decoder) -> TractResult<Self> {
+        Ok(match u8::decode(decoder)? {
+            0 => DataFormat::NCHW,
+            1 => DataFormat::NHWC,
+            2 => DataFormat::CHW,
+            3 => DataFormat::HWC,
+            code => bail!("Invalid DataFormat code {}", code),
+        })
+    }
+}
 impl Default for DataFormat {
     fn default() -> DataFormat {
         DataFormat::NCHW
@@ -6,6 +6,8 @@ pub struct GlobalAvgPool {
     //    data_is_nhwc: bool, // default is nchw (onnx)
 }
 
+impl_native_codec!(GlobalAvgPool {});
+
 impl GlobalAvgPool {
     fn eval_t<D: Datum + ::num_traits::Float + ::num_traits::FromPrimitive>(
         &self,
@@ -49,6 +51,7 @@ impl StatelessOp for GlobalAvgPool {
 }
 
 impl TypedOp for GlobalAvgPool {
+    native_save!("GlobalAvgPool");
     as_op!();
 
     fn output_facts(&self, inputs: &[&TypedFact]) -> TractResult<TVec<TypedFact>> {
@@ -61,6 +64,8 @@ pub struct GlobalLpPool {
     p: usize, //    data_is_nhwc: bool, // default is nchw (onnx)
 }
 
+impl_native_codec!(GlobalLpPool { p });
+
 impl GlobalLpPool {
     fn eval_t<D: Datum + ::num_traits::Float>(
         &self,
@@ -107,6 +112,7 @@ impl StatelessOp for GlobalLpPool {
     }
 }
 impl TypedOp for GlobalLpPool {
+    native_save!("GlobalLpPool");
     as_op!();
 
     fn output_facts(&self, inputs: &[&TypedFact]) -> TractResult<TVec<TypedFact>> {
@@ -119,6 +125,8 @@ pub struct GlobalMaxPool {
     //    data_is_nhwc: bool, // default is nchw (onnx)
 }
 
+impl_native_codec!(GlobalMaxPool {});
+
 impl GlobalMaxPool {
     fn eval_t<D: Datum + ::num_traits::Float>(
         &self,
@@ -157,6 +165,7 @@ impl StatelessOp for GlobalMaxPool {
 }
 
 impl TypedOp for GlobalMaxPool {
+    native_save!("GlobalMaxPool");
     as_op!();
 
     fn output_facts(&self, inputs: &[&TypedFact]) -> TractResult<TVec<TypedFact>> {
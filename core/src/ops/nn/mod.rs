@@ -1,12 +1,16 @@
 mod arg_max_min;
 mod data_formats;
 mod global_pools;
+mod non_max_suppression;
 mod reduce;
+mod roi_align;
 
 pub use self::arg_max_min::ArgMaxMin;
 pub use self::data_formats::{BaseDataShape, DataFormat, DataShape};
 pub use self::global_pools::{GlobalAvgPool, GlobalLpPool, GlobalMaxPool};
+pub use self::non_max_suppression::NonMaxSuppression;
 pub use self::reduce::{Reduce, Reducer};
+pub use self::roi_align::RoiAlign;
 
 use num_traits::{AsPrimitive, Float};
 
@@ -0,0 +1,170 @@
+use crate::internal::*;
+use ndarray::*;
+
+/// Greedy selection of boxes by decreasing score, skipping the ones
+/// overlapping too much an already selected box of the same class.
+///
+/// Inputs are boxes `[batch, boxes, 4]`, scores `[batch, classes, boxes]`,
+/// then the optional maximum number of boxes per class (default 0), IoU
+/// threshold (default 0) and score threshold. Output is one
+/// `[batch, class, box]` row per selected box, so its number of rows is only
+/// known after evaluation.
+#[derive(Debug, Clone, Hash)]
+pub struct NonMaxSuppression {
+    /// Boxes are given as `[x_center, y_center, width, height]` instead of
+    /// two opposite corners `[y1, x1, y2, x2]`.
+    pub center_point_box: bool,
+    pub optional_max_output_boxes_per_class_input: Option<usize>,
+    pub optional_iou_threshold_input: Option<usize>,
+    pub optional_score_threshold_input: Option<usize>,
+    len: TDim,
+}
+
+impl_native_codec!(NonMaxSuppression {
+    center_point_box,
+    optional_max_output_boxes_per_class_input,
+    optional_iou_threshold_input,
+    optional_score_threshold_input,
+    len
+});
+
+impl NonMaxSuppression {
+    pub fn new(
+        center_point_box: bool,
+        optional_max_output_boxes_per_class_input: Option<usize>,
+        optional_iou_threshold_input: Option<usize>,
+        optional_score_threshold_input: Option<usize>,
+    ) -> NonMaxSuppression {
+        NonMaxSuppression {
+            center_point_box,
+            optional_max_output_boxes_per_class_input,
+            optional_iou_threshold_input,
+            optional_score_threshold_input,
+            len: TDim::data_dependent(),
+        }
+    }
+
+    /// Box as (y_min, x_min, y_max, x_max).
+    fn corners(&self, b: ArrayView1<f32>) -> (f32, f32, f32, f32) {
+        if self.center_point_box {
+            let (xc, yc, w, h) = (b[0], b[1], b[2], b[3]);
+            (yc - h / 2., xc - w / 2., yc + h / 2., xc + w / 2.)
+        } else {
+            (b[0].min(b[2]), b[1].min(b[3]), b[0].max(b[2]), b[1].max(b[3]))
+        }
+    }
+
+    fn iou(a: (f32, f32, f32, f32), b: (f32, f32, f32, f32)) -> f32 {
+        let area = |b: (f32, f32, f32, f32)| (b.2 - b.0) * (b.3 - b.1);
+        let inter_h = (a.2.min(b.2) - a.0.max(b.0)).max(0.);
+        let inter_w = (a.3.min(b.3) - a.1.max(b.1)).max(0.);
+        let inter = inter_h * inter_w;
+        let union = area(a) + area(b) - inter;
+        if inter <= 0. || union <= 0. {
+            0.
+        } else {
+            inter / union
+        }
+    }
+}
+
+impl Op for NonMaxSuppression {
+    fn name(&self) -> Cow<str> {
+        "NonMaxSuppression".into()
+    }
+
+    op_as_typed_op!();
+    not_a_pulsed_op!();
+}
+
+impl StatelessOp for NonMaxSuppression {
+    fn eval(&self, inputs: TVec<Arc<Tensor>>) -> TractResult<TVec<Arc<Tensor>>> {
+        let boxes = inputs[0].cast_to::<f32>()?;
+        let boxes = boxes.to_array_view::<f32>()?.into_dimensionality::<Ix3>()?;
+        let scores = inputs[1].cast_to::<f32>()?;
+        let scores = scores.to_array_view::<f32>()?.into_dimensionality::<Ix3>()?;
+        let max_output_boxes_per_class = match self.optional_max_output_boxes_per_class_input {
+            Some(ix) => inputs[ix].cast_to_scalar::<i64>()?.max(0) as usize,
+            None => 0,
+        };
+        let iou_threshold = match self.optional_iou_threshold_input {
+            Some(ix) => inputs[ix].cast_to_scalar::<f32>()?,
+            None => 0.,
+        };
+        let score_threshold = match self.optional_score_threshold_input {
+            Some(ix) => Some(inputs[ix].cast_to_scalar::<f32>()?),
+            None => None,
+        };
+        if boxes.shape()[0] != scores.shape()[0] || boxes.shape()[1] != scores.shape()[2] {
+            bail!("Inconsistent boxes {:?} and scores {:?}", boxes.shape(), scores.shape())
+        }
+        let mut selected: Vec<[i64; 3]> = vec![];
+        for batch in 0..scores.shape()[0] {
+            for class in 0..scores.shape()[1] {
+                let scores = scores.slice(s![batch, class, ..]);
+                let mut candidates: Vec<usize> = (0..scores.len())
+                    .filter(|&ix| score_threshold.map(|t| scores[ix] > t).unwrap_or(true))
+                    .collect();
+                candidates.sort_by(|&a, &b| {
+                    scores[b].partial_cmp(&scores[a]).unwrap_or(std::cmp::Ordering::Equal)
+                });
+                let mut kept: Vec<(f32, f32, f32, f32)> = vec![];
+                for ix in candidates {
+                    if kept.len() >= max_output_boxes_per_class {
+                        break;
+                    }
+                    let candidate = self.corners(boxes.slice(s![batch, ix, ..]));
+                    if kept.iter().all(|k| Self::iou(*k, candidate) <= iou_threshold) {
+                        kept.push(candidate);
+                        selected.push([batch as i64, class as i64, ix as i64]);
+                    }
+                }
+            }
+        }
+        let output = Array2::from_shape_fn((selected.len(), 3), |(row, col)| selected[row][col]);
+        Ok(tvec!(output.into_arc_tensor()))
+    }
+}
+
+impl TypedOp for NonMaxSuppression {
+    fn output_facts(&self, _inputs: &[&TypedFact]) -> TractResult<TVec<TypedFact>> {
+        let shape = [self.len.clone(), 3.to_dim()];
+        Ok(tvec!(TypedFact::dt_shape(i64::datum_type(), shape.as_ref())?))
+    }
+
+    fn data_dependent_dims_mut(&mut self) -> TVec<&mut TDim> {
+        tvec!(&mut self.len)
+    }
+
+    native_save!("NonMaxSuppression");
+    as_op!();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn suppress_overlapping_boxes() {
+        let op = NonMaxSuppression::new(true, Some(2), Some(3), Some(4));
+        let boxes = tensor3(&[[
+            [0.5f32, 0.5, 1.0, 1.0],
+            [0.5, 0.6, 1.0, 1.0],
+            [0.5, 0.4, 1.0, 1.0],
+            [0.5, 10.5, 1.0, 1.0],
+            [0.5, 10.6, 1.0, 1.0],
+            [0.5, 100.5, 1.0, 1.0],
+        ]]);
+        let scores = tensor3(&[[[0.9f32, 0.75, 0.6, 0.95, 0.5, 0.3]]]);
+        let output = op
+            .eval(tvec!(
+                boxes.into(),
+                scores.into(),
+                rctensor0(3i64),
+                rctensor0(0.5f32),
+                rctensor0(0.0f32)
+            ))
+            .unwrap();
+        assert_eq!(*output[0], tensor2(&[[0i64, 0, 3], [0, 0, 0], [0, 0, 5]]));
+    }
+}
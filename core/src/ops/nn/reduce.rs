@@ -25,6 +25,29 @@ pub enum Reducer {
     Sum,
 }
 
+impl Encode for Reducer {
+    fn encode(&self, encoder: &mut Encoder) -> TractResult<()> {
+        let code: u8 = match self {
+            Reducer::Max => 0,
+            Reducer::Min => 1,
+            Reducer::Prod => 2,
+            Reducer::Sum => 3,
+        };
+        code.encode(encoder)
+    }
+}
+
+impl Decode for Reducer {
+    fn decode(decoder: &mut Decoder) -> TractResult<Self> {
+        Ok(match u8::decode(decoder)? {
+            0 => Reducer::Max,
+            1 => Reducer::Min,
+            2 => Reducer::Prod,
+            3 => Reducer::Sum,
+            code => bail!("Invalid Reducer code {}", code),
+        })
+    }
+}
 impl Reducer {
     pub fn reduce(&self, axes: &[usize], input: &Tensor) -> TractResult<Tensor> {
         use Reducer::*;
@@ -107,6 +130,8 @@ pub struct Reduce {
     reducer: Reducer,
 }
 
+impl_native_codec!(Reduce { axes, reducer });
+
 impl Op for Reduce {
     fn name(&self) -> Cow<str> {
         format!("Reduce<{:?}>", self.reducer).into()
@@ -126,6 +151,7 @@ impl StatelessOp for Reduce {
 }
 
 impl TypedOp for Reduce {
+    native_save!("Reduce");
     as_op!();
     fn output_facts(&self, inputs: &[&TypedFact]) -> TractResult<TVec<TypedFact>> {
         let mut shape: TVec<_> = inputs[0].shape.to_tvec();
@@ -0,0 +1,209 @@
+use crate::internal::*;
+use ndarray::*;
+
+/// Pooling of regions of interest to a fixed size, with bilinear
+/// interpolation on a regular grid of sampling points in each output bin.
+///
+/// Inputs are the feature map `[N, C, H, W]`, the regions `[R, 4]` as
+/// `[x1, y1, x2, y2]` in input image coordinates (scaled by `spatial_scale`),
+/// and the batch index of each region `[R]`. Output is
+/// `[R, C, output_height, output_width]`.
+#[derive(Clone, Debug, Educe)]
+#[educe(Hash)]
+pub struct RoiAlign {
+    pub max_mode: bool,
+    pub output_height: usize,
+    pub output_width: usize,
+    /// Sampling points per bin on each axis, adaptive if 0.
+    pub sampling_ratio: usize,
+    #[educe(Hash(method = "hash_f32"))]
+    pub spatial_scale: f32,
+    /// Shift pixel coordinates by half a pixel, and allow regions smaller
+    /// than a pixel.
+    pub half_pixel: bool,
+}
+
+impl_native_codec!(RoiAlign {
+    max_mode,
+    output_height,
+    output_width,
+    sampling_ratio,
+    spatial_scale,
+    half_pixel
+});
+
+impl RoiAlign {
+    fn sample(&self, plane: ArrayView2<f32>, y: f32, x: f32) -> [(f32, f32); 4] {
+        let (height, width) = plane.dim();
+        if y < -1.0 || y > height as f32 || x < -1.0 || x > width as f32 {
+            return [(0., 0.); 4];
+        }
+        let (mut y, mut x) = (y.max(0.), x.max(0.));
+        let mut y_low = y as usize;
+        let y_high = if y_low >= height - 1 {
+            y_low = height - 1;
+            y = y_low as f32;
+            y_low
+        } else {
+            y_low + 1
+        };
+        let mut x_low = x as usize;
+        let x_high = if x_low >= width - 1 {
+            x_low = width - 1;
+            x = x_low as f32;
+            x_low
+        } else {
+            x_low + 1
+        };
+        let (ly, lx) = (y - y_low as f32, x - x_low as f32);
+        let (hy, hx) = (1. - ly, 1. - lx);
+        [
+            (hy * hx, plane[(y_low, x_low)]),
+            (hy * lx, plane[(y_low, x_high)]),
+            (ly * hx, plane[(y_high, x_low)]),
+            (ly * lx, plane[(y_high, x_high)]),
+        ]
+    }
+
+    fn pool(&self, plane: ArrayView2<f32>, roi: ArrayView1<f32>, mut output: ArrayViewMut2<f32>) {
+        let offset = if self.half_pixel { 0.5 } else { 0. };
+        let start_x = roi[0] * self.spatial_scale - offset;
+        let start_y = roi[1] * self.spatial_scale - offset;
+        let mut roi_width = roi[2] * self.spatial_scale - offset - start_x;
+        let mut roi_height = roi[3] * self.spatial_scale - offset - start_y;
+        if !self.half_pixel {
+            roi_width = roi_width.max(1.);
+            roi_height = roi_height.max(1.);
+        }
+        let bin_height = roi_height / self.output_height as f32;
+        let bin_width = roi_width / self.output_width as f32;
+        let (grid_height, grid_width) = if self.sampling_ratio > 0 {
+            (self.sampling_ratio, self.sampling_ratio)
+        } else {
+            (bin_height.ceil() as usize, bin_width.ceil() as usize)
+        };
+        let count = (grid_height * grid_width).max(1) as f32;
+        for ((ph, pw), output) in output.indexed_iter_mut() {
+            let mut acc: Option<f32> = None;
+            for iy in 0..grid_height {
+                let y = start_y
+                    + ph as f32 * bin_height
+                    + (iy as f32 + 0.5) * bin_height / grid_height as f32;
+                for ix in 0..grid_width {
+                    let x = start_x
+                        + pw as f32 * bin_width
+                        + (ix as f32 + 0.5) * bin_width / grid_width as f32;
+                    let samples = self.sample(plane, y, x);
+                    let value = if self.max_mode {
+                        samples.iter().map(|(w, v)| w * v).fold(std::f32::MIN, f32::max)
+                    } else {
+                        samples.iter().map(|(w, v)| w * v).sum()
+                    };
+                    acc = Some(match acc {
+                        None => value,
+                        Some(acc) if self.max_mode => acc.max(value),
+                        Some(acc) => acc + value,
+                    });
+                }
+            }
+            let acc = acc.unwrap_or(0.);
+            *output = if self.max_mode { acc } else { acc / count };
+        }
+    }
+}
+
+impl Op for RoiAlign {
+    fn name(&self) -> Cow<str> {
+        "RoiAlign".into()
+    }
+
+    fn info(&self) -> TractResult<Vec<String>> {
+        Ok(vec![format!(
+            "{} to {}x{}, sampling ratio: {}, spatial scale: {}",
+            if self.max_mode { "max" } else { "avg" },
+            self.output_height,
+            self.output_width,
+            self.sampling_ratio,
+            self.spatial_scale
+        )])
+    }
+
+    op_as_typed_op!();
+    not_a_pulsed_op!();
+}
+
+impl StatelessOp for RoiAlign {
+    fn eval(&self, mut inputs: TVec<Arc<Tensor>>) -> TractResult<TVec<Arc<Tensor>>> {
+        let (input, rois, batch_indices) = args_3!(inputs);
+        let input = input.to_array_view::<f32>()?.into_dimensionality::<Ix4>()?;
+        let rois = rois.to_array_view::<f32>()?.into_dimensionality::<Ix2>()?;
+        let batch_indices = batch_indices.cast_to::<i64>()?;
+        let batch_indices = batch_indices.as_slice::<i64>()?;
+        if rois.shape()[1] != 4 || rois.shape()[0] != batch_indices.len() {
+            bail!("Inconsistent rois {:?} and batch indices {:?}", rois.shape(), batch_indices)
+        }
+        let (n, c, _, _) = input.dim();
+        let mut output =
+            Array4::<f32>::zeros((rois.shape()[0], c, self.output_height, self.output_width));
+        for (r, &batch) in batch_indices.iter().enumerate() {
+            if batch < 0 || batch as usize >= n {
+                bail!("Invalid batch index {} for {} images", batch, n)
+            }
+            for channel in 0..c {
+                self.pool(
+                    input.slice(s![batch as usize, channel, .., ..]),
+                    rois.row(r),
+                    output.slice_mut(s![r, channel, .., ..]),
+                );
+            }
+        }
+        Ok(tvec!(output.into_arc_tensor()))
+    }
+}
+
+impl TypedOp for RoiAlign {
+    fn output_facts(&self, inputs: &[&TypedFact]) -> TractResult<TVec<TypedFact>> {
+        if inputs[0].rank() != 4 {
+            bail!("RoiAlign expects a 4D input, got {:?}", inputs[0])
+        }
+        let shape = [
+            inputs[1].shape.dim(0),
+            inputs[0].shape.dim(1),
+            self.output_height.to_dim(),
+            self.output_width.to_dim(),
+        ];
+        Ok(tvec!(TypedFact::dt_shape(inputs[0].datum_type, shape.as_ref())?))
+    }
+
+    native_save!("RoiAlign");
+    as_op!();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roi_align_avg() {
+        let op = RoiAlign {
+            max_mode: false,
+            output_height: 2,
+            output_width: 2,
+            sampling_ratio: 2,
+            spatial_scale: 1.0,
+            half_pixel: false,
+        };
+        let input = Array4::from_shape_fn((1, 1, 4, 4), |(_, _, y, x)| (y * 4 + x) as f32);
+        let output = op
+            .eval(tvec!(
+                input.into_arc_tensor(),
+                rctensor2(&[[0f32, 0., 2., 2.]]),
+                rctensor1(&[0i64])
+            ))
+            .unwrap();
+        assert_eq!(output[0].shape(), &[1, 1, 2, 2]);
+        // the input is linear, so bilinear sampling gives the value at the
+        // center of each bin
+        assert_eq!(*output[0], tensor4(&[[[[2.5f32, 3.5], [6.5, 7.5]]]]));
+    }
+}
@@ -1,23 +1,33 @@
 use crate::internal::*;
 use crate::ops::element_wise::ElementWiseOp;
+use ndarray::*;
 use num_traits::AsPrimitive;
-use num_traits::Zero;
+use num_traits::{Bounded, Zero};
 use tract_linalg::lut::Lut;
 
-#[derive(Clone, Debug, Educe)]
-#[educe(Hash)]
+#[derive(Clone, Debug, Hash)]
 pub struct QParams {
     pub c_datum_type: DatumType,
     pub zero_point_a: Option<Arc<Tensor>>,
     pub zero_point_b: Option<Arc<Tensor>>,
     pub zero_point_c: Option<Arc<Tensor>>,
-    #[educe(Hash(method = "hash_scale"))]
-    pub scale_factor: Option<f32>,
+    /// f32 scalar, or one factor per row of a (per output channel)
+    ///
+    /// Matrix multiplications requantize with a 31-bit fixed point
+    /// approximation of a scalar factor in (0, 1), rounding ties towards +inf
+    /// like gemmlowp. Convolutions with per channel factors, or a factor of
+    /// at least 1, requantize in float with `RequantizeF32`, rounding ties to
+    /// even like onnxruntime.
+    pub scale_factor: Option<Arc<Tensor>>,
 }
 
-fn hash_scale<H: std::hash::Hasher>(it: &Option<f32>, state: &mut H) {
-    Hash::hash(&it.clone().unwrap_or(1.0).to_bits(), state)
-}
+impl_native_codec!(QParams {
+    c_datum_type,
+    zero_point_a,
+    zero_point_b,
+    zero_point_c,
+    scale_factor
+});
 
 fn cleanup_zeropoint(zp: &Arc<Tensor>) -> Option<Arc<Tensor>> {
     match zp.datum_type() {
@@ -31,7 +41,7 @@ fn cleanup_zeropoint_t<T: Datum + Zero + Copy>(zp: &Arc<Tensor>) -> Option<Arc<T
     let mut zp = zp.clone();
     if zp.rank() == 1 {
         let slice = zp.as_slice::<T>().unwrap();
-        if slice[1..].iter().all(|&x| x == slice[0]) {
+        if slice.len() > 0 && slice[1..].iter().all(|&x| x == slice[0]) {
             zp = rctensor0(slice[0]);
         }
     }
@@ -42,6 +52,21 @@ fn cleanup_zeropoint_t<T: Datum + Zero + Copy>(zp: &Arc<Tensor>) -> Option<Arc<T
     }
 }
 
+fn cleanup_scale_factor(scale: &Arc<Tensor>) -> TractResult<Option<Arc<Tensor>>> {
+    let mut scale = scale.clone();
+    if scale.rank() == 1 {
+        let slice = scale.as_slice::<f32>()?;
+        if slice.len() > 0 && slice[1..].iter().all(|&x| x == slice[0]) {
+            scale = rctensor0(slice[0]);
+        }
+    }
+    if scale.rank() == 0 && *scale.to_scalar::<f32>()? == 1.0 {
+        Ok(None)
+    } else {
+        Ok(Some(scale))
+    }
+}
+
 impl QParams {
     pub fn new(dt: DatumType) -> QParams {
         QParams {
@@ -66,7 +91,11 @@ impl QParams {
     }
 
     pub fn with_scale_factor(self, scale_factor: f32) -> QParams {
-        QParams { scale_factor: Some(scale_factor), ..self }
+        QParams { scale_factor: Some(rctensor0(scale_factor)), ..self }
+    }
+
+    pub fn with_scale_factors(self, scale_factors: &Arc<Tensor>) -> TractResult<QParams> {
+        Ok(QParams { scale_factor: cleanup_scale_factor(scale_factors)?, ..self })
     }
 
     pub fn set_zero_point_a(&mut self, zero_point: &Arc<Tensor>) {
@@ -82,7 +111,12 @@ impl QParams {
     }
 
     pub fn set_scale_factor(&mut self, scale_factor: f32) {
-        self.scale_factor = Some(scale_factor)
+        self.scale_factor = Some(rctensor0(scale_factor))
+    }
+
+    pub fn set_scale_factors(&mut self, scale_factors: &Arc<Tensor>) -> TractResult<()> {
+        self.scale_factor = cleanup_scale_factor(scale_factors)?;
+        Ok(())
     }
 }
 
@@ -154,6 +188,8 @@ pub struct DequantizeLinearF32 {
     zero_point: i32,
 }
 
+impl_native_codec!(DequantizeLinearF32 { scale, zero_point });
+
 impl DequantizeLinearF32 {
     fn eval_t<T: Datum + AsPrimitive<i32>>(&self, input: &Tensor) -> TractResult<Tensor> {
         let mut output = unsafe { Tensor::uninitialized::<f32>(input.shape())? };
@@ -228,25 +264,31 @@ impl TypedOp for DequantizeLinearF32 {
                 None
             };
             if let Some((scale, zero_point, dt)) = q_params {
-                // first, try Op::quantize() on all ops in the chain
+                // first, try Op::quantize() on all ops in the chain, valid only if
+                // quant exactly reverts dequant
                 let mut patch = TypedModelPatch::default();
                 let mut wire: OutletId = patch.tap_model(model, dequant.inputs[0])?.into();
                 let mut next = model.single_succ(dequant.id)?.unwrap();
-                loop {
-                    if let Some(op) = next
-                        .op
-                        .quantize(model, dequant, dt, scale, zero_point)
-                        .chain_err(|| format!("Quantizing {}", next))?
-                    {
-                        wire = patch.wire_node(&*next.name, op, [wire].as_ref())?[0];
-                    } else {
-                        break;
-                    }
-                    if next.id == current.id {
-                        patch.shunt_outside(model, OutletId::new(quant.id, 0), wire)?;
-                        return Ok(Some(patch));
-                    } else {
-                        next = model.single_succ(next.id)?.unwrap();
+                let same_quantization = incoming_dt == dt
+                    && zero_point == self.zero_point
+                    && (scale * self.scale - 1.0).abs() < 1e-6;
+                if same_quantization {
+                    loop {
+                        if let Some(op) = next
+                            .op
+                            .quantize(model, dequant, dt, scale, zero_point)
+                            .chain_err(|| format!("Quantizing {}", next))?
+                        {
+                            wire = patch.wire_node(&*next.name, op, [wire].as_ref())?[0];
+                        } else {
+                            break;
+                        }
+                        if next.id == current.id {
+                            patch.shunt_outside(model, OutletId::new(quant.id, 0), wire)?;
+                            return Ok(Some(patch));
+                        } else {
+                            next = model.single_succ(next.id)?.unwrap();
+                        }
                     }
                 }
                 // or else make a lookup table
@@ -315,6 +357,7 @@ impl TypedOp for DequantizeLinearF32 {
         target.wire_node(&*node.name, self.clone(), &[input])
     }
 
+    native_save!("DequantizeLinearF32");
     as_op!();
 }
 
@@ -352,3 +395,151 @@ element_wise_oop!(lookup_table,
 fn hash_lookup_table<H: std::hash::Hasher>(lut: &Box<dyn Lut>, h: &mut H) {
     Hash::hash_slice(lut.table(), h)
 }
+
+/// Rounds ties to even, like `nearbyintf` in the default rounding mode.
+pub fn round_half_to_even(x: f32) -> f32 {
+    if (x - x.trunc()).abs() == 0.5 {
+        2.0 * (x / 2.0).round()
+    } else {
+        x.round()
+    }
+}
+
+/// Requantizes i32 accumulators in float, like onnxruntime: values are
+/// multiplied by the scale factor of their channel along `axis`, rounded
+/// half to even, offset by the zero point and saturated to `c_datum_type`.
+#[derive(Clone, Debug, new, Hash)]
+pub struct RequantizeF32 {
+    pub axis: usize,
+    /// f32 scalar, or one factor per channel
+    pub scale_factors: Arc<Tensor>,
+    pub zero_point: i32,
+    pub c_datum_type: DatumType,
+}
+
+impl RequantizeF32 {
+    fn eval_t<T>(&self, input: &Tensor) -> TractResult<Tensor>
+    where
+        T: Datum + Bounded + AsPrimitive<f32>,
+        f32: AsPrimitive<T>,
+    {
+        let scales = self.scale_factors.as_slice::<f32>()?;
+        let input = input.to_array_view::<i32>()?;
+        let (min, max) = (T::min_value().as_(), T::max_value().as_());
+        let zero_point = self.zero_point as f32;
+        let mut output = ArrayD::<T>::default(input.shape());
+        for (c, mut channel) in output.axis_iter_mut(Axis(self.axis)).enumerate() {
+            let scale = if scales.len() == 1 { scales[0] } else { scales[c] };
+            channel.zip_mut_with(&input.index_axis(Axis(self.axis), c), |y, &x| {
+                let x = round_half_to_even(x as f32 * scale) + zero_point;
+                *y = x.max(min).min(max).as_()
+            });
+        }
+        Ok(output.into_tensor())
+    }
+}
+
+impl Op for RequantizeF32 {
+    fn name(&self) -> Cow<str> {
+        "RequantizeF32".into()
+    }
+
+    fn info(&self) -> TractResult<Vec<String>> {
+        Ok(vec![format!(
+            "axis: {} scale factors: {:?} zero_point: {} output: {:?}",
+            self.axis, self.scale_factors, self.zero_point, self.c_datum_type
+        )])
+    }
+
+    fn validation(&self) -> Validation {
+        Validation::Accurate
+    }
+
+    op_as_typed_op!();
+    not_a_pulsed_op!();
+}
+
+impl StatelessOp for RequantizeF32 {
+    fn eval(&self, inputs: TVec<Arc<Tensor>>) -> TractResult<TVec<Arc<Tensor>>> {
+        let output = match self.c_datum_type {
+            DatumType::I8 => self.eval_t::<i8>(&inputs[0])?,
+            DatumType::U8 => self.eval_t::<u8>(&inputs[0])?,
+            DatumType::I32 => self.eval_t::<i32>(&inputs[0])?,
+            dt => bail!("Unsupported type {:?}", dt),
+        };
+        Ok(tvec!(output.into_arc_tensor()))
+    }
+}
+
+impl TypedOp for RequantizeF32 {
+    fn output_facts(&self, inputs: &[&TypedFact]) -> TractResult<TVec<TypedFact>> {
+        let mut fact = inputs[0].clone();
+        fact.datum_type = self.c_datum_type;
+        Ok(tvec!(fact))
+    }
+
+    as_op!();
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn empty_per_channel_params() {
+        let qp = QParams::new(i8::datum_type())
+            .with_zero_point_b(&rctensor1::<i8>(&[]))
+            .with_scale_factors(&rctensor1::<f32>(&[]))
+            .unwrap();
+        assert_eq!(qp.scale_factor.unwrap().len(), 0);
+        assert_eq!(qp.zero_point_b.unwrap().len(), 0);
+    }
+
+    #[test]
+    fn round_half_to_even_ties() {
+        let found: Vec<f32> = [-2.5f32, -1.5, -0.5, 0.5, 1.5, 2.5, 2.4, 2.6, -2.6]
+            .iter()
+            .map(|&x| round_half_to_even(x))
+            .collect();
+        assert_eq!(found, vec!(-2.0, -2.0, -0.0, 0.0, 2.0, 2.0, 2.0, 3.0, -3.0));
+    }
+
+    // test_qlinearconv from the onnx backend test suite: a 1x1 kernel with
+    // w = 0, so the accumulator is (x - x_zp) * (w - w_zp)
+    #[test]
+    fn requantize_f32_onnx_qlinearconv() -> TractResult<()> {
+        let x = tensor2(&[
+            [255u8, 174, 162, 25, 203, 168, 58],
+            [15, 59, 237, 95, 129, 0, 64],
+            [56, 242, 153, 221, 168, 12, 166],
+            [232, 178, 186, 195, 237, 162, 237],
+            [188, 39, 124, 77, 80, 102, 43],
+            [127, 230, 21, 83, 41, 40, 134],
+            [255, 154, 92, 141, 42, 148, 247],
+        ]);
+        let expected = tensor2(&[
+            [0u8, 81, 93, 230, 52, 87, 197],
+            [240, 196, 18, 160, 126, 255, 191],
+            [199, 13, 102, 34, 87, 243, 89],
+            [23, 77, 69, 60, 18, 93, 18],
+            [67, 216, 131, 178, 175, 153, 212],
+            [128, 25, 234, 172, 214, 215, 121],
+            [0, 101, 163, 114, 213, 107, 8],
+        ]);
+        let (x_scale, w_scale, y_scale) = (0.00369204697f32, 0.00172794575f32, 0.00162681262f32);
+        let acc = x.cast_to::<i32>()?.to_array_view::<i32>()?.mapv(|x| (x - 132) * (0 - 255));
+        let op =
+            RequantizeF32::new(0, rctensor0(x_scale * w_scale / y_scale), 123, u8::datum_type());
+        let found = op.eval(tvec!(acc.into_arc_tensor()))?.remove(0);
+        assert_eq!(*found, expected);
+        Ok(())
+    }
+
+    #[test]
+    fn requantize_f32_per_channel() -> TractResult<()> {
+        let op = RequantizeF32::new(1, rctensor1(&[0.5f32, 2.0]), -1, i8::datum_type());
+        let found = op.eval(tvec!(rctensor2(&[[1i32, 100], [-3, -100], [5, 7]])))?.remove(0);
+        assert_eq!(*found, tensor2(&[[-1i8, 127], [-3, -128], [1, 13]]));
+        Ok(())
+    }
+}
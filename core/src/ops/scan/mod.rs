@@ -13,6 +13,41 @@ pub enum InputMapping<C: Clone> {
     Scan { slot: usize, axis: usize, chunk: C },
 }
 
+impl<C: Clone + Encode> Encode for InputMapping<C> {
+    fn encode(&self, encoder: &mut Encoder) -> TractResult<()> {
+        match self {
+            InputMapping::Full { slot } => {
+                0u8.encode(encoder)?;
+                slot.encode(encoder)
+            }
+            InputMapping::State { initializer } => {
+                1u8.encode(encoder)?;
+                initializer.encode(encoder)
+            }
+            InputMapping::Scan { slot, axis, chunk } => {
+                2u8.encode(encoder)?;
+                slot.encode(encoder)?;
+                axis.encode(encoder)?;
+                chunk.encode(encoder)
+            }
+        }
+    }
+}
+
+impl<C: Clone + Decode> Decode for InputMapping<C> {
+    fn decode(decoder: &mut Decoder) -> TractResult<Self> {
+        Ok(match u8::decode(decoder)? {
+            0 => InputMapping::Full { slot: usize::decode(decoder)? },
+            1 => InputMapping::State { initializer: StateInitializer::decode(decoder)? },
+            2 => InputMapping::Scan {
+                slot: usize::decode(decoder)?,
+                axis: usize::decode(decoder)?,
+                chunk: C::decode(decoder)?,
+            },
+            code => bail!("Invalid InputMapping code {}", code),
+        })
+    }
+}
 impl<C: Clone> InputMapping<C> {
     pub fn as_state(&self) -> Option<&StateInitializer> {
         match self {
@@ -72,6 +107,29 @@ pub struct OutputMapping<C: Clone, F: Clone> {
     pub state: bool,
 }
 
+impl<C: Clone + Encode, F: Clone + Encode> Encode for OutputMapping<C, F> {
+    fn encode(&self, encoder: &mut Encoder) -> TractResult<()> {
+        self.full_slot.encode(encoder)?;
+        self.axis.encode(encoder)?;
+        self.chunk.encode(encoder)?;
+        self.full_dim_hint.encode(encoder)?;
+        self.last_value_slot.encode(encoder)?;
+        self.state.encode(encoder)
+    }
+}
+
+impl<C: Clone + Decode, F: Clone + Decode> Decode for OutputMapping<C, F> {
+    fn decode(decoder: &mut Decoder) -> TractResult<Self> {
+        Ok(OutputMapping {
+            full_slot: Decode::decode(decoder)?,
+            axis: Decode::decode(decoder)?,
+            chunk: Decode::decode(decoder)?,
+            full_dim_hint: Decode::decode(decoder)?,
+            last_value_slot: Decode::decode(decoder)?,
+            state: Decode::decode(decoder)?,
+        })
+    }
+}
 impl<C: Clone, F: Clone> OutputMapping<C, F> {
     pub fn invisible(&self) -> bool {
         self.full_slot.is_none() && self.last_value_slot.is_none()
@@ -100,6 +158,30 @@ pub enum StateInitializer {
     Value(Arc<Tensor>),
 }
 
+impl Encode for StateInitializer {
+    fn encode(&self, encoder: &mut Encoder) -> TractResult<()> {
+        match self {
+            StateInitializer::FromInput(slot) => {
+                0u8.encode(encoder)?;
+                slot.encode(encoder)
+            }
+            StateInitializer::Value(t) => {
+                1u8.encode(encoder)?;
+                t.encode(encoder)
+            }
+        }
+    }
+}
+
+impl Decode for StateInitializer {
+    fn decode(decoder: &mut Decoder) -> TractResult<Self> {
+        Ok(match u8::decode(decoder)? {
+            0 => StateInitializer::FromInput(usize::decode(decoder)?),
+            1 => StateInitializer::Value(Arc::decode(decoder)?),
+            code => bail!("Invalid StateInitializer code {}", code),
+        })
+    }
+}
 impl fmt::Debug for StateInitializer {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
         use StateInitializer::*;
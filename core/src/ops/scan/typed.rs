@@ -12,7 +12,27 @@ pub struct TypedScan {
     pub output_mapping: Vec<OutputMapping<TDim, TDim>>,
 }
 
+impl_native_codec!(TypedScan {
+    skip,
+    body,
+    decluttered,
+    seq_length_input_slot,
+    input_mapping,
+    output_mapping
+});
+
 impl TypedScan {
+    /// Chunk of the first scanned input, giving the number of iterations.
+    fn input_chunk(&self) -> TractResult<usize> {
+        let (_slot, _axis, chunk) = self
+            .input_mapping
+            .iter()
+            .filter_map(InputMapping::as_scan)
+            .next()
+            .ok_or("Scan without scanned input")?;
+        Ok(chunk.to_integer()? as usize)
+    }
+
     pub fn to_codegen_op(&self) -> TractResult<Codegen> {
         trace!("Optimizing(Codegen) inner model");
         let plan = SimplePlan::new(self.body.clone().into_optimized()?)?;
@@ -201,8 +221,13 @@ impl TypedScan {
         model: &TypedModel,
         node: &TypedNode,
     ) -> TractResult<Option<TypedModelPatch>> {
+        let scanning_inputs = self.input_mapping.iter().filter(|m| m.as_scan().is_some()).count();
         for (inner_input_id, input) in self.body.input_outlets()?.iter().enumerate() {
             let source_node = self.body.node(input.node);
+            // the last scanning input gives the iteration count, it must stay
+            if scanning_inputs == 1 && self.input_mapping[inner_input_id].as_scan().is_some() {
+                continue;
+            }
             if source_node.outputs[0].successors.len() == 0 {
                 let mut new_inputs = node.inputs.clone();
                 let slot = match &self.input_mapping[inner_input_id] {
@@ -373,6 +398,10 @@ impl TypedScan {
     ) -> TractResult<Option<TypedModelPatch>> {
         for (model_ix, mapping) in self.output_mapping.iter().enumerate() {
             let slot = if let Some(slot) = mapping.full_slot { slot } else { continue };
+            // the value fed back to the body must stay computed in the body
+            if mapping.state || mapping.last_value_slot.is_some() {
+                continue;
+            }
             let emitter_outlet = self.body.output_outlets()?[model_ix];
             let emitter_node = self.body.node(emitter_outlet.node);
             if emitter_node.outputs[emitter_outlet.slot].successors.len() > 1
@@ -580,6 +609,7 @@ impl StatefullOp for TypedScan {
 }
 
 impl TypedOp for TypedScan {
+    native_save!("TypedScan");
     as_op!();
 
     fn output_facts(&self, inputs: &[&TypedFact]) -> TractResult<TVec<TypedFact>> {
@@ -699,26 +729,50 @@ impl TypedOp for TypedScan {
         mapping: &HashMap<OutletId, OutletId>,
         _pulse: usize,
     ) -> TractResult<TVec<OutletId>> {
-        for input_id in 0..node.inputs.len() {
-            let input = mapping[&node.inputs[input_id]];
-            let input_fact = target.outlet_fact(input)?;
-            let (_slot, axis, _chunk) = self
+        for (ix, input) in node.inputs.iter().enumerate() {
+            let fact = target.outlet_fact(mapping[input])?;
+            let (_slot, axis, chunk) = self
                 .input_mapping
                 .iter()
                 .filter_map(InputMapping::as_scan)
-                .find(|mapping| mapping.0 == input_id)
-                .unwrap();
-            if input_fact.axis != axis {
+                .find(|mapping| mapping.0 == ix)
+                .ok_or_else(|| {
+                    format!(
+                        "Scan pulsification requires input #{} to be a constant, as it is not \
+                        scanned. State initializers and full inputs can not depend on the stream.",
+                        ix
+                    )
+                })?;
+            if fact.axis != axis {
                 bail!("Scan pulsification limited to scanning axis");
             }
+            let chunk = chunk.to_integer()? as usize;
+            if fact.pulse() % chunk != 0 || fact.delay % chunk != 0 {
+                bail!(
+                    "Scan pulsification requires pulse ({}) and delay ({}) to be multiples of the \
+                    chunk ({})",
+                    fact.pulse(),
+                    fact.delay,
+                    chunk
+                );
+            }
+        }
+        if self.output_mapping.iter().any(|om| om.last_value_slot.is_some()) {
+            bail!(
+                "Scan pulsification does not support last value outputs, as they are only known \
+                at the end of the stream"
+            );
         }
 
-        let pulse_inputs = node.inputs.iter().map(|i| mapping[i]).collect::<TVec<_>>();
+        let pulse_inputs = crate::pulse::sync_inputs(node, target, mapping)?;
 
+        // hidden states are carried over from one pulse to the next by the
+        // op state, the iterations covering the delay are skipped
         let mut op = self.clone();
-        op.skip = target.outlet_fact(pulse_inputs[0])?.delay;
-        op.output_mapping.iter_mut().find(|om| om.full_slot.is_some()).unwrap().full_dim_hint =
-            None;
+        op.skip = target.outlet_fact(pulse_inputs[0])?.delay / self.input_chunk()?;
+        for om in op.output_mapping.iter_mut() {
+            om.full_dim_hint = None;
+        }
         target.wire_node(&*node.name, op, &pulse_inputs)
     }
 
@@ -747,35 +801,126 @@ impl TypedOp for TypedScan {
 
 impl PulsedOp for TypedScan {
     fn pulsed_output_facts(&self, inputs: &[&PulsedFact]) -> TractResult<TVec<PulsedFact>> {
-        let (output_body_ix, output_mapping) = self
-            .output_mapping
-            .iter()
-            .enumerate()
-            .find(|(_ix, om)| om.full_slot == Some(0))
-            .ok_or("Expects output 0 to be the full stream (and no other output)")?;
-        let output_body_fact = self.body.output_fact(output_body_ix)?;
-        let shape = output_body_fact
-            .shape
-            .iter()
-            .enumerate()
-            .map(|(axis, d)| {
-                if axis == output_mapping.axis {
-                    inputs[0].pulse()
-                } else {
-                    d.to_integer().unwrap() as usize
-                }
-            })
-            .collect();
-        let fact = PulsedFact {
-            datum_type: output_body_fact.datum_type,
-            shape,
-            axis: output_mapping.axis,
-            dim: inputs[0].dim.clone(),
-            delay: inputs[0].delay,
-        };
-        Ok(tvec!(fact))
+        let input_chunk = self.input_chunk()?;
+        let iters = inputs[0].pulse() / input_chunk;
+        let mut facts = tvec!();
+        for (ix, om) in self.output_mapping.iter().enumerate() {
+            if om.last_value_slot.is_some() {
+                bail!("Pulsed scan can not have last value outputs")
+            }
+            if let Some(slot) = om.full_slot {
+                let body_fact = self.body.output_fact(ix)?;
+                let chunk = om.chunk.to_integer()? as usize;
+                let mut shape: TVec<usize> = body_fact
+                    .shape
+                    .iter()
+                    .map(|d| Ok(d.to_integer()? as usize))
+                    .collect::<TractResult<_>>()?;
+                shape[om.axis] = iters * chunk;
+                let fact = PulsedFact {
+                    datum_type: body_fact.datum_type,
+                    shape,
+                    axis: om.axis,
+                    dim: inputs[0].dim.clone() / input_chunk as u32 * chunk as i32,
+                    delay: inputs[0].delay / input_chunk * chunk,
+                };
+                facts.push((slot, fact));
+            }
+        }
+        facts.sort_by_key(|f| f.0);
+        Ok(facts.into_iter().map(|f| f.1).collect())
     }
 
     as_op!();
     pulsed_op_to_typed_op!();
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ops::array::{Pad, PadMode, Slice};
+    use crate::pulse::StreamingSession;
+    use ndarray::*;
+
+    // state = tanh(state + x), preceded by a one frame delay
+    fn recurrent(len: TDim, last_value: bool) -> TypedModel {
+        let chunk_fact = TypedFact::dt_shape(f32::datum_type(), [1, 2].as_ref()).unwrap();
+        let mut body = TypedModel::default();
+        let x = body.add_source("x", chunk_fact.clone()).unwrap();
+        let state = body.add_source("state", chunk_fact).unwrap();
+        let sum = body.wire_node("sum", crate::ops::math::add::bin_typed(), &[state, x]).unwrap();
+        let tanh = body.wire_node("tanh", crate::ops::math::tanh(), &sum).unwrap();
+        body.set_output_outlets(&tanh).unwrap();
+        let input_mapping = vec![
+            InputMapping::Scan { slot: 0, axis: 0, chunk: 1.to_dim() },
+            InputMapping::State { initializer: StateInitializer::Value(rctensor2(&[[0f32, 0.]])) },
+        ];
+        let output_mapping = vec![OutputMapping {
+            state: true,
+            axis: 0,
+            chunk: 1.to_dim(),
+            full_dim_hint: None,
+            last_value_slot: if last_value { Some(1) } else { None },
+            full_slot: Some(0),
+        }];
+
+        let mut model = TypedModel::default();
+        let shape = [len.clone(), 2.to_dim()];
+        let a = model.add_source("a", TypedFact::dt_shape(f32::datum_type(), &shape[..]).unwrap());
+        let pad = Pad::new(vec![(2, 0), (0, 0)], PadMode::Constant(rctensor0(0f32)));
+        let pad = model.wire_node("pad", pad, &[a.unwrap()]).unwrap();
+        let slice = model.wire_node("slice", Slice::new(0, 1.to_dim(), len + 1), &pad).unwrap();
+        let scan = TypedScan::new(body, input_mapping, output_mapping, None).unwrap();
+        let scan = model.wire_node("scan", scan, &slice).unwrap();
+        model.set_output_outlets(&scan).unwrap();
+        model
+    }
+
+    #[test]
+    fn pulsed_state_is_carried_over() {
+        let input = Array2::from_shape_fn((11, 2), |(t, c)| (t as f32 - c as f32 * 5.) / 4.);
+        let expected = SimplePlan::new(recurrent(11.to_dim(), false))
+            .unwrap()
+            .run(tvec!(input.clone().into_tensor()))
+            .unwrap()
+            .remove(0);
+        let decluttered = recurrent(11.to_dim(), false).declutter().unwrap();
+        let found = SimplePlan::new(decluttered)
+            .unwrap()
+            .run(tvec!(input.clone().into_tensor()))
+            .unwrap()
+            .remove(0);
+        found.close_enough(&expected, true).unwrap();
+
+        let model = recurrent(TDim::s(), false).declutter().unwrap();
+        let pulsed = PulsedModel::new(&model.into_normalized().unwrap(), 4).unwrap();
+        let mut session = StreamingSession::new(pulsed).unwrap();
+        let mut outputs = vec![];
+        for chunk in &[0..3, 3..9, 9..11] {
+            let chunk = input.slice(s![chunk.clone(), ..]).to_owned().into_tensor();
+            outputs.push(session.push(chunk).unwrap());
+        }
+        outputs.push(session.finish().unwrap());
+        let found = Tensor::stack_tensors(0, &outputs).unwrap();
+        found.close_enough(&expected, true).unwrap();
+    }
+
+    #[test]
+    fn state_is_only_buffered_when_pulsed() {
+        let model = recurrent(TDim::s(), false).declutter().unwrap();
+        let scan = model.node_by_name("scan").unwrap();
+        let inputs = model.node_input_facts(scan.id).unwrap();
+        assert!(scan.op.cost(&inputs).unwrap().is_empty());
+        let pulsed = PulsedModel::new(&model.clone().into_normalized().unwrap(), 4).unwrap();
+        let scan = pulsed.node_by_name("scan").unwrap().id;
+        let report = pulsed.latencies(4).unwrap();
+        let scan = report.iter().find(|l| l.node == scan).unwrap();
+        assert_eq!(scan.buffer, 2 * 4);
+    }
+
+    #[test]
+    fn pulsed_last_value_is_rejected() {
+        let model = recurrent(TDim::s(), true).into_normalized().unwrap();
+        assert!(PulsedModel::new(&model, 4).is_err());
+    }
+}
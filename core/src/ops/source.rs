@@ -19,6 +19,8 @@ pub struct TypedSource {
     fact: TypedFact,
 }
 
+impl_native_codec!(TypedSource { fact });
+
 impl Op for TypedSource {
     fn name(&self) -> Cow<str> {
         "TypedSource".into()
@@ -74,6 +76,7 @@ impl TypedOp for TypedSource {
         Ok(tvec!(id))
     }
 
+    native_save!("TypedSource");
     as_op!();
 }
 
@@ -2,9 +2,9 @@ use crate::internal::*;
 
 #[derive(Debug, Clone, Hash)]
 pub struct UnimplementedOp {
-    outputs: usize,
-    name: String,
-    message: String,
+    pub outputs: usize,
+    pub name: String,
+    pub message: String,
 }
 
 impl UnimplementedOp {
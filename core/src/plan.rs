@@ -2,17 +2,66 @@ use std::borrow::Borrow;
 use std::fmt::{Debug, Display};
 use std::marker::PhantomData;
 
+use crate::arena::{Arena, MemoryPlan};
 use crate::internal::*;
 use crate::model::order::eval_order_for_nodes;
 use crate::model::{Fact, ModelImpl, OutletId};
 
-#[derive(Debug, Default)]
+#[derive(Debug, Default, Clone)]
 pub struct SessionState {
     pub inputs: HashMap<usize, Arc<Tensor>>,
     pub known_stream_len: Option<usize>,
+    pub resolved_symbols: SymbolValues,
     pub tensors: HashMap<String, Tensor>,
 }
 
+impl SessionState {
+    /// Bind the symbols appearing alone as a dimension of `fact` to the
+    /// actual dimension of `tensor`.
+    ///
+    /// Only dimensions made of a bare symbol (`N`, not `2*N` or `N+1`) are
+    /// bound. Binding a symbol already resolved to a different value is an
+    /// error.
+    pub fn resolve_symbols(&mut self, fact: &TypedFact, tensor: &Tensor) -> TractResult<()> {
+        self.bind_symbols(fact, tensor, |_| true)
+    }
+
+    /// Bind the data-dependent symbols appearing alone as a dimension of
+    /// `fact` to the actual dimension of `tensor`, as computed by the
+    /// operator.
+    pub fn resolve_data_dependent_symbols(
+        &mut self,
+        fact: &TypedFact,
+        tensor: &Tensor,
+    ) -> TractResult<()> {
+        self.bind_symbols(fact, tensor, crate::dim::is_data_dependent_symbol)
+    }
+
+    fn bind_symbols(
+        &mut self,
+        fact: &TypedFact,
+        tensor: &Tensor,
+        filter: impl Fn(char) -> bool,
+    ) -> TractResult<()> {
+        for (dim, &value) in fact.shape.iter().zip(tensor.shape().iter()) {
+            if let &[symbol] = &*dim.symbols() {
+                if filter(symbol) && dim == TDim::sym(symbol) {
+                    match self.resolved_symbols.get(symbol) {
+                        Some(bound) if bound != value as i32 => bail!(
+                            "Symbol {} is bound to both {} and {}",
+                            TDim::sym(symbol),
+                            bound,
+                            value
+                        ),
+                        _ => self.resolved_symbols.set(symbol, value as i32),
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
 #[derive(Debug, Clone, Educe)]
 #[educe(Hash)]
 pub struct SimplePlan<F, O, M>
@@ -25,6 +74,11 @@ where
     pub outputs: Vec<OutletId>,
     pub order: Vec<usize>,
     pub flush_lists: Vec<TVec<usize>>,
+    #[educe(Hash(ignore))]
+    pub memory_plan: MemoryPlan,
+    /// Facts of the outputs with data-dependent dimensions, by node.
+    #[educe(Hash(ignore))]
+    data_dependent_outputs: Vec<TVec<(usize, TypedFact)>>,
     _casper: PhantomData<(F, O)>,
 }
 
@@ -63,10 +117,26 @@ where
                 flush_lists[flush_at].push(node)
             }
         }
+        let memory_plan = MemoryPlan::new(model.borrow(), &order, outputs)?;
+        let data_dependent_outputs = model
+            .borrow()
+            .nodes()
+            .iter()
+            .map(|node| {
+                node.outputs
+                    .iter()
+                    .enumerate()
+                    .filter_map(|(ix, o)| o.fact.to_typed_fact().ok().map(|f| (ix, f)))
+                    .filter(|(_, f)| f.shape.is_data_dependent())
+                    .collect()
+            })
+            .collect();
         Ok(SimplePlan {
             model,
             order,
             flush_lists,
+            memory_plan,
+            data_dependent_outputs,
             outputs: outputs.to_vec(),
             _casper: PhantomData,
         })
@@ -94,6 +164,7 @@ where
     pub states: Vec<Option<Box<dyn OpState>>>,
     pub session_state: SessionState,
     pub values: Vec<Option<TVec<Arc<Tensor>>>>,
+    arena: Arena,
     _phantom: PhantomData<(M, F, O)>,
 }
 
@@ -117,6 +188,7 @@ where
             states,
             session_state: SessionState::default(),
             values: self.values.clone(),
+            arena: Arena::default(),
             _phantom: PhantomData,
         }
     }
@@ -142,7 +214,15 @@ where
             .iter()
             .map(|n: &BaseNode<F, O>| n.op().state(&mut session, n.id))
             .collect::<TractResult<_>>()?;
-        Ok(SimpleState { plans, states, session_state: session, values, _phantom: PhantomData })
+        let arena = Arena::new(&plans[0].borrow().memory_plan)?;
+        Ok(SimpleState {
+            plans,
+            states,
+            session_state: session,
+            values,
+            arena,
+            _phantom: PhantomData,
+        })
     }
 
     /// Reset wires state.
@@ -173,18 +253,25 @@ where
         inputs: TVec<Tensor>,
         plan: usize,
     ) -> TractResult<TVec<Arc<Tensor>>> {
+        self.set_inputs(inputs)?;
+        self.exec_plan(plan)
+    }
+
+    /// Run a plan on the inputs already set in the session.
+    pub fn exec_plan(&mut self, plan: usize) -> TractResult<TVec<Arc<Tensor>>> {
         let mut result = tvec!();
         {
-            self.set_inputs(inputs)?;
             let &mut SimpleState {
                 ref plans,
                 ref mut session_state,
                 ref mut states,
                 ref mut values,
+                ref mut arena,
                 ..
             } = self;
             let plan = plans[plan].borrow();
             let model = plan.model().borrow();
+            let allocations = &plan.memory_plan.allocations;
             for (step, n) in plan.order.iter().enumerate() {
                 let node = model.node(*n);
                 trace!("Running step {}, node {}", step, node);
@@ -198,83 +285,89 @@ where
                     inputs.push(prec[i.slot].clone().into())
                 }
 
+                let mut flushed = tvec!();
                 for flush in &plan.flush_lists[step] {
                     trace!("  flushing node {} {}", flush, node);
-                    values[*flush] = None;
+                    if let Some(vs) = values[*flush].take() {
+                        flushed.push((*flush, vs));
+                    }
                 }
 
                 if cfg!(debug_assertions) {
-                    let facts = model.node_input_facts(node.id)?;
-                    if facts.len() != inputs.len() {
-                        bail!(
-                            "Evaluating {}: expected {} inputs, got {}",
-                            node,
-                            facts.len(),
-                            inputs.len()
-                        );
-                    }
-                    for (ix, (v, f)) in inputs.iter().zip(facts.iter()).enumerate() {
-                        if let Err(e) = f.matches(v) {
-                            bail!(
-                                "Evaluating {}: input {:?}, expected {:?}, got {:?} ({})",
-                                node,
-                                ix,
-                                f,
-                                v,
-                                e
-                            );
-                        }
-                    }
+                    check_inputs(model, node, &inputs, &session_state.resolved_symbols)?;
                 }
 
                 let vs = match states[node.id] {
                     Some(ref mut state) => state.eval(session_state, node.op(), inputs),
-                    None => node.op().as_stateless().expect("as_stateless").eval(inputs),
+                    None => {
+                        let op = node.op().as_stateless().expect("as_stateless");
+                        let mut buffers = (0..node.outputs.len())
+                            .map(|ix| {
+                                allocations
+                                    .get(&OutletId::new(node.id, ix))
+                                    .map(|a| arena.buffer(&plan.memory_plan, a))
+                                    .transpose()
+                            })
+                            .collect::<TractResult<TVec<Option<Tensor>>>>()?;
+                        if buffers.iter().any(|b| b.is_some()) {
+                            let vs = op.eval_with_buffers(session_state, inputs, &mut buffers);
+                            for (ix, buffer) in buffers.into_iter().enumerate() {
+                                if let Some(buffer) = buffer {
+                                    arena.give_back(
+                                        &allocations[&OutletId::new(node.id, ix)],
+                                        buffer,
+                                    )
+                                }
+                            }
+                            vs
+                        } else {
+                            op.eval_in_session(session_state, inputs)
+                        }
+                    }
                 }
                 .chain_err(|| format!("Evaluating {}", node))?;
 
-                if cfg!(debug_assertions) {
-                    let facts = model.node_output_facts(node.id)?;
-                    if facts.len() != vs.len() {
-                        bail!(
-                            "Evaluating {}: expected {} outputs, got {}",
-                            node,
-                            facts.len(),
-                            vs.len()
-                        );
-                    }
-                    for (ix, (v, f)) in vs.iter().zip(facts.iter()).enumerate() {
-                        if node.outputs[ix].successors.len() == 0 {
-                            continue;
-                        }
-                        if let Err(e) = f.matches(v) {
-                            bail!(
-                                "Evaluating {}: output {:?}, expected {:?}, got {:?} ({})",
-                                node,
-                                ix,
-                                f,
-                                v,
-                                e
-                            );
+                for (flushed, vs) in flushed {
+                    for (ix, v) in vs.into_iter().enumerate() {
+                        if let Some(allocation) = allocations.get(&OutletId::new(flushed, ix)) {
+                            arena.recycle(allocation, v)
                         }
                     }
                 }
 
+                for (ix, fact) in &plan.data_dependent_outputs[node.id] {
+                    session_state.resolve_data_dependent_symbols(fact, &vs[*ix])?;
+                }
+
+                if cfg!(debug_assertions) {
+                    check_outputs(model, node, &vs, &session_state.resolved_symbols)?;
+                }
+
                 values[node.id] = Some(vs);
             }
             for output in &plan.outputs {
                 result.push(values[output.node].as_ref().unwrap()[output.slot].clone())
             }
+            for (node, vs) in values.iter_mut().enumerate() {
+                for (ix, v) in vs.take().into_iter().flatten().enumerate() {
+                    if let Some(allocation) = allocations.get(&OutletId::new(node, ix)) {
+                        arena.recycle(allocation, v)
+                    }
+                }
+            }
         }
         self.reset_wires()?;
         Ok(result)
     }
 
+    /// Set all the inputs for a run.
+    ///
+    /// Symbols resolved by the previous run are forgotten.
     pub fn set_inputs(&mut self, inputs: TVec<Tensor>) -> TractResult<()> {
-        let SimpleState { ref plans, ref mut session_state, .. } = self;
-        plans[0].borrow().model().input_outlets()?.iter().zip(inputs).for_each(|(input, t)| {
-            session_state.inputs.insert(input.node, t.into());
-        });
+        self.session_state.resolved_symbols = SymbolValues::default();
+        for (ix, t) in inputs.into_iter().enumerate() {
+            self.set_input(ix, t)?;
+        }
         Ok(())
     }
 
@@ -285,6 +378,11 @@ where
             .get(input)
             .ok_or_else(|| format!("Invalid input id for model ({}).", input))?
             .node;
+        if let Ok(fact) = self.model().node(id).outputs[0].fact.to_typed_fact() {
+            self.session_state
+                .resolve_symbols(&fact, &t)
+                .chain_err(|| format!("Binding symbols of input {}", input))?;
+        }
         self.session_state.inputs.insert(id, t.into());
         Ok(())
     }
@@ -328,7 +426,7 @@ where
         }
         let vs = match self.states[node.id] {
             Some(ref mut state) => state.eval(session_state, node.op(), inputs),
-            None => node.op().as_stateless().unwrap().eval(inputs),
+            None => node.op().as_stateless().unwrap().eval_in_session(session_state, inputs),
         }
         .map_err(|e| format!("Evaluating {}: {}", node, e))?;
         values[node.id] = Some(vs);
@@ -357,9 +455,11 @@ where
                 Some(ref mut state) => {
                     state.eval(session_state, plans[0].borrow().model().nodes()[node].op(), inputs)
                 }
-                None => {
-                    plan.borrow().model().nodes()[node].op().as_stateless().unwrap().eval(inputs)
-                }
+                None => plan.borrow().model().nodes()[node]
+                    .op()
+                    .as_stateless()
+                    .unwrap()
+                    .eval_in_session(session_state, inputs),
             }
             .map_err(|e| format!("Evaluating {:?}: {:?}", node, e))?
         };
@@ -389,3 +489,442 @@ where
         self.plan().model()
     }
 }
+
+/// A state running the independent branches of a plan concurrently.
+///
+/// Nodes are dispatched on the thread pool of the current tract-linalg
+/// executor as soon as all their inputs are computed. Values are released
+/// once their last consumer has started. Operators run on a snapshot of the
+/// session, so that no lock is held while they evaluate. Stateful operators
+/// evaluate one at a time on the calling thread, and can use the pool for
+/// their own matrix multiplications. With a single thread executor (or
+/// without the `multithread-mmm` feature), this falls back to the sequential
+/// evaluation of `SimpleState`.
+#[derive(Debug)]
+pub struct ConcurrentState<F, O, M, P>(pub SimpleState<F, O, M, P>)
+where
+    F: Fact + Clone + 'static + Hash,
+    O: Debug + Display + AsRef<dyn Op> + AsMut<dyn Op> + Clone + 'static + DynHash,
+    M: Borrow<ModelImpl<F, O>> + Hash,
+    P: Borrow<SimplePlan<F, O, M>>;
+
+impl<F, O, M, P> ConcurrentState<F, O, M, P>
+where
+    F: Fact + Clone + 'static + Hash,
+    O: Debug + Display + AsRef<dyn Op> + AsMut<dyn Op> + Clone + 'static + DynHash + Send + Sync,
+    M: Borrow<ModelImpl<F, O>> + Hash,
+    P: Borrow<SimplePlan<F, O, M>> + Clone,
+{
+    pub fn new(plan: P) -> TractResult<ConcurrentState<F, O, M, P>> {
+        Ok(ConcurrentState(SimpleState::new(plan)?))
+    }
+
+    pub fn run(&mut self, inputs: TVec<Tensor>) -> TractResult<TVec<Arc<Tensor>>> {
+        self.0.set_inputs(inputs)?;
+        match tract_linalg::multithread::current_tract_executor() {
+            #[cfg(feature = "multithread-mmm")]
+            tract_linalg::multithread::Executor::MultiThread(pool) => self.exec_on_pool(&pool),
+            _ => self.0.exec_plan(0),
+        }
+    }
+
+    #[cfg(feature = "multithread-mmm")]
+    fn exec_on_pool(&mut self, pool: &Arc<rayon::ThreadPool>) -> TractResult<TVec<Arc<Tensor>>> {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::{mpsc, Mutex, RwLock};
+        use tract_linalg::multithread::{multithread_tract_scope, Executor};
+
+        struct Schedule<'a, F, O>
+        where
+            F: Fact + Clone + 'static + Hash,
+            O: Debug + Display + AsRef<dyn Op> + AsMut<dyn Op> + Clone + 'static + DynHash,
+        {
+            model: &'a ModelImpl<F, O>,
+            data_dependent_outputs: &'a [TVec<(usize, TypedFact)>],
+            precursors: Vec<TVec<usize>>,
+            successors: Vec<TVec<usize>>,
+            pending: Vec<AtomicUsize>,
+            readers: Vec<AtomicUsize>,
+            values: Vec<Mutex<Option<TVec<Arc<Tensor>>>>>,
+            states: Vec<Mutex<Option<Box<dyn OpState>>>>,
+            session: RwLock<SessionState>,
+            error: Mutex<Option<TractError>>,
+            executor: Executor,
+            /// Nodes not evaluated yet.
+            remaining: AtomicUsize,
+            /// Stateful nodes ready to be evaluated on the calling thread, or
+            /// None when the run is over.
+            ready: Mutex<mpsc::Sender<Option<usize>>>,
+        }
+
+        fn dispatch<'s, F, O>(schedule: &'s Schedule<'s, F, O>, scope: &rayon::Scope<'s>, n: usize)
+        where
+            F: Fact + Clone + 'static + Hash,
+            O: Debug
+                + Display
+                + AsRef<dyn Op>
+                + AsMut<dyn Op>
+                + Clone
+                + 'static
+                + DynHash
+                + Send
+                + Sync,
+        {
+            if schedule.states[n].lock().unwrap().is_some() {
+                let _ = schedule.ready.lock().unwrap().send(Some(n));
+            } else {
+                scope.spawn(move |scope| run_node(schedule, scope, n))
+            }
+        }
+
+        fn run_node<'s, F, O>(schedule: &'s Schedule<'s, F, O>, scope: &rayon::Scope<'s>, n: usize)
+        where
+            F: Fact + Clone + 'static + Hash,
+            O: Debug
+                + Display
+                + AsRef<dyn Op>
+                + AsMut<dyn Op>
+                + Clone
+                + 'static
+                + DynHash
+                + Send
+                + Sync,
+        {
+            let result =
+                multithread_tract_scope(schedule.executor.clone(), || eval_node(schedule, n));
+            if let Err(e) = result {
+                let mut error = schedule.error.lock().unwrap();
+                if error.is_none() {
+                    *error = Some(e);
+                    let _ = schedule.ready.lock().unwrap().send(None);
+                }
+                return;
+            }
+            for &succ in &schedule.successors[n] {
+                if schedule.pending[succ].fetch_sub(1, Ordering::SeqCst) == 1 {
+                    dispatch(schedule, scope, succ)
+                }
+            }
+            if schedule.remaining.fetch_sub(1, Ordering::SeqCst) == 1 {
+                let _ = schedule.ready.lock().unwrap().send(None);
+            }
+        }
+
+        fn eval_node<F, O>(schedule: &Schedule<F, O>, n: usize) -> TractResult<()>
+        where
+            F: Fact + Clone + 'static + Hash,
+            O: Debug + Display + AsRef<dyn Op> + AsMut<dyn Op> + Clone + 'static + DynHash,
+        {
+            if schedule.error.lock().unwrap().is_some() {
+                return Ok(());
+            }
+            let node = schedule.model.node(n);
+            trace!("Running node {}", node);
+            let mut inputs: TVec<Arc<Tensor>> = tvec![];
+            for i in &node.inputs {
+                let prec = schedule.values[i.node].lock().unwrap();
+                let prec = prec.as_ref().ok_or_else(|| {
+                    format!(
+                        "Computing {}, precursor {} not done",
+                        node,
+                        schedule.model.node(i.node)
+                    )
+                })?;
+                inputs.push(prec[i.slot].clone())
+            }
+            for &prec in &schedule.precursors[n] {
+                if schedule.readers[prec].fetch_sub(1, Ordering::SeqCst) == 1 {
+                    trace!("  flushing node {}", prec);
+                    *schedule.values[prec].lock().unwrap() = None;
+                }
+            }
+            // evaluate on a snapshot of the session, so that no lock is held
+            // while the operator runs
+            let mut session = schedule.session.read().unwrap().clone();
+            if cfg!(debug_assertions) {
+                check_inputs(schedule.model, node, &inputs, &session.resolved_symbols)?;
+            }
+            let mut state = schedule.states[n].lock().unwrap().take();
+            let vs = match state {
+                Some(ref mut state) => state.eval(&mut session, node.op(), inputs),
+                None => node
+                    .op()
+                    .as_stateless()
+                    .expect("as_stateless")
+                    .eval_in_session(&session, inputs),
+            };
+            let stateful = state.is_some();
+            *schedule.states[n].lock().unwrap() = state;
+            let vs = vs.chain_err(|| format!("Evaluating {}", node))?;
+            {
+                let mut shared = schedule.session.write().unwrap();
+                if stateful {
+                    // stateful nodes run one at a time on the calling thread
+                    shared.tensors = session.tensors;
+                }
+                for (ix, fact) in &schedule.data_dependent_outputs[n] {
+                    shared.resolve_data_dependent_symbols(fact, &vs[*ix])?;
+                }
+                if cfg!(debug_assertions) {
+                    check_outputs(schedule.model, node, &vs, &shared.resolved_symbols)?;
+                }
+            }
+            *schedule.values[n].lock().unwrap() = Some(vs);
+            Ok(())
+        }
+
+        let SimpleState { ref plans, ref mut session_state, ref mut states, .. } = self.0;
+        let plan = plans[0].borrow();
+        let model = plan.model();
+        let mut in_plan = vec![false; model.nodes().len()];
+        for &n in &plan.order {
+            in_plan[n] = true;
+        }
+        let mut precursors: Vec<TVec<usize>> = vec![tvec!(); model.nodes().len()];
+        let mut successors: Vec<TVec<usize>> = vec![tvec!(); model.nodes().len()];
+        for &n in &plan.order {
+            for i in &model.node(n).inputs {
+                if in_plan[i.node] && !precursors[n].contains(&i.node) {
+                    precursors[n].push(i.node);
+                    successors[i.node].push(n);
+                }
+            }
+        }
+        let readers = (0..model.nodes().len())
+            .map(|n| {
+                let output = plan.outputs.iter().any(|o| o.node == n);
+                AtomicUsize::new(successors[n].len() + output as usize)
+            })
+            .collect();
+        let (sender, receiver) = mpsc::channel();
+        let schedule = Schedule {
+            model,
+            data_dependent_outputs: &plan.data_dependent_outputs,
+            pending: precursors.iter().map(|p| AtomicUsize::new(p.len())).collect(),
+            precursors,
+            successors,
+            readers,
+            values: (0..model.nodes().len()).map(|_| Mutex::new(None)).collect(),
+            states: states.drain(..).map(Mutex::new).collect(),
+            session: RwLock::new(std::mem::take(session_state)),
+            error: Mutex::new(None),
+            executor: Executor::MultiThread(pool.clone()),
+            remaining: AtomicUsize::new(plan.order.len()),
+            ready: Mutex::new(sender),
+        };
+        let roots: Vec<usize> =
+            plan.order.iter().cloned().filter(|&n| schedule.precursors[n].len() == 0).collect();
+        // stateless nodes run on the pool, stateful ones on this thread, so
+        // that they never wait for a pool thread
+        pool.in_place_scope(|scope| {
+            for n in roots {
+                dispatch(&schedule, scope, n)
+            }
+            if plan.order.len() > 0 {
+                while let Ok(Some(n)) = receiver.recv() {
+                    run_node(&schedule, scope, n)
+                }
+            }
+        });
+        let Schedule { values, states: node_states, session, error, .. } = schedule;
+        *states = node_states.into_iter().map(|s| s.into_inner().unwrap()).collect();
+        *session_state = session.into_inner().unwrap();
+        if let Some(e) = error.into_inner().unwrap() {
+            return Err(e);
+        }
+        let values: Vec<_> = values.into_iter().map(|v| v.into_inner().unwrap()).collect();
+        plan.outputs
+            .iter()
+            .map(|o| {
+                let vs = values[o.node]
+                    .as_ref()
+                    .ok_or_else(|| format!("Output {:?} was not computed", o))?;
+                Ok(vs[o.slot].clone())
+            })
+            .collect()
+    }
+}
+
+fn check_inputs<F, O>(
+    model: &ModelImpl<F, O>,
+    node: &BaseNode<F, O>,
+    inputs: &[Arc<Tensor>],
+    symbols: &SymbolValues,
+) -> TractResult<()>
+where
+    F: Fact + Clone + 'static + Hash,
+    O: Debug + Display + AsRef<dyn Op> + AsMut<dyn Op> + Clone + 'static + DynHash,
+{
+    let facts = model.node_input_facts(node.id)?;
+    if facts.len() != inputs.len() {
+        bail!("Evaluating {}: expected {} inputs, got {}", node, facts.len(), inputs.len());
+    }
+    for (ix, (v, f)) in inputs.iter().zip(facts.iter()).enumerate() {
+        if let Err(e) = f.matches(v) {
+            bail!("Evaluating {}: input {:?}, expected {:?}, got {:?} ({})", node, ix, f, v, e);
+        }
+        if !matches_with(&**f, v, symbols) {
+            bail!("Evaluating {}: input {:?}, expected {:?}, got {:?}", node, ix, f, v);
+        }
+    }
+    Ok(())
+}
+
+fn check_outputs<F, O>(
+    model: &ModelImpl<F, O>,
+    node: &BaseNode<F, O>,
+    vs: &[Arc<Tensor>],
+    symbols: &SymbolValues,
+) -> TractResult<()>
+where
+    F: Fact + Clone + 'static + Hash,
+    O: Debug + Display + AsRef<dyn Op> + AsMut<dyn Op> + Clone + 'static + DynHash,
+{
+    let facts = model.node_output_facts(node.id)?;
+    if facts.len() != vs.len() {
+        bail!("Evaluating {}: expected {} outputs, got {}", node, facts.len(), vs.len());
+    }
+    for (ix, (v, f)) in vs.iter().zip(facts.iter()).enumerate() {
+        if node.outputs[ix].successors.len() == 0 {
+            continue;
+        }
+        if let Err(e) = f.matches(v) {
+            bail!("Evaluating {}: output {:?}, expected {:?}, got {:?} ({})", node, ix, f, v, e);
+        }
+        if !matches_with(&**f, v, symbols) {
+            bail!("Evaluating {}: output {:?}, expected {:?}, got {:?}", node, ix, f, v);
+        }
+    }
+    Ok(())
+}
+
+/// Check a tensor against a fact, given the symbols values known so far.
+/// Facts that are not fully typed and symbolic dimensions match anything.
+fn matches_with<F: Fact>(fact: &F, tensor: &Tensor, symbols: &SymbolValues) -> bool {
+    match fact.to_typed_fact() {
+        Ok(fact) => {
+            fact.datum_type == tensor.datum_type()
+                && fact.shape.matches_with(tensor.shape(), symbols)
+        }
+        Err(_) => true,
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ops::math;
+
+    fn wide_model() -> TractResult<TypedModel> {
+        let mut model = TypedModel::default();
+        let x =
+            model.add_source("x", TypedFact::dt_shape(f32::datum_type(), [4usize].as_ref())?)?;
+        let mut branches = vec![];
+        for i in 0..8 {
+            let c = rctensor1(&[i as f32; 4]);
+            let y = model.wire_node(format!("mul-{}", i), math::mul::unary(c), &[x])?[0];
+            let y = model.wire_node(format!("exp-{}", i), math::exp(), &[y])?[0];
+            branches.push(y);
+        }
+        let mut sum = branches[0];
+        for (i, b) in branches.iter().enumerate().skip(1) {
+            sum = model.wire_node(format!("add-{}", i), math::add::bin_typed(), &[sum, *b])?[0];
+        }
+        model.set_output_outlets(&[sum, branches[3]])?;
+        Ok(model)
+    }
+
+    #[test]
+    fn concurrent_state_matches_simple_state() -> TractResult<()> {
+        let model = wide_model()?;
+        let plan = SimplePlan::new(&model)?;
+        let input = || tvec!(tensor1(&[0.1f32, 0.2, 0.3, 0.4]));
+        let expected = plan.run(input())?;
+        let mut state = ConcurrentState::new(&plan)?;
+        assert_eq!(state.run(input())?, expected);
+        #[cfg(feature = "multithread-mmm")]
+        {
+            use tract_linalg::multithread::*;
+            let found = multithread_tract_scope(Executor::multithread(4), || state.run(input()))?;
+            assert_eq!(found, expected);
+        }
+        Ok(())
+    }
+
+    #[cfg(feature = "multithread-mmm")]
+    #[test]
+    fn concurrent_state_with_stateful_node_and_multithread_matmul() -> TractResult<()> {
+        use crate::ops::matmul::MatMulUnary;
+        use crate::pulse::{delay::Delay, PulsedFact};
+        use tract_linalg::multithread::*;
+        let mut model = TypedModel::default();
+        let shape = [32usize, 32];
+        let x = model.add_source("x", TypedFact::dt_shape(f32::datum_type(), shape.as_ref())?)?;
+        let a = ndarray::Array2::from_shape_fn(shape, |(i, j)| (i * 3 + j) as f32 % 7.);
+        let a = a.into_arc_tensor();
+        let mm = MatMulUnary::new(a.clone(), false, false, false, None);
+        let y = model.wire_node("matmul", mm.clone(), &[x])?[0];
+        let fact = PulsedFact {
+            datum_type: f32::datum_type(),
+            shape: shape.iter().cloned().collect(),
+            axis: 0,
+            dim: TDim::s(),
+            delay: 0,
+        };
+        let delayed = model.wire_node("delay", Delay::new(&fact, 4, 0), &[x])?[0];
+        let z = model.wire_node("matmul-delayed", mm, &[delayed])?[0];
+        model.set_output_outlets(&[y, z])?;
+        let plan = SimplePlan::new(model.into_optimized()?)?;
+        let mut simple = SimpleState::new(&plan)?;
+        let mut concurrent = ConcurrentState::new(&plan)?;
+        multithread_tract_scope(Executor::multithread(4), || {
+            for i in 0..3 {
+                let input = ndarray::Array2::from_shape_fn(shape, |(r, c)| (r + c * i) as f32);
+                let input = input.into_tensor();
+                let found = concurrent.run(tvec!(input.clone()))?;
+                let expected = simple.run(tvec!(input))?;
+                // the delay buffer is uninitialized during the first run
+                let checked = if i == 0 { 1 } else { 2 };
+                assert_eq!(found[..checked], expected[..checked]);
+            }
+            Ok(())
+        })
+    }
+
+    #[test]
+    fn data_dependent_dims_are_bound_after_eval() -> TractResult<()> {
+        let mut model = TypedModel::default();
+        let x =
+            model.add_source("x", TypedFact::dt_shape(f32::datum_type(), [4usize].as_ref())?)?;
+        let c =
+            model.add_source("c", TypedFact::dt_shape(bool::datum_type(), [4usize].as_ref())?)?;
+        let y = model.wire_node("compress", crate::ops::array::Compress::new(Some(0)), &[x, c])?;
+        let len = model.outlet_fact(y[0])?.shape.dim(0);
+        assert!(len.is_data_dependent());
+        let y = model.wire_node("add_axis", AxisOp::Add(1), &y)?;
+        let broadcast = crate::ops::array::MultiBroadcastTo::new(tvec!(len, 2.to_dim()));
+        let y = model.wire_node("broadcast", broadcast, &y)?;
+        model.set_output_outlets(&y)?;
+        let plan = SimplePlan::new(model.into_optimized()?)?;
+        let output =
+            plan.run(tvec!(tensor1(&[1f32, 2., 3., 4.]), tensor1(&[true, false, false, true])))?;
+        assert_eq!(*output[0], tensor2(&[[1f32, 1.], [4., 4.]]));
+        Ok(())
+    }
+
+    #[test]
+    fn conflicting_symbol_bindings_are_rejected() -> TractResult<()> {
+        let mut model = TypedModel::default();
+        let fact = TypedFact::dt_shape(f32::datum_type(), [TDim::sym('N')].as_ref())?;
+        let a = model.add_source("a", fact.clone())?;
+        let b = model.add_source("b", fact)?;
+        let sum = model.wire_node("add", math::add::bin_typed(), &[a, b])?;
+        model.set_output_outlets(&sum)?;
+        let plan = SimplePlan::new(model)?;
+        let mut state = SimpleState::new(&plan)?;
+        assert!(state.run(tvec!(tensor1(&[1f32, 2.]), tensor1(&[1f32, 2., 3.]))).is_err());
+        let output = state.run(tvec!(tensor1(&[1f32, 2., 3.]), tensor1(&[1f32, 2., 3.])))?;
+        assert_eq!(*output[0], tensor1(&[2f32, 4., 6.]));
+        Ok(())
+    }
+}
@@ -70,6 +70,8 @@ pub struct Delay {
     overlap: usize,
 }
 
+impl_native_codec!(Delay { datum_type, buffer_shape, axis, delay, overlap });
+
 impl Delay {
     pub fn new(input_fact: &PulsedFact, delay: usize, overlap: usize) -> Delay {
         let axis = input_fact.axis;
@@ -77,6 +79,14 @@ impl Delay {
         buffer_shape[axis] = delay + overlap;
         Delay { datum_type: input_fact.datum_type, buffer_shape, axis, delay, overlap }
     }
+
+    pub fn delay(&self) -> usize {
+        self.delay
+    }
+
+    pub fn overlap(&self) -> usize {
+        self.overlap
+    }
 }
 
 impl Op for Delay {
@@ -109,6 +119,7 @@ impl StatefullOp for Delay {
 }
 
 impl TypedOp for Delay {
+    native_save!("Delay");
     as_op!();
 
     fn output_facts(&self, inputs: &[&TypedFact]) -> TractResult<TVec<TypedFact>> {
@@ -0,0 +1,98 @@
+use crate::internal::*;
+use crate::ops::scan::{InputMapping, TypedScan};
+use crate::pulse::delay::Delay;
+
+/// Streaming figures of a node of a pulsed model.
+#[derive(Clone, Debug, PartialEq)]
+pub struct NodeLatency {
+    pub node: usize,
+    /// Delay of each output, in frames of its own streaming axis.
+    pub delay: TVec<usize>,
+    /// Delay of each output, converted to frames of the streaming symbol `S`.
+    pub stream_delay: TVec<usize>,
+    /// Frames of context prepended to each pulse, for `Delay` operators.
+    pub overlap: usize,
+    /// Bytes kept in the node state from one pulse to the next.
+    pub buffer: usize,
+}
+
+impl PulsedModel {
+    /// Delays, overlaps and buffer memory of every node, in evaluation order,
+    /// for a model pulsified with a pulse of `stream_pulse`.
+    pub fn latencies(&self, stream_pulse: usize) -> TractResult<Vec<NodeLatency>> {
+        let mut report = vec![];
+        for node in crate::model::eval_order(self)? {
+            let op = &self.node(node).op;
+            let overlap = op.as_op().downcast_ref::<Delay>().map(|d| d.overlap()).unwrap_or(0);
+            let outputs = &self.node(node).outputs;
+            let delay = outputs.iter().map(|o| o.fact.delay).collect();
+            // overlapping frames are repeated, they do not change the stream rate
+            let stream_delay = outputs
+                .iter()
+                .map(|o| {
+                    let mut fact = o.fact.clone();
+                    fact.shape[fact.axis] -= overlap;
+                    fact.stream_delay(stream_pulse)
+                })
+                .collect();
+            let inputs: TVec<TypedFact> =
+                self.node_input_facts(node)?.into_iter().map(|f| f.into()).collect();
+            let inputs: TVec<&TypedFact> = inputs.iter().collect();
+            let mut buffer = match op.as_op().downcast_ref::<TypedScan>() {
+                Some(scan) => scan_states(scan)?,
+                None => 0,
+            };
+            for (cost, count) in op.to_typed().cost(&*inputs)? {
+                if let Cost::Buffer(dt) = cost {
+                    buffer += count.to_integer()? as usize * dt.size_of();
+                }
+            }
+            report.push(NodeLatency { node, delay, stream_delay, overlap, buffer });
+        }
+        Ok(report)
+    }
+}
+
+/// Bytes of the hidden states a scan carries over from one pulse to the
+/// next. They are not a cost of the scan op itself, as they are just
+/// discarded when the whole sequence is processed at once.
+fn scan_states(scan: &TypedScan) -> TractResult<usize> {
+    let mut bytes = 0;
+    for (slot, input) in scan.input_mapping.iter().enumerate() {
+        if let InputMapping::State { .. } = input {
+            let fact = scan.body.input_fact(slot)?;
+            let len = fact.shape.iter().maybe_product()?.to_integer()? as usize;
+            bytes += len * fact.datum_type.size_of();
+        }
+    }
+    Ok(bytes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn delay_and_buffer() -> TractResult<()> {
+        let mut model = PulsedModel::default();
+        let fact = PulsedFact {
+            datum_type: f32::datum_type(),
+            shape: tvec![4, 3],
+            axis: 0,
+            dim: TDim::s(),
+            delay: 0,
+        };
+        let source = model.add_source("source", fact.clone())?;
+        model.wire_node("delay", Delay::new(&fact, 3, 2), &[source])?;
+        model.auto_outputs()?;
+        let report = model.latencies(4)?;
+        assert_eq!(report.len(), 2);
+        assert_eq!(report[0].delay, tvec!(0));
+        assert_eq!(report[0].buffer, 0);
+        assert_eq!(report[1].delay, tvec!(5));
+        assert_eq!(report[1].stream_delay, tvec!(5));
+        assert_eq!(report[1].overlap, 2);
+        assert_eq!(report[1].buffer, 5 * 3 * 4);
+        Ok(())
+    }
+}
@@ -3,6 +3,11 @@ use crate::model::translator::Translate;
 use std::fmt;
 
 pub mod delay;
+pub mod latency;
+pub mod session;
+
+pub use self::latency::NodeLatency;
+pub use self::session::StreamingSession;
 
 #[derive(Clone, PartialEq, Hash)]
 pub struct PulsedFact {
@@ -55,12 +60,33 @@ impl<'a> From<&'a Box<dyn PulsedOp>> for Box<dyn TypedOp> {
 }
 
 impl PulsedFact {
+    /// Pulsed fact for a streamed source, `pulse` being the pulse of the
+    /// streaming symbol `S`.
+    ///
+    /// Sources streaming at a different rate than `S` (like `S/4` or `2*S`)
+    /// get a pulse scaled accordingly, so `pulse` must be compatible with the
+    /// ratio.
     pub fn from_tensor_fact_pulse(tf: &NormalizedFact, pulse: usize) -> TractResult<PulsedFact> {
         let datum_type = tf.datum_type;
         let stream =
             tf.shape.stream_info.as_ref().ok_or("Can not pulse a tensor with no streaming dim")?;
-        let shape =
-            tf.shape.iter().map(|d| d.to_integer().map(|d| d as usize).unwrap_or(pulse)).collect();
+        let stream_pulse = stream.len.eval(pulse as i32).unwrap_or(0);
+        // the length must scale exactly with the pulse, including over the
+        // multiples of its divisor, where floor divisions would show
+        let k = 2 * stream.len.divisor() as i32;
+        if stream_pulse <= 0 || stream.len.eval(k * pulse as i32) != Some(k * stream_pulse) {
+            bail!(
+                "Can not pulse a stream of length {:?} with a pulse of {}: length must be \
+                proportional to S, and pulse a multiple of the ratio",
+                stream.len,
+                pulse
+            )
+        }
+        let shape = tf
+            .shape
+            .iter()
+            .map(|d| d.to_integer().map(|d| d as usize).unwrap_or(stream_pulse as usize))
+            .collect();
         Ok(PulsedFact { datum_type, shape, axis: stream.axis, dim: stream.len.clone(), delay: 0 })
     }
 
@@ -68,6 +94,31 @@ impl PulsedFact {
         self.shape[self.axis]
     }
 
+    /// Delay converted to frames of the streaming symbol `S`, in a model
+    /// pulsified with a pulse of `stream_pulse` for `S`.
+    pub fn stream_delay(&self, stream_pulse: usize) -> usize {
+        (self.delay * stream_pulse + self.pulse() - 1) / self.pulse()
+    }
+
+    /// Check that two pulsed tensors stream along the same axis, at the same
+    /// rate, so they can be combined frame by frame once their delays are
+    /// aligned. Streams at different rates must be brought to a common rate
+    /// (with a `Downsample` for instance) before they meet.
+    pub fn check_synchronized(&self, other: &PulsedFact) -> TractResult<()> {
+        if self.axis != other.axis {
+            bail!("Can not combine streams along different axes: {:?} and {:?}", self, other)
+        }
+        if self.pulse() != other.pulse() {
+            bail!(
+                "Can not combine streams at different rates: {:?} and {:?} (resample one of \
+                them first)",
+                self,
+                other
+            )
+        }
+        Ok(())
+    }
+
     pub fn to_pulse_fact(&self) -> NormalizedFact {
         NormalizedFact::dt_shape(self.datum_type, &*self.shape).unwrap()
     }
@@ -82,7 +133,7 @@ impl PulsedFact {
 
     pub fn to_streaming_fact(&self) -> NormalizedFact {
         let mut info = self.to_pulse_fact();
-        info.shape.stream_info = Some(StreamFact { axis: self.axis, len: self.dim.clone() });
+        info.shape.set_dim(self.axis, self.dim.clone()).unwrap();
         info
     }
 }
@@ -102,11 +153,67 @@ impl PulsedModel {
         Pulsifier(pulse).translate_model_with_mappings(source)
     }
 
+    /// Latency of each output, in frames of the streaming symbol `S`, for a
+    /// model pulsified with a pulse of `stream_pulse`.
+    pub fn output_latencies(&self, stream_pulse: usize) -> TractResult<TVec<usize>> {
+        self.output_outlets()?
+            .iter()
+            .map(|o| Ok(self.outlet_fact(*o)?.stream_delay(stream_pulse)))
+            .collect()
+    }
+
     pub fn into_typed(self) -> TractResult<TypedModel> {
         crate::model::translator::IntoTranslator.translate_model(&self)
     }
 }
 
+/// Smallest pulse allowed by the strides of `model`. Strided wires stream
+/// dimensions like S/2, only linear in S on its multiples of 2, so pulses
+/// must be multiples of the divisors of all streaming dimensions.
+pub fn stride_multiple(model: &NormalizedModel) -> usize {
+    let mut multiple = 1;
+    for node in model.nodes() {
+        for output in &node.outputs {
+            for dim in output.fact.shape.iter().filter(|d| d.is_stream()) {
+                multiple = num_integer::lcm(multiple, dim.divisor() as usize);
+            }
+        }
+    }
+    multiple
+}
+
+/// Map the inputs of `node` in `target`, delaying them as needed so they all
+/// have the same delay. Inputs must stream along the same axis at the same
+/// rate.
+pub(crate) fn sync_inputs(
+    node: &NormalizedNode,
+    target: &mut PulsedModel,
+    mapping: &HashMap<OutletId, OutletId>,
+) -> TractResult<TVec<OutletId>> {
+    let facts = node
+        .inputs
+        .iter()
+        .map(|i| Ok(target.outlet_fact(mapping[i])?.clone()))
+        .collect::<TractResult<TVec<_>>>()?;
+    for fact in &facts[1..] {
+        facts[0].check_synchronized(fact)?;
+    }
+    let delay = facts.iter().map(|f| f.delay).max().unwrap_or(0);
+    let mut inputs = tvec!();
+    for (ix, fact) in facts.iter().enumerate() {
+        let mut input = mapping[&node.inputs[ix]];
+        if fact.delay < delay {
+            input = target.wire_node(
+                format!("{}/Delay-{}", &*node.name, ix),
+                delay::Delay::new(fact, delay - fact.delay, 0),
+                &[input],
+            )?[0];
+        }
+        inputs.push(input);
+    }
+    Ok(inputs)
+}
+
 #[derive(Debug)]
 struct Pulsifier(usize);
 impl
@@ -131,6 +238,7 @@ impl
 #[cfg(test)]
 mod tests {
     use super::*;
+    use ndarray::*;
 
     #[test]
     fn test_source_must_stream() {
@@ -160,6 +268,21 @@ mod tests {
         );
     }
 
+    #[test]
+    fn stride_multiple_of_chained_downsamples() {
+        use crate::ops::downsample::Downsample;
+        let mut model = TypedModel::default();
+        let fact = TypedFact::dt_shape(f32::datum_type(), [TDim::s()].as_ref()).unwrap();
+        let a = model.add_source("a", fact).unwrap();
+        let a = model.wire_node("ds2", Downsample::new(0, 2, 0), &[a]).unwrap();
+        model.wire_node("ds3", Downsample::new(0, 3, 0), &a).unwrap();
+        model.auto_outputs().unwrap();
+        let model = model.into_normalized().unwrap();
+        assert_eq!(stride_multiple(&model), 6);
+        assert!(PulsedModel::new(&model, 6).is_ok());
+        assert!(PulsedModel::new(&model, 4).is_err());
+    }
+
     #[test]
     fn test_immediate() {
         let mut model = TypedModel::default();
@@ -186,4 +309,113 @@ mod tests {
             TypedFact::dt_shape(DatumType::F32, &*vec!(4, 2, 3)).unwrap()
         );
     }
+
+    fn stream_source(model: &mut TypedModel, name: &str, len: TDim) -> OutletId {
+        let fact = TypedFact::dt_shape(f32::datum_type(), [len, 2.to_dim()].as_ref()).unwrap();
+        model.add_source(name, fact).unwrap()
+    }
+
+    fn delayed(model: &mut TypedModel, name: &str, input: OutletId) -> OutletId {
+        let pad = crate::ops::array::Pad::new(
+            vec![(2, 0), (0, 0)],
+            crate::ops::array::PadMode::Constant(rctensor0(0f32)),
+        );
+        let pad = model.wire_node(format!("{}-pad", name), pad, &[input]).unwrap();
+        let slice = crate::ops::array::Slice::new(0, 1.to_dim(), TDim::s() + 1);
+        model.wire_node(name, slice, &pad).unwrap()[0]
+    }
+
+    #[test]
+    fn test_sources_with_ratio() {
+        let mut model = TypedModel::default();
+        let _c = stream_source(&mut model, "c", TDim::s() / 4);
+        model.auto_outputs().unwrap();
+        let model = model.into_normalized().unwrap();
+        let pulsed = PulsedModel::new(&model, 8).unwrap();
+        assert_eq!(pulsed.input_fact(0).unwrap().shape, tvec!(2, 2));
+        assert!(PulsedModel::new(&model, 6).is_err());
+        assert!(PulsedModel::new(&model, 5).is_err());
+    }
+
+    #[test]
+    fn test_merge_reconciles_delays() {
+        let mut model = TypedModel::default();
+        let a = stream_source(&mut model, "a", TDim::s());
+        let b = stream_source(&mut model, "b", TDim::s());
+        let b = delayed(&mut model, "b-delay", b);
+        let add = model.wire_node("add", crate::ops::math::add::bin_typed(), &[a, b]).unwrap();
+        model.set_output_outlets(&add).unwrap();
+        let pulsed = PulsedModel::new(&model.into_normalized().unwrap(), 4).unwrap();
+        assert!(pulsed.node_by_name("add/Delay-0").unwrap().op_is::<delay::Delay>());
+        let output = pulsed.output_fact(0).unwrap();
+        assert_eq!(output.shape, tvec!(4, 2));
+        assert_eq!(pulsed.output_latencies(4).unwrap(), tvec!(output.delay));
+        assert!(output.delay > 0);
+    }
+
+    #[test]
+    fn test_merge_different_rates() {
+        let mut model = TypedModel::default();
+        let _a = stream_source(&mut model, "a", TDim::s());
+        let _b = stream_source(&mut model, "b", TDim::s() / 4);
+        model.auto_outputs().unwrap();
+        let pulsed = PulsedModel::new(&model.into_normalized().unwrap(), 4).unwrap();
+        let a = pulsed.input_fact(0).unwrap();
+        let b = pulsed.input_fact(1).unwrap();
+        assert!(a.check_synchronized(a).is_ok());
+        assert!(a.check_synchronized(b).is_err());
+    }
+
+    #[test]
+    fn test_merge_downsampled_stream() -> TractResult<()> {
+        use crate::ops::downsample::Downsample;
+        let mut model = TypedModel::default();
+        let a = stream_source(&mut model, "a", TDim::s());
+        let b = stream_source(&mut model, "b", TDim::s().div_ceil(2));
+        let a = delayed(&mut model, "a-delay", a);
+        let a = model.wire_node("a-down", Downsample::new(0, 2, 0), &[a])?[0];
+        let add = model.wire_node("add", crate::ops::math::add::bin_typed(), &[a, b])?;
+        model.set_output_outlets(&add)?;
+        let pulsed = PulsedModel::new(&model.clone().into_normalized()?, 4)?;
+        assert_eq!(pulsed.input_fact(1)?.shape, tvec!(2, 2));
+        let delay = pulsed.output_fact(0)?.delay;
+
+        let a = Array2::from_shape_fn((16, 2), |(t, c)| (t * 2 + c) as f32);
+        let b = Array2::from_shape_fn((8, 2), |(t, c)| 100. * (t * 2 + c) as f32);
+        let expected = SimplePlan::new(model)?
+            .run(tvec!(a.clone().into_tensor(), b.clone().into_tensor()))?
+            .remove(0);
+        let expected = expected.to_array_view::<f32>()?;
+
+        let mut state = SimpleState::new(SimplePlan::new(pulsed.into_typed()?)?)?;
+        let mut found = vec![];
+        for i in 0..4 {
+            let a = a.slice(s![4 * i..4 * (i + 1), ..]).to_owned().into_tensor();
+            let b = b.slice(s![2 * i..2 * (i + 1), ..]).to_owned().into_tensor();
+            let output = state.run(tvec!(a, b))?.remove(0);
+            found.extend(output.to_array_view::<f32>()?.outer_iter().map(|f| f.to_owned()));
+        }
+        for (t, frame) in found.iter().enumerate().skip(delay) {
+            assert_eq!(frame, &expected.index_axis(Axis(0), t - delay));
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_concat_across_stream_axis() {
+        let mut model = TypedModel::default();
+        let a = stream_source(&mut model, "a", TDim::s());
+        let b = stream_source(&mut model, "b", TDim::s());
+        let b = delayed(&mut model, "b-delay", b);
+        let concat = crate::ops::array::TypedConcat::new(
+            1,
+            tvec!(crate::ops::array::ConcatSlice::Var, crate::ops::array::ConcatSlice::Var),
+        );
+        let concat = model.wire_node("concat", concat, &[a, b]).unwrap();
+        model.set_output_outlets(&concat).unwrap();
+        let pulsed = PulsedModel::new(&model.into_normalized().unwrap(), 4).unwrap();
+        let output = pulsed.output_fact(0).unwrap();
+        assert_eq!(output.shape, tvec!(4, 4));
+        assert!(pulsed.node_by_name("concat/Delay-0").unwrap().op_is::<delay::Delay>());
+    }
 }
@@ -0,0 +1,239 @@
+use crate::internal::*;
+use ndarray::*;
+
+type PulsedPlan = SimplePlan<PulsedFact, Box<dyn PulsedOp>, PulsedModel>;
+type PulsedState = SimpleState<PulsedFact, Box<dyn PulsedOp>, PulsedModel, PulsedPlan>;
+
+/// Runs a pulsed model over a stream fed by chunks of any length.
+///
+/// Chunks are buffered into pulses, the warm-up frames accounted for by the
+/// output `delay` are dropped, and `finish` flushes the frames depending on
+/// the end of the stream, so that concatenating everything `push` and
+/// `finish` return gives the output of the non-pulsed model.
+#[derive(Clone)]
+pub struct StreamingSession {
+    state: PulsedState,
+    input_fact: PulsedFact,
+    output_fact: PulsedFact,
+    pending_input: Option<Tensor>,
+    pending_output: Option<Tensor>,
+    /// Input frames received.
+    written: usize,
+    /// Delay frames still to be dropped from the output.
+    to_skip: usize,
+    /// Output frames returned.
+    emitted: usize,
+}
+
+impl StreamingSession {
+    pub fn new(model: PulsedModel) -> TractResult<StreamingSession> {
+        if model.input_outlets()?.len() != 1 || model.output_outlets()?.len() != 1 {
+            bail!("StreamingSession expects a model with a single input and a single output")
+        }
+        let input_fact = model.input_fact(0)?.clone();
+        let output_fact = model.output_fact(0)?.clone();
+        if output_fact.pulse() == 0 {
+            bail!("StreamingSession expects a model producing frames at each pulse")
+        }
+        let state = SimpleState::new(SimplePlan::new(model)?)?;
+        let to_skip = output_fact.delay;
+        Ok(StreamingSession {
+            state,
+            input_fact,
+            output_fact,
+            pending_input: None,
+            pending_output: None,
+            written: 0,
+            to_skip,
+            emitted: 0,
+        })
+    }
+
+    pub fn input_fact(&self) -> &PulsedFact {
+        &self.input_fact
+    }
+
+    pub fn output_fact(&self) -> &PulsedFact {
+        &self.output_fact
+    }
+
+    /// Feed a chunk of input, and get the output frames that are now known.
+    ///
+    /// The chunk may have any length on the streaming axis, but must match
+    /// the input fact on the other axes.
+    pub fn push(&mut self, chunk: Tensor) -> TractResult<Tensor> {
+        let axis = self.input_fact.axis;
+        if chunk.datum_type() != self.input_fact.datum_type
+            || chunk.rank() != self.input_fact.shape.len()
+            || chunk
+                .shape()
+                .iter()
+                .zip(self.input_fact.shape.iter())
+                .enumerate()
+                .any(|(ix, (c, f))| ix != axis && c != f)
+        {
+            bail!("Chunk {:?} does not match input {:?}", chunk, self.input_fact)
+        }
+        self.written += chunk.shape()[axis];
+        let input = match self.pending_input.take() {
+            Some(pending) => Tensor::stack_tensors(axis, &[pending, chunk])?,
+            None => chunk,
+        };
+        let pulse = self.input_fact.pulse();
+        let len = input.shape()[axis];
+        let full_pulses = len / pulse;
+        if full_pulses == 1 && len == pulse {
+            self.run_pulse(input)?;
+        } else {
+            for ix in 0..full_pulses {
+                self.run_pulse(input.slice(axis, ix * pulse, (ix + 1) * pulse)?)?;
+            }
+            if len > full_pulses * pulse {
+                self.pending_input = Some(input.slice(axis, full_pulses * pulse, len)?);
+            }
+        }
+        // the stream is at least as long as what we have seen so far
+        let known = self.output_len(self.written)?;
+        self.take_output(known)
+    }
+
+    /// Signal the end of the stream, and get the remaining output frames.
+    ///
+    /// The session is reset afterwards, and can be used for another stream.
+    pub fn finish(&mut self) -> TractResult<Tensor> {
+        let axis = self.input_fact.axis;
+        let pulse = self.input_fact.pulse();
+        let total = self.output_len(self.written)?;
+        self.state.session_state.known_stream_len = Some(self.written);
+        if let Some(pending) = self.pending_input.take() {
+            let missing = pulse - pending.shape()[axis];
+            let filler = self.filler(missing)?;
+            self.run_pulse(Tensor::stack_tensors(axis, &[pending, filler])?)?;
+        }
+        while self.emitted + self.pending_output_len() < total {
+            let filler = self.filler(pulse)?;
+            self.run_pulse(filler)?;
+        }
+        let output = self.take_output(total)?;
+        self.reset()?;
+        Ok(output)
+    }
+
+    /// Drop the current stream.
+    pub fn reset(&mut self) -> TractResult<()> {
+        self.state.reset_op_states()?;
+        self.state.reset_wires()?;
+        self.state.session_state.known_stream_len = None;
+        self.pending_input = None;
+        self.pending_output = None;
+        self.written = 0;
+        self.to_skip = self.output_fact.delay;
+        self.emitted = 0;
+        Ok(())
+    }
+
+    fn output_len(&self, input_len: usize) -> TractResult<usize> {
+        let len = self.output_fact.dim.eval(input_len as i32).ok_or_else(|| {
+            format!("Can not compute output length from {:?}", self.output_fact.dim)
+        })?;
+        Ok(len.max(0) as usize)
+    }
+
+    fn pending_output_len(&self) -> usize {
+        self.pending_output.as_ref().map(|t| t.shape()[self.output_fact.axis]).unwrap_or(0)
+    }
+
+    fn filler(&self, len: usize) -> TractResult<Tensor> {
+        let mut shape = self.input_fact.shape.clone();
+        shape[self.input_fact.axis] = len;
+        fn zeros<T: Datum>(shape: &[usize]) -> TractResult<Tensor> {
+            Ok(ArrayD::<T>::default(shape).into_tensor())
+        }
+        dispatch_datum!(zeros(self.input_fact.datum_type)(&shape))
+    }
+
+    fn run_pulse(&mut self, input: Tensor) -> TractResult<()> {
+        let axis = self.output_fact.axis;
+        let mut output = self.state.run(tvec!(input))?.remove(0).into_tensor();
+        let len = output.shape()[axis];
+        if self.to_skip >= len {
+            self.to_skip -= len;
+            return Ok(());
+        }
+        if self.to_skip > 0 {
+            output = output.slice(axis, self.to_skip, len)?;
+            self.to_skip = 0;
+        }
+        self.pending_output = Some(match self.pending_output.take() {
+            Some(pending) => Tensor::stack_tensors(axis, &[pending, output])?,
+            None => output,
+        });
+        Ok(())
+    }
+
+    /// Returns the pending output frames, up to `total` output frames since
+    /// the beginning of the stream.
+    fn take_output(&mut self, total: usize) -> TractResult<Tensor> {
+        let axis = self.output_fact.axis;
+        let available = self.pending_output_len();
+        let wanted = total.saturating_sub(self.emitted).min(available);
+        self.emitted += wanted;
+        match self.pending_output.take() {
+            Some(pending) if wanted == available => Ok(pending),
+            Some(pending) => {
+                self.pending_output = Some(pending.slice(axis, wanted, available)?);
+                pending.slice(axis, 0, wanted)
+            }
+            None => {
+                let mut shape = self.output_fact.shape.clone();
+                shape[axis] = 0;
+                fn empty<T: Datum>(shape: &[usize]) -> TractResult<Tensor> {
+                    Ok(ArrayD::<T>::default(shape).into_tensor())
+                }
+                dispatch_datum!(empty(self.output_fact.datum_type)(&shape))
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ops::array::{Pad, PadMode, Slice};
+
+    fn model(len: TDim) -> TypedModel {
+        let mut model = TypedModel::default();
+        let shape = [len.clone(), 2.to_dim()];
+        let a = model.add_source("a", TypedFact::dt_shape(f32::datum_type(), &shape[..]).unwrap());
+        let pad = Pad::new(vec![(2, 3), (0, 0)], PadMode::Constant(rctensor0(1f32)));
+        let pad = model.wire_node("pad", pad, &[a.unwrap()]).unwrap();
+        let slice = Slice::new(0, 1.to_dim(), len + 4);
+        let slice = model.wire_node("slice", slice, &pad).unwrap();
+        model.set_output_outlets(&slice).unwrap();
+        model
+    }
+
+    #[test]
+    fn streamed_chunks_match_full_run() {
+        let input = Array2::from_shape_fn((11, 2), |(t, c)| (t * 2 + c) as f32 + 10.);
+        let expected = SimplePlan::new(model(11.to_dim()))
+            .unwrap()
+            .run(tvec!(input.clone().into_tensor()))
+            .unwrap()
+            .remove(0);
+        let pulsed = PulsedModel::new(&model(TDim::s()).into_normalized().unwrap(), 4).unwrap();
+        let mut session = StreamingSession::new(pulsed).unwrap();
+        for _ in 0..2 {
+            let mut outputs = vec![];
+            let mut fed = 0;
+            for &len in &[1, 5, 0, 3, 2] {
+                let chunk = input.slice(s![fed..fed + len, ..]).to_owned().into_tensor();
+                fed += len;
+                outputs.push(session.push(chunk).unwrap());
+            }
+            outputs.push(session.finish().unwrap());
+            let found = Tensor::stack_tensors(0, &outputs).unwrap();
+            assert_eq!(&found, &*expected);
+        }
+    }
+}
@@ -0,0 +1,302 @@
+//! Post-training static quantization.
+//!
+//! A `Calibrator` runs a float `TypedModel` over a user-supplied dataset and
+//! records the range of the tensors flowing in and out of the convolutions
+//! and matrix products. `quantize` then rewrites these operators to their
+//! int8 form: activations are quantized with an asymmetric per-tensor
+//! scheme, weights with a symmetric one, bias in i32 at the accumulator
+//! scale, and the accumulator is requantized to the output range.
+//!
+//! Each rewritten operator is surrounded by a QuantizeLinear and a
+//! DequantizeLinear, so the model stays valid at all times. Decluttering
+//! then folds the dequantize, element-wise ops and quantize sequences found
+//! between two quantized operators into integer lookup tables.
+
+use crate::internal::*;
+use crate::ops::cnn::ConvUnary;
+use crate::ops::matmul::MatMulUnary;
+use crate::ops::quant::{quantize_linear_i8, DequantizeLinearF32, QParams};
+
+/// Range of the values observed on a tensor during calibration.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Range {
+    pub min: f32,
+    pub max: f32,
+}
+
+impl Default for Range {
+    fn default() -> Range {
+        Range { min: std::f32::INFINITY, max: std::f32::NEG_INFINITY }
+    }
+}
+
+impl Range {
+    fn observe(&mut self, t: &Tensor) -> TractResult<()> {
+        for &x in t.as_slice::<f32>()? {
+            if x.is_finite() {
+                self.min = self.min.min(x);
+                self.max = self.max.max(x);
+            }
+        }
+        Ok(())
+    }
+
+    /// Scale and zero point of an asymmetric i8 quantization covering the
+    /// range, zero included.
+    pub fn i8_scale_and_zero_point(&self) -> (f32, i8) {
+        let min = self.min.min(0.0);
+        let max = self.max.max(0.0);
+        let scale = if max > min { (max - min) / 255.0 } else { 1.0 };
+        let zero_point = (-128.0 - min / scale).round().max(-128.0).min(127.0) as i8;
+        (scale, zero_point)
+    }
+}
+
+fn is_quantizable(model: &TypedModel, node: &TypedNode) -> TractResult<bool> {
+    let float_weights = if let Some(conv) = node.op_as::<ConvUnary>() {
+        conv.q_params.is_none() && conv.kernel.datum_type() == f32::datum_type()
+    } else if let Some(mm) = node.op_as::<MatMulUnary>() {
+        mm.q_params.is_none() && mm.a.datum_type() == f32::datum_type()
+    } else {
+        false
+    };
+    Ok(float_weights && model.outlet_fact(node.inputs[0])?.datum_type == f32::datum_type())
+}
+
+/// Collects the ranges needed to quantize a model.
+pub struct Calibrator<'m> {
+    plan: SimplePlan<TypedFact, Box<dyn TypedOp>, &'m TypedModel>,
+    outlets: Vec<OutletId>,
+    ranges: HashMap<OutletId, Range>,
+}
+
+impl<'m> Calibrator<'m> {
+    pub fn new(model: &'m TypedModel) -> TractResult<Calibrator<'m>> {
+        let mut outlets = vec![];
+        for node in model.nodes() {
+            if is_quantizable(model, node)? {
+                outlets.push(node.inputs[0]);
+                outlets.push(OutletId::new(node.id, 0));
+            }
+        }
+        if outlets.is_empty() {
+            bail!("No quantizable operator found in model")
+        }
+        outlets.sort();
+        outlets.dedup();
+        let plan = SimplePlan::new_for_outputs(model, &outlets)?;
+        Ok(Calibrator { plan, outlets, ranges: HashMap::new() })
+    }
+
+    /// Runs the float model on a sample, extending the observed ranges.
+    pub fn observe(&mut self, inputs: TVec<Tensor>) -> TractResult<()> {
+        let values = self.plan.run(inputs)?;
+        for (outlet, value) in self.outlets.iter().zip(values.iter()) {
+            self.ranges.entry(*outlet).or_default().observe(value)?;
+        }
+        Ok(())
+    }
+
+    pub fn ranges(&self) -> &HashMap<OutletId, Range> {
+        &self.ranges
+    }
+
+    /// Quantizes the calibrated model.
+    pub fn quantize(&self) -> TractResult<TypedModel> {
+        quantize(self.plan.model(), &self.ranges)
+    }
+}
+
+/// Symmetric i8 quantization of a weight tensor.
+fn quantize_weights(weights: &Tensor) -> TractResult<(Arc<Tensor>, f32)> {
+    let abs_max = weights.as_slice::<f32>()?.iter().fold(0f32, |acc, x| acc.max(x.abs()));
+    let scale = if abs_max > 0.0 { abs_max / 127.0 } else { 1.0 };
+    let quantized =
+        weights.to_array_view::<f32>()?.mapv(|x| (x / scale).round().max(-127.0).min(127.0) as i8);
+    Ok((quantized.into_arc_tensor(), scale))
+}
+
+fn quantize_bias(bias: &Tensor, scale: f32) -> TractResult<Arc<Tensor>> {
+    Ok(bias
+        .cast_to::<f32>()?
+        .to_array_view::<f32>()?
+        .mapv(|x| (x / scale).round() as i32)
+        .into_arc_tensor())
+}
+
+/// Rewrites the quantizable operators which ranges have been observed to
+/// their int8 form.
+///
+/// Operators with no calibration data, or for which the requantization
+/// factor is not below one, are left in float.
+pub fn quantize(model: &TypedModel, ranges: &HashMap<OutletId, Range>) -> TractResult<TypedModel> {
+    let mut quantized = model.clone();
+    for node in model.nodes() {
+        if !is_quantizable(model, node)? {
+            continue;
+        }
+        let (input_range, output_range) =
+            match (ranges.get(&node.inputs[0]), ranges.get(&OutletId::new(node.id, 0))) {
+                (Some(input), Some(output)) => (input, output),
+                _ => continue,
+            };
+        let (input_scale, input_zero_point) = input_range.i8_scale_and_zero_point();
+        let (output_scale, output_zero_point) = output_range.i8_scale_and_zero_point();
+        let conv = node.op_as::<ConvUnary>();
+        let mm = node.op_as::<MatMulUnary>();
+        let weights = conv.map(|conv| &conv.kernel).or(mm.map(|mm| &mm.a)).unwrap();
+        let (weights, weights_scale) = quantize_weights(weights)?;
+        let factor = input_scale * weights_scale / output_scale;
+        if !(factor > 0.0 && factor < 1.0) {
+            debug!("Leaving {} in float (requantization factor: {})", node, factor);
+            continue;
+        }
+        let q_params = QParams::new(i8::datum_type())
+            .with_zero_point_b(&rctensor0(input_zero_point))
+            .with_zero_point_c(&rctensor0(output_zero_point))
+            .with_scale_factor(factor);
+        let op: Box<dyn TypedOp> = if let Some(conv) = conv {
+            let bias = conv
+                .bias
+                .as_ref()
+                .map(|b| quantize_bias(b, input_scale * weights_scale))
+                .transpose()?;
+            Box::new(ConvUnary { kernel: weights, bias, q_params: Some(q_params), ..conv.clone() })
+        } else {
+            Box::new(MatMulUnary { a: weights, q_params: Some(q_params), ..mm.unwrap().clone() })
+        };
+        debug!("Quantizing {} (requantization factor: {})", node, factor);
+        let mut patch = TypedModelPatch::default();
+        let mut wire = patch.tap_model(&quantized, quantized.node(node.id).inputs[0])?;
+        wire = patch.wire_node(
+            format!("{}-quantize", node.name),
+            quantize_linear_i8(input_scale.recip(), input_zero_point),
+            &[wire],
+        )?[0];
+        wire = patch.wire_node(&*node.name, op, &[wire])?[0];
+        wire = patch.wire_node(
+            format!("{}-dequantize", node.name),
+            DequantizeLinearF32::new(output_scale, output_zero_point as i32),
+            &[wire],
+        )?[0];
+        patch.shunt_outside(&quantized, OutletId::new(node.id, 0), wire)?;
+        patch.apply(&mut quantized)?;
+    }
+    crate::model::compact::compact(&quantized)?.declutter()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::ops::cnn::{PaddingSpec, PoolSpec};
+    use crate::ops::math::scalar_max;
+    use crate::ops::nn::DataFormat;
+    use ndarray::*;
+
+    fn conv(kernel: Array3<f32>, bias: Array1<f32>, padding: PaddingSpec) -> ConvUnary {
+        let pool_spec = PoolSpec {
+            data_format: DataFormat::NCHW,
+            kernel_shape: tvec!(kernel.shape()[2]),
+            padding,
+            dilations: None,
+            strides: None,
+            output_channel_override: Some(kernel.shape()[0]),
+        };
+        ConvUnary::new(
+            pool_spec,
+            crate::ops::cnn::KernelFormat::OIHW,
+            kernel.into_arc_tensor(),
+            1,
+            Some(bias.into_arc_tensor()),
+            None,
+        )
+    }
+
+    fn sample(seed: usize) -> Tensor {
+        Array3::from_shape_fn((1, 2, 16), |(_, c, x)| {
+            (((seed * 7 + c * 5 + x * 3) % 11) as f32 - 4.0) / 3.0
+        })
+        .into_tensor()
+    }
+
+    fn model() -> TractResult<TypedModel> {
+        let mut model = TypedModel::default();
+        let mut wire = model
+            .add_source("input", TypedFact::dt_shape(f32::datum_type(), [1, 2, 16].as_ref())?)?;
+        let k1 = Array3::from_shape_fn((3, 2, 3), |(o, i, x)| {
+            ((o * 6 + i * 3 + x) % 5) as f32 * 0.2 - 0.4
+        });
+        wire = model.wire_node(
+            "conv1",
+            conv(k1, arr1(&[0.1f32, -0.2, 0.3]), PaddingSpec::Valid),
+            &[wire],
+        )?[0];
+        wire = model.wire_node("relu", scalar_max(tensor0(0f32)), &[wire])?[0];
+        let k2 = Array3::from_shape_fn((2, 3, 3), |(o, i, x)| {
+            ((o * 9 + i * 3 + x) % 7) as f32 * 0.1 - 0.3
+        });
+        wire = model.wire_node(
+            "conv2",
+            conv(k2, arr1(&[0.5f32, -0.5]), PaddingSpec::SameUpper),
+            &[wire],
+        )?[0];
+        model.set_output_outlets(&[wire])?;
+        Ok(model)
+    }
+
+    #[test]
+    fn quantized_convs_match_float() -> TractResult<()> {
+        let model = model()?;
+        let mut calibrator = Calibrator::new(&model)?;
+        for seed in 0..8 {
+            calibrator.observe(tvec!(sample(seed)))?;
+        }
+        let quantized = calibrator.quantize()?;
+        let kernels: Vec<DatumType> = quantized
+            .nodes()
+            .iter()
+            .filter_map(|n| n.op_as::<ConvUnary>())
+            .map(|c| c.kernel.datum_type())
+            .collect();
+        assert_eq!(kernels, vec!(i8::datum_type(), i8::datum_type()));
+        let range = calibrator.ranges()[&OutletId::new(3, 0)];
+        let tolerance = (range.max - range.min) * 0.05;
+        let optimized = quantized.clone().into_optimized()?;
+        for seed in 0..8 {
+            let expected = SimplePlan::new(&model)?.run(tvec!(sample(seed)))?.remove(0);
+            let expected = expected.to_array_view::<f32>()?;
+            for m in &[&quantized, &optimized] {
+                let found = SimplePlan::new(*m)?.run(tvec!(sample(seed)))?.remove(0);
+                let found = found.to_array_view::<f32>()?;
+                let error =
+                    Zip::from(&expected).and(&found).fold(0f32, |acc, a, b| acc.max((a - b).abs()));
+                assert!(error <= tolerance, "error: {} tolerance: {}", error, tolerance);
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn quantized_matmul_uses_int8_weights() -> TractResult<()> {
+        let mut model = TypedModel::default();
+        let wire =
+            model.add_source("input", TypedFact::dt_shape(f32::datum_type(), [4, 3].as_ref())?)?;
+        let a = arr2(&[[0.5f32, -1.0, 0.25, 0.0], [1.0, 0.5, -0.5, 0.75]]).into_arc_tensor();
+        let wire =
+            model.wire_node("mm", MatMulUnary::new(a, false, false, false, None), &[wire])?[0];
+        model.set_output_outlets(&[wire])?;
+        let input =
+            Array2::from_shape_fn((4, 3), |(i, j)| (i as f32 - j as f32) * 0.5).into_tensor();
+        let mut calibrator = Calibrator::new(&model)?;
+        calibrator.observe(tvec!(input.clone()))?;
+        let quantized = calibrator.quantize()?;
+        let mm = quantized.nodes().iter().find_map(|n| n.op_as::<MatMulUnary>()).unwrap();
+        assert_eq!(mm.a.datum_type(), i8::datum_type());
+        let expected = SimplePlan::new(&model)?.run(tvec!(input.clone()))?.remove(0);
+        let found = SimplePlan::new(&quantized)?.run(tvec!(input))?.remove(0);
+        let expected = expected.to_array_view::<f32>()?;
+        let found = found.to_array_view::<f32>()?;
+        Zip::from(&expected).and(&found).apply(|a, b| assert!((a - b).abs() < 0.05, "{} {}", a, b));
+        Ok(())
+    }
+}
@@ -13,13 +13,21 @@ use serde::ser::{Serialize, Serializer};
 use std::sync::Arc;
 
 pub mod litteral;
+pub mod mmap;
+
+use self::mmap::Mmap;
 
 /// Tensor is a concrete tensor in tract.
+///
+/// Its data is either owned on the heap, or borrowed from a read-only
+/// memory mapped region (see `from_mmap`), in which case it is copied to
+/// the heap on the first mutable access.
 pub struct Tensor {
     dt: DatumType,
     shape: TVec<usize>,
     layout: alloc::Layout,
     data: *mut u8,
+    mmap: Option<Arc<Mmap>>,
 }
 
 unsafe impl Send for Tensor {}
@@ -89,7 +97,7 @@ impl Drop for Tensor {
                     .for_each(|s| std::ptr::drop_in_place(s as *mut TDim));
             }
         }
-        if !self.data.is_null() && self.layout.size() > 0 {
+        if self.mmap.is_none() && !self.data.is_null() && self.layout.size() > 0 {
             unsafe { alloc::dealloc(self.data, self.layout) }
         }
     }
@@ -134,7 +142,7 @@ impl Tensor {
             assert!(!ptr.is_null());
             ptr
         } as *mut u8;
-        Ok(Tensor { layout, dt, shape: shape.into(), data })
+        Ok(Tensor { layout, dt, shape: shape.into(), data, mmap: None })
     }
 
     pub fn stack_tensors(
@@ -181,10 +189,64 @@ impl Tensor {
         content: &[u8],
     ) -> TractResult<Tensor> {
         let bytes = shape.iter().cloned().product::<usize>() * dt.size_of();
+        if bytes == 0 {
+            return Tensor::uninitialized_dt(dt, shape);
+        }
         let layout = alloc::Layout::from_size_align(bytes, dt.alignment())?;
         let data = alloc::alloc(layout);
         content.as_ptr().copy_to_nonoverlapping(data, bytes);
-        Ok(Tensor { dt, shape: shape.into(), data, layout })
+        Ok(Tensor { dt, shape: shape.into(), data, layout, mmap: None })
+    }
+
+    /// Create a tensor borrowing its data from a memory mapped region.
+    ///
+    /// The data starts at `offset` in the region. It is not copied: the
+    /// tensor keeps the region alive and reads from it, unless the data is
+    /// not aligned for `dt`, in which case it is copied to the heap. Only
+    /// plain data types are supported.
+    pub unsafe fn from_mmap(
+        dt: DatumType,
+        shape: &[usize],
+        mmap: Arc<Mmap>,
+        offset: usize,
+    ) -> TractResult<Tensor> {
+        if [DatumType::String, DatumType::TDim, DatumType::Blob].contains(&dt) {
+            bail!("Can not map a tensor of {:?}", dt)
+        }
+        let bytes = shape.iter().cloned().product::<usize>() * dt.size_of();
+        if offset + bytes > mmap.len() {
+            bail!(
+                "Can not map {} bytes at offset {} from a region of {} bytes",
+                bytes,
+                offset,
+                mmap.len()
+            )
+        }
+        let content = &mmap.as_bytes()[offset..][..bytes];
+        if bytes == 0 || content.as_ptr() as usize % dt.alignment() != 0 {
+            return Tensor::from_raw_dt(dt, shape, content);
+        }
+        let layout = alloc::Layout::from_size_align(bytes, dt.alignment())?;
+        let data = content.as_ptr() as *mut u8;
+        Ok(Tensor { dt, shape: shape.into(), data, layout, mmap: Some(mmap) })
+    }
+
+    /// Is the tensor data borrowed from a memory mapped region ?
+    pub fn is_mmapped(&self) -> bool {
+        self.mmap.is_some()
+    }
+
+    /// Copy memory mapped data to the heap, before mutating it.
+    fn make_owned(&mut self) {
+        if self.mmap.is_some() {
+            unsafe {
+                let data = alloc::alloc(self.layout);
+                assert!(!data.is_null());
+                self.data.copy_to_nonoverlapping(data, self.layout.size());
+                self.data = data;
+            }
+            self.mmap = None;
+        }
     }
 
     /// Get the number of dimensions (or axes) of the tensor.
@@ -203,8 +265,8 @@ impl Tensor {
     }
 
     /// Reshape the tensor to `shape`.
-    pub unsafe fn into_shape(self, shape: &[usize]) -> TractResult<Tensor> {
-        let t = Tensor { shape: shape.into(), ..self };
+    pub unsafe fn into_shape(mut self, shape: &[usize]) -> TractResult<Tensor> {
+        let t = Tensor { shape: shape.into(), mmap: self.mmap.take(), ..self };
         std::mem::forget(self);
         Ok(t)
     }
@@ -229,6 +291,28 @@ impl Tensor {
         self.dt = dt
     }
 
+    /// Reuse the memory of the tensor for an uninitialized tensor of another
+    /// datum type and shape.
+    ///
+    /// Only plain data types are supported. Returns None if the memory is too
+    /// small or not aligned enough.
+    pub unsafe fn reuse_as(self, dt: DatumType, shape: &[usize]) -> Option<Tensor> {
+        let plain = |dt| ![DatumType::String, DatumType::TDim, DatumType::Blob].contains(&dt);
+        let bytes = shape.iter().cloned().product::<usize>() * dt.size_of();
+        if !plain(self.dt)
+            || !plain(dt)
+            || self.layout.size() < bytes
+            || self.layout.align() < dt.alignment()
+            || (bytes > 0 && self.data.is_null())
+            || self.mmap.is_some()
+        {
+            return None;
+        }
+        let t = Tensor { dt, shape: shape.into(), mmap: None, ..self };
+        std::mem::forget(self);
+        Some(t)
+    }
+
     /// Dump the tensor in a human readable form.
     ///
     /// `force_full` will force the tensor to be dump in full even if it is big.
@@ -332,6 +416,7 @@ impl Tensor {
 
     /// Transform the data as a mutable `ndarray::Array`.
     pub unsafe fn to_array_view_mut_unchecked<'a, D: Datum>(&'a mut self) -> ArrayViewMutD<'a, D> {
+        self.make_owned();
         if self.len() != 0 {
             ArrayViewMutD::from_shape_ptr(&*self.shape, self.data as *mut D)
         } else {
@@ -347,7 +432,9 @@ impl Tensor {
 
     /// Access the data as a mutable pointer.
     pub fn as_ptr_mut<D: Datum>(&mut self) -> TractResult<*mut D> {
-        self.as_ptr::<D>().map(|p| p as *mut D)
+        self.check_for_access::<D>()?;
+        self.make_owned();
+        Ok(self.data as *mut D)
     }
 
     /// Access the data as a slice.
@@ -367,6 +454,7 @@ impl Tensor {
 
     /// Access the data as a mutable slice.
     pub unsafe fn as_slice_mut_unchecked<D: Datum>(&mut self) -> &mut [D] {
+        self.make_owned();
         std::slice::from_raw_parts_mut::<D>(self.data as *mut D, self.len())
     }
 
@@ -501,25 +589,37 @@ impl Tensor {
         let layout =
             alloc::Layout::from_size_align(vec.len() * size_of::<T>(), align_of::<T>()).unwrap();
         let data = Box::into_raw(vec) as *mut u8;
-        Tensor { dt: T::datum_type(), shape, layout, data }
+        Tensor { dt: T::datum_type(), shape, layout, data, mmap: None }
     }
 
     pub fn deep_clone(&self) -> Tensor {
         if self.dt == DatumType::String {
             let data: Vec<String> = self.as_slice::<String>().unwrap().to_vec();
-            let t = Tensor { data: data.as_ptr() as *mut u8, shape: self.shape.clone(), ..*self };
+            let t = Tensor {
+                data: data.as_ptr() as *mut u8,
+                shape: self.shape.clone(),
+                mmap: None,
+                ..*self
+            };
             std::mem::forget(data);
             t
         } else if self.dt == DatumType::TDim {
             let data: Vec<TDim> = self.as_slice::<TDim>().unwrap().to_vec();
-            let t = Tensor { data: data.as_ptr() as *mut u8, shape: self.shape.clone(), ..*self };
+            let t = Tensor {
+                data: data.as_ptr() as *mut u8,
+                shape: self.shape.clone(),
+                mmap: None,
+                ..*self
+            };
             std::mem::forget(data);
             t
+        } else if let Some(mmap) = &self.mmap {
+            Tensor { shape: self.shape.clone(), mmap: Some(mmap.clone()), ..*self }
         } else {
             unsafe {
                 let data = alloc::alloc(self.layout) as *mut u8;
                 self.data.copy_to_nonoverlapping(data, self.layout.size());
-                Tensor { data, shape: self.shape.clone(), ..*self }
+                Tensor { data, shape: self.shape.clone(), mmap: None, ..*self }
             }
         }
     }
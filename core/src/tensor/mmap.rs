@@ -0,0 +1,145 @@
+//! Read-only memory mapped file regions.
+//!
+//! A `Mmap` can back the storage of constant tensors (see
+//! `Tensor::from_mmap`), so that model weights are not copied to the heap
+//! and the pages are shared between processes loading the same file.
+use crate::internal::*;
+use std::fmt;
+use std::fs::File;
+use std::path::Path;
+
+/// A read-only view on a region of a file.
+///
+/// On unix, the region is mapped with `mmap`. On other platforms, it is read
+/// to the heap.
+pub struct Mmap {
+    #[cfg(unix)]
+    map: *mut libc::c_void,
+    #[cfg(unix)]
+    map_len: usize,
+    #[cfg(not(unix))]
+    _buffer: Vec<u8>,
+    data: *const u8,
+    len: usize,
+}
+
+unsafe impl Send for Mmap {}
+unsafe impl Sync for Mmap {}
+
+impl Mmap {
+    /// Map a whole file.
+    pub fn open(path: impl AsRef<Path>) -> TractResult<Mmap> {
+        let file =
+            File::open(path.as_ref()).chain_err(|| format!("Opening {:?}", path.as_ref()))?;
+        let len = file.metadata()?.len() as usize;
+        Mmap::map(&file, 0, len)
+    }
+
+    /// Map `len` bytes of `file`, starting at `offset`.
+    ///
+    /// Offset does not need to be aligned on a page boundary.
+    pub fn map(file: &File, offset: u64, len: usize) -> TractResult<Mmap> {
+        let file_len = file.metadata()?.len();
+        if offset + len as u64 > file_len {
+            bail!(
+                "Can not map {} bytes at offset {} from a file of {} bytes",
+                len,
+                offset,
+                file_len
+            )
+        }
+        Self::map_checked(file, offset, len)
+    }
+
+    #[cfg(unix)]
+    fn map_checked(file: &File, offset: u64, len: usize) -> TractResult<Mmap> {
+        use std::os::unix::io::AsRawFd;
+        if len == 0 {
+            return Ok(Mmap {
+                map: std::ptr::null_mut(),
+                map_len: 0,
+                data: std::ptr::NonNull::dangling().as_ptr(),
+                len: 0,
+            });
+        }
+        let page = unsafe { libc::sysconf(libc::_SC_PAGESIZE) } as u64;
+        let delta = offset % page;
+        let map_len = len + delta as usize;
+        let map = unsafe {
+            libc::mmap(
+                std::ptr::null_mut(),
+                map_len,
+                libc::PROT_READ,
+                libc::MAP_PRIVATE,
+                file.as_raw_fd(),
+                (offset - delta) as libc::off_t,
+            )
+        };
+        if map == libc::MAP_FAILED {
+            Err(std::io::Error::last_os_error()).chain_err(|| "Mapping file")?
+        }
+        let data = unsafe { (map as *const u8).add(delta as usize) };
+        Ok(Mmap { map, map_len, data, len })
+    }
+
+    #[cfg(not(unix))]
+    fn map_checked(file: &File, offset: u64, len: usize) -> TractResult<Mmap> {
+        use std::io::{Read, Seek, SeekFrom};
+        let mut file = file;
+        file.seek(SeekFrom::Start(offset))?;
+        let mut buffer = vec![0u8; len];
+        file.read_exact(&mut buffer)?;
+        Ok(Mmap { data: buffer.as_ptr(), len, _buffer: buffer })
+    }
+
+    /// Length of the region, in bytes.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    /// Access the region content.
+    pub fn as_bytes(&self) -> &[u8] {
+        unsafe { std::slice::from_raw_parts(self.data, self.len) }
+    }
+}
+
+#[cfg(unix)]
+impl Drop for Mmap {
+    fn drop(&mut self) {
+        if !self.map.is_null() {
+            unsafe {
+                libc::munmap(self.map, self.map_len);
+            }
+        }
+    }
+}
+
+impl fmt::Debug for Mmap {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "Mmap({} bytes)", self.len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mapped_tensor_copy_on_write() {
+        let path = std::env::temp_dir().join(format!("tract-mmap-{}", std::process::id()));
+        let content = (0u8..12).chain([1f32, 2., 3.].iter().flat_map(|f| f.to_le_bytes().to_vec()));
+        std::fs::write(&path, content.collect::<Vec<u8>>()).unwrap();
+        let file = File::open(&path).unwrap();
+        let mmap = Arc::new(Mmap::map(&file, 8, 16).unwrap());
+        std::fs::remove_file(&path).unwrap();
+        let tensor = unsafe { Tensor::from_mmap(f32::datum_type(), &[3], mmap, 4).unwrap() };
+        assert!(tensor.is_mmapped());
+        assert_eq!(tensor.as_slice::<f32>().unwrap(), &[1f32, 2., 3.]);
+        let mut copy = tensor.clone();
+        assert!(copy.is_mmapped());
+        copy.as_slice_mut::<f32>().unwrap()[0] = 4.;
+        assert!(!copy.is_mmapped());
+        assert_eq!(copy, tensor1(&[4f32, 2., 3.]));
+        assert_eq!(tensor, tensor1(&[1f32, 2., 3.]));
+    }
+}
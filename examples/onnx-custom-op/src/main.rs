@@ -0,0 +1,143 @@
+use tract_onnx::model::ParsingContext;
+use tract_onnx::pb;
+use tract_onnx::prelude::*;
+use tract_onnx::tract_hir::internal::*;
+
+/// Multiply the input by a constant factor.
+#[derive(Debug, Clone)]
+struct Scale {
+    factor: f32,
+}
+
+impl Hash for Scale {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        hash_f32(&self.factor, state)
+    }
+}
+
+impl Op for Scale {
+    fn name(&self) -> Cow<str> {
+        "com.ourcompany.Scale".into()
+    }
+
+    op_as_typed_op!();
+    not_a_pulsed_op!();
+}
+
+impl StatelessOp for Scale {
+    fn eval(&self, mut inputs: TVec<Arc<Tensor>>) -> TractResult<TVec<Arc<Tensor>>> {
+        let input = args_1!(inputs);
+        let mut output = input.into_tensor().into_array::<f32>()?;
+        output *= self.factor;
+        Ok(tvec!(output.into_arc_tensor()))
+    }
+}
+
+impl InferenceRulesOp for Scale {
+    fn rules<'r, 'p: 'r, 's: 'r>(
+        &'s self,
+        s: &mut Solver<'r>,
+        inputs: &'p [TensorProxy],
+        outputs: &'p [TensorProxy],
+    ) -> InferenceResult {
+        check_input_arity(&inputs, 1)?;
+        check_output_arity(&outputs, 1)?;
+        s.equals(&inputs[0].datum_type, f32::datum_type())?;
+        s.equals(&outputs[0].datum_type, &inputs[0].datum_type)?;
+        s.equals(&outputs[0].shape, &inputs[0].shape)?;
+        Ok(())
+    }
+
+    as_op!();
+    to_typed!();
+}
+
+impl TypedOp for Scale {
+    fn output_facts(&self, inputs: &[&TypedFact]) -> TractResult<TVec<TypedFact>> {
+        Ok(tvec!(inputs[0].clone()))
+    }
+
+    as_op!();
+}
+
+fn scale(
+    _ctx: &ParsingContext,
+    node: &pb::NodeProto,
+) -> TractResult<(Box<dyn InferenceOp>, Vec<String>)> {
+    let factor = node.get_attr("factor")?;
+    Ok((Box::new(Scale { factor }), vec![]))
+}
+
+fn value_info(name: &str, shape: &[i64]) -> pb::ValueInfoProto {
+    use pb::tensor_shape_proto::{dimension::Value, Dimension};
+    let dim = shape
+        .iter()
+        .map(|&d| Dimension { value: Some(Value::DimValue(d)), ..Dimension::default() })
+        .collect();
+    let tensor = pb::type_proto::Tensor {
+        elem_type: pb::tensor_proto::DataType::Float as i32,
+        shape: Some(pb::TensorShapeProto { dim }),
+    };
+    pb::ValueInfoProto {
+        name: name.to_string(),
+        r#type: Some(pb::TypeProto {
+            value: Some(pb::type_proto::Value::TensorType(tensor)),
+            ..pb::TypeProto::default()
+        }),
+        ..pb::ValueInfoProto::default()
+    }
+}
+
+/// y = Relu(Scale(x, factor=2)), with x of shape 2x3.
+fn model() -> pb::ModelProto {
+    let scale = pb::NodeProto {
+        op_type: "Scale".to_string(),
+        domain: "com.ourcompany".to_string(),
+        input: vec!["x".to_string()],
+        output: vec!["scaled".to_string()],
+        attribute: vec![pb::AttributeProto {
+            name: "factor".to_string(),
+            r#type: pb::attribute_proto::AttributeType::Float as i32,
+            f: 2.0,
+            ..pb::AttributeProto::default()
+        }],
+        ..pb::NodeProto::default()
+    };
+    let relu = pb::NodeProto {
+        op_type: "Relu".to_string(),
+        input: vec!["scaled".to_string()],
+        output: vec!["y".to_string()],
+        ..pb::NodeProto::default()
+    };
+    let opset = |domain: &str| pb::OperatorSetIdProto { domain: domain.to_string(), version: 1 };
+    pb::ModelProto {
+        opset_import: vec![opset(""), opset("com.ourcompany")],
+        graph: Some(pb::GraphProto {
+            node: vec![scale, relu],
+            input: vec![value_info("x", &[2, 3])],
+            output: vec![value_info("y", &[2, 3])],
+            ..pb::GraphProto::default()
+        }),
+        ..pb::ModelProto::default()
+    }
+}
+
+fn main() -> TractResult<()> {
+    let proto = model();
+    let mut onnx = tract_onnx::onnx();
+
+    // without the custom operator, parsing fails listing unknown operators
+    if let Err(e) = onnx.model_for_proto_model(&proto) {
+        println!("Without the custom operator: {}", e);
+    }
+
+    onnx.register_op("com.ourcompany", "Scale", scale);
+    let model = onnx.model_for_proto_model(&proto)?.into_optimized()?;
+    let plan = SimplePlan::new(&model)?;
+
+    let x = tensor2(&[[-1f32, 0., 1.], [2., 3., 4.]]);
+    let y = plan.run(tvec!(x))?;
+    println!("{:?}", y[0].to_array_view::<f32>()?);
+    assert_eq!(*y[0], tensor2(&[[0f32, 0., 2.], [4., 6., 8.]]));
+    Ok(())
+}
@@ -0,0 +1,110 @@
+use proptest::proptest;
+use proptest::test_runner::TestCaseResult;
+use tract_hir::internal::*;
+use tract_hir::ops::{array, cnn};
+
+use super::*;
+
+#[derive(Debug, Clone)]
+struct DeconvProblem {
+    input: Vec<f32>,
+    kernel: Vec<f32>,
+    bias: f32,
+    pulse: usize,
+    delay: usize,
+    stride: usize,
+    dilation: usize,
+    pads: (usize, usize),
+}
+
+impl Arbitrary for DeconvProblem {
+    type Parameters = ();
+    type Strategy = BoxedStrategy<Self>;
+
+    fn arbitrary_with(_: Self::Parameters) -> BoxedStrategy<Self> {
+        (1usize..4, 1usize..4, 0usize..3, 1usize..3, 1usize..4, 0usize..3, 0usize..3)
+            .prop_flat_map(|(kernel_len, pulse, delay, stride, dilation, before, after)| {
+                let kernel_field = (kernel_len - 1) * dilation + 1;
+                let (before, after) = (before.min(kernel_field - 1), after.min(kernel_field - 1));
+                let after = after.min(kernel_field - 1 - before);
+                (
+                    vec(delay + 1..delay + 10),
+                    vec(Just(kernel_len)),
+                    -3i32..3,
+                    Just((pulse, delay, stride, dilation, (before, after))),
+                )
+            })
+            .prop_map(|(input, kernel, bias, (pulse, delay, stride, dilation, pads))| {
+                DeconvProblem {
+                    input,
+                    kernel,
+                    bias: bias as f32,
+                    pulse,
+                    delay,
+                    stride,
+                    dilation,
+                    pads,
+                }
+            })
+            .boxed()
+    }
+}
+
+impl DeconvProblem {
+    pub fn run(&self) -> TestCaseResult {
+        let mut model = InferenceModel::default();
+        let a = model
+            .add_source("a", InferenceFact::dt_shape(f32::datum_type(), shapefactoid!(1, 1, S)))
+            .unwrap();
+        let crop = model.wire_node("crop", array::Crop::new(2, self.delay, 0), &[a]).unwrap();
+        let kernel = arr1(&self.kernel).into_shape((1, 1, self.kernel.len())).unwrap();
+        let kernel = model.add_const("kernel", kernel.into_tensor()).unwrap();
+        let bias = model.add_const("bias", tensor1(&[self.bias])).unwrap();
+        let op = cnn::Deconv::default()
+            .padding(cnn::PaddingSpec::Explicit(tvec!(self.pads.0), tvec!(self.pads.1)))
+            .strides(tvec!(self.stride))
+            .dilations(tvec!(self.dilation))
+            .bias_input(2);
+        let deconv = model.wire_node("deconv", op, &[crop[0], kernel.into(), bias.into()]).unwrap();
+        model.set_output_outlets(&deconv).unwrap();
+        let input = arr1(&self.input).into_shape((1, 1, self.input.len())).unwrap().into_dyn();
+        proptest_regular_against_pulse(model, self.pulse as _, input, 2)
+    }
+}
+
+proptest! {
+    #[test]
+    fn proptest(pb in DeconvProblem::arbitrary()) { pb.run().unwrap() }
+}
+
+#[test]
+fn test_overlap_across_pulses() {
+    DeconvProblem {
+        input: vec![1.0, 2.0, 3.0],
+        kernel: vec![1.0, 10.0, 100.0],
+        bias: 0.0,
+        pulse: 1,
+        delay: 0,
+        stride: 1,
+        dilation: 1,
+        pads: (0, 0),
+    }
+    .run()
+    .unwrap()
+}
+
+#[test]
+fn test_delay_and_stride() {
+    DeconvProblem {
+        input: vec![1.0, 2.0, 3.0, 4.0],
+        kernel: vec![1.0, 10.0, 100.0],
+        bias: 1.0,
+        pulse: 2,
+        delay: 1,
+        stride: 2,
+        dilation: 1,
+        pads: (1, 0),
+    }
+    .run()
+    .unwrap()
+}
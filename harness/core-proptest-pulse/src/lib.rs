@@ -8,6 +8,7 @@ use tract_hir::internal::*;
 use tract_ndarray::*;
 
 mod conv_plus_conv;
+mod deconv;
 mod delay_plus_pool;
 mod pad_plus_conv;
 
@@ -96,6 +96,10 @@ impl InferenceModelExt for InferenceModel {
     fn into_typed(mut self) -> TractResult<TypedModel> {
         self.analyse(false)?;
         let m = self.incorporate()?;
+        let unimplemented = crate::ops::unimpl::unimplemented_ops(&m)?;
+        if unimplemented.len() > 0 {
+            bail!("Unimplemented operators:\n{}", unimplemented.join("\n"))
+        }
 
         #[derive(Debug)]
         struct ToTypedTranslator;
@@ -8,6 +8,7 @@ extern crate log;
 #[macro_use]
 pub mod macros;
 pub mod framework;
+pub mod pb_wire;
 pub mod model;
 
 pub mod infer;
@@ -22,8 +23,10 @@ pub mod ops {
     pub mod binary;
     pub use tract_core::ops::cast::cast;
     pub mod cnn;
+    pub mod control_flow;
     pub mod downsample;
     pub mod dummy;
+    pub mod einsum;
     pub mod element_wise;
     pub mod identity;
     pub mod konst;
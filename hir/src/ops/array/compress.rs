@@ -0,0 +1,37 @@
+use crate::infer::*;
+use crate::internal::*;
+
+pub use tract_core::ops::array::Compress;
+
+impl InferenceRulesOp for Compress {
+    fn rules<'r, 'p: 'r, 's: 'r>(
+        &'s self,
+        s: &mut Solver<'r>,
+        inputs: &'p [TensorProxy],
+        outputs: &'p [TensorProxy],
+    ) -> InferenceResult {
+        check_input_arity(&inputs, 2)?;
+        check_output_arity(&outputs, 1)?;
+        s.equals(&inputs[0].datum_type, &outputs[0].datum_type)?;
+        s.equals(&inputs[1].datum_type, bool::datum_type())?;
+        s.equals(&inputs[1].rank, 1)?;
+        if let Some(op_axis) = self.axis {
+            s.equals(&inputs[0].rank, &outputs[0].rank)?;
+            s.given(&inputs[0].rank, move |s, rank| {
+                let rank = rank as usize;
+                for axis in 0..rank {
+                    if axis != op_axis {
+                        s.equals(&inputs[0].shape[axis], &outputs[0].shape[axis])?;
+                    }
+                }
+                Ok(())
+            })?;
+        } else {
+            s.equals(&outputs[0].rank, 1)?;
+        }
+        Ok(())
+    }
+
+    as_op!();
+    to_typed!();
+}
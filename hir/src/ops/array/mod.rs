@@ -1,14 +1,17 @@
 mod add_dims;
 mod broadcast;
+mod compress;
 mod concat;
 mod constant_like;
 mod constant_of_shape;
 mod crop;
 mod flatten;
 mod gather;
+mod non_zero;
 mod pad;
 mod permute_axes;
 mod reshape;
+mod resize;
 mod rm_dims;
 mod shape;
 mod size;
@@ -17,18 +20,23 @@ mod split;
 mod squeeze;
 mod strided_slice;
 mod tile;
+mod topk;
+mod unique;
 
 pub use add_dims::AddDims;
 pub use broadcast::MultiBroadcastTo;
+pub use compress::Compress;
 pub use concat::{Concat, ConcatSlice, TypedConcat};
 pub use constant_like::{ConstantLike, EyeLike};
 pub use constant_of_shape::ConstantOfShape;
 pub use crop::Crop;
 pub use flatten::Flatten;
 pub use gather::Gather;
+pub use non_zero::NonZero;
 pub use pad::{Pad, PadMode};
 pub use permute_axes::PermuteAxes;
 pub use reshape::Reshape;
+pub use resize::{CoordTransformer, Interpolator, Nearest, Resize};
 pub use rm_dims::RmDims;
 pub use shape::Shape;
 pub use size::Size;
@@ -37,3 +45,5 @@ pub use split::Split;
 pub use squeeze::Squeeze;
 pub use strided_slice::StridedSlice;
 pub use tile::Tile;
+pub use topk::TopK;
+pub use unique::Unique;
@@ -0,0 +1,23 @@
+use crate::infer::*;
+use crate::internal::*;
+
+pub use tract_core::ops::array::NonZero;
+
+impl InferenceRulesOp for NonZero {
+    fn rules<'r, 'p: 'r, 's: 'r>(
+        &'s self,
+        s: &mut Solver<'r>,
+        inputs: &'p [TensorProxy],
+        outputs: &'p [TensorProxy],
+    ) -> InferenceResult {
+        check_input_arity(&inputs, 1)?;
+        check_output_arity(&outputs, 1)?;
+        s.equals(&outputs[0].datum_type, DatumType::I64)?;
+        s.equals(&outputs[0].rank, 2)?;
+        s.equals(&outputs[0].shape[0], inputs[0].rank.bex().to_dim())?;
+        Ok(())
+    }
+
+    as_op!();
+    to_typed!();
+}
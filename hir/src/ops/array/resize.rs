@@ -0,0 +1,46 @@
+use crate::infer::*;
+use crate::internal::*;
+
+pub use tract_core::ops::array::{CoordTransformer, Interpolator, Nearest, Resize};
+
+impl InferenceRulesOp for Resize {
+    fn rules<'r, 'p: 'r, 's: 'r>(
+        &'s self,
+        s: &mut Solver<'r>,
+        inputs: &'p [TensorProxy],
+        outputs: &'p [TensorProxy],
+    ) -> InferenceResult {
+        let optionals =
+            [self.optional_roi_input, self.optional_scales_input, self.optional_sizes_input];
+        check_input_arity(&inputs, 1 + optionals.iter().filter(|o| o.is_some()).count())?;
+        check_output_arity(&outputs, 1)?;
+        s.equals(&inputs[0].datum_type, &outputs[0].datum_type)?;
+        s.equals(&inputs[0].rank, &outputs[0].rank)?;
+        if let Some(scales) = self.optional_scales_input {
+            s.given_2(&inputs[0].shape, &inputs[scales].value, move |s, shape, scales| {
+                if scales.len() > 0 {
+                    let scales = scales.cast_to::<f32>()?;
+                    let output_shape =
+                        self.compute_output_shape(&*shape, Some(scales.as_slice::<f32>()?), None)?;
+                    s.equals(&outputs[0].shape, output_shape)?;
+                }
+                Ok(())
+            })?;
+        }
+        if let Some(sizes) = self.optional_sizes_input {
+            s.given(&inputs[sizes].value, move |s, sizes| {
+                if sizes.len() > 0 {
+                    let sizes = sizes.cast_to::<i64>()?;
+                    for (ix, &d) in sizes.as_slice::<i64>()?.iter().enumerate() {
+                        s.equals(&outputs[0].shape[ix], d.to_dim())?;
+                    }
+                }
+                Ok(())
+            })?;
+        }
+        Ok(())
+    }
+
+    as_op!();
+    to_typed!();
+}
@@ -0,0 +1,43 @@
+use crate::infer::*;
+use crate::internal::*;
+
+pub use tract_core::ops::array::TopK;
+
+impl InferenceRulesOp for TopK {
+    fn rules<'r, 'p: 'r, 's: 'r>(
+        &'s self,
+        s: &mut Solver<'r>,
+        inputs: &'p [TensorProxy],
+        outputs: &'p [TensorProxy],
+    ) -> InferenceResult {
+        check_input_arity(&inputs, 2)?;
+        check_output_arity(&outputs, 2)?;
+        s.equals(&outputs[0].datum_type, &inputs[0].datum_type)?;
+        s.equals(&outputs[1].datum_type, DatumType::I64)?;
+        s.equals(&outputs[0].rank, &inputs[0].rank)?;
+        s.equals(&outputs[1].rank, &inputs[0].rank)?;
+        s.given(&inputs[0].rank, move |s, rank| {
+            let axis = self.resolved_axis(rank as usize)?;
+            for ix in 0..rank as usize {
+                s.equals(&outputs[0].shape[ix], &outputs[1].shape[ix])?;
+                if ix != axis {
+                    s.equals(&outputs[0].shape[ix], &inputs[0].shape[ix])?;
+                }
+            }
+            s.given(&inputs[1].value, move |s, k| {
+                let k = k.cast_to::<i64>()?;
+                if let &[k] = k.as_slice::<i64>()? {
+                    s.equals(&outputs[0].shape[axis], k.to_dim())?;
+                }
+                Ok(())
+            })
+        })
+    }
+
+    fn nboutputs(&self) -> TractResult<usize> {
+        Ok(2)
+    }
+
+    as_op!();
+    to_typed!();
+}
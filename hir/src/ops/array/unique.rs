@@ -0,0 +1,120 @@
+use crate::infer::*;
+use crate::internal::*;
+
+use tract_core::ops::array::Unique as Typed;
+
+/// Unique values, with only some of the index outputs.
+///
+/// `optional_outputs` gives the node output slot, if any, of each of the
+/// indices, inverse indices and counts outputs of the typed operator.
+#[derive(Debug, Clone, new, Hash)]
+pub struct Unique {
+    pub axis: Option<i64>,
+    pub sorted: bool,
+    pub optional_outputs: TVec<Option<usize>>,
+}
+
+impl Unique {
+    fn resolve_axis(&self, rank: i64) -> TractResult<Option<usize>> {
+        match self.axis {
+            None => Ok(None),
+            Some(axis) if 0 <= axis && axis <= rank - 1 => Ok(Some(axis as usize)),
+            Some(axis) if -rank <= axis && axis < 0 => Ok(Some((axis + rank) as usize)),
+            Some(axis) => {
+                bail!("Illegal combination of values for rank and axis: {} and {}", rank, axis)
+            }
+        }
+    }
+
+    fn output_count(&self) -> usize {
+        1 + self.optional_outputs.iter().flatten().count()
+    }
+
+    fn slots(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        self.optional_outputs
+            .iter()
+            .enumerate()
+            .filter_map(|(ix, slot)| slot.map(|slot| (ix + 1, slot)))
+    }
+}
+
+impl Op for Unique {
+    fn name(&self) -> Cow<str> {
+        "Unique".into()
+    }
+
+    not_a_typed_op!();
+    not_a_pulsed_op!();
+}
+
+impl StatelessOp for Unique {
+    fn eval(&self, inputs: TVec<Arc<Tensor>>) -> TractResult<TVec<Arc<Tensor>>> {
+        let axis = self.resolve_axis(inputs[0].rank() as i64)?;
+        let typed = Typed::new(axis, self.sorted).eval(inputs)?;
+        let mut outputs = tvec!(typed[0].clone(); self.output_count());
+        for (ix, slot) in self.slots() {
+            outputs[slot] = typed[ix].clone();
+        }
+        Ok(outputs)
+    }
+}
+
+impl InferenceRulesOp for Unique {
+    fn rules<'r, 'p: 'r, 's: 'r>(
+        &'s self,
+        s: &mut Solver<'r>,
+        inputs: &'p [TensorProxy],
+        outputs: &'p [TensorProxy],
+    ) -> InferenceResult {
+        check_input_arity(&inputs, 1)?;
+        check_output_arity(&outputs, self.output_count())?;
+        s.equals(&outputs[0].datum_type, &inputs[0].datum_type)?;
+        if self.axis.is_some() {
+            s.equals(&outputs[0].rank, &inputs[0].rank)?;
+            s.given(&inputs[0].rank, move |s, rank| {
+                let op_axis = self.resolve_axis(rank as i64)?.unwrap();
+                for axis in 0..rank as usize {
+                    if axis != op_axis {
+                        s.equals(&inputs[0].shape[axis], &outputs[0].shape[axis])?;
+                    }
+                }
+                for (ix, slot) in self.slots() {
+                    if ix == 2 {
+                        s.equals(&outputs[slot].shape[0], &inputs[0].shape[op_axis])?;
+                    }
+                }
+                Ok(())
+            })?;
+        } else {
+            s.equals(&outputs[0].rank, 1)?;
+        }
+        for (_, slot) in self.slots() {
+            s.equals(&outputs[slot].datum_type, DatumType::I64)?;
+            s.equals(&outputs[slot].rank, 1)?;
+        }
+        Ok(())
+    }
+
+    fn nboutputs(&self) -> TractResult<usize> {
+        Ok(self.output_count())
+    }
+
+    fn to_typed(
+        &self,
+        _source: &InferenceModel,
+        node: &InferenceNode,
+        target: &mut TypedModel,
+        mapping: &HashMap<OutletId, OutletId>,
+    ) -> TractResult<TVec<OutletId>> {
+        let input = mapping[&node.inputs[0]];
+        let axis = self.resolve_axis(target.outlet_fact(input)?.rank() as i64)?;
+        let wires = target.wire_node(&*node.name, Typed::new(axis, self.sorted), &[input])?;
+        let mut outputs = tvec!(wires[0]; self.output_count());
+        for (ix, slot) in self.slots() {
+            outputs[slot] = wires[ix];
+        }
+        Ok(outputs)
+    }
+
+    as_op!();
+}
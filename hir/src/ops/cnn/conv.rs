@@ -128,30 +128,34 @@ impl Conv {
         if let Some(kvalue) = kernel.konst.clone() {
             let mut qp = None;
             let dt = self.override_output_datum_type.unwrap_or(input.datum_type);
-            let mut scale = 1.0;
+            // filter scale may be per output channel
+            let mut scale = tvec!(1.0f32);
             if let Some(slot) = self.x_scale_input {
                 if let Some(ref value) = inputs[slot].borrow().konst {
-                    scale *= value.to_scalar::<f32>()?;
+                    scale[0] *= value.to_scalar::<f32>()?;
                 } else {
                     bail!("Input scale must be const")
                 }
             }
             if let Some(slot) = self.k_scale_input {
                 if let Some(ref value) = inputs[slot].borrow().konst {
-                    scale *= value.to_scalar::<f32>()?;
+                    let k_scale = value.as_slice::<f32>()?;
+                    scale = k_scale.iter().map(|k| scale[0] * k).collect();
                 } else {
                     bail!("Filter scale must be const")
                 }
             }
             if let Some(slot) = self.y_scale_input {
                 if let Some(ref value) = inputs[slot].borrow().konst {
-                    scale /= value.to_scalar::<f32>()?;
+                    let y_scale = value.to_scalar::<f32>()?;
+                    scale.iter_mut().for_each(|s| *s /= y_scale);
                 } else {
                     bail!("Output scale must be const")
                 }
             }
-            if scale != 1.0 {
-                qp.get_or_insert(QParams::new(dt)).set_scale_factor(scale);
+            if scale.iter().any(|&s| s != 1.0) {
+                let scale = if scale.len() == 1 { rctensor0(scale[0]) } else { rctensor1(&scale) };
+                qp.get_or_insert(QParams::new(dt)).set_scale_factors(&scale)?;
             }
             if let Some(slot) = self.x_zero_point_input {
                 if let Some(ref value) = inputs[slot].borrow().konst {
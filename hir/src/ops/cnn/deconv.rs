@@ -0,0 +1,304 @@
+use crate::infer::*;
+use crate::internal::*;
+
+use tract_core::ops::cnn::deconv;
+use tract_core::ops::cnn::{DeconvUnary, KernelFormat, PaddingSpec, PoolSpec};
+use tract_core::ops::nn::DataFormat;
+
+/// Transposed convolution, with the kernel (and optional bias) as inputs.
+///
+/// The output spatial shape can be given explicitly, as an attribute or as a
+/// full shape input: padding is then computed to match it.
+#[derive(Debug, Clone, Default, Hash)]
+pub struct Deconv {
+    pub data_format: DataFormat,
+    pub kernel_format: KernelFormat,
+    pub padding: PaddingSpec,
+    pub strides: Option<TVec<usize>>,
+    pub dilations: Option<TVec<usize>>,
+    pub adjustments: Option<TVec<usize>>,
+    pub output_shape: Option<TVec<usize>>,
+    pub group: Option<usize>,
+
+    pub data_input: Option<usize>,
+    pub k_input: Option<usize>,
+    pub bias_input: Option<usize>,
+    pub output_shape_input: Option<usize>,
+}
+
+impl Deconv {
+    pub fn nhwc(self) -> Deconv {
+        Deconv { data_format: DataFormat::NHWC, ..self }
+    }
+
+    pub fn hwio(self) -> Deconv {
+        Deconv { kernel_format: KernelFormat::HWIO, ..self }
+    }
+
+    pub fn padding(self, padding: PaddingSpec) -> Deconv {
+        Deconv { padding, ..self }
+    }
+
+    pub fn strides(self, strides: TVec<usize>) -> Deconv {
+        Deconv { strides: Some(strides), ..self }
+    }
+
+    pub fn dilations(self, dilations: TVec<usize>) -> Deconv {
+        Deconv { dilations: Some(dilations), ..self }
+    }
+
+    pub fn adjustments(self, adjustments: TVec<usize>) -> Deconv {
+        Deconv { adjustments: Some(adjustments), ..self }
+    }
+
+    pub fn output_shape(self, output_shape: TVec<usize>) -> Deconv {
+        Deconv { output_shape: Some(output_shape), ..self }
+    }
+
+    pub fn group(self, group: usize) -> Deconv {
+        Deconv { group: Some(group), ..self }
+    }
+
+    pub fn data_input(self, input: usize) -> Deconv {
+        Deconv { data_input: Some(input), ..self }
+    }
+
+    pub fn k_input(self, input: usize) -> Deconv {
+        Deconv { k_input: Some(input), ..self }
+    }
+
+    pub fn bias_input(self, input: usize) -> Deconv {
+        Deconv { bias_input: Some(input), ..self }
+    }
+
+    pub fn output_shape_input(self, input: usize) -> Deconv {
+        Deconv { output_shape_input: Some(input), ..self }
+    }
+
+    fn data_ix(&self) -> usize {
+        self.data_input.unwrap_or(0)
+    }
+
+    fn k_ix(&self) -> usize {
+        self.k_input.unwrap_or(1)
+    }
+
+    fn output_channels(&self, kshape: &[usize]) -> usize {
+        let i = match self.kernel_format {
+            KernelFormat::OIHW => kshape[1],
+            KernelFormat::HWIO => kshape[kshape.len() - 2],
+        };
+        i * self.group.unwrap_or(1)
+    }
+
+    fn pool_spec(&self, kshape: &[usize]) -> PoolSpec {
+        let spatial_rank = kshape.len() - 2;
+        PoolSpec {
+            data_format: self.data_format,
+            kernel_shape: kshape[self.kernel_format.h_axis()..][..spatial_rank].into(),
+            padding: self.padding.clone(),
+            dilations: self.dilations.clone(),
+            strides: self.strides.clone(),
+            output_channel_override: None,
+        }
+    }
+
+    /// Explicit padding and adjustments matching the requested output
+    /// spatial shape.
+    fn padding_for_output_shape(
+        &self,
+        pool_spec: &PoolSpec,
+        input_spatial_shape: &[usize],
+        output_spatial_shape: &[usize],
+    ) -> (PaddingSpec, TVec<usize>) {
+        let (mut before, mut after, mut adjustments) = (tvec!(), tvec!(), tvec!());
+        for (geo_axis, (&x, &y)) in
+            input_spatial_shape.iter().zip(output_spatial_shape.iter()).enumerate()
+        {
+            let stride = pool_spec.stride(geo_axis);
+            let kernel_field =
+                (pool_spec.kernel_shape[geo_axis] - 1) * pool_spec.dilation(geo_axis) + 1;
+            let adjustment =
+                self.adjustments.as_ref().and_then(|a| a.get(geo_axis)).cloned().unwrap_or(0);
+            let total = (stride * (x - 1) + adjustment + kernel_field) as isize - y as isize;
+            if total >= 0 {
+                let total = total as usize;
+                let (b, a) = if self.padding == PaddingSpec::SameUpper {
+                    (total / 2, total - total / 2)
+                } else {
+                    (total - total / 2, total / 2)
+                };
+                before.push(b);
+                after.push(a);
+                adjustments.push(adjustment);
+            } else {
+                before.push(0);
+                after.push(0);
+                adjustments.push(adjustment + (-total) as usize);
+            }
+        }
+        (PaddingSpec::Explicit(before, after), adjustments)
+    }
+
+    pub fn to_unary(&self, inputs: &[&TypedFact]) -> TractResult<DeconvUnary> {
+        let kernel =
+            inputs[self.k_ix()].konst.clone().ok_or("Deconvolution kernel must be a constant")?;
+        let bias = if let Some(slot) = self.bias_input {
+            Some(inputs[slot].konst.clone().ok_or("Deconvolution bias must be a constant")?)
+        } else {
+            None
+        };
+        let mut pool_spec = self.pool_spec(kernel.shape());
+        let mut adjustments = self.adjustments.clone().unwrap_or(tvec!());
+        let output_spatial_shape: Option<TVec<usize>> = if let Some(slot) = self.output_shape_input
+        {
+            let shape = inputs[slot]
+                .konst
+                .clone()
+                .ok_or("Deconvolution output shape must be a constant")?;
+            let shape = shape.cast_to::<i64>()?;
+            let shape: TVec<usize> = shape.as_slice::<i64>()?.iter().map(|&d| d as usize).collect();
+            Some(self.data_format.shape(shape)?.hw_dims().into())
+        } else {
+            self.output_shape.clone()
+        };
+        if let Some(output_spatial_shape) = output_spatial_shape {
+            let input_shape = inputs[self.data_ix()]
+                .shape
+                .as_finite()
+                .ok_or("Deconvolution with explicit output shape needs a known input shape")?;
+            let input_shape = self.data_format.shape(input_shape)?;
+            let (padding, adj) = self.padding_for_output_shape(
+                &pool_spec,
+                input_shape.hw_dims(),
+                &output_spatial_shape,
+            );
+            pool_spec.padding = padding;
+            adjustments = adj;
+        }
+        Ok(DeconvUnary::new(
+            pool_spec,
+            self.kernel_format,
+            kernel,
+            self.group.unwrap_or(1),
+            bias,
+            adjustments,
+        ))
+    }
+}
+
+impl Op for Deconv {
+    fn name(&self) -> Cow<str> {
+        "Deconv".into()
+    }
+
+    fn validation(&self) -> Validation {
+        Validation::Rounding
+    }
+
+    not_a_typed_op!();
+    not_a_pulsed_op!();
+}
+
+impl StatelessOp for Deconv {
+    fn eval(&self, inputs: TVec<Arc<Tensor>>) -> TractResult<TVec<Arc<Tensor>>> {
+        let inputs_info: TVec<TypedFact> = inputs.iter().map(|t| TypedFact::from(&**t)).collect();
+        let unary = self.to_unary(&inputs_info.iter().collect::<TVec<_>>())?;
+        unary.eval(tvec!(inputs[self.data_ix()].clone()))
+    }
+}
+
+impl InferenceRulesOp for Deconv {
+    fn rules<'r, 'p: 'r, 's: 'r>(
+        &'s self,
+        s: &mut Solver<'r>,
+        inputs: &'p [TensorProxy],
+        outputs: &'p [TensorProxy],
+    ) -> InferenceResult {
+        if inputs.len() < 2 {
+            bail!("Wrong number of inputs. Expected 2 or more, got {}", inputs.len());
+        }
+        check_output_arity(&outputs, 1)?;
+        let data = &inputs[self.data_ix()];
+        let kernel = &inputs[self.k_ix()];
+        s.equals(&data.datum_type, &kernel.datum_type)?;
+        s.equals(&outputs[0].datum_type, &data.datum_type)?;
+        s.equals(&outputs[0].rank, &data.rank)?;
+        let has_n = self.data_format == DataFormat::NHWC || self.data_format == DataFormat::NCHW;
+        s.equals(&data.rank, kernel.rank.bex() + (has_n as usize as i32 - 1))?;
+        if let Some(bias) = self.bias_input {
+            s.equals(&inputs[bias].rank, 1)?;
+            s.equals(&inputs[bias].datum_type, &data.datum_type)?;
+        }
+        if let Some(slot) = self.output_shape_input {
+            s.given(&inputs[slot].value, move |s, shape| {
+                let shape = shape.cast_to::<i64>()?;
+                for (ix, &d) in shape.as_slice::<i64>()?.iter().enumerate() {
+                    s.equals(&outputs[0].shape[ix], d.to_dim())?;
+                }
+                Ok(())
+            })?;
+        }
+        s.given_2(&data.shape, &kernel.shape, move |s, ishape, kshape| {
+            if let Ok(kshape) =
+                kshape.iter().map(|d| d.to_integer()).collect::<TractResult<Vec<_>>>()
+            {
+                let kshape: TVec<usize> = kshape.into_iter().map(|d| d as usize).collect();
+                let pool_spec = self.pool_spec(&kshape);
+                let mut oshape = deconv::output_shape(
+                    &pool_spec,
+                    self.adjustments.as_ref().map(|a| &**a).unwrap_or(&[]),
+                    &*ishape,
+                    self.output_channels(&kshape),
+                )?;
+                if let Some(output_spatial_shape) = &self.output_shape {
+                    let h_axis = self.data_format.shape(&*oshape)?.h_axis();
+                    for (ix, &d) in output_spatial_shape.iter().enumerate() {
+                        oshape[h_axis + ix] = d.to_dim();
+                    }
+                }
+                if self.output_shape_input.is_none() {
+                    s.equals(&outputs[0].shape, oshape)?;
+                }
+            }
+            Ok(())
+        })
+    }
+
+    as_op!();
+
+    fn to_typed(
+        &self,
+        _source: &InferenceModel,
+        node: &InferenceNode,
+        target: &mut TypedModel,
+        mapping: &HashMap<OutletId, OutletId>,
+    ) -> TractResult<TVec<OutletId>> {
+        let inputs: TVec<OutletId> = node.inputs.iter().map(|t| mapping[t]).collect();
+        let unary = {
+            let facts: TVec<&TypedFact> =
+                inputs.iter().map(|t| target.outlet_fact(*t)).collect::<TractResult<_>>()?;
+            self.to_unary(&*facts).chain_err(|| {
+                format!("Can not make {} into a typed op. (inputs facts: {:?})", node, facts)
+            })?
+        };
+        target.wire_node(&*node.name, unary, &[inputs[self.data_ix()]])
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn explicit_output_shape_sets_padding() -> TractResult<()> {
+        let op = Deconv::default().strides(tvec!(2)).output_shape(tvec!(6));
+        let input = rctensor3(&[[[1f32, 2., 3.]]]);
+        let kernel = rctensor3(&[[[1f32, 10., 100.]]]);
+        let output = op.eval(tvec!(input, kernel))?;
+        // full valid output is [1, 10, 102, 20, 203, 30, 300]: one frame of
+        // padding, taken before
+        assert_eq!(output[0], rctensor3(&[[[10f32, 102., 20., 203., 30., 300.]]]));
+        Ok(())
+    }
+}
@@ -0,0 +1,341 @@
+use crate::infer::*;
+use crate::internal::*;
+
+pub use tract_core::ops::control_flow::{wire_body, TypedIf, TypedLoop};
+
+fn scalar_fact(dt: DatumType) -> InferenceFact {
+    InferenceFact::dt_shape(dt, shapefactoid!())
+}
+
+#[derive(Debug, Clone, new, Default, Hash)]
+pub struct InferenceIf {
+    pub then_body: InferenceModel,
+    pub then_input_mapping: Vec<usize>,
+    pub else_body: InferenceModel,
+    pub else_input_mapping: Vec<usize>,
+}
+
+impl Op for InferenceIf {
+    fn name(&self) -> Cow<str> {
+        "If::Inference".into()
+    }
+
+    fn nested_models(&self) -> Vec<(Cow<str>, &dyn Model, Vec<String>, Vec<String>)> {
+        vec![
+            (
+                "then".into(),
+                &self.then_body,
+                self.then_input_mapping.iter().map(|s| format!("Input #{}", s)).collect(),
+                vec![],
+            ),
+            (
+                "else".into(),
+                &self.else_body,
+                self.else_input_mapping.iter().map(|s| format!("Input #{}", s)).collect(),
+                vec![],
+            ),
+        ]
+    }
+
+    not_a_typed_op!();
+    not_a_pulsed_op!();
+}
+
+impl StatefullOp for InferenceIf {
+    fn state(
+        &self,
+        session: &mut SessionState,
+        node_id: usize,
+    ) -> TractResult<Option<Box<dyn OpState>>> {
+        self.to_typed_if()?.state(session, node_id)
+    }
+}
+
+impl InferenceIf {
+    fn to_typed_if(&self) -> TractResult<TypedIf> {
+        TypedIf::new(
+            self.then_body.clone().into_typed()?,
+            self.then_input_mapping.clone(),
+            self.else_body.clone().into_typed()?,
+            self.else_input_mapping.clone(),
+        )
+    }
+
+    fn unify_facts(
+        &mut self,
+        inputs: &mut [InferenceFact],
+        outputs: &mut [InferenceFact],
+    ) -> TractResult<bool> {
+        let mut changed = inputs[0].unify_with(&scalar_fact(bool::datum_type()))?;
+        for (ix, slot) in self.then_input_mapping.iter().enumerate() {
+            changed |= inputs[*slot].unify_with_mut(self.then_body.input_fact_mut(ix)?)?;
+        }
+        for (ix, slot) in self.else_input_mapping.iter().enumerate() {
+            changed |= inputs[*slot].unify_with_mut(self.else_body.input_fact_mut(ix)?)?;
+        }
+        let cond = inputs[0].value.concretize().map(|c| c.to_scalar::<bool>().map(|c| *c));
+        let cond = cond.transpose()?;
+        for (ix, output) in outputs.iter_mut().enumerate() {
+            match cond {
+                Some(true) => {
+                    changed |= output.unify_with_mut(self.then_body.output_fact_mut(ix)?)?
+                }
+                Some(false) => {
+                    changed |= output.unify_with_mut(self.else_body.output_fact_mut(ix)?)?
+                }
+                None => {
+                    let then_fact = self.then_body.output_fact_mut(ix)?;
+                    let else_fact = self.else_body.output_fact_mut(ix)?;
+                    changed |= Factoid::unify_all(&mut [
+                        &mut output.datum_type,
+                        &mut then_fact.datum_type,
+                        &mut else_fact.datum_type,
+                    ])?;
+                    // branches may legitimately disagree on dimensions, but
+                    // not on ranks (see TypedIf)
+                    let ranks = (then_fact.shape.rank(), else_fact.shape.rank());
+                    if let (Some(t), Some(e)) = (ranks.0.concretize(), ranks.1.concretize()) {
+                        if t != e {
+                            bail!("If branches output #{} have ranks {} and {}", ix, t, e)
+                        }
+                    }
+                    if let Ok(shape) = then_fact.shape.unify(&else_fact.shape) {
+                        changed |= output.shape.unify_with(&shape)?;
+                    }
+                }
+            }
+        }
+        Ok(changed)
+    }
+}
+
+impl InferenceOp for InferenceIf {
+    fn infer_facts(
+        &mut self,
+        inputs: TVec<&InferenceFact>,
+        outputs: TVec<&InferenceFact>,
+        _observed: TVec<&InferenceFact>,
+    ) -> TractResult<(TVec<InferenceFact>, TVec<InferenceFact>, TVec<InferenceFact>)> {
+        if self.then_body.input_outlets()?.len() != self.then_input_mapping.len()
+            || self.else_body.input_outlets()?.len() != self.else_input_mapping.len()
+        {
+            bail!("If input mappings do not match branches inputs")
+        }
+        if outputs.len() != self.then_body.output_outlets()?.len()
+            || outputs.len() != self.else_body.output_outlets()?.len()
+        {
+            bail!("If has {} outputs, branches do not match", outputs.len())
+        }
+        let mut inputs: TVec<InferenceFact> = inputs.into_iter().cloned().collect();
+        let mut outputs: TVec<InferenceFact> = outputs.into_iter().cloned().collect();
+        loop {
+            let mut changed = self.unify_facts(&mut inputs, &mut outputs)?;
+            let cond = inputs[0].value.concretize();
+            let cond = cond.as_ref().map(|c| c.to_scalar::<bool>().map(|c| *c)).transpose()?;
+            if cond != Some(false) {
+                changed |= self
+                    .then_body
+                    .analyse(false)
+                    .map_err(|e| format!("analysing then branch: {}", e))?;
+            }
+            if cond != Some(true) {
+                changed |= self
+                    .else_body
+                    .analyse(false)
+                    .map_err(|e| format!("analysing else branch: {}", e))?;
+            }
+            if !changed {
+                break;
+            }
+        }
+        Ok((inputs, outputs, tvec!()))
+    }
+
+    fn to_typed(
+        &self,
+        _source: &InferenceModel,
+        node: &InferenceNode,
+        target: &mut TypedModel,
+        mapping: &HashMap<OutletId, OutletId>,
+    ) -> TractResult<TVec<OutletId>> {
+        let inputs = node.inputs.iter().map(|m| mapping[m]).collect::<TVec<_>>();
+        if let Some(cond) = &target.outlet_fact(inputs[0])?.konst {
+            // only the selected branch has to be typeable
+            let (body, body_mapping) = if *cond.to_scalar::<bool>()? {
+                (&self.then_body, &self.then_input_mapping)
+            } else {
+                (&self.else_body, &self.else_input_mapping)
+            };
+            let body_inputs = body_mapping.iter().map(|s| inputs[*s]).collect::<TVec<_>>();
+            return wire_body(target, &node.name, &body.clone().into_typed()?, &body_inputs);
+        }
+        target.wire_node(&*node.name, self.to_typed_if()?, &*inputs)
+    }
+
+    fn nboutputs(&self) -> TractResult<usize> {
+        Ok(self.then_body.output_outlets()?.len())
+    }
+
+    as_op!();
+}
+
+#[derive(Debug, Clone, new, Default, Hash)]
+pub struct InferenceLoop {
+    pub body: InferenceModel,
+    pub trip_count_slot: Option<usize>,
+    pub cond_slot: Option<usize>,
+    pub state_slots: Vec<usize>,
+    pub closure_slots: Vec<usize>,
+}
+
+impl Op for InferenceLoop {
+    fn name(&self) -> Cow<str> {
+        "Loop::Inference".into()
+    }
+
+    fn nested_models(&self) -> Vec<(Cow<str>, &dyn Model, Vec<String>, Vec<String>)> {
+        vec![("loop".into(), &self.body, vec![], vec![])]
+    }
+
+    not_a_typed_op!();
+    not_a_pulsed_op!();
+}
+
+impl StatefullOp for InferenceLoop {
+    fn state(
+        &self,
+        session: &mut SessionState,
+        node_id: usize,
+    ) -> TractResult<Option<Box<dyn OpState>>> {
+        self.to_typed_loop()?.state(session, node_id)
+    }
+}
+
+impl InferenceLoop {
+    fn to_typed_loop(&self) -> TractResult<TypedLoop> {
+        TypedLoop::new(
+            self.body.clone().into_typed()?,
+            self.trip_count_slot,
+            self.cond_slot,
+            self.state_slots.clone(),
+            self.closure_slots.clone(),
+        )
+    }
+
+    fn static_iterations(&self, inputs: &[InferenceFact]) -> TractResult<Option<usize>> {
+        let trip_count = match self.trip_count_slot.and_then(|s| inputs[s].value.concretize()) {
+            Some(trip_count) => trip_count.cast_to_scalar::<i64>()?,
+            None => return Ok(None),
+        };
+        if let Some(slot) = self.cond_slot {
+            match inputs[slot].value.concretize() {
+                Some(cond) if *cond.to_scalar::<bool>()? => (),
+                _ => return Ok(None),
+            }
+        }
+        let mut cond_out = self.body.output_outlets()?[0];
+        while self.body.node(cond_out.node).op_is::<super::identity::Identity>() {
+            cond_out = self.body.node(cond_out.node).inputs[0];
+        }
+        let always_true = cond_out == self.body.input_outlets()?[1]
+            || match self.body.outlet_fact(cond_out)?.value.concretize() {
+                Some(cond) => *cond.to_scalar::<bool>()?,
+                None => false,
+            };
+        Ok(if always_true { Some(trip_count.max(0) as usize) } else { None })
+    }
+
+    fn unify_facts(
+        &mut self,
+        inputs: &mut [InferenceFact],
+        outputs: &mut [InferenceFact],
+    ) -> TractResult<bool> {
+        let mut changed = false;
+        if let Some(slot) = self.trip_count_slot {
+            changed |= inputs[slot].unify_with(&scalar_fact(i64::datum_type()))?;
+        }
+        if let Some(slot) = self.cond_slot {
+            changed |= inputs[slot].unify_with(&scalar_fact(bool::datum_type()))?;
+        }
+        changed |= self.body.input_fact_mut(0)?.unify_with(&scalar_fact(i64::datum_type()))?;
+        changed |= self.body.input_fact_mut(1)?.unify_with(&scalar_fact(bool::datum_type()))?;
+        changed |= self.body.output_fact_mut(0)?.unify_with(&scalar_fact(bool::datum_type()))?;
+        let carried = self.state_slots.len();
+        for (ix, slot) in self.state_slots.iter().enumerate() {
+            let mut facts = self.body.outlets_fact_mut(&[
+                self.body.input_outlets()?[2 + ix],
+                self.body.output_outlets()?[1 + ix],
+            ])?;
+            facts.push(&mut inputs[*slot]);
+            facts.push(&mut outputs[ix]);
+            changed |= Factoid::unify_all(
+                &mut *facts.iter_mut().map(|f| &mut f.datum_type).collect::<TVec<_>>(),
+            )?;
+            changed |= Factoid::unify_all(
+                &mut *facts.iter_mut().map(|f| &mut f.shape).collect::<TVec<_>>(),
+            )?;
+        }
+        for (ix, slot) in self.closure_slots.iter().enumerate() {
+            changed |= inputs[*slot].unify_with_mut(self.body.input_fact_mut(2 + carried + ix)?)?;
+        }
+        let iters = match self.static_iterations(inputs)? {
+            Some(iters) => DimFact::from(iters.to_dim()),
+            None => DimFact::default(),
+        };
+        for (ix, output) in outputs.iter_mut().enumerate().skip(carried) {
+            let body_fact = self.body.output_fact_mut(1 + ix)?;
+            changed |= output.datum_type.unify_with_mut(&mut body_fact.datum_type)?;
+            if body_fact.shape.rank().concretize().is_some() {
+                let mut dims = tvec!(iters.clone());
+                dims.extend(body_fact.shape.dims());
+                changed |= output.shape.unify_with(&ShapeFactoid::closed(dims))?;
+            }
+        }
+        Ok(changed)
+    }
+}
+
+impl InferenceOp for InferenceLoop {
+    fn infer_facts(
+        &mut self,
+        inputs: TVec<&InferenceFact>,
+        outputs: TVec<&InferenceFact>,
+        _observed: TVec<&InferenceFact>,
+    ) -> TractResult<(TVec<InferenceFact>, TVec<InferenceFact>, TVec<InferenceFact>)> {
+        let carried = self.state_slots.len();
+        if self.body.input_outlets()?.len() != 2 + carried + self.closure_slots.len() {
+            bail!("Loop body inputs do not match the loop carried values and closures")
+        }
+        if self.body.output_outlets()?.len() != 1 + outputs.len() || outputs.len() < carried {
+            bail!("Loop has {} outputs, body does not match", outputs.len())
+        }
+        let mut inputs: TVec<InferenceFact> = inputs.into_iter().cloned().collect();
+        let mut outputs: TVec<InferenceFact> = outputs.into_iter().cloned().collect();
+        loop {
+            let mut changed = self.unify_facts(&mut inputs, &mut outputs)?;
+            changed |=
+                self.body.analyse(false).map_err(|e| format!("analysing loop body: {}", e))?;
+            if !changed {
+                break;
+            }
+        }
+        Ok((inputs, outputs, tvec!()))
+    }
+
+    fn to_typed(
+        &self,
+        _source: &InferenceModel,
+        node: &InferenceNode,
+        target: &mut TypedModel,
+        mapping: &HashMap<OutletId, OutletId>,
+    ) -> TractResult<TVec<OutletId>> {
+        let inputs = node.inputs.iter().map(|m| mapping[m]).collect::<TVec<_>>();
+        target.wire_node(&*node.name, self.to_typed_loop()?, &*inputs)
+    }
+
+    fn nboutputs(&self) -> TractResult<usize> {
+        Ok(self.body.output_outlets()?.len() - 1)
+    }
+
+    as_op!();
+}
@@ -0,0 +1,379 @@
+use crate::infer::*;
+use crate::internal::*;
+
+use tract_core::ops::array::TypedReshape;
+use tract_core::ops::matmul::MatMul;
+use tract_core::ops::nn::{Reduce, Reducer};
+
+/// Labels of the axes of an einsum operand, with the position of the
+/// ellipsis standing for the unlabelled axes, if any.
+#[derive(Debug, Clone, PartialEq, Hash)]
+struct Term {
+    labels: TVec<char>,
+    ellipsis: Option<usize>,
+}
+
+impl Term {
+    fn parse(s: &str) -> TractResult<Term> {
+        let mut labels = tvec!();
+        let mut ellipsis = None;
+        let mut chars = s.chars().peekable();
+        while let Some(c) = chars.next() {
+            if c.is_ascii_alphabetic() {
+                labels.push(c);
+            } else if c == '.' && chars.next() == Some('.') && chars.next() == Some('.') {
+                if ellipsis.is_some() {
+                    bail!("Einsum: more than one ellipsis in {:?}", s)
+                }
+                ellipsis = Some(labels.len());
+            } else {
+                bail!("Einsum: invalid term {:?}", s)
+            }
+        }
+        Ok(Term { labels, ellipsis })
+    }
+
+    /// Explicit labels, using `ellipsis` for the labels of the unlabelled
+    /// axes (aligned on the right).
+    fn expand(&self, ellipsis: &[char], rank: usize) -> TractResult<TVec<char>> {
+        match self.ellipsis {
+            None if rank == self.labels.len() => Ok(self.labels.clone()),
+            Some(pos) if rank >= self.labels.len() => {
+                let hidden = rank - self.labels.len();
+                let mut labels = self.labels.clone();
+                labels.insert_many(pos, ellipsis[ellipsis.len() - hidden..].iter().cloned());
+                Ok(labels)
+            }
+            _ => bail!("Einsum: term {:?} does not match rank {}", self, rank),
+        }
+    }
+}
+
+/// Einstein summation, following an equation like `bhid,bhjd->bhij`.
+///
+/// Each pair of operands is contracted by a `MatMul`, axes summed over by a
+/// single operand are reduced first, so the translation to the typed model
+/// only uses core primitives. Repeated labels in one operand (diagonals) are
+/// not supported.
+#[derive(Debug, Clone, Hash)]
+pub struct Einsum {
+    inputs: TVec<Term>,
+    output: Option<Term>,
+}
+
+impl Einsum {
+    pub fn new(equation: &str) -> TractResult<Einsum> {
+        let equation: String = equation.chars().filter(|c| !c.is_whitespace()).collect();
+        let (inputs, output) = match equation.find("->") {
+            Some(ix) => (&equation[..ix], Some(Term::parse(&equation[ix + 2..])?)),
+            None => (&*equation, None),
+        };
+        let inputs = inputs.split(',').map(Term::parse).collect::<TractResult<_>>()?;
+        Ok(Einsum { inputs, output })
+    }
+
+    /// Explicit labels of the inputs and output axes, given the inputs
+    /// ranks.
+    fn labels(&self, ranks: &[usize]) -> TractResult<(TVec<TVec<char>>, TVec<char>)> {
+        if ranks.len() != self.inputs.len() {
+            bail!("Einsum expects {} inputs, got {}", self.inputs.len(), ranks.len())
+        }
+        let hidden = self
+            .inputs
+            .iter()
+            .zip(ranks.iter())
+            .filter(|(term, _)| term.ellipsis.is_some())
+            .map(|(term, &rank)| rank.saturating_sub(term.labels.len()))
+            .max()
+            .unwrap_or(0);
+        let ellipsis: TVec<char> =
+            (0..hidden as u32).map(|ix| std::char::from_u32(0xE000 + ix).unwrap()).collect();
+        let inputs = self
+            .inputs
+            .iter()
+            .zip(ranks.iter())
+            .map(|(term, &rank)| term.expand(&ellipsis, rank))
+            .collect::<TractResult<TVec<_>>>()?;
+        let output = if let Some(output) = &self.output {
+            output.expand(
+                &ellipsis,
+                output.labels.len() + hidden * output.ellipsis.is_some() as usize,
+            )?
+        } else {
+            let mut once: TVec<char> = self
+                .inputs
+                .iter()
+                .flat_map(|term| term.labels.iter().cloned())
+                .filter(|l| {
+                    self.inputs.iter().flat_map(|t| t.labels.iter()).filter(|o| *o == l).count()
+                        == 1
+                })
+                .collect();
+            once.sort();
+            ellipsis.iter().cloned().chain(once.into_iter()).collect()
+        };
+        for labels in inputs.iter().chain(std::iter::once(&output)) {
+            if labels.iter().enumerate().any(|(ix, l)| labels[ix + 1..].contains(l)) {
+                bail!("Einsum: repeated labels in one term are not supported")
+            }
+        }
+        if let Some(l) = output.iter().find(|l| !inputs.iter().any(|i| i.contains(l))) {
+            bail!("Einsum: output label {} does not appear in inputs", l)
+        }
+        Ok((inputs, output))
+    }
+
+    fn output_shape<D: DimLike>(&self, shapes: &[TVec<D>]) -> TractResult<TVec<D>> {
+        let ranks: TVec<usize> = shapes.iter().map(|s| s.len()).collect();
+        let (inputs, output) = self.labels(&ranks)?;
+        output
+            .iter()
+            .map(|label| {
+                let mut dim = D::one();
+                for (labels, shape) in inputs.iter().zip(shapes.iter()) {
+                    if let Some(ix) = labels.iter().position(|l| l == label) {
+                        if dim == D::one() {
+                            dim = shape[ix].clone();
+                        } else if shape[ix] != D::one() && shape[ix] != dim {
+                            bail!("Einsum: inconsistent dimensions for {}: {:?}", label, shapes)
+                        }
+                    }
+                }
+                Ok(dim)
+            })
+            .collect()
+    }
+
+    /// Wire the summation in `target` with `MatMul`, `Reduce`, `AxisOp` and
+    /// `TypedReshape` operators.
+    pub fn wire(
+        &self,
+        name: &str,
+        target: &mut TypedModel,
+        inputs: &[OutletId],
+    ) -> TractResult<TVec<OutletId>> {
+        let ranks = inputs
+            .iter()
+            .map(|i| Ok(target.outlet_fact(*i)?.rank()))
+            .collect::<TractResult<TVec<_>>>()?;
+        let (labels, output) = self.labels(&ranks)?;
+        let needed_after = |ix: usize| -> TVec<char> {
+            labels[ix + 1..].iter().flat_map(|l| l.iter()).chain(output.iter()).cloned().collect()
+        };
+        let mut wire = inputs[0];
+        let mut wire_labels = labels[0].clone();
+        if inputs.len() == 1 {
+            wire = sum_over(target, &format!("{}-sum", name), wire, &mut wire_labels, &output)?;
+        }
+        for ix in 1..inputs.len() {
+            let keep = needed_after(ix);
+            let mut b_labels = labels[ix].clone();
+            let keep_a: TVec<char> = keep.iter().chain(b_labels.iter()).cloned().collect();
+            let keep_b: TVec<char> = keep.iter().chain(wire_labels.iter()).cloned().collect();
+            let prefix = format!("{}-{}", name, ix);
+            wire = sum_over(target, &format!("{}-sum-a", prefix), wire, &mut wire_labels, &keep_a)?;
+            let b =
+                sum_over(target, &format!("{}-sum-b", prefix), inputs[ix], &mut b_labels, &keep_b)?;
+            let (w, l) = contract(target, &prefix, (wire, &wire_labels), (b, &b_labels), &keep)?;
+            wire = w;
+            wire_labels = l;
+        }
+        if wire_labels != output {
+            let perm =
+                output.iter().map(|l| wire_labels.iter().position(|w| w == l).unwrap()).collect();
+            wire =
+                target.wire_node(format!("{}-permute", name), AxisOp::Permute(perm), &[wire])?[0];
+        }
+        Ok(tvec!(wire))
+    }
+}
+
+/// Sum over the axes of `wire` whose labels are not in `keep`, and remove
+/// them.
+fn sum_over(
+    target: &mut TypedModel,
+    name: &str,
+    mut wire: OutletId,
+    labels: &mut TVec<char>,
+    keep: &[char],
+) -> TractResult<OutletId> {
+    let axes: TVec<usize> = (0..labels.len()).filter(|&ix| !keep.contains(&labels[ix])).collect();
+    if axes.len() > 0 {
+        wire = target.wire_node(name, Reduce::new(axes.clone(), Reducer::Sum), &[wire])?[0];
+        for (ix, &axis) in axes.iter().enumerate().rev() {
+            wire = target.wire_node(format!("{}-rm-{}", name, ix), AxisOp::Rm(axis), &[wire])?[0];
+            labels.remove(axis);
+        }
+    }
+    Ok(wire)
+}
+
+/// Lay `wire` out as the `batch` axes followed by two axes merging the
+/// `first` and `second` groups of labels, in this order or swapped, whichever
+/// needs no permutation. Returns the wire and whether groups are swapped.
+fn arrange(
+    target: &mut TypedModel,
+    name: &str,
+    mut wire: OutletId,
+    labels: &[char],
+    batch: &[char],
+    first: &[char],
+    second: &[char],
+) -> TractResult<(OutletId, bool)> {
+    let natural: TVec<char> = batch.iter().chain(first).chain(second).cloned().collect();
+    let swapped: TVec<char> = batch.iter().chain(second).chain(first).cloned().collect();
+    let (order, trans) = if labels == &*swapped { (swapped, true) } else { (natural, false) };
+    if labels != &*order {
+        let perm = order.iter().map(|l| labels.iter().position(|w| w == l).unwrap()).collect();
+        wire = target.wire_node(format!("{}-permute", name), AxisOp::Permute(perm), &[wire])?[0];
+    }
+    let (left, right) = if trans { (second, first) } else { (first, second) };
+    if left.len() != 1 || right.len() != 1 {
+        let shape = target.outlet_fact(wire)?.shape.to_tvec();
+        let (batch_dims, rest) = shape.split_at(batch.len());
+        let (left_dims, right_dims) = rest.split_at(left.len());
+        let mut merged: TVec<TDim> = batch_dims.into();
+        merged.push(left_dims.iter().maybe_product()?);
+        merged.push(right_dims.iter().maybe_product()?);
+        wire =
+            target.wire_node(format!("{}-reshape", name), TypedReshape::new(merged), &[wire])?[0];
+    }
+    Ok((wire, trans))
+}
+
+/// Contract two operands with a `MatMul`, keeping the labels in `keep`.
+/// Returns the result and its labels.
+fn contract(
+    target: &mut TypedModel,
+    name: &str,
+    (a, a_labels): (OutletId, &[char]),
+    (b, b_labels): (OutletId, &[char]),
+    keep: &[char],
+) -> TractResult<(OutletId, TVec<char>)> {
+    let common: TVec<char> = a_labels.iter().filter(|l| b_labels.contains(l)).cloned().collect();
+    let batch: TVec<char> = common.iter().filter(|l| keep.contains(l)).cloned().collect();
+    let k: TVec<char> = common.iter().filter(|l| !keep.contains(l)).cloned().collect();
+    let m: TVec<char> = a_labels.iter().filter(|l| !b_labels.contains(l)).cloned().collect();
+    let n: TVec<char> = b_labels.iter().filter(|l| !a_labels.contains(l)).cloned().collect();
+    let a_shape = target.outlet_fact(a)?.shape.to_tvec();
+    let b_shape = target.outlet_fact(b)?.shape.to_tvec();
+    let (a, a_trans) = arrange(target, &format!("{}-a", name), a, a_labels, &batch, &m, &k)?;
+    let (b, b_trans) = arrange(target, &format!("{}-b", name), b, b_labels, &batch, &k, &n)?;
+    let matmul = MatMul::default().with_a_trans(a_trans).with_b_trans(b_trans);
+    let mut wire = target.wire_node(format!("{}-matmul", name), matmul, &[a, b])?[0];
+    if m.len() != 1 || n.len() != 1 {
+        let dim = |shape: &[TDim], labels: &[char], l: &char| {
+            shape[labels.iter().position(|x| x == l).unwrap()].clone()
+        };
+        let mut shape = target.outlet_fact(wire)?.shape.to_tvec();
+        shape.truncate(batch.len());
+        shape.extend(m.iter().map(|l| dim(&a_shape, a_labels, l)));
+        shape.extend(n.iter().map(|l| dim(&b_shape, b_labels, l)));
+        wire = target.wire_node(format!("{}-reshape", name), TypedReshape::new(shape), &[wire])?[0];
+    }
+    let labels = batch.iter().chain(m.iter()).chain(n.iter()).cloned().collect();
+    Ok((wire, labels))
+}
+
+impl Op for Einsum {
+    fn name(&self) -> Cow<str> {
+        "Einsum".into()
+    }
+
+    not_a_typed_op!();
+    not_a_pulsed_op!();
+}
+
+impl StatelessOp for Einsum {
+    fn eval(&self, inputs: TVec<Arc<Tensor>>) -> TractResult<TVec<Arc<Tensor>>> {
+        let mut model = TypedModel::default();
+        let wires = inputs
+            .iter()
+            .enumerate()
+            .map(|(ix, input)| {
+                let fact = TypedFact::dt_shape(input.datum_type(), input.shape())?;
+                model.add_source(format!("input-{}", ix), fact)
+            })
+            .collect::<TractResult<TVec<_>>>()?;
+        let output = self.wire("einsum", &mut model, &wires)?;
+        model.set_output_outlets(&output)?;
+        let inputs = inputs.into_iter().map(|t| t.into_tensor()).collect();
+        SimplePlan::new(model)?.run(inputs)
+    }
+}
+
+impl InferenceRulesOp for Einsum {
+    fn rules<'r, 'p: 'r, 's: 'r>(
+        &'s self,
+        s: &mut Solver<'r>,
+        inputs: &'p [TensorProxy],
+        outputs: &'p [TensorProxy],
+    ) -> InferenceResult {
+        check_input_arity(&inputs, self.inputs.len())?;
+        check_output_arity(&outputs, 1)?;
+        for input in inputs {
+            s.equals(&input.datum_type, &outputs[0].datum_type)?;
+        }
+        s.given_all(inputs.iter().map(|i| &i.rank), move |s, ranks: Vec<i32>| {
+            let ranks: TVec<usize> = ranks.iter().map(|&r| r as usize).collect();
+            let (_, output) = self.labels(&ranks)?;
+            s.equals(&outputs[0].rank, output.len() as i32)
+        })?;
+        s.given_all(inputs.iter().map(|i| &i.shape), move |s, shapes: Vec<TVec<TDim>>| {
+            s.equals(&outputs[0].shape, self.output_shape(&shapes)?)
+        })
+    }
+
+    fn to_typed(
+        &self,
+        _source: &InferenceModel,
+        node: &InferenceNode,
+        target: &mut TypedModel,
+        mapping: &HashMap<OutletId, OutletId>,
+    ) -> TractResult<TVec<OutletId>> {
+        let inputs = node.inputs.iter().map(|i| mapping[i]).collect::<TVec<_>>();
+        self.wire(&*node.name, target, &inputs)
+    }
+
+    as_op!();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tract_core::ndarray::*;
+
+    fn check(equation: &str, inputs: TVec<Tensor>, expected: Tensor) {
+        let op = Einsum::new(equation).unwrap();
+        let found = op.eval(inputs.into_iter().map(|t| t.into_arc_tensor()).collect()).unwrap();
+        found[0].close_enough(&expected, true).unwrap();
+    }
+
+    #[test]
+    fn einsum_batched_attention() {
+        let q = Array3::from_shape_fn((2, 3, 4), |(b, i, d)| (b + i * d) as f32);
+        let k = Array3::from_shape_fn((2, 5, 4), |(b, j, d)| (b * j + d) as f32 / 2.);
+        let expected = Array3::from_shape_fn((2, 3, 5), |(b, i, j)| {
+            (0..4).map(|d| q[(b, i, d)] * k[(b, j, d)]).sum::<f32>()
+        });
+        check("bid,bjd->bij", tvec!(q.into_tensor(), k.into_tensor()), expected.into_tensor());
+    }
+
+    #[test]
+    fn einsum_implicit_output_with_ellipsis() {
+        let a = Array3::from_shape_fn((2, 2, 3), |(x, y, z)| (x * 6 + y * 3 + z) as f32);
+        let b = Array2::from_shape_fn((3, 4), |(z, w)| (z + w) as f32);
+        let expected = Array3::from_shape_fn((2, 2, 4), |(x, y, w)| {
+            (0..3).map(|z| a[(x, y, z)] * b[(z, w)]).sum::<f32>()
+        });
+        check("...j,jk", tvec!(a.into_tensor(), b.into_tensor()), expected.into_tensor());
+    }
+
+    #[test]
+    fn einsum_sums_and_transposes() {
+        let a = Array3::from_shape_fn((2, 3, 4), |(x, y, z)| (x * 12 + y * 4 + z) as f32);
+        let expected =
+            Array2::from_shape_fn((4, 2), |(z, x)| (0..3).map(|y| a[(x, y, z)]).sum::<f32>());
+        check("xyz->zx", tvec!(a.into_tensor()), expected.into_tensor());
+    }
+}
@@ -1,9 +1,11 @@
 use crate::infer::*;
 use crate::internal::*;
+use tract_ndarray::{ArrayViewMut1, Axis};
 
 #[derive(Debug, Clone, new, Default, Hash)]
 pub struct LayerHardmax {
     axis: isize,
+    coerce_to_2d: bool,
 }
 
 impl LayerHardmax {
@@ -12,13 +14,9 @@ impl LayerHardmax {
         input: Arc<Tensor>,
     ) -> TractResult<TVec<Arc<Tensor>>> {
         let array = input.into_tensor().into_array::<D>()?;
-        let shape = array.shape().to_vec();
-        let axis =
-            if self.axis < 0 { shape.len() as isize + self.axis } else { self.axis } as usize;
-        let first_dim: usize = array.shape()[0..axis].iter().product();
-        let second_dim: usize = array.len() / first_dim;
-        let mut array = array.into_shape((first_dim, second_dim))?;
-        array.outer_iter_mut().for_each(|mut layer| {
+        let rank = array.ndim();
+        let axis = if self.axis < 0 { rank as isize + self.axis } else { self.axis } as usize;
+        let hardmax = |mut layer: ArrayViewMut1<D>| {
             let max = layer
                 .iter()
                 .enumerate()
@@ -30,8 +28,20 @@ impl LayerHardmax {
                 .iter_mut()
                 .enumerate()
                 .for_each(|(ix, r)| *r = D::from_usize((ix == max) as usize).unwrap());
-        });
-        Ok(tvec!(array.into_shape(shape)?.into_arc_tensor()))
+        };
+        let array = if self.coerce_to_2d {
+            let shape = array.shape().to_vec();
+            let first_dim: usize = shape[0..axis].iter().product();
+            let second_dim: usize = array.len() / first_dim;
+            let mut array = array.into_shape((first_dim, second_dim))?;
+            array.outer_iter_mut().for_each(hardmax);
+            array.into_shape(shape)?
+        } else {
+            let mut array = array;
+            array.lanes_mut(Axis(axis)).into_iter().for_each(hardmax);
+            array
+        };
+        Ok(tvec!(array.into_arc_tensor()))
     }
 }
 
@@ -41,7 +51,7 @@ impl Op for LayerHardmax {
     }
 
     fn info(&self) -> TractResult<Vec<String>> {
-        Ok(vec![format!("axis: {}", self.axis)])
+        Ok(vec![format!("axis: {} coerce_to_2d: {}", self.axis, self.coerce_to_2d)])
     }
 
     op_as_typed_op!();
@@ -79,6 +89,7 @@ impl TypedOp for LayerHardmax {
 #[derive(Debug, Clone, new, Default, Hash)]
 pub struct LayerLogSoftmax {
     axis: isize,
+    coerce_to_2d: bool,
 }
 
 impl LayerLogSoftmax {
@@ -88,7 +99,7 @@ impl LayerLogSoftmax {
         &self,
         input: Arc<Tensor>,
     ) -> TractResult<Tensor> {
-        let mut softmax = LayerSoftmax::new(self.axis).eval_t::<T>(input)?;
+        let mut softmax = LayerSoftmax::new(self.axis, self.coerce_to_2d).eval_t::<T>(input)?;
         softmax.as_slice_mut::<T>()?.iter_mut().for_each(|x| *x = x.ln());
         Ok(softmax)
     }
@@ -99,7 +110,7 @@ impl Op for LayerLogSoftmax {
         "LayerLogSoftmax".into()
     }
     fn info(&self) -> TractResult<Vec<String>> {
-        Ok(vec![format!("axis: {}", self.axis)])
+        Ok(vec![format!("axis: {} coerce_to_2d: {}", self.axis, self.coerce_to_2d)])
     }
     fn validation(&self) -> Validation {
         Validation::Rounding
@@ -134,8 +145,13 @@ impl InferenceRulesOp for LayerLogSoftmax {
         target: &mut TypedModel,
         mapping: &HashMap<OutletId, OutletId>,
     ) -> TractResult<TVec<OutletId>> {
-        let softmax =
-            InferenceOp::to_typed(&LayerSoftmax::new(self.axis), source, node, target, mapping)?[0];
+        let softmax = InferenceOp::to_typed(
+            &LayerSoftmax::new(self.axis, self.coerce_to_2d),
+            source,
+            node,
+            target,
+            mapping,
+        )?[0];
         target.wire_node(
             format!("{}-logsoftmax", node.name),
             tract_core::ops::math::ln(),
@@ -147,6 +163,9 @@ impl InferenceRulesOp for LayerLogSoftmax {
 #[derive(Debug, Clone, new, Default, Hash)]
 pub struct LayerSoftmax {
     axis: isize,
+    /// Normalize over all the dimensions from `axis` on (ONNX before operator
+    /// set 13), instead of `axis` alone.
+    coerce_to_2d: bool,
 }
 
 impl LayerSoftmax {
@@ -157,13 +176,9 @@ impl LayerSoftmax {
         input: Arc<Tensor>,
     ) -> TractResult<Tensor> {
         let array = input.into_tensor().into_array::<T>()?;
-        let shape = array.shape().to_vec();
-        let axis =
-            if self.axis < 0 { shape.len() as isize + self.axis } else { self.axis } as usize;
-        let first_dim: usize = array.shape()[0..axis].iter().product();
-        let second_dim: usize = array.len() / first_dim;
-        let mut array = array.into_shape((first_dim, second_dim))?;
-        array.outer_iter_mut().for_each(|mut layer| {
+        let rank = array.ndim();
+        let axis = if self.axis < 0 { rank as isize + self.axis } else { self.axis } as usize;
+        let softmax = |mut layer: ArrayViewMut1<T>| {
             // https://jamesmccaffrey.wordpress.com/2016/03/04/the-max-trick-when-computing-softmax/
             let max: Option<T> = layer
                 .iter()
@@ -172,8 +187,20 @@ impl LayerSoftmax {
             layer.mapv_inplace(|x| (x - max.unwrap()).exp());
             let divisor = layer.iter().cloned().sum();
             layer.mapv_inplace(|x| x / divisor);
-        });
-        Ok(array.into_shape(shape)?.into_tensor())
+        };
+        let array = if self.coerce_to_2d {
+            let shape = array.shape().to_vec();
+            let first_dim: usize = shape[0..axis].iter().product();
+            let second_dim: usize = array.len() / first_dim;
+            let mut array = array.into_shape((first_dim, second_dim))?;
+            array.outer_iter_mut().for_each(softmax);
+            array.into_shape(shape)?
+        } else {
+            let mut array = array;
+            array.lanes_mut(Axis(axis)).into_iter().for_each(softmax);
+            array
+        };
+        Ok(array.into_tensor())
     }
 }
 
@@ -182,7 +209,7 @@ impl Op for LayerSoftmax {
         "LayerSoftmax".into()
     }
     fn info(&self) -> TractResult<Vec<String>> {
-        Ok(vec![format!("axis: {}", self.axis)])
+        Ok(vec![format!("axis: {} coerce_to_2d: {}", self.axis, self.coerce_to_2d)])
     }
     fn validation(&self) -> Validation {
         Validation::Rounding
@@ -221,7 +248,7 @@ impl InferenceRulesOp for LayerSoftmax {
         let input = mapping[&node.inputs[0]];
         let rank = target.outlet_fact(input)?.rank();
         let axis = if self.axis < 0 { rank as isize + self.axis } else { self.axis } as usize;
-        let reducing_axes = (axis..rank).collect::<TVec<usize>>();
+        let reducing_axes = if self.coerce_to_2d { (axis..rank).collect() } else { tvec!(axis) };
         let maxes = target.wire_node(
             format!("{}-max", node.name),
             nn::Reduce::new(reducing_axes.clone(), nn::Reducer::Max),
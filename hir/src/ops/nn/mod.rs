@@ -1,12 +1,16 @@
 mod arg_max_min;
 mod global_pools;
 mod layer_max;
+mod non_max_suppression;
 mod reduce;
+mod roi_align;
 
 pub use arg_max_min::ArgMaxMin;
 pub use global_pools::*;
 pub use layer_max::*;
+pub use non_max_suppression::NonMaxSuppression;
 pub use reduce::{Reduce, Reducer};
+pub use roi_align::RoiAlign;
 
 pub use tract_core::ops::nn::{
     elu, hard_sigmoid, leaky_relu, parametric_softplus, scaled_tanh, selu, sigmoid, softplus,
@@ -0,0 +1,32 @@
+use crate::infer::*;
+use crate::internal::*;
+
+pub use tract_core::ops::nn::NonMaxSuppression;
+
+impl InferenceRulesOp for NonMaxSuppression {
+    fn rules<'r, 'p: 'r, 's: 'r>(
+        &'s self,
+        s: &mut Solver<'r>,
+        inputs: &'p [TensorProxy],
+        outputs: &'p [TensorProxy],
+    ) -> InferenceResult {
+        let expected_inputs = 2
+            + self.optional_max_output_boxes_per_class_input.is_some() as usize
+            + self.optional_iou_threshold_input.is_some() as usize
+            + self.optional_score_threshold_input.is_some() as usize;
+        check_input_arity(&inputs, expected_inputs)?;
+        check_output_arity(&outputs, 1)?;
+        s.equals(&inputs[0].rank, 3)?;
+        s.equals(&inputs[1].rank, 3)?;
+        s.equals(&inputs[0].shape[0], &inputs[1].shape[0])?;
+        s.equals(&inputs[0].shape[1], &inputs[1].shape[2])?;
+        s.equals(&inputs[0].shape[2], 4.to_dim())?;
+        s.equals(&outputs[0].datum_type, DatumType::I64)?;
+        s.equals(&outputs[0].rank, 2)?;
+        s.equals(&outputs[0].shape[1], 3.to_dim())?;
+        Ok(())
+    }
+
+    as_op!();
+    to_typed!();
+}
@@ -0,0 +1,31 @@
+use crate::infer::*;
+use crate::internal::*;
+
+pub use tract_core::ops::nn::RoiAlign;
+
+impl InferenceRulesOp for RoiAlign {
+    fn rules<'r, 'p: 'r, 's: 'r>(
+        &'s self,
+        s: &mut Solver<'r>,
+        inputs: &'p [TensorProxy],
+        outputs: &'p [TensorProxy],
+    ) -> InferenceResult {
+        check_input_arity(&inputs, 3)?;
+        check_output_arity(&outputs, 1)?;
+        s.equals(&inputs[0].rank, 4)?;
+        s.equals(&inputs[1].rank, 2)?;
+        s.equals(&inputs[1].shape[1], 4.to_dim())?;
+        s.equals(&inputs[2].rank, 1)?;
+        s.equals(&inputs[2].shape[0], &inputs[1].shape[0])?;
+        s.equals(&outputs[0].datum_type, &inputs[0].datum_type)?;
+        s.equals(&outputs[0].rank, 4)?;
+        s.equals(&outputs[0].shape[0], &inputs[1].shape[0])?;
+        s.equals(&outputs[0].shape[1], &inputs[0].shape[1])?;
+        s.equals(&outputs[0].shape[2], self.output_height.to_dim())?;
+        s.equals(&outputs[0].shape[3], self.output_width.to_dim())?;
+        Ok(())
+    }
+
+    as_op!();
+    to_typed!();
+}
@@ -3,6 +3,24 @@ use crate::internal::*;
 
 pub use tract_core::ops::unimpl::UnimplementedOp;
 
+/// Describe the unimplemented operators `model` needs, nested models
+/// included, one per line.
+pub fn unimplemented_ops(model: &dyn Model) -> TractResult<Vec<String>> {
+    let mut found = vec![];
+    for id in model.eval_order()? {
+        let op = model.node_op(id);
+        if let Some(unimpl) = op.downcast_ref::<UnimplementedOp>() {
+            found.push(format!("{} ({}): {}", model.node_name(id), unimpl.name, unimpl.message));
+        }
+        for (label, nested, _, _) in op.nested_models() {
+            for line in unimplemented_ops(nested)? {
+                found.push(format!("{}.{}.{}", model.node_name(id), label, line));
+            }
+        }
+    }
+    Ok(found)
+}
+
 impl InferenceRulesOp for UnimplementedOp {
     fn rules<'r, 'p: 'r, 's: 'r>(
         &'s self,
@@ -22,6 +40,6 @@ impl InferenceRulesOp for UnimplementedOp {
         _target: &mut TypedModel,
         _mapping: &HashMap<OutletId, OutletId>,
     ) -> TractResult<TVec<OutletId>> {
-        bail!("Operator can not be made a TypedOp.")
+        bail!("Operator {} can not be made a TypedOp: {}", self.name, self.message)
     }
 }
@@ -0,0 +1,118 @@
+//! Minimal access to the protobuf wire format.
+//!
+//! Framework loaders use it to rewrite a serialized model before decoding
+//! it, so that big tensor contents can be left in the (memory mapped) model
+//! file instead of being copied in the decoded protobuf messages.
+use tract_core::internal::*;
+
+/// Wire type of length-delimited fields (strings, bytes, messages).
+pub const LEN: u8 = 2;
+
+/// A field in a serialized protobuf message.
+#[derive(Debug, Clone)]
+pub struct Field<'a> {
+    pub tag: u32,
+    pub wire_type: u8,
+    /// The whole field, key included.
+    pub raw: &'a [u8],
+    /// The content of length-delimited fields, empty for other wire types.
+    pub payload: &'a [u8],
+}
+
+/// Read a varint at the beginning of `bytes`, and skip it.
+pub fn read_varint(bytes: &mut &[u8]) -> TractResult<u64> {
+    let mut value = 0u64;
+    for shift in (0..64).step_by(7) {
+        let (byte, rest) = bytes.split_first().ok_or("Truncated varint")?;
+        *bytes = rest;
+        value |= ((byte & 0x7f) as u64) << shift;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+    bail!("Invalid varint")
+}
+
+pub fn write_varint(buf: &mut Vec<u8>, mut value: u64) {
+    while value >= 0x80 {
+        buf.push(value as u8 | 0x80);
+        value >>= 7;
+    }
+    buf.push(value as u8)
+}
+
+/// Split a serialized message in its fields.
+pub fn fields(msg: &[u8]) -> TractResult<Vec<Field<'_>>> {
+    let mut fields = vec![];
+    let mut cursor = msg;
+    while cursor.len() > 0 {
+        let start = msg.len() - cursor.len();
+        let key = read_varint(&mut cursor)?;
+        let wire_type = (key & 0x7) as u8;
+        let mut payload = &msg[0..0];
+        let skip = match wire_type {
+            0 => {
+                read_varint(&mut cursor)?;
+                0
+            }
+            1 => 8,
+            LEN => {
+                let len = read_varint(&mut cursor)? as usize;
+                payload = cursor.get(..len).ok_or("Truncated protobuf field")?;
+                len
+            }
+            5 => 4,
+            _ => bail!("Unsupported protobuf wire type {}", wire_type),
+        };
+        cursor = cursor.get(skip..).ok_or("Truncated protobuf field")?;
+        let end = msg.len() - cursor.len();
+        fields.push(Field { tag: (key >> 3) as u32, wire_type, raw: &msg[start..end], payload });
+    }
+    Ok(fields)
+}
+
+/// Append a length-delimited field to `buf`.
+pub fn write_len_field(buf: &mut Vec<u8>, tag: u32, payload: &[u8]) {
+    write_varint(buf, (tag as u64) << 3 | LEN as u64);
+    write_varint(buf, payload.len() as u64);
+    buf.extend_from_slice(payload);
+}
+
+/// Rewrite the length-delimited fields with number `tag` through `f`,
+/// copying the other fields.
+pub fn map_len_fields(
+    msg: &[u8],
+    tag: u32,
+    mut f: impl FnMut(&[u8]) -> TractResult<Vec<u8>>,
+) -> TractResult<Vec<u8>> {
+    let mut buf = Vec::with_capacity(msg.len());
+    for field in fields(msg)? {
+        if field.tag == tag && field.wire_type == LEN {
+            write_len_field(&mut buf, tag, &f(field.payload)?);
+        } else {
+            buf.extend_from_slice(field.raw);
+        }
+    }
+    Ok(buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rewrite_nested() {
+        // { 1: 150, 2: { 3: "ab" }, 4: fixed32 }
+        let msg = [0x08, 0x96, 0x01, 0x12, 0x04, 0x1a, 0x02, b'a', b'b', 0x25, 1, 2, 3, 4];
+        let fields = fields(&msg).unwrap();
+        assert_eq!(fields.iter().map(|f| f.tag).collect::<Vec<_>>(), vec!(1, 2, 4));
+        assert_eq!(fields[1].payload, &[0x1a, 0x02, b'a', b'b']);
+        let rewritten =
+            map_len_fields(&msg, 2, |inner| map_len_fields(inner, 3, |_| Ok(b"xyz".to_vec())))
+                .unwrap();
+        assert_eq!(
+            rewritten,
+            vec!(0x08, 0x96, 0x01, 0x12, 0x05, 0x1a, 0x03, b'x', b'y', b'z', 0x25, 1, 2, 3, 4)
+        );
+    }
+}
@@ -19,6 +19,7 @@ pub enum FusedSpec<TI: Copy + Debug> {
     ScalarAdd(TI),
     QTowardsEven(TI, usize),
     QTowardsPlusInf(TI, usize),
+    PerRowQTowardsPlusInf(Vec<TI>, Vec<usize>),
 }
 
 impl<TI: Copy + Debug> Debug for FusedSpec<TI> {
@@ -36,6 +37,7 @@ impl<TI: Copy + Debug> Debug for FusedSpec<TI> {
             FusedSpec::ScalarAdd(_) => write!(fmt, "ScalarAdd"),
             FusedSpec::QTowardsEven(_, _) => write!(fmt, "QTowardsEven"),
             FusedSpec::QTowardsPlusInf(_, _) => write!(fmt, "QTowardsPlusInf"),
+            FusedSpec::PerRowQTowardsPlusInf(_, _) => write!(fmt, "PerRowQTowardsPlusInf"),
         }
     }
 }
@@ -67,6 +69,10 @@ impl<TI: Copy + Debug> std::hash::Hash for FusedSpec<TI> {
                 h(&[*a], state);
                 h(&[*b], state);
             }
+            PerRowQTowardsPlusInf(a, b) => {
+                h(&*a, state);
+                h(&*b, state);
+            }
         }
     }
 }
@@ -87,16 +93,22 @@ pub enum FusedKerSpec<TI: Copy> {
     ScalarAdd(TI),
     QTowardsEven(TI, usize),
     QTowardsPlusInf(TI, usize),
+    PerRowQTowardsPlusInf(*const TI, *const usize),
 }
 
 pub struct ScratchSpaceFusedNonLinear<TI: Copy> {
     uspecs: Vec<FusedKerSpec<TI>>,
     non_linear_buffers: Vec<Vec<TI>>,
+    shift_buffers: Vec<Vec<usize>>,
 }
 
 impl<TI: Copy> Default for ScratchSpaceFusedNonLinear<TI> {
     fn default() -> ScratchSpaceFusedNonLinear<TI> {
-        ScratchSpaceFusedNonLinear { uspecs: vec![], non_linear_buffers: vec![] }
+        ScratchSpaceFusedNonLinear {
+            uspecs: vec![],
+            non_linear_buffers: vec![],
+            shift_buffers: vec![],
+        }
     }
 }
 
@@ -198,6 +210,29 @@ impl<TI: Copy> ScratchSpaceFusedNonLinear<TI> {
                     FusedSpec::ScalarAdd(t) => FusedKerSpec::ScalarAdd(*t),
                     FusedSpec::QTowardsEven(m, s) => FusedKerSpec::QTowardsEven(*m, *s),
                     FusedSpec::QTowardsPlusInf(m, s) => FusedKerSpec::QTowardsPlusInf(*m, *s),
+                    FusedSpec::PerRowQTowardsPlusInf(mults, shifts) => {
+                        let have = mults.len() - down * K::mr();
+                        let mult_ptr = if have < K::mr() {
+                            let mut buf = vec![TI::zero(); K::mr()];
+                            buf[..have].copy_from_slice(&mults[down * K::mr()..][..have]);
+                            let ptr = buf.as_ptr();
+                            self.non_linear_buffers.push(buf);
+                            ptr
+                        } else {
+                            mults.as_ptr().add(down * K::mr())
+                        };
+                        let have = shifts.len() - down * K::mr();
+                        let shift_ptr = if have < K::mr() {
+                            let mut buf = vec![0; K::mr()];
+                            buf[..have].copy_from_slice(&shifts[down * K::mr()..][..have]);
+                            let ptr = buf.as_ptr();
+                            self.shift_buffers.push(buf);
+                            ptr
+                        } else {
+                            shifts.as_ptr().add(down * K::mr())
+                        };
+                        FusedKerSpec::PerRowQTowardsPlusInf(mult_ptr, shift_ptr)
+                    }
                 };
                 self.uspecs.push(s);
             }
@@ -372,6 +407,13 @@ pub mod test {
                     }
                 }
 
+                #[test]
+                fn return_q_towards_plusinf_per_row() {
+                    if $cond {
+                        test::return_q_towards_plusinf_per_row::<$ker, $ta, $tb, $tc, $ti>()
+                    }
+                }
+
                 proptest::proptest! {
                     #[test]
                     fn return_q_towards_plusinf_prop(pb in any::<QTowardsPlusInfProblem<$ker, $ta, $tb, $tc, $ti>>()) {
@@ -750,6 +792,38 @@ pub mod test {
                     }));
                 }
 
+    pub fn return_q_towards_plusinf_per_row<K, TA, TB, TC, TI>()
+        where
+        K: MatMatMulKer<TA, TB, TC, TI>,
+        TA: Copy,
+        TB: Copy,
+        TC: Copy + Debug + 'static + PartialEq + AsPrimitive<i64>,
+        TI: Copy + Debug + 'static,
+        usize: AsPrimitive<TC> + AsPrimitive<TI>,
+        i64: AsPrimitive<TC>,
+        {
+            let len = K::mr() * K::nr();
+            let v: Vec<TC> = (0..len).map(|ix| (ix as i64 - len as i64 / 2).as_()).collect();
+            let mults: Vec<TI> = vec![(1usize << 30).as_(); K::mr()];
+            let shifts: Vec<usize> = (0..K::mr()).map(|row| row % 4).collect();
+            let found = fused_ops::<K, TA, TB, TC, TI>(
+                &*v,
+                &[
+                FusedKerSpec::ScalarMul(2usize.as_()),
+                FusedKerSpec::PerRowQTowardsPlusInf(mults.as_ptr(), shifts.as_ptr()),
+                ],
+                );
+            let expected = v
+                .iter()
+                .enumerate()
+                .map(|(ix, x)| {
+                    let x: i64 = x.as_();
+                    ((((2 * x) >> shifts[ix / K::nr()]) + 1) >> 1).as_()
+                })
+            .collect::<Vec<TC>>();
+            assert_eq!(found, expected);
+        }
+
     #[derive(Debug, new)]
     pub struct QTowardsPlusInfProblem<K, TA, TB, TC, TI>
         where
@@ -6,6 +6,7 @@ use std::ops::{Add, Mul};
 use num_traits::Zero;
 
 use crate::frame::{PackA, PackB};
+use crate::multithread::Executor;
 
 use super::fuse::ScratchSpaceFusedNonLinear;
 use super::*;
@@ -213,11 +214,69 @@ where
     }
 
     unsafe fn run(&self, a: *const TA, b: *const TB, c: *mut TC, non_linear: &[FusedSpec<TI>]) {
-        let mr = K::mr();
-        let nr = K::nr();
-        let m = self.m;
-        let n = self.n;
-        let mut scratch = ScratchSpaceFusedNonLinear::default();
+        let operands = Operands {
+            a: self.a_storage.wrap(a),
+            b: self.b_storage.wrap(b),
+            c: self.c_storage.wrap(c),
+            non_linear,
+        };
+        let row_panels = (self.m + K::mr() - 1) / K::mr();
+        match crate::multithread::current_tract_executor() {
+            Executor::SingleThread => {
+                let mut scratch = TileScratch::new(K::mr(), K::nr());
+                for ia in 0..row_panels {
+                    self.run_row_panel(&operands, ia, &mut scratch);
+                }
+            }
+            #[cfg(feature = "multithread-mmm")]
+            Executor::MultiThread(pool) => {
+                use rayon::prelude::*;
+                // too few row panels to keep all threads busy: split the
+                // rows in tiles instead
+                let col_panels = (self.n + K::nr() - 1) / K::nr();
+                pool.install(|| {
+                    if row_panels >= pool.current_num_threads() {
+                        (0..row_panels).into_par_iter().for_each_init(
+                            || TileScratch::new(K::mr(), K::nr()),
+                            |scratch, ia| self.run_row_panel(&operands, ia, scratch),
+                        )
+                    } else {
+                        (0..row_panels * col_panels).into_par_iter().for_each_init(
+                            || TileScratch::new(K::mr(), K::nr()),
+                            |scratch, ix| {
+                                self.run_tile(&operands, ix / col_panels, ix % col_panels, scratch)
+                            },
+                        )
+                    }
+                })
+            }
+        }
+    }
+}
+
+/// Wrapped operands of a multiplication, shared by all row panels.
+struct Operands<'a, TA: Copy, TB: Copy, TC: Copy, TI: Copy + Debug> {
+    a: MatrixStore<'a, TA>,
+    b: MatrixStore<'a, TB>,
+    c: MatrixStore<'a, TC>,
+    non_linear: &'a [FusedSpec<TI>],
+}
+
+// row panels write to disjoint parts of c
+unsafe impl<'a, TA: Copy, TB: Copy, TC: Copy, TI: Copy + Debug> Sync
+    for Operands<'a, TA, TB, TC, TI>
+{
+}
+
+/// Per-thread buffers for tiles going through a temporary storage.
+struct TileScratch<TC: Copy, TI: Copy> {
+    fused: ScratchSpaceFusedNonLinear<TI>,
+    tmpc: Vec<TC>,
+    tmp_c_storage: MatrixStoreSpec,
+}
+
+impl<TC: Copy, TI: Copy> TileScratch<TC, TI> {
+    unsafe fn new(mr: usize, nr: usize) -> TileScratch<TC, TI> {
         let mut tmpc = Vec::with_capacity(mr * nr);
         tmpc.set_len(mr * nr);
         let tmp_c_storage = MatrixStoreSpec::Strides {
@@ -226,70 +285,66 @@ where
             mr,
             nr,
         };
-        let ref mut tmp_tile = tmp_c_storage.wrap(tmpc.as_ptr());
-        let a = self.a_storage.wrap(a);
-        let b = self.b_storage.wrap(b);
-        let mut c = self.c_storage.wrap(c);
-        let ref linear = LinearSpec::k(self.k);
-        for ia in 0..m / mr {
-            let ref a = a.panel_a(ia);
-            for ib in 0..n / nr {
-                let ref b = b.panel_b(nr, ib, nr);
-                let ref direct_c = c.tile_c(ia, ib);
-                let non_linear = scratch.for_tile::<TA, TB, TC, K>(non_linear, ia, ib);
-                let err = K::kernel(&MatMatMulKerSpec {
-                    a: a as _,
-                    b: b as _,
-                    c: direct_c as _,
-                    linear,
-                    non_linear,
-                });
-                debug_assert_eq!(err, 0, "Kernel return error {}", err);
-            }
-            if n % nr != 0 {
-                let ref b = b.panel_b(nr, n / nr, n % nr);
-                let ref tmp_tile_c = tmp_tile.tile_c(0, 0);
-                let non_linear = scratch.for_tile::<TA, TB, TC, K>(non_linear, ia, n / nr);
-                let err = K::kernel(&MatMatMulKerSpec {
-                    a: a as _,
-                    b: b as _,
-                    c: tmp_tile_c as _,
-                    linear,
-                    non_linear,
-                });
-                debug_assert_eq!(err, 0, "Kernel return error {}", err);
-                c.set_from_tile(ia, n / nr, mr, n % nr, &*tmpc);
-            }
+        TileScratch { fused: ScratchSpaceFusedNonLinear::default(), tmpc, tmp_c_storage }
+    }
+}
+
+impl<K, TA, TB, TC, TI> MatMatMulImpl<K, TA, TB, TC, TI>
+where
+    TA: Copy + Zero + Debug + 'static,
+    TB: Copy + Zero + Debug + 'static,
+    TC: Copy + Debug + 'static,
+    TI: Copy + Add + Mul + Zero + Debug + 'static,
+    K: MatMatMulKer<TA, TB, TC, TI> + 'static,
+{
+    unsafe fn run_row_panel(
+        &self,
+        operands: &Operands<TA, TB, TC, TI>,
+        ia: usize,
+        scratch: &mut TileScratch<TC, TI>,
+    ) {
+        for ib in 0..(self.n + K::nr() - 1) / K::nr() {
+            self.run_tile(operands, ia, ib, scratch)
         }
-        if m % mr != 0 {
-            let ref panel_a = a.panel_a(m / mr);
+    }
+
+    unsafe fn run_tile(
+        &self,
+        operands: &Operands<TA, TB, TC, TI>,
+        ia: usize,
+        ib: usize,
+        scratch: &mut TileScratch<TC, TI>,
+    ) {
+        let mr = K::mr();
+        let nr = K::nr();
+        let height = (self.m - ia * mr).min(mr);
+        let width = (self.n - ib * nr).min(nr);
+        let ref a = operands.a.panel_a(ia);
+        let ref b = operands.b.panel_b(nr, ib, width);
+        let ref linear = LinearSpec::k(self.k);
+        let non_linear = scratch.fused.for_tile::<TA, TB, TC, K>(operands.non_linear, ia, ib);
+        if height == mr && width == nr {
+            let ref direct_c = operands.c.tile_c(ia, ib);
+            let err = K::kernel(&MatMatMulKerSpec {
+                a: a as _,
+                b: b as _,
+                c: direct_c as _,
+                linear,
+                non_linear,
+            });
+            debug_assert_eq!(err, 0, "Kernel return error {}", err);
+        } else {
+            let tmp_tile = scratch.tmp_c_storage.wrap(scratch.tmpc.as_ptr());
             let ref tmp_tile_c = tmp_tile.tile_c(0, 0);
-            for ib in 0..n / nr {
-                let ref b = b.panel_b(nr, ib, nr);
-                let non_linear = scratch.for_tile::<TA, TB, TC, K>(non_linear, m / mr, ib);
-                let err = K::kernel(&MatMatMulKerSpec {
-                    a: panel_a as _,
-                    b: b as _,
-                    c: tmp_tile_c as _,
-                    linear,
-                    non_linear,
-                });
-                debug_assert_eq!(err, 0, "Kernel return error {}", err);
-                c.set_from_tile(m / mr, ib, m % mr, nr, &*tmpc);
-            }
-            if n % nr != 0 {
-                let ref b = b.panel_b(nr, n / nr, n % nr);
-                let non_linear = scratch.for_tile::<TA, TB, TC, K>(non_linear, m / mr, n / nr);
-                let err = K::kernel(&MatMatMulKerSpec {
-                    a: panel_a as _,
-                    b: b as _,
-                    c: tmp_tile_c as _,
-                    linear,
-                    non_linear,
-                });
-                debug_assert_eq!(err, 0, "Kernel return error {}", err);
-                c.set_from_tile(m / mr, n / nr, m % mr, n % nr, &*tmpc);
-            }
+            let err = K::kernel(&MatMatMulKerSpec {
+                a: a as _,
+                b: b as _,
+                c: tmp_tile_c as _,
+                linear,
+                non_linear,
+            });
+            debug_assert_eq!(err, 0, "Kernel return error {}", err);
+            operands.c.set_from_tile(ia, ib, height, width, &*scratch.tmpc);
         }
     }
 }
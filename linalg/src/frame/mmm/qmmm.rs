@@ -24,7 +24,9 @@ where
     unsafe fn set_zero_point_b_vector(&mut self, values: Vec<TB>);
 
     unsafe fn set_zero_point_c_scalar(&mut self, value: TC);
+    /// Scale factors must be in (0, 1), see `scale_factor_as_mult_shift`.
     unsafe fn set_scale_factor(&mut self, factor: f32);
+    unsafe fn set_scale_factor_vector(&mut self, factors: Vec<f32>);
 
     unsafe fn run(&self, a: *const TA, b: *const TB, c: *mut TC, non_linear: &[FusedSpec<TI>]);
 }
@@ -36,6 +38,23 @@ dyn_clone::clone_trait_object!(<TA, TB, TC, TI> QMatMatMul<TA, TB, TC, TI> where
     TI: Copy + Add + Mul + Zero + Debug + 'static,
 );
 
+/// Splits a scale factor in (0, 1) into a 31-bit fixed point multiplier and
+/// a right shift, as expected by `FusedSpec::QTowardsPlusInf`.
+///
+/// Returns None for factors outside (0, 1), which would need a left shift.
+pub fn scale_factor_as_mult_shift(factor: f32) -> Option<(i32, usize)> {
+    if !(factor > 0.0 && factor < 1.0) {
+        return None;
+    }
+    // https://github.com/microsoft/onnxruntime/blob/master/onnxruntime/core/util/gemmlowp_common.h#L16
+    let factor_bits = factor.to_bits();
+    let current_exponent = factor_bits >> 23;
+    let bumped_multi = f32::from_bits(factor_bits & 0x007fffff | 0x3f000000);
+    let int_multi = (bumped_multi * (1i64 << 31) as f32).round() as i32;
+    let shift = 126 - current_exponent;
+    Some((int_multi, shift as usize))
+}
+
 #[derive(Debug, Clone)]
 pub enum QuantizedParam<TI> {
     Scalar(TI),
@@ -56,7 +75,7 @@ where
     pub zero_point_b: Option<QuantizedParam<TB>>,
 
     pub zero_point_c: Option<TC>,
-    pub scale_factor: Option<(TI, usize)>,
+    pub scale_factor: Option<QuantizedParam<(TI, usize)>>,
 }
 
 impl<K, TA, TB, TC, TI> QMatMatMulImpl<K, TA, TB, TC, TI>
@@ -239,13 +258,21 @@ where
     }
 
     unsafe fn set_scale_factor(&mut self, factor: f32) {
-        // https://github.com/microsoft/onnxruntime/blob/master/onnxruntime/core/util/gemmlowp_common.h#L16
-        let factor_bits = factor.to_bits();
-        let current_exponent = factor_bits >> 23;
-        let bumped_multi = f32::from_bits(factor_bits & 0x007fffff | 0x3f000000);
-        let int_multi = (bumped_multi * (1i64 << 31) as f32).round() as i32;
-        let shift = 126 - current_exponent;
-        self.scale_factor = Some((int_multi.as_(), shift as usize));
+        let (mult, shift) = scale_factor_as_mult_shift(factor)
+            .unwrap_or_else(|| panic!("Scale factor {} is not in (0, 1)", factor));
+        self.scale_factor = Some(QuantizedParam::Scalar((mult.as_(), shift)));
+    }
+
+    unsafe fn set_scale_factor_vector(&mut self, factors: Vec<f32>) {
+        let factors = factors
+            .into_iter()
+            .map(|f| {
+                let (mult, shift) = scale_factor_as_mult_shift(f)
+                    .unwrap_or_else(|| panic!("Scale factor {} is not in (0, 1)", f));
+                (mult.as_(), shift)
+            })
+            .collect();
+        self.scale_factor = Some(QuantizedParam::Vector(factors));
     }
 
     unsafe fn run(&self, a: *const TA, b: *const TB, c: *mut TC, non_linear: &[FusedSpec<TI>]) {
@@ -303,8 +330,15 @@ where
             };
             non_linear.insert(0, term);
         }
-        if let Some(scale) = self.scale_factor {
-            non_linear.push(FusedSpec::QTowardsPlusInf(scale.0, scale.1));
+        match &self.scale_factor {
+            Some(QuantizedParam::Scalar((mult, shift))) => {
+                non_linear.push(FusedSpec::QTowardsPlusInf(*mult, *shift))
+            }
+            Some(QuantizedParam::Vector(factors)) => {
+                let (mults, shifts) = factors.iter().cloned().unzip();
+                non_linear.push(FusedSpec::PerRowQTowardsPlusInf(mults, shifts))
+            }
+            None => (),
         }
         if let Some(c0) = self.zero_point_c {
             non_linear.push(FusedSpec::ScalarAdd(c0.as_()));
@@ -354,6 +388,16 @@ pub mod test {
     use std::marker::PhantomData;
     use std::ops::{AddAssign, Sub};
 
+    #[test]
+    fn scale_factor_must_be_below_one() {
+        assert_eq!(scale_factor_as_mult_shift(0.5), Some((1 << 30, 0)));
+        assert_eq!(scale_factor_as_mult_shift(0.125), Some((1 << 30, 2)));
+        assert_eq!(scale_factor_as_mult_shift(1.0), None);
+        assert_eq!(scale_factor_as_mult_shift(3.0), None);
+        assert_eq!(scale_factor_as_mult_shift(0.0), None);
+        assert_eq!(scale_factor_as_mult_shift(-0.5), None);
+    }
+
     #[derive(Debug)]
     pub struct QMatMulProblem<TA, TB, TC, TI> {
         pub m: usize,
@@ -486,6 +530,26 @@ pub mod test {
         }
     }
 
+    pub fn per_row_scale_factor<K: MatMatMulKer<i8, i8, i8, i32>>() {
+        let scales = [0.5f32, 0.25, 0.3, 0.7, 0.125, 0.9, 0.06, 0.5, 0.2];
+        let (m, k, n) = (scales.len(), 2, 3);
+        let a = vec![4i8; m * k];
+        let b = vec![8i8; k * n];
+        unsafe {
+            let mut c = vec![0i8; m * n];
+            let mut mmm = QMatMatMulImpl::from(MatMatMulImpl::<K, i8, i8, i8, i32>::new(m, k, n));
+            let mut packed_a = Buffer::uninitialized(mmm.a_pack().len(), mmm.a_pack().alignment());
+            mmm.a_pack().pack(packed_a.as_mut_ptr(), a.as_ptr(), k as isize, 1);
+            let mut packed_b = Buffer::uninitialized(mmm.b_pack().len(), mmm.b_pack().alignment());
+            mmm.b_pack().pack(packed_b.as_mut_ptr(), b.as_ptr(), n as isize, 1);
+            mmm.set_scale_factor_vector(scales.to_vec());
+            mmm.run(packed_a.as_ptr(), packed_b.as_ptr(), c.as_mut_ptr(), &[]);
+            let expected =
+                (0..m * n).map(|ix| (64f32 * scales[ix / n]).round() as i8).collect::<Vec<i8>>();
+            assert_eq!(c, expected);
+        }
+    }
+
     #[macro_export]
     macro_rules! qmmm_frame_tests {
         ($cond:expr, $ker:ty, $ta: ty, $tb: ty, $tc: ty, $ti: ty) => {
@@ -619,6 +683,13 @@ pub mod test {
                     }
                 }
 
+                #[test]
+                fn q_mat_mul_per_row_scale_factor() {
+                    if $cond {
+                        per_row_scale_factor::<$ker>()
+                    }
+                }
+
                 #[test]
                 fn q_mat_mul_1_1_1() {
                     if $cond {
@@ -111,7 +111,7 @@ impl<'a, T: Copy> MatrixStore<'a, T> {
     }
 
     pub(super) unsafe fn set_from_tile(
-        &mut self,
+        &self,
         down: usize,
         right: usize,
         height: usize,
@@ -318,6 +318,15 @@ where
                             }
                         }
                     }
+                    FusedKerSpec::PerRowQTowardsPlusInf(mults, shifts) => {
+                        for i in 0..4 {
+                            let mult = *mults.offset(i as isize);
+                            let shift = *shifts.offset(i as isize);
+                            for j in 0..4 {
+                                ab[i][j] = ab[i][j].q_to_plus_inf(mult, shift);
+                            }
+                        }
+                    }
                 }
                 pnl = pnl.add(1);
             }
@@ -596,6 +605,15 @@ where
                             }
                         }
                     }
+                    FusedKerSpec::PerRowQTowardsPlusInf(mults, shifts) => {
+                        for i in 0..3 {
+                            let mult = *mults.offset(i as isize);
+                            let shift = *shifts.offset(i as isize);
+                            for j in 0..2 {
+                                ab[i][j] = ab[i][j].q_to_plus_inf(mult, shift);
+                            }
+                        }
+                    }
                 }
                 pnl = pnl.add(1);
             }
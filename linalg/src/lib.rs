@@ -15,6 +15,7 @@ pub mod f16;
 #[macro_use]
 pub mod frame;
 mod generic;
+pub mod multithread;
 
 #[cfg(target_arch = "x86_64")]
 pub mod x86_64_fma;
@@ -0,0 +1,160 @@
+//! Executor selection for the parallelizable loops of tract-linalg.
+//!
+//! By default everything runs on the calling thread. With the
+//! `multithread-mmm` feature, a rayon thread pool can be installed either
+//! globally with `set_default_executor` or for the duration of a closure
+//! with `multithread_tract_scope`. Matrix multiplications then dispatch their
+//! row panels across the pool.
+
+#[cfg(feature = "multithread-mmm")]
+use std::cell::RefCell;
+#[cfg(feature = "multithread-mmm")]
+use std::sync::atomic::{AtomicUsize, Ordering};
+#[cfg(feature = "multithread-mmm")]
+use std::sync::{Arc, Mutex};
+
+#[derive(Debug, Clone)]
+pub enum Executor {
+    SingleThread,
+    #[cfg(feature = "multithread-mmm")]
+    MultiThread(Arc<rayon::ThreadPool>),
+}
+
+impl Default for Executor {
+    fn default() -> Executor {
+        Executor::SingleThread
+    }
+}
+
+impl Executor {
+    /// Build an executor backed by a dedicated pool of `threads` threads.
+    #[cfg(feature = "multithread-mmm")]
+    pub fn multithread(threads: usize) -> Executor {
+        let pool = rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .thread_name(|ix| format!("tract-linalg-{}", ix))
+            .build()
+            .expect("Failed to build rayon thread pool");
+        Executor::MultiThread(Arc::new(pool))
+    }
+}
+
+#[cfg(feature = "multithread-mmm")]
+lazy_static::lazy_static! {
+    static ref DEFAULT_EXECUTOR: Mutex<Executor> = Mutex::new(Executor::SingleThread);
+}
+
+/// Bumped each time the default executor changes, so that threads only lock
+/// it when their cached copy is stale.
+#[cfg(feature = "multithread-mmm")]
+static DEFAULT_EXECUTOR_GENERATION: AtomicUsize = AtomicUsize::new(0);
+
+#[cfg(feature = "multithread-mmm")]
+thread_local! {
+    static TLS_EXECUTOR_OVERRIDE: RefCell<Option<Executor>> = RefCell::new(None);
+    static TLS_DEFAULT_EXECUTOR: RefCell<(usize, Executor)> =
+        RefCell::new((0, Executor::SingleThread));
+}
+
+/// The executor in use on the current thread.
+#[cfg(feature = "multithread-mmm")]
+pub fn current_tract_executor() -> Executor {
+    if let Some(executor) = TLS_EXECUTOR_OVERRIDE.with(|e| e.borrow().clone()) {
+        return executor;
+    }
+    TLS_DEFAULT_EXECUTOR.with(|cached| {
+        let mut cached = cached.borrow_mut();
+        let generation = DEFAULT_EXECUTOR_GENERATION.load(Ordering::Acquire);
+        if cached.0 != generation {
+            *cached = (generation, DEFAULT_EXECUTOR.lock().unwrap().clone());
+        }
+        cached.1.clone()
+    })
+}
+
+/// The executor in use on the current thread: always single threaded
+/// without the `multithread-mmm` feature.
+#[cfg(not(feature = "multithread-mmm"))]
+pub fn current_tract_executor() -> Executor {
+    Executor::SingleThread
+}
+
+/// Set the executor used by threads that do not override it.
+#[cfg(feature = "multithread-mmm")]
+pub fn set_default_executor(executor: Executor) {
+    let mut default = DEFAULT_EXECUTOR.lock().unwrap();
+    *default = executor;
+    DEFAULT_EXECUTOR_GENERATION.fetch_add(1, Ordering::Release);
+}
+
+/// Set the executor used by threads that do not override it.
+#[cfg(not(feature = "multithread-mmm"))]
+pub fn set_default_executor(_executor: Executor) {}
+
+/// Run `f` with `executor` overriding the default on the current thread.
+#[cfg(feature = "multithread-mmm")]
+pub fn multithread_tract_scope<R, F: FnOnce() -> R>(executor: Executor, f: F) -> R {
+    let previous = TLS_EXECUTOR_OVERRIDE.with(|e| e.replace(Some(executor)));
+    struct Restore(Option<Executor>);
+    impl Drop for Restore {
+        fn drop(&mut self) {
+            let previous = self.0.take();
+            TLS_EXECUTOR_OVERRIDE.with(|e| *e.borrow_mut() = previous);
+        }
+    }
+    let _restore = Restore(previous);
+    f()
+}
+
+/// Run `f` with `executor` overriding the default on the current thread.
+#[cfg(not(feature = "multithread-mmm"))]
+pub fn multithread_tract_scope<R, F: FnOnce() -> R>(_executor: Executor, f: F) -> R {
+    f()
+}
+
+#[cfg(all(test, feature = "multithread-mmm"))]
+mod test {
+    use super::*;
+    use crate::align::Buffer;
+
+    fn mat_mul(m: usize, k: usize, n: usize) -> Vec<f32> {
+        let a: Vec<f32> = (0..m * k).map(|i| (i % 7) as f32 - 3.0).collect();
+        let b: Vec<f32> = (0..k * n).map(|i| (i % 5) as f32 * 0.5).collect();
+        let mmm = (crate::generic().mmm_f32)(m, k, n);
+        unsafe {
+            let mut packed_a = Buffer::uninitialized(mmm.a_pack().len(), mmm.a_pack().alignment());
+            mmm.a_pack().pack(packed_a.as_mut_ptr(), a.as_ptr(), k as isize, 1);
+            let mut packed_b = Buffer::uninitialized(mmm.b_pack().len(), mmm.b_pack().alignment());
+            mmm.b_pack().pack(packed_b.as_mut_ptr(), b.as_ptr(), n as isize, 1);
+            let mut c = vec![0f32; m * n];
+            mmm.run(packed_a.as_ptr(), packed_b.as_ptr(), c.as_mut_ptr(), &[]);
+            c
+        }
+    }
+
+    #[test]
+    fn multithread_mmm_matches_single_thread() {
+        let single = mat_mul(37, 13, 29);
+        let multi = multithread_tract_scope(Executor::multithread(4), || mat_mul(37, 13, 29));
+        assert_eq!(single, multi);
+    }
+
+    #[test]
+    fn multithread_mmm_splits_short_matrices_in_tiles() {
+        let single = mat_mul(3, 13, 29);
+        let multi = multithread_tract_scope(Executor::multithread(4), || mat_mul(3, 13, 29));
+        assert_eq!(single, multi);
+    }
+
+    #[test]
+    fn scope_restores_executor() {
+        multithread_tract_scope(Executor::multithread(2), || {
+            assert!(matches!(current_tract_executor(), Executor::MultiThread(_)));
+        });
+        assert!(matches!(current_tract_executor(), Executor::SingleThread));
+        set_default_executor(Executor::multithread(2));
+        assert!(matches!(current_tract_executor(), Executor::MultiThread(_)));
+        set_default_executor(Executor::SingleThread);
+        assert!(matches!(current_tract_executor(), Executor::SingleThread));
+    }
+}
@@ -20,6 +20,8 @@ pub mod pb {
 
 pub mod pb_helpers;
 pub mod tensor;
+#[cfg(test)]
+mod test_util;
 
 pub use model::Onnx;
 
@@ -44,5 +46,5 @@ pub fn for_reader<R: std::io::Read>(mut r: R) -> TractResult<InferenceModel> {
 pub fn onnx() -> Onnx {
     let mut ops = crate::model::OnnxOpRegister::default();
     ops::register_all_ops(&mut ops);
-    Onnx { op_register: ops }
+    Onnx { op_register: ops, ..Onnx::default() }
 }
@@ -1,8 +1,8 @@
-use std::convert::TryInto;
-
 use std::collections::HashMap;
+use std::path;
 
 use tract_hir::internal::*;
+use tract_hir::tract_core::tensor::mmap::Mmap;
 
 use crate::pb;
 use prost::Message;
@@ -37,6 +37,8 @@ pub struct ParsingContext<'a> {
     pub framework: &'a Onnx,
     pub model: &'a pb::ModelProto,
     pub parent_graphs: Vec<&'a pb::GraphProto>,
+    pub symbols: &'a HashMap<String, char>,
+    pub model_dir: Option<&'a path::Path>,
 }
 
 #[derive(Clone, Debug)]
@@ -47,6 +49,19 @@ pub struct ParseResult {
 }
 
 impl<'a> ParsingContext<'a> {
+    pub fn translate_fact(&self, t: &pb::type_proto::Tensor) -> TractResult<InferenceFact> {
+        crate::tensor::translate_inference_fact(t, self.symbols)
+    }
+
+    pub fn load_tensor(&self, t: &pb::TensorProto) -> TractResult<Tensor> {
+        crate::tensor::load_tensor(t, self.model_dir)
+    }
+
+    /// The operator set version the model imports for `domain`.
+    pub fn opset_version(&self, domain: &str) -> Option<i64> {
+        opset_version(self.model, domain)
+    }
+
     pub fn parse_graph(&self, graph: &pb::GraphProto) -> TractResult<ParseResult> {
         let mut ctx = self.clone();
         ctx.parent_graphs.push(graph);
@@ -56,7 +71,7 @@ impl<'a> ParsingContext<'a> {
         let mut initializers: HashMap<&str, Tensor> = graph
             .initializer
             .iter()
-            .map(|init| Ok((&*init.name, init.try_into()?)))
+            .map(|init| Ok((&*init.name, ctx.load_tensor(init)?)))
             .collect::<TractResult<_>>()?;
         for (k, v) in initializers.iter() {
             trace!("Initializer: {} {:?}", k, v);
@@ -71,7 +86,7 @@ impl<'a> ParsingContext<'a> {
                 let fact = input.r#type.as_ref().unwrap().value.as_ref().unwrap();
                 #[allow(irrefutable_let_patterns)]
                 let fact: InferenceFact = if let pb::type_proto::Value::TensorType(fact) = fact {
-                    fact.try_into()?
+                    ctx.translate_fact(fact)?
                 } else {
                     bail!("Can not parse tensor type");
                 };
@@ -104,17 +119,32 @@ impl<'a> ParsingContext<'a> {
                 .map(|_| InferenceFact::default())
                 .collect();
             trace!("  outputs {:?}", pbnode.output);
-            let (op, closures) = match self.framework.op_register.0.get(&pbnode.op_type) {
-                Some(builder) => (builder)(&ctx, pbnode)?,
-                None => (
-                    tract_hir::ops::unimpl::UnimplementedOp::new(
-                        pbnode.output.len(),
-                        &*pbnode.op_type,
-                        format!("{:?}", pbnode),
-                    )
-                    .into(),
-                    vec![],
-                ),
+            let version = ctx.opset_version(&pbnode.domain);
+            let builder = match version {
+                Some(version) => {
+                    self.framework.op_register.lookup(&pbnode.domain, &pbnode.op_type, version)
+                }
+                None => Ok(None),
+            };
+            let unimplemented = |message: String| {
+                let op = tract_hir::ops::unimpl::UnimplementedOp::new(
+                    pbnode.output.len(),
+                    &*pbnode.op_type,
+                    message,
+                );
+                (op.into(), vec![])
+            };
+            let (op, closures) = match builder {
+                Ok(Some(builder)) => (builder)(&ctx, pbnode)?,
+                Ok(None) => unimplemented(match version {
+                    Some(version) => format!(
+                        "Unsupported operator {} for operator set {}",
+                        qualified_op_name(&pbnode.domain, &pbnode.op_type),
+                        version
+                    ),
+                    None => format!("Operator set {} is not imported", pbnode.domain),
+                }),
+                Err(e) => unimplemented(e.to_string()),
             };
             let id = model.add_node(name, op, facts)?;
             for (ix, output) in pbnode.output.iter().filter(|s| !s.is_empty()).enumerate() {
@@ -149,10 +179,16 @@ impl<'a> ParsingContext<'a> {
         }
         let mut outputs = vec![];
         for output in graph.output.iter() {
+            if !outlets_by_name.contains_key(&*output.name) {
+                // subgraph directly outputting a value from an outer scope
+                let id = model.add_source(output.name.clone(), InferenceFact::default())?;
+                unresolved_inputs.push(output.name.to_string());
+                outlets_by_name.insert(output.name.to_string(), id);
+            }
             let fact = output.r#type.as_ref().unwrap().value.as_ref().unwrap();
             #[allow(irrefutable_let_patterns)]
             let fact = if let pb::type_proto::Value::TensorType(fact) = fact {
-                fact.try_into()?
+                ctx.translate_fact(fact)?
             } else {
                 bail!("Can not parse tensor type");
             };
@@ -165,51 +201,306 @@ impl<'a> ParsingContext<'a> {
     }
 }
 
+fn collect_dim_params(graph: &pb::GraphProto, names: &mut Vec<String>) {
+    for value in graph.input.iter().chain(graph.output.iter()).chain(graph.value_info.iter()) {
+        if let Some(pb::type_proto::Value::TensorType(t)) =
+            value.r#type.as_ref().and_then(|t| t.value.as_ref())
+        {
+            for d in t.shape.iter().flat_map(|s| s.dim.iter()) {
+                if let Some(pb::tensor_shape_proto::dimension::Value::DimParam(p)) = &d.value {
+                    if !names.contains(p) {
+                        names.push(p.clone())
+                    }
+                }
+            }
+        }
+    }
+    for attr in graph.node.iter().flat_map(|n| n.attribute.iter()) {
+        for g in attr.g.iter().chain(attr.graphs.iter()) {
+            collect_dim_params(g, names)
+        }
+    }
+}
+
+/// Pick a symbol for each named dimension (`dim_param`) of the model.
+///
+/// The dimension named `streaming`, if any, is mapped to the streaming
+/// dimension S. Other single letter names keep their letter, uppercased.
+/// Longer names get their uppercased initial if it is still free, or the
+/// first free letter. S is never picked for them. Models with more distinct
+/// names than there are letters are rejected.
+fn symbols_for_dim_params(
+    graph: &pb::GraphProto,
+    streaming: Option<&str>,
+) -> TractResult<HashMap<String, char>> {
+    let mut names = vec![];
+    collect_dim_params(graph, &mut names);
+    let mut symbols = HashMap::new();
+    let mut taken = vec!['S'];
+    if let Some(streaming) = streaming {
+        if names.iter().any(|n| n == streaming) {
+            symbols.insert(streaming.to_string(), 'S');
+        }
+    }
+    for name in &names {
+        let mut chars = name.chars();
+        if let (Some(c), None) = (chars.next(), chars.next()) {
+            let c = c.to_ascii_uppercase();
+            if c.is_ascii_uppercase() && !taken.contains(&c) && !symbols.contains_key(name) {
+                symbols.insert(name.clone(), c);
+                taken.push(c);
+            }
+        }
+    }
+    for name in names {
+        if symbols.contains_key(&name) {
+            continue;
+        }
+        let initial = name.chars().next().map(|c| c.to_ascii_uppercase());
+        let symbol = initial
+            .into_iter()
+            .chain('A'..='Z')
+            .find(|c| c.is_ascii_uppercase() && !taken.contains(c));
+        if let Some(symbol) = symbol {
+            symbols.insert(name, symbol);
+            taken.push(symbol);
+        } else {
+            bail!("Ran out of symbols for dimension {} ({} are taken)", name, taken.len());
+        }
+    }
+    Ok(symbols)
+}
+
+pub type OpBuilder =
+    fn(&ParsingContext, node: &pb::NodeProto) -> TractResult<(Box<dyn InferenceOp>, Vec<String>)>;
+
+/// Operator builders, by domain and operator name.
+///
+/// An operator can have several builders, each one valid from the operator
+/// set version it is registered with until the next one, or until the last
+/// version it is known to be valid for.
 #[derive(Clone, Default)]
-pub struct OnnxOpRegister(
-    pub  HashMap<
-        String,
-        fn(
-            &ParsingContext,
-            node: &pb::NodeProto,
-        ) -> TractResult<(Box<dyn InferenceOp>, Vec<String>)>,
-    >,
-);
+pub struct OnnxOpRegister(pub HashMap<(String, String), Vec<(i64, Option<i64>, OpBuilder)>>);
+
+/// The default domain can be named "" or "ai.onnx".
+pub fn canonical_domain(domain: &str) -> &str {
+    if domain == "ai.onnx" {
+        ""
+    } else {
+        domain
+    }
+}
+
+/// Operator name, prefixed by its domain if it is not the default one.
+pub fn qualified_op_name(domain: &str, op: &str) -> String {
+    let domain = canonical_domain(domain);
+    if domain.is_empty() {
+        op.to_string()
+    } else {
+        format!("{}.{}", domain, op)
+    }
+}
+
+/// The operator set version `model` imports for `domain`.
+pub fn opset_version(model: &pb::ModelProto, domain: &str) -> Option<i64> {
+    let domain = canonical_domain(domain);
+    model
+        .opset_import
+        .iter()
+        .find(|import| canonical_domain(&import.domain) == domain)
+        .map(|import| import.version)
+}
 
 impl OnnxOpRegister {
-    pub fn insert(
+    /// Register an operator of the default domain, for all operator sets.
+    pub fn insert(&mut self, s: &'static str, builder: OpBuilder) {
+        self.insert_since("", s, 1, builder)
+    }
+
+    /// Register an operator of `domain`, from operator set `since_version`
+    /// on.
+    pub fn insert_since(&mut self, domain: &str, s: &str, since_version: i64, builder: OpBuilder) {
+        self.register(domain, s, since_version, None, builder)
+    }
+
+    /// Register an operator of `domain`, from operator set `since_version`
+    /// up to `until_version` included. Later versions are rejected until
+    /// a builder is registered for them.
+    pub fn insert_until(
+        &mut self,
+        domain: &str,
+        s: &str,
+        since_version: i64,
+        until_version: i64,
+        builder: OpBuilder,
+    ) {
+        self.register(domain, s, since_version, Some(until_version), builder)
+    }
+
+    fn register(
         &mut self,
-        s: &'static str,
-        builder: fn(
-            &ParsingContext,
-            node: &pb::NodeProto,
-        ) -> TractResult<(Box<dyn InferenceOp>, Vec<String>)>,
+        domain: &str,
+        s: &str,
+        since_version: i64,
+        until_version: Option<i64>,
+        builder: OpBuilder,
     ) {
-        self.0.insert(s.into(), builder);
+        let versions =
+            self.0.entry((canonical_domain(domain).to_string(), s.to_string())).or_default();
+        versions.retain(|(v, _, _)| *v != since_version);
+        versions.push((since_version, until_version, builder));
+        versions.sort_by_key(|(v, _, _)| *v);
+    }
+
+    /// Find the builder for an operator in the given operator set version.
+    ///
+    /// Returns None if the operator is unknown, and an error if it is only
+    /// known in other versions.
+    pub fn lookup(&self, domain: &str, s: &str, version: i64) -> TractResult<Option<OpBuilder>> {
+        let domain = canonical_domain(domain);
+        let versions = match self.0.get(&(domain.to_string(), s.to_string())) {
+            Some(versions) => versions,
+            None => return Ok(None),
+        };
+        match versions.iter().rev().find(|(since, _, _)| *since <= version) {
+            Some((_, Some(until), _)) if *until < version => bail!(
+                "Unsupported operator {} for operator set {} (supported up to version {})",
+                qualified_op_name(domain, s),
+                version,
+                until
+            ),
+            Some((_, _, builder)) => Ok(Some(*builder)),
+            None => bail!(
+                "Unsupported operator {} for operator set {} (supported from version {})",
+                qualified_op_name(domain, s),
+                version,
+                versions[0].0
+            ),
+        }
+    }
+
+    /// Operator names, prefixed by their domain if it is not the default one.
+    pub fn names(&self) -> Vec<String> {
+        let mut names: Vec<String> =
+            self.0.keys().map(|(domain, op)| qualified_op_name(domain, op)).collect();
+        names.sort();
+        names
     }
 }
 
 #[derive(Clone, Default)]
 pub struct Onnx {
     pub op_register: OnnxOpRegister,
+    /// Named dimension (`dim_param`) standing for the streaming dimension S.
+    pub streaming_dim_param: Option<String>,
+    /// Fail to parse models using operators that can not be built, instead
+    /// of leaving them unimplemented until the model is typed.
+    pub reject_unsupported_ops: bool,
 }
 
 impl Onnx {
+    /// Map the named dimension (`dim_param`) `name` to the streaming
+    /// dimension S.
+    ///
+    /// Without it, named dimensions are mapped to other symbols, even if
+    /// one is named "S".
+    pub fn with_streaming_dim_param(self, name: impl Into<String>) -> Onnx {
+        Onnx { streaming_dim_param: Some(name.into()), ..self }
+    }
+
+    /// Fail to parse models using operators that can not be built, listing
+    /// them all (see `unsupported_ops`).
+    ///
+    /// Without it, these operators are parsed as unimplemented, so the model
+    /// can still be inspected, and only fails to be typed, with an error
+    /// listing them all too.
+    pub fn with_unsupported_ops_rejected(self) -> Onnx {
+        Onnx { reject_unsupported_ops: true, ..self }
+    }
+
+    /// Register a builder for operator `op` of `domain`, for all versions of
+    /// the domain operator set.
+    ///
+    /// This is the way to support operators from private domains: `builder`
+    /// gets the node and returns an `InferenceOp` implementation (and the
+    /// names of the outer scope values it needs), usually an
+    /// `InferenceRulesOp` translating itself to a `TypedOp` in `to_typed`.
+    /// See the `onnx-custom-op` example.
+    pub fn register_op(&mut self, domain: &str, op: &str, builder: OpBuilder) {
+        self.op_register.insert_since(domain, op, 1, builder)
+    }
+
+    /// List the operators of the model, subgraphs included, that can not be
+    /// built in the operator set version the model imports for their domain.
+    pub fn unsupported_ops(&self, proto: &pb::ModelProto) -> Vec<String> {
+        let mut unsupported = vec![];
+        if let Some(graph) = proto.graph.as_ref() {
+            self.collect_unsupported_ops(proto, graph, &mut unsupported);
+        }
+        unsupported
+    }
+
+    fn collect_unsupported_ops(
+        &self,
+        proto: &pb::ModelProto,
+        graph: &pb::GraphProto,
+        unsupported: &mut Vec<String>,
+    ) {
+        for node in graph.node.iter() {
+            let name = qualified_op_name(&node.domain, &node.op_type);
+            let issue = match opset_version(proto, &node.domain) {
+                None => Some(format!("{} (domain not imported)", name)),
+                Some(version) => {
+                    match self.op_register.lookup(&node.domain, &node.op_type, version) {
+                        Ok(Some(_)) => None,
+                        _ => Some(format!("{} in operator set {}", name, version)),
+                    }
+                }
+            };
+            if let Some(issue) = issue {
+                if !unsupported.contains(&issue) {
+                    unsupported.push(issue);
+                }
+            }
+            for attr in node.attribute.iter() {
+                for g in attr.g.iter().chain(attr.graphs.iter()) {
+                    self.collect_unsupported_ops(proto, g, unsupported)
+                }
+            }
+        }
+    }
+
     pub fn parse(&self, proto: &pb::ModelProto) -> TractResult<ParseResult> {
+        self.parse_with_model_dir(proto, None)
+    }
+
+    /// Parse a model, resolving the tensors stored as external data relative
+    /// to `model_dir`.
+    pub fn parse_with_model_dir(
+        &self,
+        proto: &pb::ModelProto,
+        model_dir: Option<&path::Path>,
+    ) -> TractResult<ParseResult> {
         let onnx_operator_set_version =
-            proto.opset_import.iter().find(|import| import.domain == "").unwrap().version;
+            opset_version(proto, "").ok_or("Model does not import the default operator set")?;
+        if self.reject_unsupported_ops {
+            let unsupported = self.unsupported_ops(proto);
+            if unsupported.len() > 0 {
+                bail!("Unsupported operators: {}", unsupported.join(", "))
+            }
+        }
         let graph = &proto.graph;
         debug!("ONNX operator set version: {:?}", onnx_operator_set_version);
-        if onnx_operator_set_version < 9 || onnx_operator_set_version > 10 {
-            warn!("ONNX operator for your model is {}, tract is tested against \
-                  operator set 9 and 10 only. Your model may still work so this is not a hard fail.",
-                  onnx_operator_set_version);
-        }
+        let symbols =
+            symbols_for_dim_params(graph.as_ref().unwrap(), self.streaming_dim_param.as_deref())?;
+        debug!("Symbols for named dimensions: {:?}", symbols);
         let ctx = ParsingContext {
             framework: self,
             model: proto,
             parent_graphs: vec![],
             onnx_operator_set_version,
+            symbols: &symbols,
+            model_dir,
         };
         ctx.parse_graph(graph.as_ref().unwrap())
     }
@@ -230,4 +521,317 @@ impl Framework<pb::ModelProto> for Onnx {
         }
         Ok(model)
     }
+
+    /// Tensors stored as external data are looked for next to the model.
+    ///
+    /// The model file is memory mapped, and its big initializers are read
+    /// in place instead of being copied (unless their offset in the file is
+    /// not aligned for their type).
+    fn model_for_path(&self, p: impl AsRef<path::Path>) -> TractResult<InferenceModel> {
+        let proto = match p.as_ref().file_name().and_then(|name| name.to_str()) {
+            Some(location) => {
+                // big initializers become external data pointing back to the
+                // model file, which only makes sense relative to its directory
+                let mmap = Mmap::open(p.as_ref())?;
+                let bytes = crate::tensor::externalize_initializers(mmap.as_bytes(), location)?;
+                crate::pb::ModelProto::decode(bytes::Bytes::from(bytes))
+                    .map_err(|e| format!("{:?}", e))?
+            }
+            None => self.proto_model_for_path(p.as_ref())?,
+        };
+        let ParseResult { model, unresolved_inputs, .. } =
+            self.parse_with_model_dir(&proto, p.as_ref().parent())?;
+        if unresolved_inputs.len() > 0 {
+            bail!("Could not resolve inputs at top-level: {:?}", unresolved_inputs)
+        }
+        Ok(model)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_util::*;
+
+    fn input_dims(onnx: &Onnx) -> TVec<TDim> {
+        let proto = model(
+            graph(
+                vec![node("Identity", &["x"], &["y"], vec![])],
+                vec![value("x", f32::datum_type(), &["S", "seq", "batch"])],
+                vec![value("y", f32::datum_type(), &["S", "seq", "batch"])],
+                vec![],
+            ),
+            9,
+        );
+        let model = onnx.model_for_proto_model(&proto).unwrap().into_typed().unwrap();
+        model.input_fact(0).unwrap().shape.iter().collect()
+    }
+
+    #[test]
+    fn lookup_picks_the_builder_for_the_operator_set() {
+        fn v1(
+            _: &ParsingContext,
+            _: &pb::NodeProto,
+        ) -> TractResult<(Box<dyn InferenceOp>, Vec<String>)> {
+            bail!("v1")
+        }
+        fn v5(
+            _: &ParsingContext,
+            _: &pb::NodeProto,
+        ) -> TractResult<(Box<dyn InferenceOp>, Vec<String>)> {
+            bail!("v5")
+        }
+        let mut reg = OnnxOpRegister::default();
+        reg.insert("Foo", v1);
+        reg.insert_since("ai.onnx", "Foo", 5, v5);
+        let which = |version: i64| reg.lookup("", "Foo", version).unwrap().unwrap() as usize;
+        assert_eq!(which(1), v1 as usize);
+        assert_eq!(which(4), v1 as usize);
+        assert_eq!(which(5), v5 as usize);
+        assert_eq!(which(13), v5 as usize);
+        assert!(reg.lookup("", "Bar", 13).unwrap().is_none());
+        reg.insert_until("", "Bar", 1, 12, v1);
+        assert!(reg.lookup("", "Bar", 12).unwrap().is_some());
+        let err = reg.lookup("", "Bar", 13).err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            "Unsupported operator Bar for operator set 13 (supported up to version 12)"
+        );
+        assert!(reg.lookup("com.example", "Foo", 13).unwrap().is_none());
+    }
+
+    #[test]
+    fn lookup_before_first_version() {
+        let onnx = crate::onnx();
+        assert!(onnx.op_register.lookup("", "Einsum", 12).unwrap().is_some());
+        let err = onnx.op_register.lookup("ai.onnx", "Einsum", 11).err().unwrap();
+        assert_eq!(
+            err.to_string(),
+            "Unsupported operator Einsum for operator set 11 (supported from version 12)"
+        );
+    }
+
+    fn neg(
+        _: &ParsingContext,
+        _: &pb::NodeProto,
+    ) -> TractResult<(Box<dyn InferenceOp>, Vec<String>)> {
+        Ok((Box::new(tract_hir::ops::math::neg()), vec![]))
+    }
+
+    /// Einsum(x), too recent for the operator set 11 the model imports, and
+    /// If cond then Foo(x) else Bar(x), Foo and Bar from `domain`.
+    fn if_with_custom_ops(domain: &str) -> pb::ModelProto {
+        let branch = |op: &str, out: &str| {
+            let node =
+                pb::NodeProto { domain: domain.to_string(), ..node(op, &["x"], &[out], vec![]) };
+            graph(vec![node], vec![], vec![value(out, f32::datum_type(), &["2"])], vec![])
+        };
+        let mut proto = model(
+            graph(
+                vec![
+                    node("Einsum", &["x"], &["e"], vec![attr_string("equation", "i->i")]),
+                    node(
+                        "If",
+                        &["cond"],
+                        &["y"],
+                        vec![
+                            attr_graph("then_branch", branch("Foo", "t")),
+                            attr_graph("else_branch", branch("Bar", "f")),
+                        ],
+                    ),
+                ],
+                vec![value("cond", bool::datum_type(), &[]), value("x", f32::datum_type(), &["2"])],
+                vec![value("y", f32::datum_type(), &["2"]), value("e", f32::datum_type(), &["2"])],
+                vec![],
+            ),
+            11,
+        );
+        if domain != "" {
+            proto
+                .opset_import
+                .push(pb::OperatorSetIdProto { domain: domain.to_string(), version: 1 })
+        }
+        proto
+    }
+
+    #[test]
+    fn unsupported_ops_in_subgraphs() {
+        let onnx = crate::onnx();
+        assert_eq!(
+            onnx.unsupported_ops(&if_with_custom_ops("")),
+            vec!["Einsum in operator set 11", "Foo in operator set 11", "Bar in operator set 11"]
+        );
+        assert_eq!(
+            onnx.unsupported_ops(&if_with_custom_ops("com.example")),
+            vec![
+                "Einsum in operator set 11",
+                "com.example.Foo in operator set 1",
+                "com.example.Bar in operator set 1"
+            ]
+        );
+        let mut proto = if_with_custom_ops("com.example");
+        proto.opset_import.pop();
+        assert_eq!(
+            onnx.unsupported_ops(&proto),
+            vec![
+                "Einsum in operator set 11",
+                "com.example.Foo (domain not imported)",
+                "com.example.Bar (domain not imported)"
+            ]
+        );
+    }
+
+    #[test]
+    fn register_op_in_subgraph() {
+        let mut onnx = crate::onnx();
+        onnx.register_op("com.example", "Foo", neg);
+        onnx.register_op("com.example", "Bar", |_, _| {
+            Ok((Box::new(tract_hir::ops::identity::Identity::default()), vec![]))
+        });
+        let mut proto = if_with_custom_ops("com.example");
+        let graph = proto.graph.as_mut().unwrap();
+        graph.node.remove(0);
+        graph.output.pop();
+        assert!(onnx.unsupported_ops(&proto).is_empty());
+        let model = onnx.model_for_proto_model(&proto).unwrap().into_optimized().unwrap();
+        let plan = SimplePlan::new(model).unwrap();
+        let y = plan.run(tvec!(tensor0(true), tensor1(&[1f32, 2.]))).unwrap();
+        assert_eq!(*y[0], tensor1(&[-1f32, -2.]));
+        let y = plan.run(tvec!(tensor0(false), tensor1(&[1f32, 2.]))).unwrap();
+        assert_eq!(*y[0], tensor1(&[1f32, 2.]));
+        assert_eq!(crate::onnx().unsupported_ops(&proto).len(), 2);
+    }
+
+    #[test]
+    fn unsupported_ops_are_parsed_as_unimplemented() {
+        let mut onnx = crate::onnx();
+        onnx.register_op("", "Foo", neg);
+        onnx.register_op("", "Bar", neg);
+        let proto = if_with_custom_ops("");
+        let model = onnx.model_for_proto_model(&proto).unwrap();
+        assert_eq!(model.node(2).op().name(), "Unimplemented(Einsum)");
+        let err = model.into_typed().err().unwrap();
+        let expected =
+            "Unsupported operator Einsum for operator set 11 (supported from version 12)";
+        assert!(err.iter().any(|e| e.to_string().contains(expected)), "{:?}", err);
+        // all of them are reported when typing the model
+        let model = crate::onnx().model_for_proto_model(&proto).unwrap();
+        let err = model.into_typed().err().unwrap().to_string();
+        assert!(err.contains(expected), "{}", err);
+        assert!(err.contains("Unsupported operator Foo for operator set 11"), "{}", err);
+        assert!(err.contains("Unsupported operator Bar for operator set 11"), "{}", err);
+        let err = onnx.with_unsupported_ops_rejected().model_for_proto_model(&proto).err().unwrap();
+        assert_eq!(err.to_string(), "Unsupported operators: Einsum in operator set 11");
+    }
+
+    #[test]
+    fn dim_params_are_not_streaming_by_default() {
+        let dims = input_dims(&crate::onnx());
+        assert!(dims.iter().all(|d| d.symbols().len() == 1 && *d != TDim::s()));
+        assert_ne!(dims[0], dims[1]);
+        assert_ne!(dims[1], dims[2]);
+        assert_ne!(dims[0], dims[2]);
+    }
+
+    #[test]
+    fn streaming_dim_param() {
+        let dims = input_dims(&crate::onnx().with_streaming_dim_param("seq"));
+        assert_eq!(dims[1], TDim::s());
+        assert_ne!(dims[0], TDim::s());
+        assert_ne!(dims[0], dims[2]);
+    }
+
+    #[test]
+    fn running_out_of_symbols_is_an_error() {
+        let names: Vec<String> = (0..26).map(|i| format!("dim{}", i)).collect();
+        let names: Vec<&str> = names.iter().map(|s| s.as_str()).collect();
+        let proto = model(
+            graph(
+                vec![node("Identity", &["x"], &["y"], vec![])],
+                vec![value("x", f32::datum_type(), &names)],
+                vec![value("y", f32::datum_type(), &names)],
+                vec![],
+            ),
+            9,
+        );
+        let err = crate::onnx().model_for_proto_model(&proto).err().unwrap();
+        assert!(err.to_string().contains("Ran out of symbols for dimension dim25"), "{}", err);
+        let proto = model(
+            graph(
+                vec![node("Identity", &["x"], &["y"], vec![])],
+                vec![value("x", f32::datum_type(), &names[..25])],
+                vec![value("y", f32::datum_type(), &names[..25])],
+                vec![],
+            ),
+            9,
+        );
+        assert!(crate::onnx().model_for_proto_model(&proto).is_ok());
+    }
+
+    #[test]
+    fn external_initializer_next_to_model() {
+        let dir = std::env::temp_dir().join(format!("tract-onnx-model-dir-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let data: Vec<u8> = [1f32, 2.].iter().flat_map(|f| f.to_le_bytes().to_vec()).collect();
+        std::fs::write(dir.join("w.bin"), data).unwrap();
+        let entry = |key: &str, value: &str| pb::StringStringEntryProto {
+            key: key.to_string(),
+            value: value.to_string(),
+        };
+        let w = pb::TensorProto {
+            name: "w".to_string(),
+            dims: vec![2],
+            data_type: pb::tensor_proto::DataType::Float as i32,
+            external_data: vec![entry("location", "w.bin")],
+            data_location: pb::tensor_proto::DataLocation::External as i32,
+            ..pb::TensorProto::default()
+        };
+        let proto = model(
+            graph(
+                vec![node("Add", &["x", "w"], &["y"], vec![])],
+                vec![value("x", f32::datum_type(), &["2"])],
+                vec![value("y", f32::datum_type(), &["2"])],
+                vec![w],
+            ),
+            9,
+        );
+        let mut bytes = vec![];
+        proto.encode(&mut bytes).unwrap();
+        std::fs::write(dir.join("model.onnx"), bytes).unwrap();
+        let model = crate::onnx().model_for_path(dir.join("model.onnx")).unwrap();
+        let y = SimplePlan::new(model.into_optimized().unwrap())
+            .unwrap()
+            .run(tvec!(tensor1(&[10f32, 20.])))
+            .unwrap();
+        assert_eq!(*y[0], tensor1(&[11f32, 22.]));
+        assert!(crate::onnx().model_for_proto_model(&proto).is_err());
+    }
+
+    #[test]
+    fn big_initializers_from_path() {
+        let path = std::env::temp_dir().join(format!("tract-onnx-big-{}.onnx", std::process::id()));
+        let w = tract_ndarray::Array1::from_shape_fn(2048, |i| i as f32).into_tensor();
+        let proto = model(
+            graph(
+                vec![node("Add", &["x", "w"], &["y"], vec![])],
+                vec![value("x", f32::datum_type(), &["2048"])],
+                vec![value("y", f32::datum_type(), &["2048"])],
+                vec![raw_tensor("w", &w)],
+            ),
+            9,
+        );
+        let mut bytes = vec![];
+        proto.encode(&mut bytes).unwrap();
+        std::fs::write(&path, bytes).unwrap();
+        let x = tract_ndarray::Array1::from_elem(2048, 1f32).into_tensor();
+        let expected = tract_ndarray::Array1::from_shape_fn(2048, |i| i as f32 + 1.).into_tensor();
+        let onnx = crate::onnx();
+        for model in vec![
+            onnx.model_for_path(&path).unwrap(),
+            onnx.model_for_proto_model(&onnx.proto_model_for_path(&path).unwrap()).unwrap(),
+        ] {
+            let plan = SimplePlan::new(model.into_optimized().unwrap()).unwrap();
+            assert_eq!(*plan.run(tvec!(x.clone())).unwrap()[0], expected);
+        }
+    }
 }
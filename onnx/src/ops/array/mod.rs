@@ -1,10 +1,12 @@
 mod compress;
 mod slice;
+mod topk;
 
 use tract_hir::internal::*;
 use tract_hir::ops::array;
 
-use crate::model::{OnnxOpRegister, ParsingContext};
+use crate::model::{optional_outputs, OnnxOpRegister, ParsingContext};
+use crate::ops::const_inputs::ConstInputs;
 use crate::pb::*;
 use tract_num_traits::AsPrimitive;
 
@@ -17,16 +19,33 @@ pub fn register_all_ops(reg: &mut OnnxOpRegister) {
     reg.insert("EyeLike", eye_like);
     reg.insert("Flatten", flatten);
     reg.insert("Gather", gather);
+    reg.insert("NonZero", |_, _| Ok((Box::new(array::NonZero::default()), vec![])));
     reg.insert("Pad", pad);
+    reg.insert_until("", "Pad", 11, 17, |_, node| {
+        Ok((Box::new(ConstInputs::new(node, 1, 2, pad11)), vec![]))
+    });
     reg.insert("Reshape", |_, _| Ok((Box::new(array::Reshape::default()), vec![])));
     reg.insert("Shape", |_, _| Ok((Box::new(array::Shape::new(DatumType::I64)), vec![])));
     reg.insert("Size", |_, _| Ok((Box::new(array::Size::new(DatumType::I64)), vec![])));
     reg.insert("Transpose", transpose);
     reg.insert("Tile", |_, _| Ok((Box::new(array::Tile::default()), vec![])));
-    reg.insert("Slice", slice::slice);
+    reg.insert("TopK", topk::topk1);
+    reg.insert_since("", "TopK", 10, topk::topk);
+    reg.insert("Unique", unique);
+    reg.insert("Slice", slice::slice1);
+    reg.insert_since("", "Slice", 10, slice::slice10);
     reg.insert("Split", split);
+    reg.insert_until("", "Split", 13, 17, |_, node| {
+        Ok((Box::new(ConstInputs::new(node, 1, 1, split13)), vec![]))
+    });
     reg.insert("Squeeze", squeeze);
+    reg.insert_since("", "Squeeze", 13, |_, node| {
+        Ok((Box::new(ConstInputs::new(node, 1, 1, squeeze13)), vec![]))
+    });
     reg.insert("Unsqueeze", unsqueeze);
+    reg.insert_since("", "Unsqueeze", 13, |_, node| {
+        Ok((Box::new(ConstInputs::new(node, 1, 1, unsqueeze13)), vec![]))
+    });
 }
 
 pub fn concat(
@@ -37,6 +56,16 @@ pub fn concat(
     Ok((Box::new(array::Concat::new(axis)), vec![]))
 }
 
+pub fn unique(
+    _ctx: &ParsingContext,
+    node: &NodeProto,
+) -> TractResult<(Box<dyn InferenceOp>, Vec<String>)> {
+    let axis = node.get_attr_opt::<i64>("axis")?;
+    let sorted = node.get_attr_opt("sorted")?.unwrap_or(1i64) == 1;
+    let optional_outputs = optional_outputs(node).skip(1).take(3).collect();
+    Ok((Box::new(array::Unique::new(axis, sorted, optional_outputs)), vec![]))
+}
+
 pub fn make_const<T>(shape: &[usize], v: f32) -> TractResult<Arc<Tensor>>
 where
     T: Copy + Datum,
@@ -96,12 +125,8 @@ pub fn gather(
     Ok((Box::new(array::Gather::new(axis)), vec![]))
 }
 
-pub fn pad(
-    _ctx: &ParsingContext,
-    node: &NodeProto,
-) -> TractResult<(Box<dyn InferenceOp>, Vec<String>)> {
-    let value: f32 = node.get_attr_opt("value")?.unwrap_or(0.0);
-    let mode = match node.get_attr_opt("mode")? {
+fn pad_mode(node: &NodeProto, value: Arc<Tensor>) -> TractResult<array::PadMode> {
+    Ok(match node.get_attr_opt("mode")? {
         None | Some("constant") => None,
         Some(mode) => node.check_value(
             "mode",
@@ -112,11 +137,29 @@ pub fn pad(
             },
         )?,
     }
-    .unwrap_or_else(|| array::PadMode::Constant(Arc::new(value.into())));
-    let pads = node.get_attr_tvec("pads")?;
+    .unwrap_or_else(|| array::PadMode::Constant(value)))
+}
+
+fn pads_by_axis(pads: &[usize]) -> Vec<(usize, usize)> {
     let rank = pads.len() / 2;
-    let pads = (0..rank).map(|ax| (pads[ax], pads[ax + rank])).collect();
-    Ok((Box::new(array::Pad::new(pads, mode)), vec![]))
+    (0..rank).map(|ax| (pads[ax], pads[ax + rank])).collect()
+}
+
+pub fn pad(
+    _ctx: &ParsingContext,
+    node: &NodeProto,
+) -> TractResult<(Box<dyn InferenceOp>, Vec<String>)> {
+    let value: f32 = node.get_attr_opt("value")?.unwrap_or(0.0);
+    let mode = pad_mode(node, Arc::new(value.into()))?;
+    let pads: TVec<usize> = node.get_attr_tvec("pads")?;
+    Ok((Box::new(array::Pad::new(pads_by_axis(&pads), mode)), vec![]))
+}
+
+/// Pad from operator set 11, with pads and constant value as inputs.
+fn pad11(node: &NodeProto, params: &[Option<Arc<Tensor>>]) -> TractResult<Box<dyn InferenceOp>> {
+    let pads = usize_input(&params[0])?.ok_or("Pad requires pads")?;
+    let value = params[1].clone().unwrap_or_else(|| Arc::new(0f32.into()));
+    Ok(Box::new(array::Pad::new(pads_by_axis(&pads), pad_mode(node, value)?)))
 }
 
 pub fn split(
@@ -128,6 +171,33 @@ pub fn split(
     Ok((Box::new(array::Split::new(axis, node.output.len(), split)), vec![]))
 }
 
+fn usize_input(input: &Option<Arc<Tensor>>) -> TractResult<Option<Vec<usize>>> {
+    input
+        .as_ref()
+        .map(|input| {
+            input
+                .cast_to::<i64>()?
+                .as_slice::<i64>()?
+                .iter()
+                .map(|&v| {
+                    if v < 0 {
+                        bail!("Unsupported negative value {}", v)
+                    }
+                    Ok(v as usize)
+                })
+                .collect()
+        })
+        .transpose()
+}
+
+/// Split from operator set 13, with the split sizes as an input.
+fn split13(node: &NodeProto, params: &[Option<Arc<Tensor>>]) -> TractResult<Box<dyn InferenceOp>> {
+    let axis = node.get_attr_opt("axis")?.unwrap_or(0);
+    let split = usize_input(&params[0])?;
+    let outputs = node.output.iter().filter(|s| !s.is_empty()).count();
+    Ok(Box::new(array::Split::new(axis, outputs, split)))
+}
+
 pub fn squeeze(
     _ctx: &ParsingContext,
     node: &NodeProto,
@@ -136,6 +206,14 @@ pub fn squeeze(
     Ok((Box::new(array::Squeeze::new(axes)), vec![]))
 }
 
+/// Squeeze from operator set 13, with the axes as an input.
+fn squeeze13(
+    _node: &NodeProto,
+    params: &[Option<Arc<Tensor>>],
+) -> TractResult<Box<dyn InferenceOp>> {
+    Ok(Box::new(array::Squeeze::new(usize_input(&params[0])?)))
+}
+
 pub fn transpose(
     _ctx: &ParsingContext,
     node: &NodeProto,
@@ -151,3 +229,139 @@ pub fn unsqueeze(
     let axes = node.get_attr_vec("axes")?;
     Ok((Box::new(array::AddDims::new(axes)), vec![]))
 }
+
+/// Unsqueeze from operator set 13, with the axes as an input.
+fn unsqueeze13(
+    _node: &NodeProto,
+    params: &[Option<Arc<Tensor>>],
+) -> TractResult<Box<dyn InferenceOp>> {
+    let axes = usize_input(&params[0])?.ok_or("Unsqueeze requires axes")?;
+    Ok(Box::new(array::AddDims::new(axes)))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_util::*;
+    use tract_hir::internal::*;
+
+    #[test]
+    fn pad_from_opset_11() {
+        let pad10 =
+            node("Pad", &["x"], &["y"], vec![attr_ints("pads", &[1, 2]), attr_float("value", 7.)]);
+        let y = run_node(10, pad10, &[2], vec![]).unwrap();
+        assert_eq!(*y[0], tensor1(&[7f32, 1., 2., 7., 7.]));
+        let pad11 = node("Pad", &["x", "pads", "value"], &["y"], vec![]);
+        let inits = vec![tensor("pads", &tensor1(&[1i64, 2])), tensor("value", &tensor0(7f32))];
+        let y = run_node(11, pad11, &[2], inits).unwrap();
+        assert_eq!(*y[0], tensor1(&[7f32, 1., 2., 7., 7.]));
+        let zero_pad = node("Pad", &["x", "pads"], &["y"], vec![]);
+        let y = run_node(11, zero_pad, &[2], vec![tensor("pads", &tensor1(&[0i64, 1]))]).unwrap();
+        assert_eq!(*y[0], tensor1(&[1f32, 2., 0.]));
+        let attrs_in_11 = node("Pad", &["x"], &["y"], vec![attr_ints("pads", &[1, 2])]);
+        assert!(run_node(11, attrs_in_11, &[2], vec![]).is_err());
+    }
+
+    #[test]
+    fn split_from_opset_13() {
+        let split12 = node("Split", &["x"], &["a", "b"], vec![attr_ints("split", &[1, 2])]);
+        let y = run_node(12, split12, &[3], vec![]).unwrap();
+        assert_eq!(*y[0], tensor1(&[1f32]));
+        assert_eq!(*y[1], tensor1(&[2f32, 3.]));
+        let split13 = node("Split", &["x", "split"], &["a", "b"], vec![]);
+        let y = run_node(13, split13, &[3], vec![tensor("split", &tensor1(&[1i64, 2]))]).unwrap();
+        assert_eq!(*y[0], tensor1(&[1f32]));
+        assert_eq!(*y[1], tensor1(&[2f32, 3.]));
+        let even = node("Split", &["x"], &["a", "b"], vec![]);
+        let y = run_node(13, even, &[4], vec![]).unwrap();
+        assert_eq!(*y[1], tensor1(&[3f32, 4.]));
+    }
+
+    #[test]
+    fn squeeze_from_opset_13() {
+        let squeeze12 = node("Squeeze", &["x"], &["y"], vec![attr_ints("axes", &[0])]);
+        let y = run_node(12, squeeze12, &[1, 2], vec![]).unwrap();
+        assert_eq!(*y[0], tensor1(&[1f32, 2.]));
+        let squeeze13 = node("Squeeze", &["x", "axes"], &["y"], vec![]);
+        let y = run_node(13, squeeze13, &[1, 2], vec![tensor("axes", &tensor1(&[0i64]))]).unwrap();
+        assert_eq!(*y[0], tensor1(&[1f32, 2.]));
+        let all = node("Squeeze", &["x"], &["y"], vec![]);
+        let y = run_node(13, all, &[1, 2, 1], vec![]).unwrap();
+        assert_eq!(*y[0], tensor1(&[1f32, 2.]));
+    }
+
+    #[test]
+    fn unsqueeze_from_opset_13() {
+        let unsqueeze12 = node("Unsqueeze", &["x"], &["y"], vec![attr_ints("axes", &[0])]);
+        let y = run_node(12, unsqueeze12, &[2], vec![]).unwrap();
+        assert_eq!(*y[0], tensor2(&[[1f32, 2.]]));
+        let unsqueeze13 = node("Unsqueeze", &["x", "axes"], &["y"], vec![]);
+        let y = run_node(13, unsqueeze13, &[2], vec![tensor("axes", &tensor1(&[1i64]))]).unwrap();
+        assert_eq!(*y[0], tensor2(&[[1f32], [2.]]));
+    }
+
+    #[test]
+    fn unique_negative_axis() {
+        let unique = node("Unique", &["x"], &["y", "", "inverse"], vec![attr_int("axis", -1)]);
+        let y = run_model(
+            &model(
+                graph(
+                    vec![unique],
+                    vec![value("x", f32::datum_type(), &["2", "3"])],
+                    vec![
+                        value_of_any_shape("y", f32::datum_type()),
+                        value_of_any_shape("inverse", i64::datum_type()),
+                    ],
+                    vec![],
+                ),
+                11,
+            ),
+            tvec!(tensor2(&[[1f32, 2., 1.], [3., 4., 3.]])),
+        )
+        .unwrap();
+        assert_eq!(*y[0], tensor2(&[[1f32, 2.], [3., 4.]]));
+        assert_eq!(*y[1], tensor1(&[0i64, 1, 0]));
+    }
+
+    #[test]
+    fn data_dependent_dims_through_native_format() {
+        let proto = model(
+            graph(
+                vec![
+                    node("NonZero", &["x"], &["nz"], vec![]),
+                    node("Compress", &["x", "c"], &["y"], vec![attr_int("axis", 0)]),
+                ],
+                vec![value("x", f32::datum_type(), &["4"]), value("c", bool::datum_type(), &["4"])],
+                vec![
+                    value_of_any_shape("nz", i64::datum_type()),
+                    value_of_any_shape("y", f32::datum_type()),
+                ],
+                vec![],
+            ),
+            11,
+        );
+        let typed = crate::onnx().model_for_proto_model(&proto).unwrap().into_optimized().unwrap();
+        let symbols = |model: &TypedModel| {
+            let nz = model.output_fact(0).unwrap().shape.dim(1);
+            let y = model.output_fact(1).unwrap().shape.dim(0);
+            assert!(nz.is_data_dependent() && y.is_data_dependent());
+            assert_ne!(nz, y);
+            (nz, y)
+        };
+        let before = symbols(&typed);
+        let mut buffer = vec![];
+        tract_hir::tract_core::native::save(&typed, &mut buffer).unwrap();
+        let mut loaded = tract_hir::tract_core::native::load(&mut &*buffer).unwrap();
+        assert_eq!(symbols(&loaded), before);
+        // symbols drawn after loading do not collide with the saved ones
+        let x = loaded.input_outlets().unwrap()[0];
+        let nz = tract_hir::tract_core::ops::array::NonZero::default();
+        let nz = loaded.wire_node("nz-2", nz, &[x]).unwrap();
+        let len = loaded.outlet_fact(nz[0]).unwrap().shape.dim(1);
+        assert!(len.is_data_dependent() && len != before.0 && len != before.1);
+        let x = tensor1(&[0f32, 1., 0., 2.]);
+        let c = tensor1(&[true, false, true, true]);
+        let outputs = SimplePlan::new(loaded).unwrap().run(tvec!(x, c)).unwrap();
+        assert_eq!(*outputs[0], tensor2(&[[1i64, 3]]));
+        assert_eq!(*outputs[1], tensor1(&[0f32, 0., 2.]));
+    }
+}
@@ -2,21 +2,7 @@ use crate::model::ParsingContext;
 use crate::pb::*;
 use tract_hir::internal::*;
 
-pub fn slice(
-    ctx: &ParsingContext,
-    node: &NodeProto,
-) -> TractResult<(Box<dyn InferenceOp>, Vec<String>)> {
-    let v = ctx.onnx_operator_set_version;
-    if v >= 1 && v < 10 {
-        slice1(ctx, node)
-    } else if v == 10 {
-        slice10(ctx, node)
-    } else {
-        bail!("Only Slice-1 and Slice-10 are supported")
-    }
-}
-
-fn slice1(
+pub fn slice1(
     _ctx: &ParsingContext,
     node: &NodeProto,
 ) -> TractResult<(Box<dyn InferenceOp>, Vec<String>)> {
@@ -145,7 +131,7 @@ impl InferenceRulesOp for Slice1 {
     as_op!();
 }
 
-fn slice10(
+pub fn slice10(
     _ctx: &ParsingContext,
     node: &NodeProto,
 ) -> TractResult<(Box<dyn InferenceOp>, Vec<String>)> {
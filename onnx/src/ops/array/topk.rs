@@ -0,0 +1,86 @@
+use tract_hir::internal::*;
+use tract_hir::ops::array;
+
+use crate::model::ParsingContext;
+use crate::pb::*;
+
+pub fn topk1(
+    _ctx: &ParsingContext,
+    node: &NodeProto,
+) -> TractResult<(Box<dyn InferenceOp>, Vec<String>)> {
+    let axis = node.get_attr_opt("axis")?.unwrap_or(-1);
+    let k: i64 = node.get_attr("k")?;
+    let op = TopK1 { topk: array::TopK::new(axis, true, true), k: rctensor1(&[k]) };
+    Ok((Box::new(op), vec![]))
+}
+
+pub fn topk(
+    _ctx: &ParsingContext,
+    node: &NodeProto,
+) -> TractResult<(Box<dyn InferenceOp>, Vec<String>)> {
+    let axis = node.get_attr_opt("axis")?.unwrap_or(-1);
+    let largest = node.get_attr_opt("largest")?.unwrap_or(1i64) == 1;
+    let sorted = node.get_attr_opt("sorted")?.unwrap_or(1i64) == 1;
+    Ok((Box::new(array::TopK::new(axis, largest, sorted)), vec![]))
+}
+
+/// TopK up to opset 9, with k as an attribute.
+#[derive(Debug, Clone, Hash)]
+pub struct TopK1 {
+    topk: array::TopK,
+    k: Arc<Tensor>,
+}
+
+impl Op for TopK1 {
+    fn name(&self) -> Cow<str> {
+        "onnx.TopK".into()
+    }
+
+    not_a_typed_op!();
+}
+
+impl StatelessOp for TopK1 {
+    fn eval(&self, mut inputs: TVec<Arc<Tensor>>) -> TractResult<TVec<Arc<Tensor>>> {
+        inputs.push(self.k.clone());
+        self.topk.eval(inputs)
+    }
+}
+
+impl InferenceRulesOp for TopK1 {
+    fn rules<'r, 'p: 'r, 's: 'r>(
+        &'s self,
+        s: &mut Solver<'r>,
+        inputs: &'p [TensorProxy],
+        outputs: &'p [TensorProxy],
+    ) -> InferenceResult {
+        check_input_arity(&inputs, 1)?;
+        check_output_arity(&outputs, 2)?;
+        s.equals(&outputs[0].datum_type, &inputs[0].datum_type)?;
+        s.equals(&outputs[1].datum_type, DatumType::I64)?;
+        s.equals(&outputs[0].rank, &inputs[0].rank)?;
+        s.equals(&outputs[1].rank, &inputs[0].rank)?;
+        s.given(&inputs[0].shape, move |s, mut shape| {
+            let axis = self.topk.resolved_axis(shape.len())?;
+            shape[axis] = self.k.as_slice::<i64>()?[0].to_dim();
+            s.equals(&outputs[0].shape, shape.clone())?;
+            s.equals(&outputs[1].shape, shape)
+        })
+    }
+
+    fn nboutputs(&self) -> TractResult<usize> {
+        Ok(2)
+    }
+
+    fn to_typed(
+        &self,
+        _source: &InferenceModel,
+        node: &InferenceNode,
+        target: &mut TypedModel,
+        mapping: &HashMap<OutletId, OutletId>,
+    ) -> TractResult<TVec<OutletId>> {
+        let k = target.add_const(format!("{}-k", node.name), self.k.clone())?;
+        target.wire_node(&*node.name, self.topk.clone(), &[mapping[&node.inputs[0]], k])
+    }
+
+    as_op!();
+}
@@ -5,7 +5,7 @@ use tract_hir::internal::*;
 use tract_hir::tract_core::itertools::Itertools;
 
 pub fn register_all_ops(reg: &mut OnnxOpRegister) {
-    reg.insert("CategoryMapper", category_mapper);
+    reg.insert_since("ai.onnx.ml", "CategoryMapper", 1, category_mapper);
 }
 
 fn category_mapper(
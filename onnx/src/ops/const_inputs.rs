@@ -0,0 +1,184 @@
+use crate::model::optional_inputs;
+use crate::pb::NodeProto;
+use prost::Message;
+use tract_hir::internal::*;
+
+/// Builds an operator from a node and the values of its parameter inputs.
+pub type ConstInputsBuilder =
+    fn(&NodeProto, &[Option<Arc<Tensor>>]) -> TractResult<Box<dyn InferenceOp>>;
+
+/// An operator taking some of its parameters as inputs, as many operators do
+/// since they were moved from attributes to inputs in later operator sets.
+///
+/// The parameter inputs must be constants. Once their values are known, the
+/// operator behaves like the one `build` makes from them, applied to the
+/// data inputs.
+#[derive(Debug, Clone)]
+pub struct ConstInputs {
+    name: String,
+    node: NodeProto,
+    data_inputs: usize,
+    params: TVec<Option<usize>>,
+    outputs: usize,
+    build: ConstInputsBuilder,
+    op: Option<Box<dyn InferenceOp>>,
+}
+
+impl ConstInputs {
+    /// Wrap a node with `data_inputs` leading inputs, followed by `params`
+    /// (possibly omitted) parameter inputs.
+    pub fn new(
+        node: &NodeProto,
+        data_inputs: usize,
+        params: usize,
+        build: ConstInputsBuilder,
+    ) -> ConstInputs {
+        ConstInputs {
+            name: node.op_type.clone(),
+            node: node.clone(),
+            data_inputs,
+            params: optional_inputs(node).skip(data_inputs).take(params).collect(),
+            outputs: node.output.iter().filter(|s| !s.is_empty()).count(),
+            build,
+            op: None,
+        }
+    }
+
+    fn build_op(&self, inputs: &[Option<Arc<Tensor>>]) -> TractResult<Box<dyn InferenceOp>> {
+        let values: TVec<Option<Arc<Tensor>>> =
+            self.params.iter().map(|slot| slot.and_then(|ix| inputs[ix].clone())).collect();
+        (self.build)(&self.node, &values).chain_err(|| format!("Building {}", self.name))
+    }
+}
+
+impl Hash for ConstInputs {
+    fn hash<H: std::hash::Hasher>(&self, state: &mut H) {
+        let mut node = vec![];
+        self.node.encode(&mut node).unwrap();
+        node.hash(state);
+        self.data_inputs.hash(state);
+        self.params.hash(state);
+    }
+}
+
+impl Op for ConstInputs {
+    fn name(&self) -> Cow<str> {
+        format!("onnx.{}", self.name).into()
+    }
+
+    not_a_typed_op!();
+    not_a_pulsed_op!();
+}
+
+impl StatelessOp for ConstInputs {
+    fn eval(&self, mut inputs: TVec<Arc<Tensor>>) -> TractResult<TVec<Arc<Tensor>>> {
+        let values: TVec<Option<Arc<Tensor>>> = inputs.iter().cloned().map(Some).collect();
+        let op = self.build_op(&values)?;
+        inputs.truncate(self.data_inputs);
+        op.as_stateless().ok_or_else(|| format!("{} is not stateless", op.name()))?.eval(inputs)
+    }
+}
+
+impl InferenceOp for ConstInputs {
+    fn infer_facts(
+        &mut self,
+        inputs: TVec<&InferenceFact>,
+        outputs: TVec<&InferenceFact>,
+        observed: TVec<&InferenceFact>,
+    ) -> TractResult<(TVec<InferenceFact>, TVec<InferenceFact>, TVec<InferenceFact>)> {
+        if self.op.is_none() {
+            let values: TVec<Option<Arc<Tensor>>> =
+                inputs.iter().map(|i| i.value.concretize()).collect();
+            if self.params.iter().flatten().all(|&ix| values[ix].is_some()) {
+                self.op = Some(self.build_op(&values)?);
+            }
+        }
+        let mut inputs: TVec<InferenceFact> = inputs.into_iter().cloned().collect();
+        if let Some(op) = self.op.as_mut() {
+            let (data, outputs, observed) =
+                op.infer_facts(inputs[..self.data_inputs].iter().collect(), outputs, observed)?;
+            for (ix, fact) in data.into_iter().enumerate() {
+                inputs[ix] = fact;
+            }
+            Ok((inputs, outputs, observed))
+        } else {
+            Ok((
+                inputs,
+                outputs.into_iter().cloned().collect(),
+                observed.into_iter().cloned().collect(),
+            ))
+        }
+    }
+
+    fn nboutputs(&self) -> TractResult<usize> {
+        Ok(self.outputs)
+    }
+
+    fn to_typed(
+        &self,
+        source: &InferenceModel,
+        node: &InferenceNode,
+        target: &mut TypedModel,
+        mapping: &HashMap<OutletId, OutletId>,
+    ) -> TractResult<TVec<OutletId>> {
+        let op = match self.op.as_ref() {
+            Some(op) => op,
+            None => bail!("{}: parameter inputs must be constants", self.name),
+        };
+        let mut node = node.clone();
+        node.inputs.truncate(self.data_inputs);
+        node.op = op.clone();
+        op.to_typed(source, &node, target, mapping)
+    }
+
+    as_op!();
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_util::*;
+    use tract_hir::internal::*;
+
+    fn pad(pads: Option<&Tensor>) -> crate::pb::ModelProto {
+        let mut inputs = vec![value("x", f32::datum_type(), &["2"])];
+        let mut inits = vec![];
+        match pads {
+            Some(pads) => inits.push(tensor("pads", pads)),
+            None => inputs.push(value("pads", i64::datum_type(), &["2"])),
+        }
+        model(
+            graph(
+                vec![node("Pad", &["x", "pads"], &["y"], vec![])],
+                inputs,
+                vec![value_of_any_shape("y", f32::datum_type())],
+                inits,
+            ),
+            11,
+        )
+    }
+
+    #[test]
+    fn const_params() {
+        let proto = pad(Some(&tensor1(&[1i64, 2])));
+        let y = run_model(&proto, tvec!(tensor1(&[1f32, 2.]))).unwrap();
+        assert_eq!(*y[0], tensor1(&[0f32, 1., 2., 0., 0.]));
+    }
+
+    #[test]
+    fn non_const_params() {
+        let proto = pad(None);
+        let mut model = crate::onnx().model_for_proto_model(&proto).unwrap();
+        model.analyse(false).unwrap();
+        let y = SimplePlan::new(model.clone())
+            .unwrap()
+            .run(tvec!(tensor1(&[1f32, 2.]), tensor1(&[0i64, 1])))
+            .unwrap();
+        assert_eq!(*y[0], tensor1(&[1f32, 2., 0.]));
+        let err = model.into_typed().err().unwrap();
+        assert!(
+            err.iter().any(|e| e.to_string().contains("Pad: parameter inputs must be constants")),
+            "{:?}",
+            err
+        );
+    }
+}
@@ -0,0 +1,193 @@
+use crate::model::{optional_inputs, OnnxOpRegister, ParseResult, ParsingContext};
+use crate::pb::*;
+use tract_hir::internal::*;
+
+use tract_hir::ops::control_flow::{InferenceIf, InferenceLoop};
+
+pub fn register_all_ops(reg: &mut OnnxOpRegister) {
+    reg.insert("If", if_then_else);
+    reg.insert("Loop", loop_);
+}
+
+pub fn if_then_else(
+    ctx: &ParsingContext,
+    node: &NodeProto,
+) -> TractResult<(Box<dyn InferenceOp>, Vec<String>)> {
+    let then_graph: &GraphProto = node.get_attr("then_branch")?;
+    let else_graph: &GraphProto = node.get_attr("else_branch")?;
+    let ParseResult { model: then_body, unresolved_inputs: then_closures, .. } =
+        ctx.parse_graph(then_graph)?;
+    let ParseResult { model: else_body, unresolved_inputs: else_closures, .. } =
+        ctx.parse_graph(else_graph)?;
+    // branches have no formal inputs: everything they use is a closure,
+    // wired after the condition.
+    let mut closures = then_closures.clone();
+    for closure in &else_closures {
+        if !closures.contains(closure) {
+            closures.push(closure.clone());
+        }
+    }
+    let slot = |name: &String| 1 + closures.iter().position(|c| c == name).unwrap();
+    let then_input_mapping = then_closures.iter().map(slot).collect();
+    let else_input_mapping = else_closures.iter().map(slot).collect();
+    Ok((
+        Box::new(InferenceIf::new(then_body, then_input_mapping, else_body, else_input_mapping)),
+        closures,
+    ))
+}
+
+pub fn loop_(
+    ctx: &ParsingContext,
+    node: &NodeProto,
+) -> TractResult<(Box<dyn InferenceOp>, Vec<String>)> {
+    let graph: &GraphProto = node.get_attr("body")?;
+    let ParseResult { model: body, unresolved_inputs, .. } = ctx.parse_graph(graph)?;
+    if node.input.len() < 2 {
+        bail!(
+            "Loop {} has {} inputs, expects at least the trip count and condition",
+            node.name,
+            node.input.len()
+        );
+    }
+    let mut inputs = optional_inputs(node);
+    let trip_count_slot = inputs.next().unwrap();
+    let cond_slot = inputs.next().unwrap();
+    let carried = node.input.len() - 2;
+    let state_slots: Vec<usize> = inputs.take(carried).map(|s| s.unwrap()).collect();
+    let first_closure = node.input.iter().filter(|s| !s.is_empty()).count();
+    let closure_slots = (first_closure..first_closure + unresolved_inputs.len()).collect();
+    Ok((
+        Box::new(InferenceLoop::new(body, trip_count_slot, cond_slot, state_slots, closure_slots)),
+        unresolved_inputs,
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_util::*;
+    use tract_hir::internal::*;
+
+    fn run(proto: &crate::pb::ModelProto, inputs: TVec<Tensor>) -> TVec<Arc<Tensor>> {
+        let mut model = crate::onnx().model_for_proto_model(proto).unwrap();
+        model.analyse(false).unwrap();
+        let inferred = SimplePlan::new(model.clone()).unwrap().run(inputs.clone()).unwrap();
+        let typed = SimplePlan::new(model.into_optimized().unwrap()).unwrap().run(inputs).unwrap();
+        assert_eq!(inferred, typed);
+        typed
+    }
+
+    #[test]
+    fn if_branches_with_different_shapes() {
+        let then_branch = graph(
+            vec![node("Neg", &["x"], &["t"], vec![])],
+            vec![],
+            vec![value_of_any_shape("t", f32::datum_type())],
+            vec![],
+        );
+        let else_branch = graph(
+            vec![node("Concat", &["x", "x"], &["e"], vec![attr_int("axis", 0)])],
+            vec![],
+            vec![value_of_any_shape("e", f32::datum_type())],
+            vec![],
+        );
+        let proto = model(
+            graph(
+                vec![node(
+                    "If",
+                    &["cond"],
+                    &["y"],
+                    vec![
+                        attr_graph("then_branch", then_branch),
+                        attr_graph("else_branch", else_branch),
+                    ],
+                )],
+                vec![value("cond", bool::datum_type(), &[]), value("x", f32::datum_type(), &["2"])],
+                vec![value_of_any_shape("y", f32::datum_type())],
+                vec![],
+            ),
+            11,
+        );
+        let x = tensor1(&[1f32, 2.]);
+        let result = run(&proto, tvec!(tensor0(true), x.clone()));
+        assert_eq!(*result[0], tensor1(&[-1f32, -2.]));
+        let result = run(&proto, tvec!(tensor0(false), x));
+        assert_eq!(*result[0], tensor1(&[1f32, 2., 1., 2.]));
+    }
+
+    #[test]
+    fn loop_with_scan_outputs_and_dynamic_condition() {
+        // v <- v + 1 while v < limit, collecting the successive values of v
+        let body = graph(
+            vec![
+                node("Add", &["v_in", "one"], &["v_out"], vec![]),
+                node("Less", &["v_out", "limit"], &["cond_out"], vec![]),
+                node("Identity", &["v_out"], &["scan"], vec![]),
+            ],
+            vec![
+                value("iter", i64::datum_type(), &[]),
+                value("cond_in", bool::datum_type(), &[]),
+                value("v_in", f32::datum_type(), &[]),
+            ],
+            vec![
+                value("cond_out", bool::datum_type(), &[]),
+                value("v_out", f32::datum_type(), &[]),
+                value("scan", f32::datum_type(), &[]),
+            ],
+            vec![tensor("one", &tensor0(1f32))],
+        );
+        let proto = model(
+            graph(
+                vec![node(
+                    "Loop",
+                    &["m", "cond", "v0"],
+                    &["v", "scans"],
+                    vec![attr_graph("body", body)],
+                )],
+                vec![
+                    value("m", i64::datum_type(), &[]),
+                    value("cond", bool::datum_type(), &[]),
+                    value("v0", f32::datum_type(), &[]),
+                    value("limit", f32::datum_type(), &[]),
+                ],
+                vec![
+                    value("v", f32::datum_type(), &[]),
+                    value_of_any_shape("scans", f32::datum_type()),
+                ],
+                vec![],
+            ),
+            11,
+        );
+        let inputs = tvec!(tensor0(10i64), tensor0(true), tensor0(0f32), tensor0(3f32));
+        let result = run(&proto, inputs);
+        assert_eq!(*result[0], tensor0(3f32));
+        assert_eq!(*result[1], tensor1(&[1f32, 2., 3.]));
+        let inputs = tvec!(tensor0(2i64), tensor0(true), tensor0(0f32), tensor0(3f32));
+        let result = run(&proto, inputs);
+        assert_eq!(*result[1], tensor1(&[1f32, 2.]));
+    }
+
+    #[test]
+    fn loop_without_condition_input() {
+        let body = graph(
+            vec![],
+            vec![value("iter", i64::datum_type(), &[]), value("cond_in", bool::datum_type(), &[])],
+            vec![value("cond_in", bool::datum_type(), &[])],
+            vec![],
+        );
+        let proto = model(
+            graph(
+                vec![node("Loop", &["m"], &[], vec![attr_graph("body", body)])],
+                vec![value("m", i64::datum_type(), &[])],
+                vec![],
+                vec![],
+            ),
+            11,
+        );
+        let err = crate::onnx().model_for_proto_model(&proto).unwrap_err();
+        assert!(
+            err.iter().any(|e| e.to_string().contains("expects at least the trip count")),
+            "{:?}",
+            err
+        );
+    }
+}
@@ -1,4 +1,5 @@
 use crate::model::{OnnxOpRegister, ParsingContext};
+use crate::ops::const_inputs::ConstInputs;
 use crate::pb::*;
 use tract_hir::internal::*;
 use tract_hir::ops;
@@ -21,6 +22,9 @@ pub fn register_all_ops(reg: &mut OnnxOpRegister) {
     reg.insert("Ceil", |_, _| Ok((Box::new(ops::math::ceil()), vec![])));
     reg.insert("Floor", |_, _| Ok((Box::new(ops::math::floor()), vec![])));
     reg.insert("Clip", clip);
+    reg.insert_since("", "Clip", 11, |_, node| {
+        Ok((Box::new(ConstInputs::new(node, 1, 2, clip11)), vec![]))
+    });
 
     reg.insert("Cos", |_, _| Ok((Box::new(ops::math::cos()), vec![])));
     reg.insert("Sin", |_, _| Ok((Box::new(ops::math::sin()), vec![])));
@@ -53,6 +57,7 @@ pub fn register_all_ops(reg: &mut OnnxOpRegister) {
     reg.insert("MatMulInteger", mat_mul_integer::mat_mul_integer);
     reg.insert("QLinearMatMul", mat_mul_integer::q_linear_mat_mul);
     reg.insert("Gemm", gemm);
+    reg.insert_since("", "Einsum", 12, einsum);
 }
 
 pub fn clip(
@@ -70,6 +75,18 @@ pub fn clip(
     Ok((op, vec![]))
 }
 
+/// Clip from operator set 11, with min and max as inputs.
+fn clip11(_node: &NodeProto, params: &[Option<Arc<Tensor>>]) -> TractResult<Box<dyn InferenceOp>> {
+    let min = params[0].as_ref().map(|t| t.as_ref().clone());
+    let max = params[1].as_ref().map(|t| t.as_ref().clone());
+    Ok(match (min, max) {
+        (Some(min), Some(max)) => Box::new(ops::math::scalar_min_max(max, min)),
+        (None, Some(max)) => Box::new(ops::math::scalar_min(max)),
+        (Some(min), None) => Box::new(ops::math::scalar_max(min)),
+        (None, None) => Box::new(ops::identity::Identity::default()),
+    })
+}
+
 element_wise!(erf, Erf,
     [f32] => |_, xs| {
         xs.iter_mut().for_each(|x| *x = erf_f32(*x));
@@ -118,6 +135,14 @@ pub fn gemm(
     Ok((Box::new(Gemm::new(alpha, beta, trans_a, trans_b)), vec![]))
 }
 
+pub fn einsum(
+    _ctx: &ParsingContext,
+    node: &NodeProto,
+) -> TractResult<(Box<dyn InferenceOp>, Vec<String>)> {
+    let equation: &str = node.get_attr("equation")?;
+    Ok((Box::new(ops::einsum::Einsum::new(equation)?), vec![]))
+}
+
 #[derive(Debug, Clone, new, Educe)]
 #[educe(Hash)]
 pub struct Gemm {
@@ -211,3 +236,23 @@ impl InferenceRulesOp for Gemm {
     }
     as_op!();
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::test_util::*;
+    use tract_hir::internal::*;
+
+    #[test]
+    fn clip_from_opset_11() {
+        let attrs = vec![attr_float("min", 2.), attr_float("max", 3.)];
+        let y = run_node(10, node("Clip", &["x"], &["y"], attrs), &[4], vec![]).unwrap();
+        assert_eq!(*y[0], tensor1(&[2f32, 2., 3., 3.]));
+        let clip11 = node("Clip", &["x", "min", "max"], &["y"], vec![]);
+        let inits = vec![tensor("min", &tensor0(2f32)), tensor("max", &tensor0(3f32))];
+        let y = run_node(11, clip11, &[4], inits).unwrap();
+        assert_eq!(*y[0], tensor1(&[2f32, 2., 3., 3.]));
+        let max_only = node("Clip", &["x", "", "max"], &["y"], vec![]);
+        let y = run_node(11, max_only, &[4], vec![tensor("max", &tensor0(3f32))]).unwrap();
+        assert_eq!(*y[0], tensor1(&[1f32, 2., 3., 3.]));
+    }
+}
@@ -5,11 +5,14 @@ use tract_hir::ops;
 
 mod array;
 mod category_mapper;
+mod const_inputs;
+mod control_flow;
 mod logic;
 mod math;
 mod nn;
 mod quant;
 pub mod rec;
+mod resize;
 
 pub fn register_all_ops(reg: &mut OnnxOpRegister) {
     reg.insert("Cast", cast);
@@ -17,18 +20,26 @@ pub fn register_all_ops(reg: &mut OnnxOpRegister) {
     reg.insert("Identity", |_, _| Ok((Box::new(ops::identity::Identity::default()), vec![])));
     array::register_all_ops(reg);
     category_mapper::register_all_ops(reg);
+    control_flow::register_all_ops(reg);
     logic::register_all_ops(reg);
     math::register_all_ops(reg);
     nn::register_all_ops(reg);
     quant::register_all_ops(reg);
     rec::register_all_ops(reg);
+    resize::register_all_ops(reg);
+}
+
+/// Registers the ONNX specific operators that can be found in a decluttered
+/// model, so that they can be loaded back from tract native format.
+pub fn register_native_ops(reg: &mut tract_hir::tract_core::native::Registry) {
+    rec::register_native_ops(reg);
 }
 
 fn konst(
-    _ctx: &ParsingContext,
+    ctx: &ParsingContext,
     node: &NodeProto,
 ) -> TractResult<(Box<dyn InferenceOp>, Vec<String>)> {
-    let v = node.get_attr("value")?;
+    let v = ctx.load_tensor(node.get_attr::<&TensorProto>("value")?)?;
     Ok((Box::new(tract_hir::ops::konst::Const::for_tensor(v)), vec![]))
 }
 
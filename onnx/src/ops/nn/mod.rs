@@ -2,7 +2,8 @@ use tract_hir::internal::*;
 use tract_hir::ops;
 use tract_hir::ops::{cnn, nn};
 
-use crate::model::{OnnxOpRegister, ParsingContext};
+use crate::model::{optional_inputs, OnnxOpRegister, ParsingContext};
+use crate::ops::const_inputs::ConstInputs;
 use crate::pb::NodeProto;
 use crate::pb_helpers::OptionExt;
 
@@ -21,6 +22,25 @@ fn reduce(
     Ok((Box::new(ops::nn::Reduce::new(axes, keep_dims, reducer)), vec![]))
 }
 
+/// ReduceSum from operator set 13, with the axes as an input.
+fn reduce_sum13(
+    node: &NodeProto,
+    params: &[Option<Arc<Tensor>>],
+) -> TractResult<Box<dyn InferenceOp>> {
+    let axes: Option<Vec<i64>> = params[0]
+        .as_ref()
+        .map(|axes| -> TractResult<_> { Ok(axes.cast_to::<i64>()?.as_slice::<i64>()?.to_vec()) })
+        .transpose()?;
+    let keep_dims = node.get_attr_opt("keepdims")?.unwrap_or(1i64) == 1;
+    if axes.as_ref().map(|axes| axes.is_empty()).unwrap_or(true)
+        && node.get_attr_opt("noop_with_empty_axes")?.unwrap_or(0i64) == 1
+    {
+        return Ok(Box::new(ops::identity::Identity::default()));
+    }
+    let axes = axes.filter(|axes| !axes.is_empty());
+    Ok(Box::new(ops::nn::Reduce::new(axes, keep_dims, nn::Reducer::Sum)))
+}
+
 pub fn register_all_ops(reg: &mut OnnxOpRegister) {
     reg.insert("ArgMax", arg_max_min);
     reg.insert("ArgMin", arg_max_min);
@@ -28,6 +48,7 @@ pub fn register_all_ops(reg: &mut OnnxOpRegister) {
     reg.insert("BatchNormalization", batch_normalization);
     reg.insert("Conv", conv);
     reg.insert("ConvInteger", conv_integer);
+    reg.insert("ConvTranspose", conv_transpose);
     reg.insert("Dropout", dropout::dropout);
     reg.insert("Elu", elu);
     reg.insert("GlobalAveragePool", |_, _| {
@@ -35,32 +56,41 @@ pub fn register_all_ops(reg: &mut OnnxOpRegister) {
     });
     reg.insert("GlobalLpPool", global_lp_pool);
     reg.insert("GlobalMaxPool", |_, _| Ok((Box::new(ops::nn::GlobalMaxPool::default()), vec![])));
-    reg.insert("Hardmax", layer_hard_max);
+    reg.insert_until("", "Hardmax", 1, 12, layer_hard_max);
+    reg.insert_since("", "Hardmax", 13, layer_hard_max13);
     reg.insert("HardSigmoid", hard_sigmoid);
     reg.insert("LeakyRelu", leaky_relu);
-    reg.insert("LogSoftmax", layer_log_soft_max);
+    reg.insert_until("", "LogSoftmax", 1, 12, layer_log_soft_max);
+    reg.insert_since("", "LogSoftmax", 13, layer_log_soft_max13);
     reg.insert("LRN", lrn);
     reg.insert("MaxPool", max_pool);
+    reg.insert("NonMaxSuppression", non_max_suppression);
     reg.insert("ParametricSoftplus", parametric_softplus);
     reg.insert("QLinearConv", conv_qlinear);
     reg.insert("PRelu", |_, _| Ok((Box::new(prelu::bin()), vec![])));
-    reg.insert("ReduceL1", |_, node| reduce(node, nn::Reducer::L1));
-    reg.insert("ReduceL2", |_, node| reduce(node, nn::Reducer::L2));
-    reg.insert("ReduceLogSum", |_, node| reduce(node, nn::Reducer::LogSum));
-    reg.insert("ReduceLogSumExp", |_, node| reduce(node, nn::Reducer::LogSumExp));
-    reg.insert("ReduceMax", |_, node| reduce(node, nn::Reducer::Max));
-    reg.insert("ReduceMean", |_, node| reduce(node, nn::Reducer::Mean));
-    reg.insert("ReduceMin", |_, node| reduce(node, nn::Reducer::Min));
-    reg.insert("ReduceProd", |_, node| reduce(node, nn::Reducer::Prod));
+    reg.insert_until("", "ReduceL1", 1, 17, |_, node| reduce(node, nn::Reducer::L1));
+    reg.insert_until("", "ReduceL2", 1, 17, |_, node| reduce(node, nn::Reducer::L2));
+    reg.insert_until("", "ReduceLogSum", 1, 17, |_, node| reduce(node, nn::Reducer::LogSum));
+    reg.insert_until("", "ReduceLogSumExp", 1, 17, |_, node| reduce(node, nn::Reducer::LogSumExp));
+    reg.insert_until("", "ReduceMax", 1, 17, |_, node| reduce(node, nn::Reducer::Max));
+    reg.insert_until("", "ReduceMean", 1, 17, |_, node| reduce(node, nn::Reducer::Mean));
+    reg.insert_until("", "ReduceMin", 1, 17, |_, node| reduce(node, nn::Reducer::Min));
+    reg.insert_until("", "ReduceProd", 1, 17, |_, node| reduce(node, nn::Reducer::Prod));
     reg.insert("ReduceSum", |_, node| reduce(node, nn::Reducer::Sum));
-    reg.insert("ReduceSumSquare", |_, node| reduce(node, nn::Reducer::SumSquare));
+    reg.insert_since("", "ReduceSum", 13, |_, node| {
+        Ok((Box::new(ConstInputs::new(node, 1, 1, reduce_sum13)), vec![]))
+    });
+    reg.insert_until("", "ReduceSumSquare", 1, 17, |_, node| reduce(node, nn::Reducer::SumSquare));
+    reg.insert("RoiAlign", |_, node| roi_align(node, "output_half_pixel"));
+    reg.insert_since("", "RoiAlign", 16, |_, node| roi_align(node, "half_pixel"));
     reg.insert("Relu", |_, _| Ok((Box::new(ops::math::scalar_max((0.0).into())), vec![])));
     reg.insert("ScaledTanh", scaled_tanh);
     reg.insert("Shrink", shrink);
     reg.insert("ThresholdedRelu", thresholded_relu);
     reg.insert("Selu", selu);
     reg.insert("Sigmoid", |_, _| Ok((Box::new(ops::nn::sigmoid()), vec![])));
-    reg.insert("Softmax", layer_soft_max);
+    reg.insert_until("", "Softmax", 1, 12, layer_soft_max);
+    reg.insert_since("", "Softmax", 13, layer_soft_max13);
     reg.insert("Softplus", |_, _| Ok((Box::new(ops::nn::softplus()), vec![])));
     reg.insert("Softsign", |_, _| Ok((Box::new(ops::nn::softsign()), vec![])));
 }
@@ -108,6 +138,48 @@ pub fn arg_max_min(
     Ok((Box::new(ops::nn::ArgMaxMin::new(max, axis, keepdims)), vec![]))
 }
 
+pub fn non_max_suppression(
+    _ctx: &ParsingContext,
+    node: &NodeProto,
+) -> TractResult<(Box<dyn InferenceOp>, Vec<String>)> {
+    let center_point_box = node.get_attr_opt("center_point_box")?.unwrap_or(0i64) == 1;
+    let mut options = optional_inputs(node).skip(2);
+    let op = nn::NonMaxSuppression::new(
+        center_point_box,
+        options.next().unwrap(),
+        options.next().unwrap(),
+        options.next().unwrap(),
+    );
+    Ok((Box::new(op), vec![]))
+}
+
+/// RoiAlign, with the default coordinate transformation of the operator set.
+fn roi_align(
+    node: &NodeProto,
+    default_transform: &str,
+) -> TractResult<(Box<dyn InferenceOp>, Vec<String>)> {
+    let max_mode = match node.get_attr_opt("mode")?.unwrap_or("avg") {
+        "avg" => false,
+        "max" => true,
+        mode => bail!("Unsupported RoiAlign mode: {}", mode),
+    };
+    let half_pixel =
+        match node.get_attr_opt("coordinate_transformation_mode")?.unwrap_or(default_transform) {
+            "half_pixel" => true,
+            "output_half_pixel" => false,
+            mode => bail!("Unsupported RoiAlign coordinate_transformation_mode: {}", mode),
+        };
+    let op = nn::RoiAlign {
+        max_mode,
+        output_height: node.get_attr_opt("output_height")?.unwrap_or(1),
+        output_width: node.get_attr_opt("output_width")?.unwrap_or(1),
+        sampling_ratio: node.get_attr_opt("sampling_ratio")?.unwrap_or(0),
+        spatial_scale: node.get_attr_opt("spatial_scale")?.unwrap_or(1.0),
+        half_pixel,
+    };
+    Ok((Box::new(op), vec![]))
+}
+
 pub fn batch_normalization(
     _ctx: &ParsingContext,
     node: &NodeProto,
@@ -148,6 +220,32 @@ pub fn conv(
     Ok((Box::new(op), vec![]))
 }
 
+pub fn conv_transpose(
+    _ctx: &ParsingContext,
+    node: &NodeProto,
+) -> TractResult<(Box<dyn InferenceOp>, Vec<String>)> {
+    let mut op = ops::cnn::Deconv::default().padding(pad(node)?);
+    if let Some(group) = node.get_attr_opt("group")? {
+        op = op.group(group);
+    }
+    if let Some(v) = dilations(node)? {
+        op = op.dilations(v);
+    }
+    if let Some(v) = strides(node)? {
+        op = op.strides(v);
+    }
+    if let Some(v) = node.get_attr_opt_tvec("output_padding")? {
+        op = op.adjustments(v);
+    }
+    if let Some(v) = node.get_attr_opt_tvec("output_shape")? {
+        op = op.output_shape(v);
+    }
+    if node.input.len() == 3 {
+        op = op.bias_input(2);
+    }
+    Ok((Box::new(op), vec![]))
+}
+
 pub fn conv_integer(
     _ctx: &ParsingContext,
     node: &NodeProto,
@@ -230,7 +328,15 @@ pub fn layer_hard_max(
     node: &NodeProto,
 ) -> TractResult<(Box<dyn InferenceOp>, Vec<String>)> {
     let axis = node.get_attr_opt("axis")?.unwrap_or(1);
-    Ok((Box::new(ops::nn::LayerHardmax::new(axis)), vec![]))
+    Ok((Box::new(ops::nn::LayerHardmax::new(axis, true)), vec![]))
+}
+
+pub fn layer_hard_max13(
+    _ctx: &ParsingContext,
+    node: &NodeProto,
+) -> TractResult<(Box<dyn InferenceOp>, Vec<String>)> {
+    let axis = node.get_attr_opt("axis")?.unwrap_or(-1);
+    Ok((Box::new(ops::nn::LayerHardmax::new(axis, false)), vec![]))
 }
 
 pub fn layer_log_soft_max(
@@ -238,7 +344,15 @@ pub fn layer_log_soft_max(
     node: &NodeProto,
 ) -> TractResult<(Box<dyn InferenceOp>, Vec<String>)> {
     let axis = node.get_attr_opt("axis")?.unwrap_or(1);
-    Ok((Box::new(ops::nn::LayerLogSoftmax::new(axis)), vec![]))
+    Ok((Box::new(ops::nn::LayerLogSoftmax::new(axis, true)), vec![]))
+}
+
+pub fn layer_log_soft_max13(
+    _ctx: &ParsingContext,
+    node: &NodeProto,
+) -> TractResult<(Box<dyn InferenceOp>, Vec<String>)> {
+    let axis = node.get_attr_opt("axis")?.unwrap_or(-1);
+    Ok((Box::new(ops::nn::LayerLogSoftmax::new(axis, false)), vec![]))
 }
 
 pub fn layer_soft_max(
@@ -246,7 +360,15 @@ pub fn layer_soft_max(
     node: &NodeProto,
 ) -> TractResult<(Box<dyn InferenceOp>, Vec<String>)> {
     let axis = node.get_attr_opt("axis")?.unwrap_or(1);
-    Ok((Box::new(ops::nn::LayerSoftmax::new(axis)), vec![]))
+    Ok((Box::new(ops::nn::LayerSoftmax::new(axis, true)), vec![]))
+}
+
+pub fn layer_soft_max13(
+    _ctx: &ParsingContext,
+    node: &NodeProto,
+) -> TractResult<(Box<dyn InferenceOp>, Vec<String>)> {
+    let axis = node.get_attr_opt("axis")?.unwrap_or(-1);
+    Ok((Box::new(ops::nn::LayerSoftmax::new(axis, false)), vec![]))
 }
 
 pub fn leaky_relu(
@@ -385,3 +507,57 @@ pub fn thresholded_relu(
     let alpha = node.get_attr_opt("alpha")?.unwrap_or(1.);
     Ok((Box::new(ops::nn::threshold_relu(alpha)), vec![]))
 }
+
+#[cfg(test)]
+mod tests {
+    use crate::test_util::*;
+    use tract_hir::internal::*;
+
+    #[test]
+    fn reduce_sum_from_opset_13() {
+        let attrs = vec![attr_ints("axes", &[1]), attr_int("keepdims", 0)];
+        let y = run_node(12, node("ReduceSum", &["x"], &["y"], attrs), &[2, 2], vec![]).unwrap();
+        assert_eq!(*y[0], tensor1(&[3f32, 7.]));
+        let reduce13 = node("ReduceSum", &["x", "axes"], &["y"], vec![attr_int("keepdims", 0)]);
+        let axes = vec![tensor("axes", &tensor1(&[1i64]))];
+        let y = run_node(13, reduce13, &[2, 2], axes).unwrap();
+        assert_eq!(*y[0], tensor1(&[3f32, 7.]));
+        let all = node("ReduceSum", &["x"], &["y"], vec![]);
+        let y = run_node(13, all, &[2, 2], vec![]).unwrap();
+        assert_eq!(*y[0], tensor2(&[[10f32]]));
+        let noop = node("ReduceSum", &["x"], &["y"], vec![attr_int("noop_with_empty_axes", 1)]);
+        let y = run_node(13, noop, &[2, 2], vec![]).unwrap();
+        assert_eq!(*y[0], tensor2(&[[1f32, 2.], [3., 4.]]));
+    }
+
+    #[test]
+    fn softmax_from_opset_13() {
+        let softmax = |xs: &[f32]| {
+            let sum: f32 = xs.iter().map(|x| x.exp()).sum();
+            xs.iter().map(|x| x.exp() / sum).collect::<Vec<f32>>()
+        };
+        let default = node("Softmax", &["x"], &["y"], vec![]);
+        let y = run_node(12, default.clone(), &[1, 2, 2], vec![]).unwrap();
+        let s = softmax(&[1., 2., 3., 4.]);
+        let all = tensor3(&[[[s[0], s[1]], [s[2], s[3]]]]);
+        y[0].close_enough(&all, true).unwrap();
+        assert!(run_node(13, default.clone(), &[1, 2, 2], vec![]).unwrap()[0]
+            .close_enough(&all, true)
+            .is_err());
+        let y = run_node(13, default, &[1, 2, 2], vec![]).unwrap();
+        let (r0, r1) = (softmax(&[1., 2.]), softmax(&[3., 4.]));
+        let rows = tensor3(&[[[r0[0], r0[1]], [r1[0], r1[1]]]]);
+        y[0].close_enough(&rows, true).unwrap();
+        let axis_0 = node("Softmax", &["x"], &["y"], vec![attr_int("axis", 0)]);
+        let y = run_node(13, axis_0, &[2, 2], vec![]).unwrap();
+        let (a, b) = (softmax(&[1., 3.]), softmax(&[2., 4.]));
+        y[0].close_enough(&tensor2(&[[a[0], b[0]], [a[1], b[1]]]), true).unwrap();
+        let log_softmax = node("LogSoftmax", &["x"], &["y"], vec![attr_int("axis", 0)]);
+        let y = run_node(13, log_softmax, &[2, 2], vec![]).unwrap();
+        let expected = tensor2(&[[a[0].ln(), b[0].ln()], [a[1].ln(), b[1].ln()]]);
+        y[0].close_enough(&expected, true).unwrap();
+        let hardmax = node("Hardmax", &["x"], &["y"], vec![attr_int("axis", 0)]);
+        let y = run_node(13, hardmax, &[2, 2], vec![]).unwrap();
+        assert_eq!(*y[0], tensor2(&[[0f32, 0.], [1., 1.]]));
+    }
+}
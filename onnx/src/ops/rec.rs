@@ -1,4 +1,7 @@
 use crate::model::OnnxOpRegister;
+use crate::pb::*;
+use tract_hir::internal::*;
+use tract_hir::tract_core::native::Registry;
 
 pub mod gru;
 pub mod lstm;
@@ -11,3 +14,339 @@ pub fn register_all_ops(reg: &mut OnnxOpRegister) {
     reg.insert("RNN", rnn::rnn);
     reg.insert("Scan", scan::scan);
 }
+
+pub fn register_native_ops(reg: &mut Registry) {
+    reg.register_op::<gru::GRU>("onnx.GRU");
+    reg.register_op::<lstm::LSTM>("onnx.LSTM");
+    reg.register_op::<rnn::RNN>("onnx.RNN");
+    reg.register_op::<WholeSequence>("onnx.WholeSequence");
+}
+
+/// Order in which a recurrent layer goes through the sequence.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Direction {
+    Forward,
+    Reverse,
+    Bidirectional,
+}
+
+impl Direction {
+    pub fn parse(pb: &NodeProto) -> TractResult<Direction> {
+        Ok(match pb.get_attr_opt("direction")? {
+            None => Direction::Forward,
+            Some(dir) => pb.check_value(
+                "direction",
+                match dir {
+                    "forward" => Ok(Direction::Forward),
+                    "reverse" => Ok(Direction::Reverse),
+                    "bidirectional" => Ok(Direction::Bidirectional),
+                    _ => Err(dir),
+                },
+            )?,
+        })
+    }
+
+    /// Is the `dir`-th direction (as in the num_directions axis) going from
+    /// the end of the sequence to its beginning ?
+    pub fn is_backward(&self, dir: usize) -> bool {
+        match self {
+            Direction::Forward => false,
+            Direction::Reverse => true,
+            Direction::Bidirectional => dir == 1,
+        }
+    }
+}
+
+impl Encode for Direction {
+    fn encode(&self, encoder: &mut Encoder) -> TractResult<()> {
+        let code: u8 = match self {
+            Direction::Forward => 0,
+            Direction::Reverse => 1,
+            Direction::Bidirectional => 2,
+        };
+        code.encode(encoder)
+    }
+}
+
+impl Decode for Direction {
+    fn decode(decoder: &mut Decoder) -> TractResult<Self> {
+        Ok(match u8::decode(decoder)? {
+            0 => Direction::Forward,
+            1 => Direction::Reverse,
+            2 => Direction::Bidirectional,
+            code => bail!("Invalid Direction code {}", code),
+        })
+    }
+}
+
+/// Output facts for a recurrent layer kept as a single op: Y if `y` is set,
+/// then `states` last-value outputs (Y_h, Y_c).
+fn output_facts(inputs: &[&TypedFact], y: bool, states: usize) -> TractResult<TVec<TypedFact>> {
+    let dt = inputs[0].datum_type;
+    let seq_length = inputs[0].shape.dim(0);
+    let batch_size = inputs[0].shape.dim(1);
+    let num_directions = inputs[1].shape.dim(0);
+    let hidden_size = inputs[2].shape.dim(2);
+    let mut facts = tvec!();
+    if y {
+        let shape = [seq_length, num_directions.clone(), batch_size.clone(), hidden_size.clone()];
+        facts.push(TypedFact::dt_shape(dt, shape.as_ref())?);
+    }
+    for _ in 0..states {
+        let shape = [num_directions.clone(), batch_size.clone(), hidden_size.clone()];
+        facts.push(TypedFact::dt_shape(dt, shape.as_ref())?);
+    }
+    Ok(facts)
+}
+
+/// Recurrent layer going backward in time, kept as a single op working on
+/// the whole sequence. Its constant inputs are embedded in the op.
+#[derive(Debug, Clone, Hash)]
+pub struct WholeSequence {
+    pub direction: Direction,
+    pub op: Box<dyn TypedOp>,
+    pub konsts: TVec<Option<Arc<Tensor>>>,
+}
+
+impl_native_codec!(WholeSequence { direction, op, konsts });
+
+impl WholeSequence {
+    fn wire(
+        direction: Direction,
+        op: Box<dyn TypedOp>,
+        node: &InferenceNode,
+        target: &mut TypedModel,
+        mapping: &HashMap<OutletId, OutletId>,
+    ) -> TractResult<TVec<OutletId>> {
+        let mut konsts = tvec!();
+        let mut inputs = tvec!();
+        for input in &node.inputs {
+            let konst = target.outlet_fact(mapping[input])?.konst.clone();
+            if konst.is_none() {
+                inputs.push(mapping[input]);
+            }
+            konsts.push(konst);
+        }
+        let op = WholeSequence { direction, op, konsts };
+        target.wire_node(&*node.name, op, &*inputs)
+    }
+
+    fn merge_inputs<T: Clone>(&self, inputs: &[T], konst: impl Fn(&Arc<Tensor>) -> T) -> TVec<T> {
+        let mut inputs = inputs.iter();
+        self.konsts
+            .iter()
+            .map(|k| k.as_ref().map(|k| konst(k)).unwrap_or_else(|| inputs.next().unwrap().clone()))
+            .collect()
+    }
+}
+
+impl Op for WholeSequence {
+    fn name(&self) -> Cow<str> {
+        self.op.name()
+    }
+
+    fn info(&self) -> TractResult<Vec<String>> {
+        Ok(vec![format!("{:?}", self.direction)])
+    }
+
+    fn validation(&self) -> Validation {
+        Validation::Rounding
+    }
+
+    op_as_typed_op!();
+}
+
+impl StatelessOp for WholeSequence {
+    fn eval(&self, inputs: TVec<Arc<Tensor>>) -> TractResult<TVec<Arc<Tensor>>> {
+        let inputs = self.merge_inputs(&inputs, |k| k.clone());
+        self.op.as_stateless().ok_or("Expected a stateless op")?.eval(inputs)
+    }
+}
+
+impl TypedOp for WholeSequence {
+    as_op!();
+    native_save!("onnx.WholeSequence");
+
+    fn output_facts(&self, inputs: &[&TypedFact]) -> TractResult<TVec<TypedFact>> {
+        let inputs = inputs.iter().map(|&f| f.clone()).collect::<TVec<_>>();
+        let inputs = self.merge_inputs(&inputs, |k| k.clone().into());
+        let inputs = inputs.iter().collect::<TVec<_>>();
+        self.op.output_facts(&*inputs)
+    }
+
+    fn pulsify(
+        &self,
+        _source: &NormalizedModel,
+        node: &NormalizedNode,
+        _target: &mut PulsedModel,
+        _mapping: &HashMap<OutletId, OutletId>,
+        _pulse: usize,
+    ) -> TractResult<TVec<OutletId>> {
+        bail!(
+            "{} {:?} is {:?}: its first output depends on the last frame of the sequence, so \
+            it can not be streamed. Only forward recurrent layers can be pulsified.",
+            self.op.name(),
+            node.name,
+            self.direction
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_util::*;
+    use tract_hir::internal::*;
+    use tract_hir::tract_core::native::{self, Registry};
+    use tract_ndarray::prelude::*;
+
+    fn sigmoid(x: f32) -> f32 {
+        1.0 / (1.0 + (-x).exp())
+    }
+
+    /// A single layer model, with input X [seq, batch, 1] and output Y.
+    fn layer(
+        op: &str,
+        gates: usize,
+        hidden: usize,
+        seq: &str,
+        batch: usize,
+        attrs: Vec<crate::pb::AttributeProto>,
+    ) -> crate::pb::ModelProto {
+        let w = Array3::from_shape_fn((1, gates * hidden, 1), |(_, i, _)| 0.1 * i as f32 - 0.3);
+        let r = Array3::from_shape_fn((1, gates * hidden, hidden), |(_, i, j)| {
+            0.05 * (i + j) as f32 - 0.2
+        });
+        let b = Array2::from_shape_fn((1, 2 * gates * hidden), |(_, i)| 0.02 * i as f32 - 0.1);
+        let mut attrs = attrs;
+        attrs.push(attr_int("hidden_size", hidden as i64));
+        let graph = graph(
+            vec![node(op, &["x", "w", "r", "b"], &["y"], attrs)],
+            vec![value("x", f32::datum_type(), &[seq, &*batch.to_string(), "1"])],
+            vec![value(
+                "y",
+                f32::datum_type(),
+                &[seq, "1", &*batch.to_string(), &*hidden.to_string()],
+            )],
+            vec![
+                tensor("w", &w.into_tensor()),
+                tensor("r", &r.into_tensor()),
+                tensor("b", &b.into_tensor()),
+            ],
+        );
+        model(graph, 9)
+    }
+
+    /// Run the model through its inference ops and through its typed
+    /// lowering, check they agree and return Y.
+    fn run(proto: &crate::pb::ModelProto, x: Tensor) -> TractResult<Arc<Tensor>> {
+        let model = crate::onnx().model_for_proto_model(proto)?;
+        let inferred = SimplePlan::new(model.clone())?.run(tvec!(x.clone()))?.remove(0);
+        let typed = SimplePlan::new(model.into_optimized()?)?.run(tvec!(x))?.remove(0);
+        inferred.close_enough(&typed, true)?;
+        Ok(typed)
+    }
+
+    #[test]
+    fn y_layout() -> TractResult<()> {
+        for (op, gates) in &[("RNN", 1), ("GRU", 3), ("LSTM", 4)] {
+            let proto = layer(op, *gates, 2, "3", 2, vec![]);
+            let x = Array3::from_shape_fn((3, 2, 1), |(s, b, _)| s as f32 - b as f32).into_tensor();
+            let y = run(&proto, x).chain_err(|| format!("{}", op))?;
+            assert_eq!(y.shape(), &[3, 1, 2, 2], "{}", op);
+        }
+        Ok(())
+    }
+
+    fn gru_reference(xs: &[f32], hidden: usize, linear_before_reset: bool) -> Vec<f32> {
+        // same weights and biases as layer(), input and batch sizes are 1
+        let w = |i: usize| 0.1 * i as f32 - 0.3;
+        let r = |i: usize, j: usize| 0.05 * (i + j) as f32 - 0.2;
+        let b = |i: usize| 0.02 * i as f32 - 0.1;
+        let mut h = vec![0.0f32; hidden];
+        let mut ys = vec![];
+        for &x in xs {
+            let gate = |g: usize, i: usize, h: &[f32]| {
+                let row = g * hidden + i;
+                let rh: f32 = (0..hidden).map(|j| r(row, j) * h[j]).sum();
+                (x * w(row) + b(row), rh + b(3 * hidden + row))
+            };
+            let z: Vec<f32> = (0..hidden)
+                .map(|i| {
+                    let (wx, rh) = gate(0, i, &h);
+                    sigmoid(wx + rh)
+                })
+                .collect();
+            let rt: Vec<f32> = (0..hidden)
+                .map(|i| {
+                    let (wx, rh) = gate(1, i, &h);
+                    sigmoid(wx + rh)
+                })
+                .collect();
+            let reset: Vec<f32> = h.iter().zip(rt.iter()).map(|(h, r)| h * r).collect();
+            let ht: Vec<f32> = (0..hidden)
+                .map(|i| {
+                    if linear_before_reset {
+                        let (wx, rh) = gate(2, i, &h);
+                        (wx + rt[i] * rh).tanh()
+                    } else {
+                        let (wx, rh) = gate(2, i, &reset);
+                        (wx + rh).tanh()
+                    }
+                })
+                .collect();
+            h = (0..hidden).map(|i| (1.0 - z[i]) * ht[i] + z[i] * h[i]).collect();
+            ys.extend(h.iter());
+        }
+        ys
+    }
+
+    #[test]
+    fn gru_values() -> TractResult<()> {
+        let xs = [1.0f32, -2.0, 3.0, 0.5];
+        for &lbr in &[false, true] {
+            let proto =
+                layer("GRU", 3, 2, "4", 1, vec![attr_int("linear_before_reset", lbr as i64)]);
+            let x = Array3::from_shape_vec((4, 1, 1), xs.to_vec())?.into_tensor();
+            let y = run(&proto, x).chain_err(|| format!("linear_before_reset: {}", lbr))?;
+            let expected = Array4::from_shape_vec((4, 1, 1, 2), gru_reference(&xs, 2, lbr))?;
+            y.close_enough(&expected.into_tensor(), true)?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn reverse_layers_through_native_format() -> TractResult<()> {
+        let mut registry = Registry::default();
+        crate::ops::register_native_ops(&mut registry);
+        for (op, gates) in &[("RNN", 1), ("GRU", 3), ("LSTM", 4)] {
+            let proto = layer(op, *gates, 2, "3", 1, vec![attr_string("direction", "reverse")]);
+            let model = crate::onnx().model_for_proto_model(&proto)?.into_typed()?.declutter()?;
+            assert!(model.nodes().iter().any(|n| n.op_is::<super::WholeSequence>()), "{}", op);
+            let mut buffer = vec![];
+            native::save(&model, &mut buffer)?;
+            let reloaded = native::load_with_registry(&mut &*buffer, &registry)?;
+            let x = tensor3(&[[[1f32]], [[-2.]], [[3.]]]);
+            let expected = SimplePlan::new(model)?.run(tvec!(x.clone()))?;
+            let found = SimplePlan::new(reloaded)?.run(tvec!(x))?;
+            assert_eq!(expected, found, "{}", op);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn reverse_layers_can_not_be_pulsified() -> TractResult<()> {
+        let proto = layer("RNN", 1, 2, "S", 1, vec![attr_string("direction", "reverse")]);
+        let model = crate::onnx()
+            .with_streaming_dim_param("S")
+            .model_for_proto_model(&proto)?
+            .into_normalized()?;
+        let err = PulsedModel::new(&model, 1).err().unwrap();
+        assert!(
+            err.iter()
+                .any(|e| e.to_string().contains("Only forward recurrent layers can be pulsified")),
+            "{:?}",
+            err
+        );
+        Ok(())
+    }
+}
@@ -19,6 +19,9 @@ pub fn gru(
     gru.optional_y_output = options.next().unwrap();
     gru.optional_y_h_output = options.next().unwrap();
 
+    gru.direction = super::Direction::parse(pb)?;
+    gru.linear_before_reset = pb.get_attr_opt::<i64>("linear_before_reset")?.unwrap_or(0) != 0;
+
     Ok((Box::new(gru), vec![]))
 }
 
@@ -29,11 +32,24 @@ pub struct GRU {
     pub optional_initial_h_input: Option<usize>,
     pub optional_y_output: Option<usize>,
     pub optional_y_h_output: Option<usize>,
+    pub direction: super::Direction,
     pub f: Box<dyn TypedOp>,
     pub g: Box<dyn TypedOp>,
     pub linear_before_reset: bool,
 }
 
+impl_native_codec!(GRU {
+    optional_bias_input,
+    optional_sequence_lens_input,
+    optional_initial_h_input,
+    optional_y_output,
+    optional_y_h_output,
+    direction,
+    f,
+    g,
+    linear_before_reset
+});
+
 impl Default for GRU {
     fn default() -> GRU {
         GRU {
@@ -42,6 +58,7 @@ impl Default for GRU {
             optional_initial_h_input: None,
             optional_y_output: None,
             optional_y_h_output: None,
+            direction: super::Direction::Forward,
             f: Box::new(ops::nn::sigmoid()),
             g: Box::new(ops::math::tanh()),
             linear_before_reset: false,
@@ -58,7 +75,7 @@ impl Op for GRU {
         Validation::Rounding
     }
 
-    not_a_typed_op!();
+    op_as_typed_op!();
 }
 
 impl InferenceRulesOp for GRU {
@@ -134,6 +151,16 @@ impl InferenceRulesOp for GRU {
         target: &mut TypedModel,
         mapping: &HashMap<OutletId, OutletId>,
     ) -> TractResult<TVec<OutletId>> {
+        if self.direction != super::Direction::Forward {
+            return super::WholeSequence::wire(
+                self.direction,
+                Box::new(self.clone()),
+                node,
+                target,
+                mapping,
+            );
+        }
+
         use ops::{array, math, matmul, scan};
 
         let x_fact = target.outlet_fact(mapping[&node.inputs[0]])?.clone();
@@ -142,8 +169,6 @@ impl InferenceRulesOp for GRU {
         let b_size = x_fact.shape.dim(1).to_integer().unwrap() as usize;
         let h_size = r_fact.shape.dim(2).to_integer().unwrap() as usize;
 
-        // FIXME: bidi
-
         let mut body = TypedModel::default();
         let mut outer_inputs = vec![];
         let mut input_mapping = vec![];
@@ -269,6 +294,12 @@ impl InferenceRulesOp for GRU {
         wire!(Xt_WhT = matmul::MatMul::default().with_b_trans(true), Xt, Wh);
         let rt_Ht_1_RhT = if self.linear_before_reset {
             wire!(Ht_1_RhT = matmul::MatMul::default().with_b_trans(true), Ht_1, Rh);
+            let mut Ht_1_RhT = Ht_1_RhT;
+            if let Some(b) = b {
+                wire!(Rbh = array::Slice::new(0, 5 * h_size, 6 * h_size), b);
+                wire!(Ht_1_RhT_Rbh = math::add::bin_typed(), Ht_1_RhT, Rbh);
+                Ht_1_RhT = Ht_1_RhT_Rbh
+            }
             wire!(rt_Ht_1_RhT = math::mul::bin_typed(), rt, Ht_1_RhT);
             rt_Ht_1_RhT
         } else {
@@ -280,10 +311,15 @@ impl InferenceRulesOp for GRU {
         let mut ht0 = ht0;
         if let Some(b) = b {
             wire!(Wbh = array::Slice::new(0, 2 * h_size, 3 * h_size), b);
-            wire!(Rbh = array::Slice::new(0, 5 * h_size, 6 * h_size), b);
-            wire!(Wbh_Rbh = math::add::bin_typed(), Wbh, Rbh);
-            wire!(ht0_biased = math::add::bin_typed(), ht0, Wbh_Rbh);
-            ht0 = ht0_biased
+            if self.linear_before_reset {
+                wire!(ht0_biased = math::add::bin_typed(), ht0, Wbh);
+                ht0 = ht0_biased
+            } else {
+                wire!(Rbh = array::Slice::new(0, 5 * h_size, 6 * h_size), b);
+                wire!(Wbh_Rbh = math::add::bin_typed(), Wbh, Rbh);
+                wire!(ht0_biased = math::add::bin_typed(), ht0, Wbh_Rbh);
+                ht0 = ht0_biased
+            }
         }
         wire!(ht = self.g.clone(), ht0);
 
@@ -319,7 +355,7 @@ impl InferenceRulesOp for GRU {
 
         let mut result = tvec!();
         if let Some(slot) = self.optional_y_output {
-            target_wire!(y = AxisOp::Add(0), scan_outputs[slot]);
+            target_wire!(y = AxisOp::Add(1), scan_outputs[slot]);
             result.push(y);
         }
         if let Some(slot) = self.optional_y_h_output {
@@ -330,6 +366,19 @@ impl InferenceRulesOp for GRU {
     }
 }
 
+impl TypedOp for GRU {
+    as_op!();
+    native_save!("onnx.GRU");
+
+    fn output_facts(&self, inputs: &[&TypedFact]) -> TractResult<TVec<TypedFact>> {
+        super::output_facts(
+            inputs,
+            self.optional_y_output.is_some(),
+            self.optional_y_h_output.is_some() as usize,
+        )
+    }
+}
+
 impl StatelessOp for GRU {
     fn eval(&self, inputs: TVec<Arc<Tensor>>) -> TractResult<TVec<Arc<Tensor>>> {
         let x: ArrayView3<f32> = inputs[0].to_array_view::<f32>()?.into_dimensionality()?; // [seq_length, batch_size, input_size]
@@ -370,7 +419,7 @@ impl StatelessOp for GRU {
             };
 
             for ix in 0..seq_length {
-                let ix = if dir == 0 { ix } else { seq_length - 1 - ix };
+                let ix = if self.direction.is_backward(dir) { seq_length - 1 - ix } else { ix };
                 let x = x.index_axis_move(Axis(0), ix);
 
                 // Xt*W_zrh^T + Wb_zrh
@@ -382,10 +431,10 @@ impl StatelessOp for GRU {
                 // Ht-1*R_zr
                 let h_zr = ht.dot(&r.slice_axis(Axis(0), (0..2 * hidden_size).into()).t()); // batch_size x 3*hidden_size
 
-                let x_zrh: Array3<f32> = x_zrh.into_shape((batch_size, 3, hidden_size))?;
-                let h_zrh = h_zr.into_shape((batch_size, 2, hidden_size))?;
+                let gate = |k: usize| (k * hidden_size..(k + 1) * hidden_size).into();
 
-                let mut zt = x_zrh.index_axis(Axis(1), 0).to_owned() + h_zrh.index_axis(Axis(1), 0);
+                let mut zt =
+                    &x_zrh.slice_axis(Axis(1), gate(0)) + &h_zr.slice_axis(Axis(1), gate(0));
                 if let Some(bias) = bias {
                     zt += &bias.slice(s!(dir, 3 * hidden_size..4 * hidden_size));
                 }
@@ -399,7 +448,8 @@ impl StatelessOp for GRU {
                     .into_array::<f32>()?
                     .into_dimensionality()?;
 
-                let mut rt = x_zrh.index_axis(Axis(1), 1).to_owned() + h_zrh.index_axis(Axis(1), 1);
+                let mut rt =
+                    &x_zrh.slice_axis(Axis(1), gate(1)) + &h_zr.slice_axis(Axis(1), gate(1));
                 if let Some(bias) = bias {
                     rt += &bias.slice(s!(dir, 4 * hidden_size..5 * hidden_size));
                 }
@@ -413,18 +463,18 @@ impl StatelessOp for GRU {
                     .into_array::<f32>()?;
 
                 let ht1: Array2<f32> = if self.linear_before_reset {
-                    let mut ht = ht.dot(&r.slice_axis(Axis(1), (2 * hidden_size..).into()).t());
+                    let mut ht = ht.dot(&r.slice_axis(Axis(0), (2 * hidden_size..).into()).t());
                     if let Some(bias) = bias {
                         ht += &bias.slice(s!(dir, 5 * hidden_size..6 * hidden_size));
                     }
-                    ht * rt + x_zrh.index_axis(Axis(1), 2)
+                    ht * rt + x_zrh.slice_axis(Axis(1), gate(2))
                 } else {
-                    let mut ht =
-                        ht.dot(&r.slice_axis(Axis(0), (2 * hidden_size..).into()).t()) * rt;
+                    let rt_ht = &ht * &rt.into_dimensionality::<Ix2>()?;
+                    let mut ht = rt_ht.dot(&r.slice_axis(Axis(0), (2 * hidden_size..).into()).t());
                     if let Some(bias) = bias {
                         ht += &bias.slice(s!(dir, 5 * hidden_size..6 * hidden_size));
                     }
-                    ht + x_zrh.index_axis(Axis(1), 2)
+                    ht + x_zrh.slice_axis(Axis(1), gate(2))
                 };
                 let ht1 = self
                     .g
@@ -22,6 +22,8 @@ pub fn lstm(
     lstm.optional_y_h_output = options.next().unwrap();
     lstm.optional_y_c_output = options.next().unwrap();
 
+    lstm.direction = super::Direction::parse(pb)?;
+
     Ok((Box::new(lstm), vec![]))
 }
 
@@ -35,11 +37,27 @@ pub struct LSTM {
     pub optional_y_output: Option<usize>,
     pub optional_y_h_output: Option<usize>,
     pub optional_y_c_output: Option<usize>,
+    pub direction: super::Direction,
     pub f: Box<dyn TypedOp>,
     pub g: Box<dyn TypedOp>,
     pub h: Box<dyn TypedOp>,
 }
 
+impl_native_codec!(LSTM {
+    optional_bias_input,
+    optional_sequence_lens_input,
+    optional_initial_h_input,
+    optional_initial_c_input,
+    optional_p_input,
+    optional_y_output,
+    optional_y_h_output,
+    optional_y_c_output,
+    direction,
+    f,
+    g,
+    h
+});
+
 impl Default for LSTM {
     fn default() -> LSTM {
         LSTM {
@@ -51,6 +69,7 @@ impl Default for LSTM {
             optional_y_output: None,
             optional_y_h_output: None,
             optional_y_c_output: None,
+            direction: super::Direction::Forward,
             f: Box::new(ops::nn::sigmoid()),
             g: Box::new(ops::math::tanh()),
             h: Box::new(ops::math::tanh()),
@@ -67,7 +86,7 @@ impl Op for LSTM {
         Validation::Rounding
     }
 
-    not_a_typed_op!();
+    op_as_typed_op!();
 }
 
 impl InferenceRulesOp for LSTM {
@@ -168,6 +187,16 @@ impl InferenceRulesOp for LSTM {
         target: &mut TypedModel,
         mapping: &HashMap<OutletId, OutletId>,
     ) -> TractResult<TVec<OutletId>> {
+        if self.direction != super::Direction::Forward {
+            return super::WholeSequence::wire(
+                self.direction,
+                Box::new(self.clone()),
+                node,
+                target,
+                mapping,
+            );
+        }
+
         use tract_hir::ops::{array, math, matmul, scan};
 
         let x_fact = target.outlet_fact(mapping[&node.inputs[0]])?.clone();
@@ -176,8 +205,6 @@ impl InferenceRulesOp for LSTM {
         let b_size = x_fact.shape.dim(1).to_integer().unwrap() as usize;
         let h_size = r_fact.shape.dim(2).to_integer().unwrap() as usize;
 
-        // FIXME: bidi
-
         let mut body = TypedModel::default();
         let mut outer_inputs = vec![];
         let mut input_mapping = vec![];
@@ -434,7 +461,7 @@ impl InferenceRulesOp for LSTM {
 
         let mut result = tvec!();
         if let Some(slot) = self.optional_y_output {
-            target_wire!(y = AxisOp::Add(0), scan_outputs[slot]);
+            target_wire!(y = AxisOp::Add(1), scan_outputs[slot]);
             result.push(y);
         }
         if let Some(slot) = self.optional_y_h_output {
@@ -448,6 +475,20 @@ impl InferenceRulesOp for LSTM {
     }
 }
 
+impl TypedOp for LSTM {
+    as_op!();
+    native_save!("onnx.LSTM");
+
+    fn output_facts(&self, inputs: &[&TypedFact]) -> TractResult<TVec<TypedFact>> {
+        super::output_facts(
+            inputs,
+            self.optional_y_output.is_some(),
+            self.optional_y_h_output.is_some() as usize
+                + self.optional_y_c_output.is_some() as usize,
+        )
+    }
+}
+
 impl StatelessOp for LSTM {
     fn eval(&self, inputs: TVec<Arc<Tensor>>) -> TractResult<TVec<Arc<Tensor>>> {
         let x: ArrayView3<f32> = inputs[0].to_array_view::<f32>()?.into_dimensionality()?; // [seq_length, batch_size, input_size]
@@ -509,7 +550,7 @@ impl StatelessOp for LSTM {
             let peephole = peephole.map(|p| p.index_axis_move(Axis(0), dir));
 
             for ix in 0..seq_length {
-                let ix = if dir == 0 { ix } else { seq_length - 1 - ix };
+                let ix = if self.direction.is_backward(dir) { seq_length - 1 - ix } else { ix };
                 let x = x.index_axis_move(Axis(0), ix);
                 // x -> batch_size x input_size
                 // Wt -> k=input_size x n=4*hidden_size
@@ -521,9 +562,9 @@ impl StatelessOp for LSTM {
                     iofc += &bias.slice(s!(dir, 4 * hidden_size..8 * hidden_size));
                 }
 
-                let iofc = iofc.into_shape((batch_size, 4, hidden_size))?;
+                let gate = |k: usize| (k * hidden_size..(k + 1) * hidden_size).into();
 
-                let mut i = iofc.index_axis(Axis(1), 0).to_owned();
+                let mut i = iofc.slice_axis(Axis(1), gate(0)).to_owned();
                 if let Some(peephole) = peephole {
                     i += &(ct.to_owned() * peephole.index_axis(Axis(0), 0));
                 }
@@ -535,7 +576,7 @@ impl StatelessOp for LSTM {
                     .into_array::<f32>()?
                     .into_shape((batch_size, hidden_size))?;
 
-                let mut f = iofc.index_axis(Axis(1), 2).to_owned();
+                let mut f = iofc.slice_axis(Axis(1), gate(2)).to_owned();
                 if let Some(peephole) = peephole {
                     f += &(ct.to_owned() * peephole.index_axis(Axis(0), 2));
                 }
@@ -547,7 +588,7 @@ impl StatelessOp for LSTM {
                     .into_array::<f32>()?
                     .into_shape((batch_size, hidden_size))?;
 
-                let c = iofc.index_axis(Axis(1), 3).to_owned();
+                let c = iofc.slice_axis(Axis(1), gate(3)).to_owned();
                 let mut c = self.g.as_stateless().unwrap().eval(tvec!(c.into_arc_tensor()))?;
 
                 let c = c
@@ -559,7 +600,7 @@ impl StatelessOp for LSTM {
 
                 let big_c = f * &ct + i * c;
 
-                let mut o = iofc.index_axis(Axis(1), 1).to_owned();
+                let mut o = iofc.slice_axis(Axis(1), gate(1)).to_owned();
                 if let Some(peephole) = peephole {
                     o += &(big_c.to_owned() * peephole.index_axis(Axis(0), 1));
                 }
@@ -19,6 +19,8 @@ pub fn rnn(
     rnn.optional_y_output = options.next().unwrap();
     rnn.optional_y_h_output = options.next().unwrap();
 
+    rnn.direction = super::Direction::parse(pb)?;
+
     Ok((Box::new(rnn), vec![]))
 }
 
@@ -29,10 +31,22 @@ pub struct RNN {
     pub optional_initial_h_input: Option<usize>,
     pub optional_y_output: Option<usize>,
     pub optional_y_h_output: Option<usize>,
+    pub direction: super::Direction,
     pub fore: Box<dyn TypedOp>,
     pub back: Box<dyn TypedOp>,
 }
 
+impl_native_codec!(RNN {
+    optional_bias_input,
+    optional_sequence_lens_input,
+    optional_initial_h_input,
+    optional_y_output,
+    optional_y_h_output,
+    direction,
+    fore,
+    back
+});
+
 impl Default for RNN {
     fn default() -> RNN {
         RNN {
@@ -41,6 +55,7 @@ impl Default for RNN {
             optional_initial_h_input: None,
             optional_y_output: None,
             optional_y_h_output: None,
+            direction: super::Direction::Forward,
             fore: Box::new(ops::math::tanh()),
             back: Box::new(ops::math::tanh()),
         }
@@ -56,7 +71,7 @@ impl Op for RNN {
         Validation::Rounding
     }
 
-    not_a_typed_op!();
+    op_as_typed_op!();
 }
 
 impl InferenceRulesOp for RNN {
@@ -132,6 +147,16 @@ impl InferenceRulesOp for RNN {
         target: &mut TypedModel,
         mapping: &HashMap<OutletId, OutletId>,
     ) -> TractResult<TVec<OutletId>> {
+        if self.direction != super::Direction::Forward {
+            return super::WholeSequence::wire(
+                self.direction,
+                Box::new(self.clone()),
+                node,
+                target,
+                mapping,
+            );
+        }
+
         use ops::{array, math, matmul, scan};
 
         let x_fact = target.outlet_fact(mapping[&node.inputs[0]])?.clone();
@@ -140,8 +165,6 @@ impl InferenceRulesOp for RNN {
         let b_size = x_fact.shape.dim(1).to_integer().unwrap() as usize;
         let h_size = r_fact.shape.dim(2).to_integer().unwrap() as usize;
 
-        // FIXME: bidi
-
         let mut body = TypedModel::default();
         let mut outer_inputs = vec![];
         let mut input_mapping = vec![];
@@ -272,7 +295,7 @@ impl InferenceRulesOp for RNN {
 
         let mut result = tvec!();
         if let Some(slot) = self.optional_y_output {
-            target_wire!(y = AxisOp::Add(0), scan_outputs[slot]);
+            target_wire!(y = AxisOp::Add(1), scan_outputs[slot]);
             result.push(y);
         }
         if let Some(slot) = self.optional_y_h_output {
@@ -283,6 +306,19 @@ impl InferenceRulesOp for RNN {
     }
 }
 
+impl TypedOp for RNN {
+    as_op!();
+    native_save!("onnx.RNN");
+
+    fn output_facts(&self, inputs: &[&TypedFact]) -> TractResult<TVec<TypedFact>> {
+        super::output_facts(
+            inputs,
+            self.optional_y_output.is_some(),
+            self.optional_y_h_output.is_some() as usize,
+        )
+    }
+}
+
 impl StatelessOp for RNN {
     fn eval(&self, inputs: TVec<Arc<Tensor>>) -> TractResult<TVec<Arc<Tensor>>> {
         let x: ArrayView3<f32> = inputs[0].to_array_view::<f32>()?.into_dimensionality()?; // [seq_length, batch_size, input_size]
@@ -323,7 +359,7 @@ impl StatelessOp for RNN {
             };
 
             for ix in 0..seq_length {
-                let ix = if dir == 0 { ix } else { seq_length - 1 - ix };
+                let ix = if self.direction.is_backward(dir) { seq_length - 1 - ix } else { ix };
                 let x = x.index_axis_move(Axis(0), ix);
 
                 let mut ht1 = x.dot(&w.t()) + ht.dot(&r.t()); // batch_size x 4*hidden_size
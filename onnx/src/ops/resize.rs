@@ -0,0 +1,175 @@
+use crate::model::{optional_inputs, OnnxOpRegister, ParsingContext};
+use crate::pb::NodeProto;
+use tract_hir::internal::*;
+use tract_hir::ops::array::{CoordTransformer, Interpolator, Nearest, Resize};
+
+pub fn register_all_ops(reg: &mut OnnxOpRegister) {
+    reg.insert_since("", "Resize", 10, resize10);
+    reg.insert_since("", "Resize", 11, resize);
+    reg.insert_since("", "Upsample", 7, upsample7);
+    reg.insert_since("", "Upsample", 9, upsample);
+}
+
+fn interpolator(node: &NodeProto) -> TractResult<Interpolator> {
+    match node.get_attr_opt("mode")?.unwrap_or("nearest") {
+        "nearest" => Ok(Interpolator::Nearest),
+        "linear" | "bilinear" => Ok(Interpolator::Linear),
+        "cubic" => Ok(Interpolator::Cubic),
+        mode => bail!("Unsupported resize mode: {}", mode),
+    }
+}
+
+/// Resize as Upsample and Resize-10 do it: asymmetric coordinates, nearest
+/// rounding down.
+fn legacy_resize(node: &NodeProto, optional_scales_input: Option<usize>) -> TractResult<Resize> {
+    Ok(Resize {
+        coord_transformer: CoordTransformer::Asymmetric,
+        interpolator: interpolator(node)?,
+        nearest: Nearest::Floor,
+        cubic_coeff_a: -0.75,
+        exclude_outside: false,
+        extrapolation_value: 0.0,
+        optional_roi_input: None,
+        optional_scales_input,
+        optional_sizes_input: None,
+    })
+}
+
+pub fn resize10(
+    _ctx: &ParsingContext,
+    node: &NodeProto,
+) -> TractResult<(Box<dyn InferenceOp>, Vec<String>)> {
+    Ok((Box::new(legacy_resize(node, Some(1))?), vec![]))
+}
+
+pub fn resize(
+    _ctx: &ParsingContext,
+    node: &NodeProto,
+) -> TractResult<(Box<dyn InferenceOp>, Vec<String>)> {
+    let coord_transformer =
+        match node.get_attr_opt("coordinate_transformation_mode")?.unwrap_or("half_pixel") {
+            "half_pixel" => CoordTransformer::HalfPixel,
+            "align_corners" => CoordTransformer::AlignCorners,
+            "asymmetric" => CoordTransformer::Asymmetric,
+            "pytorch_half_pixel" => CoordTransformer::PytorchHalfPixel,
+            "tf_half_pixel_for_nn" => CoordTransformer::TfHalfPixelForNn,
+            "tf_crop_and_resize" => CoordTransformer::TfCropAndResize,
+            mode => bail!("Unsupported coordinate_transformation_mode: {}", mode),
+        };
+    let nearest = match node.get_attr_opt("nearest_mode")?.unwrap_or("round_prefer_floor") {
+        "round_prefer_floor" => Nearest::RoundPreferFloor,
+        "round_prefer_ceil" => Nearest::RoundPreferCeil,
+        "floor" => Nearest::Floor,
+        "ceil" => Nearest::Ceil,
+        mode => bail!("Unsupported nearest_mode: {}", mode),
+    };
+    let mut options = optional_inputs(node).skip(1);
+    let op = Resize {
+        coord_transformer,
+        interpolator: interpolator(node)?,
+        nearest,
+        cubic_coeff_a: node.get_attr_opt("cubic_coeff_a")?.unwrap_or(-0.75),
+        exclude_outside: node.get_attr_opt::<i64>("exclude_outside")?.unwrap_or(0) == 1,
+        extrapolation_value: node.get_attr_opt("extrapolation_value")?.unwrap_or(0.0),
+        optional_roi_input: options.next().unwrap(),
+        optional_scales_input: options.next().unwrap(),
+        optional_sizes_input: options.next().unwrap(),
+    };
+    Ok((Box::new(op), vec![]))
+}
+
+pub fn upsample7(
+    _ctx: &ParsingContext,
+    node: &NodeProto,
+) -> TractResult<(Box<dyn InferenceOp>, Vec<String>)> {
+    let scales: Vec<f32> = node.get_attr_vec("scales")?;
+    let op = Upsample7 { resize: legacy_resize(node, Some(1))?, scales: rctensor1(&scales) };
+    Ok((Box::new(op), vec![]))
+}
+
+pub fn upsample(
+    _ctx: &ParsingContext,
+    node: &NodeProto,
+) -> TractResult<(Box<dyn InferenceOp>, Vec<String>)> {
+    Ok((Box::new(legacy_resize(node, Some(1))?), vec![]))
+}
+
+/// Upsample up to opset 8, with the scales as an attribute.
+#[derive(Debug, Clone, Hash)]
+pub struct Upsample7 {
+    resize: Resize,
+    scales: Arc<Tensor>,
+}
+
+impl Op for Upsample7 {
+    fn name(&self) -> Cow<str> {
+        "onnx.Upsample".into()
+    }
+
+    not_a_typed_op!();
+}
+
+impl StatelessOp for Upsample7 {
+    fn eval(&self, mut inputs: TVec<Arc<Tensor>>) -> TractResult<TVec<Arc<Tensor>>> {
+        inputs.push(self.scales.clone());
+        self.resize.eval(inputs)
+    }
+}
+
+impl InferenceRulesOp for Upsample7 {
+    fn rules<'r, 'p: 'r, 's: 'r>(
+        &'s self,
+        s: &mut Solver<'r>,
+        inputs: &'p [TensorProxy],
+        outputs: &'p [TensorProxy],
+    ) -> InferenceResult {
+        check_input_arity(&inputs, 1)?;
+        check_output_arity(&outputs, 1)?;
+        s.equals(&inputs[0].datum_type, &outputs[0].datum_type)?;
+        s.equals(&inputs[0].rank, &outputs[0].rank)?;
+        s.given(&inputs[0].shape, move |s, shape| {
+            let scales = self.scales.as_slice::<f32>()?;
+            let output_shape = self.resize.compute_output_shape(&*shape, Some(scales), None)?;
+            s.equals(&outputs[0].shape, output_shape)
+        })
+    }
+
+    fn to_typed(
+        &self,
+        _source: &InferenceModel,
+        node: &InferenceNode,
+        target: &mut TypedModel,
+        mapping: &HashMap<OutletId, OutletId>,
+    ) -> TractResult<TVec<OutletId>> {
+        let scales = target.add_const(format!("{}-scales", node.name), self.scales.clone())?;
+        target.wire_node(&*node.name, self.resize.clone(), &[mapping[&node.inputs[0]], scales])
+    }
+
+    as_op!();
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::test_util::*;
+    use tract_hir::internal::*;
+
+    #[test]
+    fn resize_from_opset_10() {
+        let resize = node("Resize", &["x", "scales"], &["y"], vec![]);
+        let scales = || vec![tensor("scales", &tensor1(&[1f32, 2.]))];
+        assert!(run_node(9, resize.clone(), &[1, 2], scales()).is_err());
+        let y = run_node(10, resize, &[1, 2], scales()).unwrap();
+        assert_eq!(*y[0], tensor2(&[[1f32, 1., 2., 2.]]));
+    }
+
+    #[test]
+    fn upsample_from_opset_7() {
+        let upsample7 = node("Upsample", &["x"], &["y"], vec![attr_floats("scales", &[1., 2.])]);
+        let y = run_node(7, upsample7, &[1, 2], vec![]).unwrap();
+        assert_eq!(*y[0], tensor2(&[[1f32, 1., 2., 2.]]));
+        let upsample9 = node("Upsample", &["x", "scales"], &["y"], vec![]);
+        let scales = vec![tensor("scales", &tensor1(&[1f32, 2.]))];
+        let y = run_node(9, upsample9, &[1, 2], scales).unwrap();
+        assert_eq!(*y[0], tensor2(&[[1f32, 1., 2., 2.]]));
+    }
+}
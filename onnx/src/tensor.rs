@@ -1,8 +1,11 @@
 use crate::pb::tensor_proto::DataType;
 use crate::pb::*;
 use prost::Message;
+use std::collections::HashMap;
 use std::convert::{TryFrom, TryInto};
+use std::{fs, path};
 use tract_hir::internal::*;
+use tract_hir::tract_core::tensor::mmap::Mmap;
 
 impl TryFrom<DataType> for DatumType {
     type Error = TractError;
@@ -24,28 +27,38 @@ impl TryFrom<DataType> for DatumType {
     }
 }
 
+/// Translate a tensor type, mapping `dim_param` dimensions to symbols.
+///
+/// Named dimensions absent from `symbols` are left undetermined.
+pub fn translate_inference_fact(
+    t: &type_proto::Tensor,
+    symbols: &HashMap<String, char>,
+) -> TractResult<InferenceFact> {
+    let mut fact = InferenceFact::default();
+    fact = fact.with_datum_type(DataType::from_i32(t.elem_type).unwrap().try_into()?);
+    if let Some(shape) = &t.shape {
+        let shape: TVec<DimFact> = shape
+            .dim
+            .iter()
+            .map(|d| match &d.value {
+                Some(tensor_shape_proto::dimension::Value::DimValue(v)) if *v > 0 => {
+                    DimFact::from(v.to_dim())
+                }
+                Some(tensor_shape_proto::dimension::Value::DimParam(p)) => {
+                    symbols.get(p).map(|&s| DimFact::from(TDim::sym(s))).unwrap_or_default()
+                }
+                _ => DimFact::default(),
+            })
+            .collect();
+        fact = fact.with_shape(ShapeFactoid::closed(shape));
+    }
+    Ok(fact)
+}
+
 impl<'a> TryFrom<&'a type_proto::Tensor> for InferenceFact {
     type Error = TractError;
     fn try_from(t: &'a type_proto::Tensor) -> TractResult<InferenceFact> {
-        let mut fact = InferenceFact::default();
-        fact = fact.with_datum_type(DataType::from_i32(t.elem_type).unwrap().try_into()?);
-        if let Some(shape) = &t.shape {
-            let shape: TVec<DimFact> = shape
-                .dim
-                .iter()
-                .map(|d| {
-                    let mut fact = DimFact::default();
-                    if let Some(tensor_shape_proto::dimension::Value::DimValue(v)) = d.value {
-                        if v > 0 {
-                            fact = DimFact::from(v.to_dim())
-                        }
-                    }
-                    fact
-                })
-                .collect();
-            fact = fact.with_shape(ShapeFactoid::closed(shape));
-        }
-        Ok(fact)
+        translate_inference_fact(t, &HashMap::new())
     }
 }
 
@@ -56,9 +69,102 @@ impl TryFrom<type_proto::Tensor> for InferenceFact {
     }
 }
 
+/// Inline tensor data at least this big is read in place from the model file
+/// when the model is loaded from its path.
+pub const MMAP_THRESHOLD: usize = 4096;
+
+/// Rewrite a serialized model so that the initializers with big raw data
+/// refer to the same bytes in the model file, `location`, as external data.
+pub fn externalize_initializers(model: &[u8], location: &str) -> TractResult<Vec<u8>> {
+    use tract_hir::pb_wire::*;
+    map_len_fields(model, 7, |graph| {
+        map_len_fields(graph, 5, |tensor| {
+            let mut rewritten = vec![];
+            let mut raw_data = None;
+            for field in fields(tensor)? {
+                if field.tag == 9 && field.payload.len() >= MMAP_THRESHOLD {
+                    raw_data = Some(field.payload);
+                } else {
+                    rewritten.extend_from_slice(field.raw);
+                }
+            }
+            if let Some(raw_data) = raw_data {
+                let offset = raw_data.as_ptr() as usize - model.as_ptr() as usize;
+                let entry = |key: &str, value: String| StringStringEntryProto {
+                    key: key.to_string(),
+                    value,
+                };
+                let external = TensorProto {
+                    external_data: vec![
+                        entry("location", location.to_string()),
+                        entry("offset", offset.to_string()),
+                        entry("length", raw_data.len().to_string()),
+                    ],
+                    data_location: tensor_proto::DataLocation::External as i32,
+                    ..TensorProto::default()
+                };
+                // serialized messages concatenation merges them
+                external.encode(&mut rewritten).map_err(|e| format!("{:?}", e))?;
+            }
+            Ok(rewritten)
+        })
+    })
+}
+
+/// Load a tensor, reading its data from its external file if it has one.
+///
+/// External data locations are relative to the directory of the model. The
+/// data is memory mapped, and plain tensors are used in place.
+pub fn load_tensor(t: &TensorProto, model_dir: Option<&path::Path>) -> TractResult<Tensor> {
+    if t.data_location != tensor_proto::DataLocation::External as i32 {
+        return t.try_into();
+    }
+    let model_dir = model_dir.ok_or_else(|| {
+        format!("Tensor {} has external data, the model must be loaded from its path", t.name)
+    })?;
+    let mut location = None;
+    let mut offset = 0u64;
+    let mut length = None;
+    for entry in &t.external_data {
+        match &*entry.key {
+            "location" => location = Some(&entry.value),
+            "offset" => offset = entry.value.parse()?,
+            "length" => length = Some(entry.value.parse::<u64>()?),
+            _ => (),
+        }
+    }
+    let location =
+        location.ok_or_else(|| format!("Tensor {} external data has no location", t.name))?;
+    let file = fs::File::open(model_dir.join(location))
+        .map_err(|e| format!("Opening external data {} for {}: {}", location, t.name, e))?;
+    let length = match length {
+        Some(length) => length,
+        None => file.metadata()?.len().saturating_sub(offset),
+    };
+    let mmap = Mmap::map(&file, offset, length as usize)
+        .chain_err(|| format!("Reading external data {} for {}", location, t.name))?;
+    let dt: DatumType = DataType::from_i32(t.data_type)
+        .ok_or_else(|| format!("Unknown DataType {} for {}", t.data_type, t.name))?
+        .try_into()?;
+    if dt != DatumType::Bool && dt != DatumType::String {
+        let shape: Vec<usize> = t.dims.iter().map(|&i| i as usize).collect();
+        return unsafe { Tensor::from_mmap(dt, &shape, Arc::new(mmap), 0) };
+    }
+    let inlined = TensorProto {
+        raw_data: mmap.as_bytes().to_vec(),
+        external_data: vec![],
+        data_location: tensor_proto::DataLocation::Default as i32,
+        ..t.clone()
+    };
+    (&inlined).try_into()
+}
+
 impl<'a> TryFrom<&'a TensorProto> for Tensor {
     type Error = TractError;
     fn try_from(t: &TensorProto) -> TractResult<Tensor> {
+        if t.data_location == tensor_proto::DataLocation::External as i32 {
+            bail!("Tensor {} has external data, use load_tensor", t.name)
+        }
         let dt = DataType::from_i32(t.data_type).unwrap().try_into()?;
         let shape: Vec<usize> = t.dims.iter().map(|&i| i as usize).collect();
         if t.raw_data.len() > 0 {
@@ -143,3 +249,84 @@ pub fn proto_from_reader<R: ::std::io::Read>(mut r: R) -> TractResult<TensorProt
 pub fn from_reader<R: ::std::io::Read>(r: R) -> TractResult<Tensor> {
     proto_from_reader(r)?.try_into()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn external(location: &str, offset: Option<usize>, length: Option<usize>) -> TensorProto {
+        let mut external_data =
+            vec![StringStringEntryProto { key: "location".into(), value: location.into() }];
+        for (key, value) in &[("offset", offset), ("length", length)] {
+            if let Some(value) = value {
+                external_data.push(StringStringEntryProto {
+                    key: key.to_string(),
+                    value: value.to_string(),
+                });
+            }
+        }
+        TensorProto {
+            name: "t".into(),
+            dims: vec![2],
+            data_type: DataType::Float as i32,
+            external_data,
+            data_location: tensor_proto::DataLocation::External as i32,
+            ..TensorProto::default()
+        }
+    }
+
+    // 4 bytes of padding, then 1.0, 2.0, 3.0 as f32
+    fn model_dir() -> path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("tract-onnx-external-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let mut data = vec![0u8; 4];
+        for f in &[1f32, 2., 3.] {
+            data.extend_from_slice(&f.to_le_bytes());
+        }
+        fs::write(dir.join("weights.bin"), data).unwrap();
+        dir
+    }
+
+    #[test]
+    fn external_data_with_offset_and_length() {
+        let dir = model_dir();
+        let t = load_tensor(&external("weights.bin", Some(8), Some(8)), Some(&dir)).unwrap();
+        assert_eq!(t, tensor1(&[2f32, 3.]));
+        let t = load_tensor(&external("weights.bin", Some(4), Some(8)), Some(&dir)).unwrap();
+        assert_eq!(t, tensor1(&[1f32, 2.]));
+    }
+
+    #[test]
+    fn external_data_up_to_end_of_file() {
+        let dir = model_dir();
+        let t = load_tensor(&external("weights.bin", Some(8), None), Some(&dir)).unwrap();
+        assert_eq!(t, tensor1(&[2f32, 3.]));
+    }
+
+    #[test]
+    fn external_data_out_of_file() {
+        let dir = model_dir();
+        assert!(load_tensor(&external("weights.bin", Some(12), Some(8)), Some(&dir)).is_err());
+    }
+
+    #[test]
+    fn external_data_missing_file() {
+        let dir = model_dir();
+        let err = load_tensor(&external("missing.bin", None, None), Some(&dir)).unwrap_err();
+        assert!(err.to_string().contains("Opening external data missing.bin"));
+    }
+
+    #[test]
+    fn external_data_unknown_data_type() {
+        let dir = model_dir();
+        let t = TensorProto { data_type: 1000, ..external("weights.bin", None, None) };
+        let err = load_tensor(&t, Some(&dir)).unwrap_err();
+        assert!(err.to_string().contains("Unknown DataType 1000"), "{:?}", err);
+    }
+
+    #[test]
+    fn external_data_needs_model_dir() {
+        assert!(load_tensor(&external("weights.bin", None, None), None).is_err());
+        assert!(Tensor::try_from(&external("weights.bin", None, None)).is_err());
+    }
+}
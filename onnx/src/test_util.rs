@@ -0,0 +1,193 @@
+//! Builders for small ONNX models, for tests.
+
+use crate::pb::*;
+use attribute_proto::AttributeType;
+use tensor_proto::DataType;
+use tract_hir::internal::*;
+
+pub fn data_type(dt: DatumType) -> DataType {
+    match dt {
+        DatumType::Bool => DataType::Bool,
+        DatumType::U8 => DataType::Uint8,
+        DatumType::I8 => DataType::Int8,
+        DatumType::I32 => DataType::Int32,
+        DatumType::I64 => DataType::Int64,
+        DatumType::F32 => DataType::Float,
+        DatumType::F64 => DataType::Double,
+        dt => panic!("no test support for {:?}", dt),
+    }
+}
+
+/// A tensor with typed data fields.
+pub fn tensor(name: &str, t: &Tensor) -> TensorProto {
+    let mut proto = TensorProto {
+        name: name.to_string(),
+        dims: t.shape().iter().map(|&d| d as i64).collect(),
+        data_type: data_type(t.datum_type()) as i32,
+        ..TensorProto::default()
+    };
+    match t.datum_type() {
+        DatumType::F32 => proto.float_data = t.as_slice::<f32>().unwrap().to_vec(),
+        DatumType::I64 => proto.int64_data = t.as_slice::<i64>().unwrap().to_vec(),
+        DatumType::I32 => proto.int32_data = t.as_slice::<i32>().unwrap().to_vec(),
+        DatumType::Bool => {
+            proto.int32_data = t.as_slice::<bool>().unwrap().iter().map(|&b| b as i32).collect()
+        }
+        dt => panic!("no test support for {:?}", dt),
+    }
+    proto
+}
+
+/// An f32 tensor with its data in `raw_data`.
+pub fn raw_tensor(name: &str, t: &Tensor) -> TensorProto {
+    TensorProto {
+        name: name.to_string(),
+        dims: t.shape().iter().map(|&d| d as i64).collect(),
+        data_type: DataType::Float as i32,
+        raw_data: t
+            .as_slice::<f32>()
+            .unwrap()
+            .iter()
+            .flat_map(|f| f.to_le_bytes().to_vec())
+            .collect(),
+        ..TensorProto::default()
+    }
+}
+
+fn attr(name: &str, t: AttributeType) -> AttributeProto {
+    AttributeProto { name: name.to_string(), r#type: t as i32, ..AttributeProto::default() }
+}
+
+pub fn attr_int(name: &str, i: i64) -> AttributeProto {
+    AttributeProto { i, ..attr(name, AttributeType::Int) }
+}
+
+pub fn attr_ints(name: &str, ints: &[i64]) -> AttributeProto {
+    AttributeProto { ints: ints.to_vec(), ..attr(name, AttributeType::Ints) }
+}
+
+pub fn attr_float(name: &str, f: f32) -> AttributeProto {
+    AttributeProto { f, ..attr(name, AttributeType::Float) }
+}
+
+pub fn attr_floats(name: &str, floats: &[f32]) -> AttributeProto {
+    AttributeProto { floats: floats.to_vec(), ..attr(name, AttributeType::Floats) }
+}
+
+pub fn attr_string(name: &str, s: &str) -> AttributeProto {
+    AttributeProto { s: s.as_bytes().to_vec(), ..attr(name, AttributeType::String) }
+}
+
+pub fn attr_tensor(name: &str, t: &Tensor) -> AttributeProto {
+    AttributeProto { t: Some(tensor("", t)), ..attr(name, AttributeType::Tensor) }
+}
+
+pub fn attr_graph(name: &str, g: GraphProto) -> AttributeProto {
+    AttributeProto { g: Some(g), ..attr(name, AttributeType::Graph) }
+}
+
+pub fn node(op: &str, inputs: &[&str], outputs: &[&str], attrs: Vec<AttributeProto>) -> NodeProto {
+    NodeProto {
+        op_type: op.to_string(),
+        input: inputs.iter().map(|s| s.to_string()).collect(),
+        output: outputs.iter().map(|s| s.to_string()).collect(),
+        attribute: attrs,
+        ..NodeProto::default()
+    }
+}
+
+/// A typed value. Dimensions are given as numbers or as `dim_param` names.
+pub fn value(name: &str, dt: DatumType, shape: &[&str]) -> ValueInfoProto {
+    use tensor_shape_proto::dimension::Value;
+    let dim = shape
+        .iter()
+        .map(|d| tensor_shape_proto::Dimension {
+            value: Some(match d.parse::<i64>() {
+                Ok(v) => Value::DimValue(v),
+                Err(_) => Value::DimParam(d.to_string()),
+            }),
+            ..tensor_shape_proto::Dimension::default()
+        })
+        .collect();
+    let t = type_proto::Tensor {
+        elem_type: data_type(dt) as i32,
+        shape: Some(TensorShapeProto { dim }),
+    };
+    ValueInfoProto {
+        name: name.to_string(),
+        r#type: Some(TypeProto {
+            value: Some(type_proto::Value::TensorType(t)),
+            ..TypeProto::default()
+        }),
+        ..ValueInfoProto::default()
+    }
+}
+
+/// A value with a type but no shape.
+pub fn value_of_any_shape(name: &str, dt: DatumType) -> ValueInfoProto {
+    let t = type_proto::Tensor { elem_type: data_type(dt) as i32, shape: None };
+    ValueInfoProto {
+        name: name.to_string(),
+        r#type: Some(TypeProto {
+            value: Some(type_proto::Value::TensorType(t)),
+            ..TypeProto::default()
+        }),
+        ..ValueInfoProto::default()
+    }
+}
+
+pub fn graph(
+    nodes: Vec<NodeProto>,
+    inputs: Vec<ValueInfoProto>,
+    outputs: Vec<ValueInfoProto>,
+    initializers: Vec<TensorProto>,
+) -> GraphProto {
+    GraphProto {
+        node: nodes,
+        input: inputs,
+        output: outputs,
+        initializer: initializers,
+        ..GraphProto::default()
+    }
+}
+
+/// A model importing the default operator set in `version`.
+pub fn model(graph: GraphProto, version: i64) -> ModelProto {
+    ModelProto {
+        ir_version: 6,
+        opset_import: vec![OperatorSetIdProto { domain: String::new(), version }],
+        graph: Some(graph),
+        ..ModelProto::default()
+    }
+}
+
+/// Run a model through its inference ops and through its typed lowering,
+/// check they agree and return the outputs.
+pub fn run_model(proto: &ModelProto, inputs: TVec<Tensor>) -> TractResult<TVec<Arc<Tensor>>> {
+    let mut model = crate::onnx().model_for_proto_model(proto)?;
+    model.analyse(false)?;
+    let inferred = SimplePlan::new(model.clone())?.run(inputs.clone())?;
+    let typed = SimplePlan::new(model.into_optimized()?)?.run(inputs)?;
+    if inferred != typed {
+        bail!("Inference and typed models disagree: {:?} vs {:?}", inferred, typed)
+    }
+    Ok(typed)
+}
+
+/// Run a single node model on an f32 input x of the given shape, filled with
+/// 1, 2, 3...
+pub fn run_node(
+    opset: i64,
+    node: NodeProto,
+    x_shape: &[usize],
+    initializers: Vec<TensorProto>,
+) -> TractResult<TVec<Arc<Tensor>>> {
+    let shape: Vec<String> = x_shape.iter().map(|d| d.to_string()).collect();
+    let shape: Vec<&str> = shape.iter().map(|s| &**s).collect();
+    let inputs = vec![value("x", f32::datum_type(), &shape)];
+    let outputs = node.output.iter().map(|o| value_of_any_shape(o, f32::datum_type())).collect();
+    let proto = model(graph(vec![node], inputs, outputs, initializers), opset);
+    let len = x_shape.iter().product::<usize>();
+    let x = tract_ndarray::Array::from_shape_fn(len, |i| i as f32 + 1.).into_shape(x_shape)?;
+    run_model(&proto, tvec!(x.into_tensor()))
+}
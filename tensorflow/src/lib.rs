@@ -57,6 +57,7 @@ pub mod conform;
 pub mod model;
 pub mod ops;
 pub mod tensor;
+pub mod tensor_bundle;
 pub mod tfpb;
 
 pub use model::Tensorflow;
@@ -1,11 +1,65 @@
-use crate::tfpb::tensorflow::{GraphDef, NodeDef, SavedModel};
+use crate::tensor::EXTERNAL_DATA_ATTR;
+use crate::tensor_bundle::TensorBundle;
+use crate::tfpb::tensorflow::saved_object::Kind;
+use crate::tfpb::tensorflow::tensor_info::Encoding;
+use crate::tfpb::tensorflow::{
+    DataType, FunctionDef, FunctionDefLibrary, GraphDef, NodeDef, SavedModel, SavedObjectGraph,
+    SignatureDef, TensorInfo, TrackableObjectGraph,
+};
 use prost::Message;
+use std::collections::HashSet;
+use std::convert::TryInto;
 use std::{fs, path};
 use tract_hir::internal::*;
+use tract_hir::tract_core::tensor::mmap::Mmap;
 
-#[derive(Default)]
-pub struct ParsingContext {
+/// Checkpoint key of the serialized TrackableObjectGraph in TF2 checkpoints.
+const OBJECT_GRAPH_KEY: &str = "_CHECKPOINTABLE_OBJECT_GRAPH";
+
+pub struct ParsingContext<'a> {
     pub node_output_arities: HashMap<String, usize>,
+    pub framework: &'a Tensorflow,
+    pub library: Option<&'a FunctionDefLibrary>,
+}
+
+impl<'a> ParsingContext<'a> {
+    pub fn function(&self, name: &str) -> TractResult<&'a FunctionDef> {
+        self.library
+            .and_then(|lib| {
+                lib.function.iter().find(|f| f.signature.as_ref().map(|s| &*s.name) == Some(name))
+            })
+            .ok_or_else(|| format!("Function {} not found in graph library", name).into())
+    }
+
+    /// Instantiate a function of the graph library as a model, with one
+    /// source per function argument and one output per returned value.
+    pub fn parse_function(&self, name: &str) -> TractResult<InferenceModel> {
+        let func = self.function(name)?;
+        let signature = func.signature.as_ref().unwrap();
+        let mut model = InferenceModel::default();
+        let mut inputs = tvec!();
+        for arg in &signature.input_arg {
+            let dt = DataType::from_i32(arg.r#type).and_then(|dt| dt.try_into().ok());
+            let fact = dt.map(InferenceFact::dt).unwrap_or_default();
+            inputs.push(model.add_source(&*arg.name, fact)?);
+        }
+        self.framework
+            .parse_nodes(self.library, &func.node_def, &mut model)
+            .map_err(|e| format!("In function {}: {}", name, e))?;
+        let ops = Tensorflow::node_ops(&func.node_def);
+        let mut outputs = tvec!();
+        for arg in &signature.output_arg {
+            let ret = func
+                .ret
+                .get(&arg.name)
+                .ok_or_else(|| format!("Function {} does not return {}", name, arg.name))?;
+            let (node, slot) = self.framework.parse_input(ret, &ops)?;
+            outputs.push(OutletId::new(model.node_by_name(node)?.id, slot));
+        }
+        model.set_input_outlets(&*inputs)?;
+        model.set_output_outlets(&*outputs)?;
+        Ok(model)
+    }
 }
 
 #[derive(Clone, Default)]
@@ -33,16 +87,51 @@ impl Tensorflow {
     // "src_output" indicating which output tensor to use from "node". If
     // "src_output" is 0 the ":0" suffix can be omitted. Regular inputs may
     // optionally be followed by control inputs that have the format "^node".
-    fn parse_input(i: &str) -> TractResult<(&str, usize)> {
+    //
+    // Inside function bodies, inputs are either a function argument name or
+    // "node:output_arg:index", the index counting inside the output_arg list.
+    fn parse_input<'i>(
+        &self,
+        i: &'i str,
+        ops: &HashMap<&str, &str>,
+    ) -> TractResult<(&'i str, usize)> {
         let pair = if i.starts_with("^") {
             (&i[1..], 0)
         } else {
-            let splits: Vec<_> = i.splitn(2, ':').collect();
-            (splits[0], if splits.len() > 1 { splits[1].parse::<usize>()? } else { 0 })
+            let splits: Vec<_> = i.splitn(3, ':').collect();
+            match splits.len() {
+                1 => (splits[0], 0),
+                2 => (splits[0], splits[1].parse::<usize>()?),
+                _ => {
+                    let op = ops.get(splits[0]).cloned().unwrap_or("");
+                    let offset = self.output_arg_offset(op, splits[1]).chain_err(|| {
+                        format!("Locating input {} in node {} outputs", i, splits[0])
+                    })?;
+                    (splits[0], offset + splits[2].parse::<usize>()?)
+                }
+            }
         };
         Ok(pair)
     }
 
+    fn node_ops(nodes: &[NodeDef]) -> HashMap<&str, &str> {
+        nodes.iter().map(|n| (&*n.name, &*n.op)).collect()
+    }
+
+    /// Position of the first tensor of an output_arg among the outputs of a
+    /// node.
+    fn output_arg_offset(&self, op: &str, arg: &str) -> TractResult<usize> {
+        if let Some(args) = output_args(op) {
+            args.iter().position(|a| *a == arg).ok_or_else(|| {
+                format!("{} has no output {} (outputs are {})", op, arg, args.join(", ")).into()
+            })
+        } else if self.op_register.0.contains_key(op) {
+            Ok(0)
+        } else {
+            bail!("Outputs of {} are unknown, can not locate its output {}", op, arg)
+        }
+    }
+
     pub fn determinize(model: &mut GraphDef) -> TractResult<()> {
         for pbnode in &mut model.node {
             if pbnode.op == "RandomUniform" {
@@ -64,6 +153,17 @@ impl Tensorflow {
         Ok(GraphDef::decode(b).map_err(|e| format!("{:?}", e))?)
     }
 
+    /// Read a frozen model from a file, leaving the big Const tensors
+    /// content in the memory mapped file.
+    ///
+    /// The content is read in place if its offset in the file is aligned
+    /// for its type, copied otherwise.
+    pub fn read_frozen_model_path(&self, p: impl AsRef<path::Path>) -> TractResult<GraphDef> {
+        let mmap = Mmap::open(p.as_ref())?;
+        let bytes = crate::tensor::externalize_constants(mmap.as_bytes(), p.as_ref())?;
+        Ok(GraphDef::decode(bytes::Bytes::from(bytes)).map_err(|e| format!("{:?}", e))?)
+    }
+
     pub fn open_saved_model(&self, r: &mut dyn std::io::Read) -> TractResult<SavedModel> {
         let mut v = vec![];
         r.read_to_end(&mut v)?;
@@ -77,12 +177,190 @@ impl Tensorflow {
         let mut saved = self.open_saved_model(r)?;
         Ok(saved.meta_graphs.remove(0).graph_def.unwrap())
     }
+
+    /// Read a SavedModel directory, binding the variables to their values
+    /// from the checkpoint and keeping only the nodes the selected signature
+    /// needs.
+    ///
+    /// Without a signature key, "serving_default" is used, or the only
+    /// signature of the model.
+    pub fn read_saved_model_dir(
+        &self,
+        dir: impl AsRef<path::Path>,
+        signature: Option<&str>,
+    ) -> TractResult<(GraphDef, SignatureDef)> {
+        let dir = dir.as_ref();
+        let saved = self.open_saved_model(&mut fs::File::open(dir.join("saved_model.pb"))?)?;
+        let serving = saved.meta_graphs.iter().position(|m| {
+            m.meta_info_def.as_ref().map(|i| i.tags.iter().any(|t| t == "serve")).unwrap_or(false)
+        });
+        let mut meta = saved
+            .meta_graphs
+            .into_iter()
+            .nth(serving.unwrap_or(0))
+            .ok_or("SavedModel contains no graph")?;
+        let mut graph = meta.graph_def.take().ok_or("SavedModel contains no graph")?;
+        let variables = dir.join("variables").join("variables");
+        if variables.with_extension("index").exists() {
+            let bundle = TensorBundle::open(&variables)?;
+            bind_variables(&mut graph, &bundle, meta.object_graph_def.as_ref())?;
+        }
+        let signature = select_signature(&mut meta.signature_def, signature)?;
+        prune(&mut graph, &signature)?;
+        Ok((graph, signature))
+    }
+
+    /// Translate the graph, with the inputs and outputs of the signature,
+    /// ordered by signature key.
+    pub fn model_for_signature(
+        &self,
+        graph: &GraphDef,
+        signature: &SignatureDef,
+    ) -> TractResult<InferenceModel> {
+        let mut model = self.model_for_proto_model(graph)?;
+        let outlets = |infos: &HashMap<String, TensorInfo>| -> TractResult<TVec<OutletId>> {
+            let mut keys = infos.keys().collect::<Vec<_>>();
+            keys.sort();
+            keys.into_iter()
+                .map(|k| {
+                    let (node, slot) =
+                        self.parse_input(tensor_name(&infos[k])?, &HashMap::new())?;
+                    Ok(OutletId::new(model.node_by_name(node)?.id, slot))
+                })
+                .collect()
+        };
+        let inputs = outlets(&signature.inputs)?;
+        let outputs = outlets(&signature.outputs)?;
+        model.set_input_outlets(&inputs)?;
+        model.set_output_outlets(&outputs)?;
+        Ok(model)
+    }
+
+    /// Convenience method: load a SavedModel directory, for the given
+    /// signature.
+    pub fn model_for_saved_model_dir(
+        &self,
+        dir: impl AsRef<path::Path>,
+        signature: Option<&str>,
+    ) -> TractResult<InferenceModel> {
+        let (graph, signature) = self.read_saved_model_dir(dir, signature)?;
+        self.model_for_signature(&graph, &signature)
+    }
+}
+
+fn tensor_name(info: &TensorInfo) -> TractResult<&str> {
+    match &info.encoding {
+        Some(Encoding::Name(name)) => Ok(name),
+        _ => bail!("Only dense tensors are supported in signatures"),
+    }
+}
+
+fn select_signature(
+    signatures: &mut HashMap<String, SignatureDef>,
+    key: Option<&str>,
+) -> TractResult<SignatureDef> {
+    let mut keys = signatures.keys().cloned().collect::<Vec<_>>();
+    keys.sort();
+    let key = match key {
+        Some(key) => key.to_string(),
+        None if signatures.contains_key("serving_default") => "serving_default".to_string(),
+        None if keys.len() == 1 => keys[0].clone(),
+        None => bail!("SavedModel has several signatures, pick one of {:?}", keys),
+    };
+    signatures
+        .remove(&key)
+        .ok_or_else(|| format!("No signature {} in SavedModel, found {:?}", key, keys).into())
+}
+
+/// Replace variables with the constants from the checkpoint.
+///
+/// TF1 checkpoints are keyed by variable names. TF2 ones are keyed by object
+/// paths: the nodes of the SavedModel object graph match the nodes of the
+/// checkpoint object graph, the former giving the variables names, the
+/// latter the checkpoint keys.
+fn bind_variables(
+    graph: &mut GraphDef,
+    bundle: &TensorBundle,
+    objects: Option<&SavedObjectGraph>,
+) -> TractResult<()> {
+    let mut keys: HashMap<String, String> = HashMap::new();
+    if let (Some(objects), true) = (objects, bundle.contains(OBJECT_GRAPH_KEY)) {
+        let tracked = bundle.strings(OBJECT_GRAPH_KEY)?.pop().ok_or("Empty object graph")?;
+        let tracked = TrackableObjectGraph::decode(bytes::Bytes::from(tracked))
+            .map_err(|e| format!("{:?}", e))?;
+        for (saved, tracked) in objects.nodes.iter().zip(tracked.nodes.iter()) {
+            if let Some(Kind::Variable(var)) = &saved.kind {
+                if let Some(attr) = tracked.attributes.iter().find(|a| a.name == "VARIABLE_VALUE") {
+                    keys.insert(var.name.clone(), attr.checkpoint_key.clone());
+                }
+            }
+        }
+    }
+    for node in &mut graph.node {
+        if node.op != "VariableV2" && node.op != "VarHandleOp" {
+            continue;
+        }
+        let shared_name = node.get_attr_opt_str("shared_name")?.filter(|s| s != "");
+        let key = Some(node.name.clone()).into_iter().chain(shared_name).find_map(|name| {
+            keys.get(&name).cloned().or_else(|| Some(name).filter(|n| bundle.contains(n)))
+        });
+        if let Some(key) = key {
+            let (dt, shape, external) = bundle.tensor_location(&key)?;
+            let value = crate::tensor::tensor_proto_for_external_data(dt, &shape)?;
+            let dt: DataType = dt.try_into()?;
+            node.op = "Const".to_string();
+            node.input.clear();
+            node.attr.clear();
+            node.attr.insert("dtype".to_string(), dt.into());
+            node.attr.insert("value".to_string(), value.into());
+            node.attr.insert(EXTERNAL_DATA_ATTR.to_string(), external.to_attr()?);
+        }
+    }
+    Ok(())
+}
+
+/// Drop the nodes the signature does not need, like the checkpoint save and
+/// restore subgraphs.
+fn prune(graph: &mut GraphDef, signature: &SignatureDef) -> TractResult<()> {
+    let mut keep: HashSet<String> = HashSet::new();
+    {
+        let nodes: HashMap<&str, &NodeDef> = graph.node.iter().map(|n| (&*n.name, n)).collect();
+        let mut todo = signature
+            .inputs
+            .values()
+            .chain(signature.outputs.values())
+            .map(|info| Ok(tensor_name(info)?.split(':').next().unwrap().to_string()))
+            .collect::<TractResult<Vec<_>>>()?;
+        while let Some(name) = todo.pop() {
+            if keep.contains(&name) {
+                continue;
+            }
+            let node = nodes.get(&*name).ok_or_else(|| format!("No node {} in graph", name))?;
+            for input in &node.input {
+                if !input.starts_with("^") {
+                    todo.push(input.split(':').next().unwrap().to_string());
+                }
+            }
+            keep.insert(name);
+        }
+    }
+    graph.node.retain(|n| keep.contains(&n.name));
+    Ok(())
 }
 
 impl Framework<GraphDef> for Tensorflow {
     /// This method will try to read as frozen model, then as a saved model.
+    ///
+    /// A SavedModel directory is loaded with its variables, for its default
+    /// signature. Use read_saved_model_dir to pick another signature.
+    ///
+    /// A frozen model file is memory mapped, and the big Const tensors are
+    /// left in it, to be read in place.
     fn proto_model_for_path(&self, r: impl AsRef<path::Path>) -> TractResult<GraphDef> {
-        self.read_frozen_model(&mut fs::File::open(r.as_ref())?)
+        if r.as_ref().is_dir() {
+            return Ok(self.read_saved_model_dir(r, None)?.0);
+        }
+        self.read_frozen_model_path(r.as_ref())
             .or_else(|_| self.read_saved_model(&mut fs::File::open(r.as_ref())?))
     }
 
@@ -92,23 +370,53 @@ impl Framework<GraphDef> for Tensorflow {
         self.read_frozen_model(r)
     }
 
+    /// A SavedModel directory is loaded with its variables, and the inputs
+    /// and outputs of its default signature. A file is read as a frozen model,
+    /// with its big constants read in place.
+    fn model_for_path(&self, p: impl AsRef<path::Path>) -> TractResult<InferenceModel> {
+        if p.as_ref().is_dir() {
+            return self.model_for_saved_model_dir(p, None);
+        }
+        let graph = self
+            .read_frozen_model_path(p.as_ref())
+            .map_err(|e| format!("Could not read {:?}: {}", p.as_ref(), e))?;
+        self.model_for_proto_model(&graph)
+    }
+
     fn model_for_proto_model(&self, graph: &GraphDef) -> TractResult<InferenceModel> {
+        let mut model = InferenceModel::default();
+        let inputs = self.parse_nodes(graph.library.as_ref(), &graph.node, &mut model)?;
+        model.set_input_outlets(&*inputs)?;
+        model.auto_outputs()?;
+        Ok(model)
+    }
+}
+
+impl Tensorflow {
+    /// Translate nodes into `model`, returning the placeholders outlets.
+    fn parse_nodes(
+        &self,
+        library: Option<&FunctionDefLibrary>,
+        nodes: &[NodeDef],
+        model: &mut InferenceModel,
+    ) -> TractResult<TVec<OutletId>> {
         use crate::ops::control_flow as cf;
 
-        let mut model = InferenceModel::default();
         let mut inputs = tvec!();
-        let mut context = ParsingContext::default();
+        let mut context =
+            ParsingContext { node_output_arities: HashMap::new(), framework: self, library };
+        let ops = Self::node_ops(nodes);
 
         // compute min output arity for all nodes
-        for pbnode in &graph.node {
+        for pbnode in nodes {
             for i in &pbnode.input {
-                let (node, slot) = Self::parse_input(i)?;
+                let (node, slot) = self.parse_input(i, &ops)?;
                 let arity = context.node_output_arities.entry(node.to_string()).or_insert(1);
                 *arity = (*arity).max(slot + 1);
             }
         }
 
-        for pbnode in &graph.node {
+        for pbnode in nodes {
             let name = &pbnode.name;
 
             if pbnode.op == "NextIteration" {
@@ -158,23 +466,23 @@ impl Framework<GraphDef> for Tensorflow {
             }
         }
 
-        for pbnode in &graph.node {
+        for pbnode in nodes {
             let node_id = if pbnode.op == "NextIteration" {
                 model.node_by_name(&*format!("{}-Sink", &pbnode.name))?.id
             } else {
                 model.node_by_name(&pbnode.name)?.id
             };
             for (ix, i) in pbnode.input.iter().enumerate() {
-                let input = Self::parse_input(i)?;
-                let prec = model.node_by_name(input.0)?.id;
+                // control dependencies are irrelevant to dataflow evaluation
                 if i.starts_with("^") {
-                    panic!();
-                } else {
-                    let outlet = OutletId::new(prec, input.1);
-                    let inlet = InletId::new(node_id, ix);
-                    model.add_edge(outlet, inlet)?;
-                    model.set_outlet_label(outlet, i.to_string());
+                    continue;
                 }
+                let input = self.parse_input(i, &ops)?;
+                let prec = model.node_by_name(input.0)?.id;
+                let outlet = OutletId::new(prec, input.1);
+                let inlet = InletId::new(node_id, ix);
+                model.add_edge(outlet, inlet)?;
+                model.set_outlet_label(outlet, i.to_string());
             }
         }
 
@@ -201,8 +509,190 @@ impl Framework<GraphDef> for Tensorflow {
                 }
             }
         }
-        model.set_input_outlets(&*inputs)?;
-        model.auto_outputs()?;
-        Ok(model)
+        Ok(inputs)
+    }
+}
+
+/// Output args of the ops with several of them, all made of a single tensor.
+///
+/// Other supported ops have a single output arg, possibly a list.
+fn output_args(op: &str) -> Option<&'static [&'static str]> {
+    Some(match op {
+        "Switch" => &["output_false", "output_true"],
+        "Merge" => &["output", "value_index"],
+        "Unique" => &["y", "idx"],
+        "TopKV2" => &["values", "indices"],
+        "BlockLSTM" | "BlockLSTMV2" => &["i", "cs", "f", "o", "ci", "co", "h"],
+        "FusedBatchNorm" | "FusedBatchNormV2" | "FusedBatchNormV3" => &[
+            "y",
+            "batch_mean",
+            "batch_variance",
+            "reserve_space_1",
+            "reserve_space_2",
+            "reserve_space_3",
+        ],
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tensor_bundle::test::write_bundle;
+    use crate::tfpb::tensorflow::meta_graph_def::MetaInfoDef;
+    use crate::tfpb::tensorflow::trackable_object_graph::trackable_object::SerializedTensor;
+    use crate::tfpb::tensorflow::trackable_object_graph::TrackableObject;
+    use crate::tfpb::tensorflow::*;
+    use crate::tfpb::{graph, node};
+
+    fn tensor_info(name: &str) -> TensorInfo {
+        TensorInfo { encoding: Some(Encoding::Name(name.to_string())), ..TensorInfo::default() }
+    }
+
+    fn save(
+        name: &str,
+        graph_def: GraphDef,
+        object_graph_def: Option<SavedObjectGraph>,
+        variables: &[(&str, Tensor)],
+    ) -> path::PathBuf {
+        let dir = std::env::temp_dir().join(format!("tract-{}-{}", name, std::process::id()));
+        fs::create_dir_all(dir.join("variables")).unwrap();
+        let signature = SignatureDef {
+            inputs: vec![("x".to_string(), tensor_info("x:0"))].into_iter().collect(),
+            outputs: vec![("y".to_string(), tensor_info("y:0"))].into_iter().collect(),
+            method_name: "tensorflow/serving/predict".to_string(),
+        };
+        let meta = MetaGraphDef {
+            meta_info_def: Some(MetaInfoDef {
+                tags: vec!["serve".into()],
+                ..MetaInfoDef::default()
+            }),
+            graph_def: Some(graph_def),
+            signature_def: vec![("serving_default".to_string(), signature)].into_iter().collect(),
+            object_graph_def,
+            ..MetaGraphDef::default()
+        };
+        let saved = SavedModel { saved_model_schema_version: 1, meta_graphs: vec![meta] };
+        let mut buf = vec![];
+        saved.encode(&mut buf).unwrap();
+        fs::write(dir.join("saved_model.pb"), buf).unwrap();
+        write_bundle(&dir.join("variables").join("variables"), variables).unwrap();
+        dir
+    }
+
+    fn placeholder() -> NodeDef {
+        node().name("x").op("Placeholder").attr("dtype", DataType::DtFloat)
+    }
+
+    #[test]
+    fn function_body_inputs() {
+        let tf = crate::tensorflow();
+        let ops: HashMap<&str, &str> =
+            vec![("lstm", "BlockLSTM"), ("add", "AddV2"), ("foo", "Foo")].into_iter().collect();
+        assert_eq!(tf.parse_input("x", &ops).unwrap(), ("x", 0));
+        assert_eq!(tf.parse_input("add:z:0", &ops).unwrap(), ("add", 0));
+        assert_eq!(tf.parse_input("lstm:i:0", &ops).unwrap(), ("lstm", 0));
+        assert_eq!(tf.parse_input("lstm:h:0", &ops).unwrap(), ("lstm", 6));
+        assert!(tf.parse_input("lstm:y:0", &ops).is_err());
+        assert!(tf.parse_input("foo:y:0", &ops).is_err());
+        assert_eq!(tf.parse_input("foo:1", &ops).unwrap(), ("foo", 1));
+    }
+
+    #[test]
+    fn frozen_model_constants_in_place() {
+        let mut w = crate::tensor::tensor_proto_for_external_data(DatumType::F32, &[1024]).unwrap();
+        w.tensor_content = (0..1024).flat_map(|i| (i as f32).to_le_bytes().to_vec()).collect();
+        let g = graph()
+            .node(placeholder())
+            .node(node().name("w").op("Const").attr("dtype", DataType::DtFloat).attr("value", w))
+            .node(node().name("y").op("AddV2").input("x").input("w"));
+        let path = std::env::temp_dir().join(format!("tract-frozen-{}.pb", std::process::id()));
+        let mut buf = vec![];
+        g.encode(&mut buf).unwrap();
+        fs::write(&path, buf).unwrap();
+        let tf = crate::tensorflow();
+        let graph = tf.read_frozen_model_path(&path).unwrap();
+        let w = graph.node.iter().find(|n| n.name == "w").unwrap();
+        assert!(w.attr.contains_key(EXTERNAL_DATA_ATTR));
+        let mut model = tf.model_for_path(&path).unwrap();
+        model.set_input_fact(0, InferenceFact::dt_shape(f32::datum_type(), tvec!(1024))).unwrap();
+        let plan = SimplePlan::new(model.into_optimized().unwrap()).unwrap();
+        let result = plan.run(tvec!(tract_ndarray::Array1::from_elem(1024, 1f32).into_tensor()));
+        assert_eq!(result.unwrap()[0].as_slice::<f32>().unwrap()[1023], 1024.);
+        fs::remove_file(&path).unwrap();
+    }
+
+    #[test]
+    fn saved_model_v1() {
+        let g = graph()
+            .node(placeholder())
+            .node(
+                node()
+                    .name("w")
+                    .op("VariableV2")
+                    .attr("dtype", DataType::DtFloat)
+                    .attr("container", "")
+                    .attr("shared_name", ""),
+            )
+            .node(node().name("w/read").op("Identity").input("w"))
+            .node(node().name("y").op("Mul").input("x").input("w/read"))
+            .node(node().name("save/RestoreV2").op("RestoreV2").input("save/Const"))
+            .node(node().name("save/Assign").op("Assign").input("w").input("save/RestoreV2"));
+        let dir = save("saved-model-v1", g, None, &[("w", tensor1(&[2f32, 3.]))]);
+        let tf = crate::tensorflow();
+        let mut model = tf.model_for_saved_model_dir(&dir, None).unwrap();
+        model.set_input_fact(0, InferenceFact::dt_shape(f32::datum_type(), tvec!(2))).unwrap();
+        let plan = SimplePlan::new(model.into_optimized().unwrap()).unwrap();
+        let result = plan.run(tvec!(tensor1(&[1f32, 2.]))).unwrap();
+        assert_eq!(result[0], rctensor1(&[2f32, 6.]));
+        assert!(tf.model_for_saved_model_dir(&dir, Some("train")).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn saved_model_v2() {
+        let g = graph()
+            .node(placeholder())
+            .node(
+                node()
+                    .name("dense/kernel")
+                    .op("VarHandleOp")
+                    .attr("dtype", DataType::DtFloat)
+                    .attr("shared_name", "dense/kernel"),
+            )
+            .node(node().name("read").op("ReadVariableOp").input("dense/kernel"))
+            .node(node().name("y").op("AddV2").input("x").input("read"));
+        let key = "layer/kernel/.ATTRIBUTES/VARIABLE_VALUE";
+        let variable =
+            SavedVariable { name: "dense/kernel".to_string(), ..SavedVariable::default() };
+        let objects = SavedObjectGraph {
+            nodes: vec![
+                SavedObject::default(),
+                SavedObject { kind: Some(Kind::Variable(variable)), ..SavedObject::default() },
+            ],
+            concrete_functions: HashMap::new(),
+        };
+        let attribute = SerializedTensor {
+            name: "VARIABLE_VALUE".to_string(),
+            full_name: "dense/kernel".to_string(),
+            checkpoint_key: key.to_string(),
+            optional_restore: false,
+        };
+        let tracked = TrackableObjectGraph {
+            nodes: vec![
+                TrackableObject::default(),
+                TrackableObject { attributes: vec![attribute], ..TrackableObject::default() },
+            ],
+        };
+        let mut buf = vec![];
+        tracked.encode(&mut buf).unwrap();
+        let variables = [(OBJECT_GRAPH_KEY, Tensor::from(Blob(buf))), (key, tensor1(&[1f32, 2.]))];
+        let dir = save("saved-model-v2", g, Some(objects), &variables);
+        let mut model = crate::tensorflow().model_for_path(&dir).unwrap();
+        model.set_input_fact(0, InferenceFact::dt_shape(f32::datum_type(), tvec!(2))).unwrap();
+        let plan = SimplePlan::new(model.into_optimized().unwrap()).unwrap();
+        let result = plan.run(tvec!(tensor1(&[1f32, 1.]))).unwrap();
+        assert_eq!(result[0], rctensor1(&[2f32, 3.]));
+        fs::remove_dir_all(&dir).unwrap();
     }
 }
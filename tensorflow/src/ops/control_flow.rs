@@ -1,6 +1,8 @@
 use tract_hir::internal::*;
+use tract_hir::ops::control_flow::{wire_body, InferenceIf, TypedLoop};
 
-use crate::model::TfOpRegister;
+use crate::model::{ParsingContext, TfOpRegister};
+use crate::tfpb::tensorflow::{FunctionDef, NodeDef};
 
 pub fn register_all_ops(reg: &mut TfOpRegister) {
     reg.insert("Enter", |_, node| {
@@ -8,6 +10,12 @@ pub fn register_all_ops(reg: &mut TfOpRegister) {
     });
     reg.insert("Exit", |_, _| Ok(Box::new(LoopGate(LoopGateRole::Exit))));
     reg.insert("LoopCond", |_, _| Ok(Box::new(LoopGate(LoopGateRole::LoopCond))));
+    reg.insert("If", if_then_else);
+    reg.insert("StatelessIf", if_then_else);
+    reg.insert("While", while_loop);
+    reg.insert("StatelessWhile", while_loop);
+    reg.insert("PartitionedCall", function_call);
+    reg.insert("StatefulPartitionedCall", function_call);
 }
 
 #[derive(Debug, Clone, Hash)]
@@ -103,3 +111,625 @@ impl InferenceRulesOp for NextIteration {
 
     as_op!();
 }
+
+fn if_then_else(ctx: &ParsingContext, node: &NodeDef) -> TractResult<Box<dyn InferenceOp>> {
+    let then_body = ctx.parse_function(node.get_attr_func_name("then_branch")?)?;
+    let else_body = ctx.parse_function(node.get_attr_func_name("else_branch")?)?;
+    let then_input_mapping = (1..=then_body.input_outlets()?.len()).collect();
+    let else_input_mapping = (1..=else_body.input_outlets()?.len()).collect();
+    Ok(Box::new(InferenceIf::new(then_body, then_input_mapping, else_body, else_input_mapping)))
+}
+
+fn function_call(ctx: &ParsingContext, node: &NodeDef) -> TractResult<Box<dyn InferenceOp>> {
+    Ok(Box::new(FunctionCall { body: ctx.parse_function(node.get_attr_func_name("f")?)? }))
+}
+
+fn while_loop(ctx: &ParsingContext, node: &NodeDef) -> TractResult<Box<dyn InferenceOp>> {
+    let cond_name = node.get_attr_func_name("cond")?;
+    let body_name = node.get_attr_func_name("body")?;
+    let counter = counter_pattern(ctx.function(cond_name)?, ctx.function(body_name)?);
+    Ok(Box::new(While {
+        cond: ctx.parse_function(cond_name)?,
+        body: ctx.parse_function(body_name)?,
+        counter,
+    }))
+}
+
+/// A call to a function of the graph library, inlined when translated to
+/// a typed model.
+#[derive(Debug, Clone, Hash)]
+pub struct FunctionCall {
+    pub body: InferenceModel,
+}
+
+impl Op for FunctionCall {
+    fn name(&self) -> Cow<str> {
+        "tf.FunctionCall".into()
+    }
+
+    fn nested_models(&self) -> Vec<(Cow<str>, &dyn Model, Vec<String>, Vec<String>)> {
+        vec![("body".into(), &self.body, vec![], vec![])]
+    }
+
+    not_a_typed_op!();
+}
+
+impl StatefullOp for FunctionCall {
+    fn state(
+        &self,
+        _session: &mut SessionState,
+        _node_id: usize,
+    ) -> TractResult<Option<Box<dyn OpState>>> {
+        let plan = SimplePlan::new(self.body.clone().into_typed()?)?;
+        Ok(Some(Box::new(FunctionCallState { plan: Arc::new(plan) })))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct FunctionCallState {
+    plan: Arc<TypedSimplePlan<TypedModel>>,
+}
+
+impl OpState for FunctionCallState {
+    fn eval(
+        &mut self,
+        _session: &mut SessionState,
+        _op: &dyn Op,
+        inputs: TVec<Arc<Tensor>>,
+    ) -> TractResult<TVec<Arc<Tensor>>> {
+        self.plan.run(inputs.into_iter().map(|t| t.into_tensor()).collect())
+    }
+}
+
+impl InferenceOp for FunctionCall {
+    fn infer_facts(
+        &mut self,
+        inputs: TVec<&InferenceFact>,
+        outputs: TVec<&InferenceFact>,
+        _observed: TVec<&InferenceFact>,
+    ) -> TractResult<(TVec<InferenceFact>, TVec<InferenceFact>, TVec<InferenceFact>)> {
+        if inputs.len() != self.body.input_outlets()?.len() {
+            bail!(
+                "Function expects {} inputs, got {}",
+                self.body.input_outlets()?.len(),
+                inputs.len()
+            )
+        }
+        let mut inputs: TVec<InferenceFact> = inputs.into_iter().cloned().collect();
+        let mut outputs: TVec<InferenceFact> = outputs.into_iter().cloned().collect();
+        loop {
+            let mut changed = false;
+            for (ix, input) in inputs.iter_mut().enumerate() {
+                changed |= input.unify_with_mut(self.body.input_fact_mut(ix)?)?;
+            }
+            for (ix, output) in outputs.iter_mut().enumerate() {
+                changed |= output.unify_with_mut(self.body.output_fact_mut(ix)?)?;
+            }
+            changed |=
+                self.body.analyse(false).map_err(|e| format!("analysing function: {}", e))?;
+            if !changed {
+                break;
+            }
+        }
+        Ok((inputs, outputs, tvec!()))
+    }
+
+    fn to_typed(
+        &self,
+        _source: &InferenceModel,
+        node: &InferenceNode,
+        target: &mut TypedModel,
+        mapping: &HashMap<OutletId, OutletId>,
+    ) -> TractResult<TVec<OutletId>> {
+        let inputs = node.inputs.iter().map(|m| mapping[m]).collect::<TVec<_>>();
+        wire_body(target, &node.name, &self.body.clone().into_typed()?, &inputs)
+    }
+
+    fn nboutputs(&self) -> TractResult<usize> {
+        Ok(self.body.output_outlets()?.len())
+    }
+
+    as_op!();
+}
+
+/// Upper bound of a loop counter.
+#[derive(Debug, Clone, Hash, PartialEq)]
+pub enum CounterLimit {
+    /// A loop variable the body passes through unchanged.
+    Var(usize),
+    /// A constant of the condition function.
+    Const(i64),
+}
+
+/// Functional while loop: `cond` and `body` both take the loop variables,
+/// `cond` returns a boolean scalar, `body` the updated loop variables.
+///
+/// `counter` is set when the loop has the shape of a `for` loop: the
+/// condition is `var < limit`, and the body increments `var` by one. The
+/// number of iterations is then known as soon as the initial values of the
+/// counter and limit are, and the loop can be lowered to a scan.
+#[derive(Debug, Clone, Hash)]
+pub struct While {
+    pub cond: InferenceModel,
+    pub body: InferenceModel,
+    pub counter: Option<(usize, CounterLimit)>,
+}
+
+impl Op for While {
+    fn name(&self) -> Cow<str> {
+        "tf.While".into()
+    }
+
+    fn info(&self) -> TractResult<Vec<String>> {
+        Ok(self.counter.iter().map(|c| format!("Counter: {:?}", c)).collect())
+    }
+
+    fn nested_models(&self) -> Vec<(Cow<str>, &dyn Model, Vec<String>, Vec<String>)> {
+        vec![
+            ("cond".into(), &self.cond, vec![], vec![]),
+            ("body".into(), &self.body, vec![], vec![]),
+        ]
+    }
+
+    not_a_typed_op!();
+}
+
+impl StatefullOp for While {
+    fn state(
+        &self,
+        _session: &mut SessionState,
+        _node_id: usize,
+    ) -> TractResult<Option<Box<dyn OpState>>> {
+        Ok(Some(Box::new(WhileState {
+            cond: Arc::new(SimplePlan::new(self.cond.clone().into_typed()?)?),
+            body: Arc::new(SimplePlan::new(self.body.clone().into_typed()?)?),
+        })))
+    }
+}
+
+#[derive(Debug, Clone)]
+struct WhileState {
+    cond: Arc<TypedSimplePlan<TypedModel>>,
+    body: Arc<TypedSimplePlan<TypedModel>>,
+}
+
+impl OpState for WhileState {
+    fn eval(
+        &mut self,
+        _session: &mut SessionState,
+        _op: &dyn Op,
+        inputs: TVec<Arc<Tensor>>,
+    ) -> TractResult<TVec<Arc<Tensor>>> {
+        let mut vars: TVec<Tensor> = inputs.into_iter().map(|t| t.into_tensor()).collect();
+        while *self.cond.run(vars.clone())?[0].to_scalar::<bool>()? {
+            vars = self.body.run(vars)?.into_iter().map(|t| t.into_tensor()).collect();
+        }
+        Ok(vars.into_iter().map(Arc::new).collect())
+    }
+}
+
+impl While {
+    fn unify_facts(
+        &mut self,
+        inputs: &mut [InferenceFact],
+        outputs: &mut [InferenceFact],
+    ) -> TractResult<bool> {
+        let mut changed = self
+            .cond
+            .output_fact_mut(0)?
+            .unify_with(&InferenceFact::dt_shape(bool::datum_type(), shapefactoid!()))?;
+        for ix in 0..inputs.len() {
+            let mut facts = self.body.outlets_fact_mut(&[
+                self.body.input_outlets()?[ix],
+                self.body.output_outlets()?[ix],
+            ])?;
+            facts.push(self.cond.input_fact_mut(ix)?);
+            facts.push(&mut inputs[ix]);
+            facts.push(&mut outputs[ix]);
+            changed |= Factoid::unify_all(
+                &mut *facts.iter_mut().map(|f| &mut f.datum_type).collect::<TVec<_>>(),
+            )?;
+            changed |= Factoid::unify_all(
+                &mut *facts.iter_mut().map(|f| &mut f.shape).collect::<TVec<_>>(),
+            )?;
+        }
+        Ok(changed)
+    }
+
+    /// Number of iterations of a counter loop with known initial values.
+    fn static_iterations(&self, facts: &[&TypedFact]) -> TractResult<Option<usize>> {
+        let (counter, limit) = match &self.counter {
+            Some(counter) => counter,
+            None => return Ok(None),
+        };
+        let start = match &facts[*counter].konst {
+            Some(start) => start.cast_to_scalar::<i64>()?,
+            None => return Ok(None),
+        };
+        let limit = match limit {
+            CounterLimit::Const(limit) => *limit,
+            CounterLimit::Var(var) => match &facts[*var].konst {
+                Some(limit) => limit.cast_to_scalar::<i64>()?,
+                None => return Ok(None),
+            },
+        };
+        Ok(Some((limit - start).max(0) as usize))
+    }
+}
+
+impl InferenceOp for While {
+    fn infer_facts(
+        &mut self,
+        inputs: TVec<&InferenceFact>,
+        outputs: TVec<&InferenceFact>,
+        _observed: TVec<&InferenceFact>,
+    ) -> TractResult<(TVec<InferenceFact>, TVec<InferenceFact>, TVec<InferenceFact>)> {
+        if self.cond.input_outlets()?.len() != inputs.len()
+            || self.body.input_outlets()?.len() != inputs.len()
+            || self.body.output_outlets()?.len() != inputs.len()
+        {
+            bail!("While loop functions do not match the {} loop variables", inputs.len())
+        }
+        let mut inputs: TVec<InferenceFact> = inputs.into_iter().cloned().collect();
+        let mut outputs: TVec<InferenceFact> = outputs.into_iter().cloned().collect();
+        loop {
+            let mut changed = self.unify_facts(&mut inputs, &mut outputs)?;
+            changed |=
+                self.cond.analyse(false).map_err(|e| format!("analysing loop cond: {}", e))?;
+            changed |=
+                self.body.analyse(false).map_err(|e| format!("analysing loop body: {}", e))?;
+            if !changed {
+                break;
+            }
+        }
+        Ok((inputs, outputs, tvec!()))
+    }
+
+    fn to_typed(
+        &self,
+        _source: &InferenceModel,
+        node: &InferenceNode,
+        target: &mut TypedModel,
+        mapping: &HashMap<OutletId, OutletId>,
+    ) -> TractResult<TVec<OutletId>> {
+        let inputs = node.inputs.iter().map(|m| mapping[m]).collect::<TVec<_>>();
+        let cond = self.cond.clone().into_typed()?;
+        let body = self.body.clone().into_typed()?;
+        let iterations = {
+            let facts =
+                inputs.iter().map(|i| target.outlet_fact(*i)).collect::<TractResult<TVec<_>>>()?;
+            self.static_iterations(&*facts)?
+        };
+
+        let mut loop_body = TypedModel::default();
+        let scalar = |dt: DatumType| TypedFact::dt_shape(dt, [0usize; 0].as_ref());
+        let mut loop_inputs = tvec!(
+            loop_body.add_source("iteration", scalar(i64::datum_type())?)?,
+            loop_body.add_source("cond", scalar(bool::datum_type())?)?,
+        );
+        for (ix, input) in body.input_outlets()?.iter().enumerate() {
+            let name = format!("var.{}", body.node(input.node).name);
+            loop_inputs.push(loop_body.add_source(name, body.input_fact(ix)?.clone())?);
+        }
+        let next = wire_body(&mut loop_body, "body", &body, &loop_inputs[2..])?;
+        let cond_next = if iterations.is_some() {
+            loop_body.add_const("cond_next", tensor0(true))?
+        } else {
+            wire_body(&mut loop_body, "cond", &cond, &next)?[0]
+        };
+        let mut loop_outputs = tvec!(cond_next);
+        loop_outputs.extend(next.iter().cloned());
+        loop_body.set_output_outlets(&loop_outputs)?;
+
+        let state_slots = (1..=inputs.len()).collect();
+        let mut loop_inputs = tvec!();
+        let op = if let Some(iterations) = iterations {
+            let name = format!("{}.trip_count", node.name);
+            loop_inputs.push(target.add_const(name, tensor0(iterations as i64))?);
+            TypedLoop::new(loop_body, Some(0), None, state_slots, vec![])?
+        } else {
+            let prefix = format!("{}.cond", node.name);
+            loop_inputs.push(wire_body(target, &prefix, &cond, &inputs)?[0]);
+            TypedLoop::new(loop_body, None, Some(0), state_slots, vec![])?
+        };
+        loop_inputs.extend(inputs.iter().cloned());
+        target.wire_node(&*node.name, op, &loop_inputs)
+    }
+
+    fn nboutputs(&self) -> TractResult<usize> {
+        Ok(self.body.output_outlets()?.len())
+    }
+
+    as_op!();
+}
+
+/// What a function tensor name designates, once Identity nodes are
+/// skipped.
+enum Resolved<'f> {
+    Arg(usize),
+    Node(&'f NodeDef),
+}
+
+fn resolve<'f>(func: &'f FunctionDef, input: &str) -> Option<Resolved<'f>> {
+    let name = input.split(':').next()?;
+    let signature = func.signature.as_ref()?;
+    if let Some(ix) = signature.input_arg.iter().position(|arg| arg.name == name) {
+        return Some(Resolved::Arg(ix));
+    }
+    let node = func.node_def.iter().find(|n| n.name == name)?;
+    if node.op == "Identity" {
+        resolve(func, node.input.get(0)?)
+    } else {
+        Some(Resolved::Node(node))
+    }
+}
+
+fn resolve_const(func: &FunctionDef, input: &str) -> Option<i64> {
+    match resolve(func, input)? {
+        Resolved::Node(node) if node.op == "Const" => {
+            node.get_attr_tensor("value").ok()?.cast_to_scalar::<i64>().ok()
+        }
+        _ => None,
+    }
+}
+
+fn returned<'f>(func: &'f FunctionDef, ix: usize) -> Option<Resolved<'f>> {
+    let arg = func.signature.as_ref()?.output_arg.get(ix)?;
+    resolve(func, func.ret.get(&arg.name)?)
+}
+
+/// Recognize `for` loops: a condition function returning `var < limit`,
+/// with a body incrementing `var` by one and leaving `limit` untouched.
+fn counter_pattern(cond: &FunctionDef, body: &FunctionDef) -> Option<(usize, CounterLimit)> {
+    let less = match returned(cond, 0)? {
+        Resolved::Node(node) if node.op == "Less" && node.input.len() >= 2 => node,
+        _ => return None,
+    };
+    let counter = match resolve(cond, &less.input[0])? {
+        Resolved::Arg(ix) => ix,
+        _ => return None,
+    };
+    let limit = match resolve(cond, &less.input[1])? {
+        Resolved::Arg(ix) => match returned(body, ix)? {
+            Resolved::Arg(passthrough) if passthrough == ix => CounterLimit::Var(ix),
+            _ => return None,
+        },
+        Resolved::Node(_) => CounterLimit::Const(resolve_const(cond, &less.input[1])?),
+    };
+    let add = match returned(body, counter)? {
+        Resolved::Node(node)
+            if (node.op == "Add" || node.op == "AddV2") && node.input.len() >= 2 =>
+        {
+            node
+        }
+        _ => return None,
+    };
+    let increments = |var: &str, step: &str| {
+        matches!(resolve(body, var), Some(Resolved::Arg(ix)) if ix == counter)
+            && resolve_const(body, step) == Some(1)
+    };
+    if increments(&add.input[0], &add.input[1]) || increments(&add.input[1], &add.input[0]) {
+        Some((counter, limit))
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tfpb::tensorflow::attr_value::Value;
+    use crate::tfpb::tensorflow::op_def::ArgDef;
+    use crate::tfpb::tensorflow::*;
+    use crate::tfpb::{graph, node};
+    use std::convert::TryInto;
+
+    fn konst(name: &str, t: Tensor) -> NodeDef {
+        let dt: DataType = t.datum_type().try_into().unwrap();
+        let t: TensorProto = (&t).try_into().unwrap();
+        node().name(name).op("Const").attr("dtype", dt).attr("value", t)
+    }
+
+    fn func_attr(name: &str) -> AttrValue {
+        let f = NameAttrList { name: name.to_string(), attr: HashMap::new() };
+        AttrValue { value: Some(Value::Func(f)) }
+    }
+
+    fn function(
+        name: &str,
+        inputs: &[(&str, DataType)],
+        outputs: &[(&str, &str)],
+        node_def: Vec<NodeDef>,
+    ) -> FunctionDef {
+        let arg = |name: &str, dt: Option<DataType>| ArgDef {
+            name: name.to_string(),
+            r#type: dt.map(|dt| dt as i32).unwrap_or(0),
+            ..ArgDef::default()
+        };
+        FunctionDef {
+            signature: Some(OpDef {
+                name: name.to_string(),
+                input_arg: inputs.iter().map(|(n, dt)| arg(n, Some(*dt))).collect(),
+                output_arg: outputs.iter().map(|(n, _)| arg(n, None)).collect(),
+                ..OpDef::default()
+            }),
+            attr: HashMap::new(),
+            node_def,
+            ret: outputs.iter().map(|(n, r)| (n.to_string(), r.to_string())).collect(),
+        }
+    }
+
+    fn library(function: Vec<FunctionDef>) -> Option<FunctionDefLibrary> {
+        Some(FunctionDefLibrary { function, gradient: vec![] })
+    }
+
+    fn placeholder(name: &str, dt: DataType) -> NodeDef {
+        node().name(name).op("Placeholder").attr("dtype", dt)
+    }
+
+    // while x < 100 { x = x * 2 }
+    fn doubling_loop() -> GraphDef {
+        let cond = function(
+            "cond",
+            &[("x", DataType::DtFloat)],
+            &[("ok", "less:z:0")],
+            vec![
+                konst("limit", tensor0(100f32)),
+                node().name("less").op("Less").input("x").input("limit:output:0"),
+            ],
+        );
+        let body = function(
+            "body",
+            &[("x", DataType::DtFloat)],
+            &[("x_next", "mul:z:0")],
+            vec![konst("two", tensor0(2f32)), node().name("mul").op("Mul").input("x").input("two")],
+        );
+        let mut g = graph()
+            .node(placeholder("x", DataType::DtFloat))
+            .node(
+                node()
+                    .name("while")
+                    .op("StatelessWhile")
+                    .input("x")
+                    .attr("cond", func_attr("cond"))
+                    .attr("body", func_attr("body")),
+            )
+            .node(node().name("result").op("Identity").input("while"));
+        g.library = library(vec![cond, body]);
+        g
+    }
+
+    // for i in 0..5 { x = x * 2 }
+    fn counter_loop() -> GraphDef {
+        let cond = function(
+            "cond",
+            &[("i", DataType::DtInt32), ("x", DataType::DtFloat)],
+            &[("ok", "identity:output:0")],
+            vec![
+                konst("limit", tensor0(5i32)),
+                node().name("less").op("Less").input("i").input("limit:output:0"),
+                node().name("identity").op("Identity").input("less:z:0"),
+            ],
+        );
+        let body = function(
+            "body",
+            &[("i", DataType::DtInt32), ("x", DataType::DtFloat)],
+            &[("i_next", "add:z:0"), ("x_next", "mul:z:0")],
+            vec![
+                konst("one", tensor0(1i32)),
+                konst("two", tensor0(2f32)),
+                node().name("add").op("AddV2").input("i").input("one:output:0"),
+                node().name("mul").op("Mul").input("x").input("two:output:0").input("^add"),
+            ],
+        );
+        let mut g = graph()
+            .node(placeholder("x", DataType::DtFloat))
+            .node(konst("zero", tensor0(0i32)))
+            .node(
+                node()
+                    .name("while")
+                    .op("While")
+                    .input("zero")
+                    .input("x")
+                    .attr("cond", func_attr("cond"))
+                    .attr("body", func_attr("body")),
+            )
+            .node(node().name("result").op("Identity").input("while:1"));
+        g.library = library(vec![cond, body]);
+        g
+    }
+
+    fn run(model: &InferenceModel, input: Tensor) -> Arc<Tensor> {
+        SimplePlan::new(model).unwrap().run(tvec!(input)).unwrap().remove(0)
+    }
+
+    fn run_typed(model: &TypedModel, input: Tensor) -> Arc<Tensor> {
+        SimplePlan::new(model).unwrap().run(tvec!(input)).unwrap().remove(0)
+    }
+
+    #[test]
+    fn while_loop() {
+        let mut model = crate::tensorflow().model_for_proto_model(&doubling_loop()).unwrap();
+        model
+            .set_input_fact(0, InferenceFact::dt_shape(f32::datum_type(), shapefactoid!()))
+            .unwrap();
+        model.analyse(false).unwrap();
+        assert_eq!(run(&model, tensor0(3f32)), rctensor0(192f32));
+        let typed = model.into_optimized().unwrap();
+        assert_eq!(run_typed(&typed, tensor0(3f32)), rctensor0(192f32));
+        assert_eq!(run_typed(&typed, tensor0(150f32)), rctensor0(150f32));
+    }
+
+    #[test]
+    fn counter_loop_as_scan() {
+        let graph = counter_loop();
+        let mut model = crate::tensorflow().model_for_proto_model(&graph).unwrap();
+        let op = model.node_by_name("while").unwrap().op_as::<While>().unwrap();
+        assert_eq!(op.counter, Some((0, CounterLimit::Const(5))));
+        model.set_output_names(&["result"]).unwrap();
+        model
+            .set_input_fact(0, InferenceFact::dt_shape(f32::datum_type(), shapefactoid!()))
+            .unwrap();
+        model.analyse(false).unwrap();
+        assert_eq!(run(&model, tensor0(1f32)), rctensor0(32f32));
+        let typed = model.into_typed().unwrap().declutter().unwrap();
+        assert!(typed.nodes().iter().any(|n| n.op_is::<tract_hir::ops::scan::TypedScan>()));
+        assert!(!typed.nodes().iter().any(|n| n.op_is::<TypedLoop>()));
+        assert_eq!(run_typed(&typed, tensor0(1f32)), rctensor0(32f32));
+    }
+
+    #[test]
+    fn if_with_function_call() {
+        let inc = function(
+            "inc",
+            &[("x", DataType::DtFloat)],
+            &[("y", "add:z:0")],
+            vec![
+                konst("one", tensor0(1f32)),
+                node().name("add").op("AddV2").input("x").input("one"),
+            ],
+        );
+        let then_branch = function(
+            "then",
+            &[("x", DataType::DtFloat)],
+            &[("y", "call:output:0")],
+            vec![node()
+                .name("call")
+                .op("StatefulPartitionedCall")
+                .input("x")
+                .attr("f", func_attr("inc"))],
+        );
+        let else_branch = function(
+            "else",
+            &[("x", DataType::DtFloat)],
+            &[("y", "sub:z:0")],
+            vec![konst("one", tensor0(1f32)), node().name("sub").op("Sub").input("x").input("one")],
+        );
+        let mut g = graph()
+            .node(placeholder("c", DataType::DtBool))
+            .node(placeholder("x", DataType::DtFloat))
+            .node(
+                node()
+                    .name("if")
+                    .op("If")
+                    .input("c")
+                    .input("x")
+                    .attr("then_branch", func_attr("then"))
+                    .attr("else_branch", func_attr("else")),
+            );
+        g.library = library(vec![inc, then_branch, else_branch]);
+        let mut model = crate::tensorflow().model_for_proto_model(&g).unwrap();
+        model
+            .set_input_fact(0, InferenceFact::dt_shape(bool::datum_type(), shapefactoid!()))
+            .unwrap();
+        model
+            .set_input_fact(1, InferenceFact::dt_shape(f32::datum_type(), shapefactoid!()))
+            .unwrap();
+        let typed = model.into_optimized().unwrap();
+        let plan = SimplePlan::new(&typed).unwrap();
+        let result = plan.run(tvec!(tensor0(true), tensor0(3f32))).unwrap();
+        assert_eq!(result[0], rctensor0(4f32));
+        let result = plan.run(tvec!(tensor0(false), tensor0(3f32))).unwrap();
+        assert_eq!(result[0], rctensor0(2f32));
+    }
+}
@@ -12,9 +12,12 @@ pub fn register_all_ops(reg: &mut TfOpRegister) {
     reg.insert("Add", |_, _| Ok(Box::new(ops::math::add::bin())));
     reg.insert("AddN", add_n);
     reg.insert("AddV2", |_, _| Ok(Box::new(ops::math::add::bin())));
+    reg.insert("BatchMatMul", batch_mat_mul);
+    reg.insert("BatchMatMulV2", batch_mat_mul);
     reg.insert("BiasAdd", |_, _| Ok(Box::new(ops::math::add::bin())));
     reg.insert("Ceil", |_, _| Ok(Box::new(ops::math::ceil())));
     reg.insert("Div", |_, _| Ok(Box::new(ops::math::div::bin())));
+    reg.insert("Einsum", einsum);
     reg.insert("FloorMod", |_, _| Ok(Box::new(ops::math::rem::bin())));
     reg.insert("MatMul", mat_mul);
     reg.insert("Max", reduce::max);
@@ -39,6 +42,16 @@ pub fn add_n(_ctx: &ParsingContext, _pb: &NodeDef) -> TractResult<Box<dyn Infere
     Ok(Box::new(ops::binary::Nary(Box::new(ops::math::Add), false)))
 }
 
+pub fn batch_mat_mul(_ctx: &ParsingContext, pb: &NodeDef) -> TractResult<Box<dyn InferenceOp>> {
+    let trans_a = pb.get_attr_opt_bool("adj_x")?.unwrap_or(false);
+    let trans_b = pb.get_attr_opt_bool("adj_y")?.unwrap_or(false);
+    Ok(Box::new(ops::matmul::MatMul::default().with_a_trans(trans_a).with_b_trans(trans_b)))
+}
+
+pub fn einsum(_ctx: &ParsingContext, pb: &NodeDef) -> TractResult<Box<dyn InferenceOp>> {
+    Ok(Box::new(ops::einsum::Einsum::new(&pb.get_attr_str("equation")?)?))
+}
+
 pub fn mat_mul(_ctx: &ParsingContext, pb: &NodeDef) -> TractResult<Box<dyn InferenceOp>> {
     let trans_a = pb.get_attr_bool("transpose_a")?;
     let trans_b = pb.get_attr_bool("transpose_b")?;
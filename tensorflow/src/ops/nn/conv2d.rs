@@ -15,6 +15,29 @@ pub fn conv2d(_ctx: &ParsingContext, pb: &NodeDef) -> TractResult<Box<dyn Infere
     Ok(Box::new(op))
 }
 
+pub fn conv2d_backprop_input(
+    _ctx: &ParsingContext,
+    pb: &NodeDef,
+) -> TractResult<Box<dyn InferenceOp>> {
+    let data_format = super::data_format(pb)?;
+    let spatial = if data_format == DataFormat::NHWC { 1..3 } else { 2..4 };
+    let strides = super::strides(pb)?;
+    let mut op = cnn::Deconv::default()
+        .hwio()
+        .padding(super::padding(pb)?)
+        .strides(strides[spatial.clone()].into())
+        .output_shape_input(0)
+        .k_input(1)
+        .data_input(2);
+    if let Some(dilations) = pb.get_attr_opt_list_int::<usize>("dilations")? {
+        op = op.dilations(dilations[spatial].into());
+    }
+    if data_format == DataFormat::NHWC {
+        op = op.nhwc()
+    }
+    Ok(Box::new(op))
+}
+
 #[cfg(test)]
 mod tests {
     #![allow(non_snake_case)]
@@ -184,4 +207,24 @@ mod tests {
             tvec![InferenceFact::dt_shape(DatumType::F32, shapefactoid!(1, 1, 1, 1))]
         );
     }
+
+    #[test]
+    fn backprop_input_stride_2() {
+        use tract_hir::ops::cnn::Deconv;
+        let op = Deconv::default()
+            .nhwc()
+            .hwio()
+            .strides(tvec!(2, 2))
+            .output_shape_input(0)
+            .k_input(1)
+            .data_input(2);
+        let sizes = rctensor1(&[1i32, 4, 4, 1]);
+        let filter =
+            Tensor::from(ArrayD::<f32>::from_elem(vec![2, 2, 1, 1], 1.0)).into_arc_tensor();
+        let input = mk(&[1, 2, 2, 1]).into_arc_tensor();
+        let output = op.eval(tvec!(sizes, filter, input)).unwrap().remove(0);
+        let expected =
+            arr2(&[[1f32, 1., 2., 2.], [1., 1., 2., 2.], [3., 3., 4., 4.], [3., 3., 4., 4.]]);
+        assert_eq!(*output, expected.into_shape((1, 4, 4, 1)).unwrap().into_tensor());
+    }
 }
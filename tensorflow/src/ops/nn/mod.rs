@@ -14,6 +14,7 @@ pub mod s2b;
 pub fn register_all_ops(reg: &mut TfOpRegister) {
     reg.insert("AvgPool", pools::avgpool);
     reg.insert("Conv2D", conv2d::conv2d);
+    reg.insert("Conv2DBackpropInput", conv2d::conv2d_backprop_input);
     reg.insert("DepthwiseConv2dNative", dw_conv2d::depthwise_conv2d);
     reg.insert("FusedBatchNorm", fused_batch_norm::fused_batch_norm);
     reg.insert("MaxPool", pools::maxpool);
@@ -22,7 +23,7 @@ pub fn register_all_ops(reg: &mut TfOpRegister) {
         Ok(Box::new(tract_hir::ops::math::scalar_min_max((6.0).into(), (0.0).into())))
     });
     reg.insert("Sigmoid", |_, _| Ok(Box::new(tract_hir::ops::nn::sigmoid())));
-    reg.insert("Softmax", |_, _| Ok(Box::new(LayerSoftmax::new(1))));
+    reg.insert("Softmax", |_, _| Ok(Box::new(LayerSoftmax::new(1, true))));
     reg.insert("SpaceToBatchND", s2b::space_to_batch_nd);
     reg.insert("BatchToSpaceND", s2b::batch_to_space_nd);
 }
@@ -181,4 +181,192 @@ impl TypedOp for BlockLSTM {
     fn output_facts(&self, inputs: &[&TypedFact]) -> TractResult<TVec<TypedFact>> {
         Ok(std::iter::repeat(inputs[1].clone()).take(7).collect())
     }
+
+    fn declutter(
+        &self,
+        model: &TypedModel,
+        node: &TypedNode,
+    ) -> TractResult<Option<TypedModelPatch>> {
+        self.lower_to_scan(model, node)
+    }
+}
+
+impl BlockLSTM {
+    /// Translate to a TypedScan over time steps, so the layer can be
+    /// pulsified, hidden and cell states being carried over from one pulse
+    /// to the next.
+    ///
+    /// Only done when weights and bias are constant, and seq_len_max is a
+    /// constant covering the whole sequence. The length of a streamed
+    /// sequence is unknown, so seq_len_max must then be at least i32::MAX,
+    /// the longest stream tract can process.
+    #[allow(non_snake_case)]
+    fn lower_to_scan(
+        &self,
+        model: &TypedModel,
+        node: &TypedNode,
+    ) -> TractResult<Option<TypedModelPatch>> {
+        use tract_hir::ops::{array, math, matmul, nn, scan};
+
+        let facts = model.node_input_facts(node.id)?;
+        let (w, bias) = match (&facts[4].konst, &facts[8].konst) {
+            (Some(w), Some(bias)) => (w.clone(), bias.clone()),
+            _ => return Ok(None),
+        };
+        if facts[1].datum_type != f32::datum_type() {
+            return Ok(None);
+        }
+        // the scan ignores seq_len_max: it must cover the whole sequence
+        let time = match facts[1].shape.dim(0).to_integer() {
+            Ok(time) => time as i64,
+            Err(_) => i32::MAX as i64,
+        };
+        match &facts[0].konst {
+            Some(len) if *len.cast_to::<i64>()?.to_scalar::<i64>()? >= time => (),
+            _ => return Ok(None),
+        }
+        let b_size = facts[1].shape.dim(1).to_integer()? as usize;
+        let c_size = facts[1].shape.dim(2).to_integer()? as usize;
+        let chunk_fact = TypedFact::dt_shape(f32::datum_type(), [1, b_size, c_size].as_ref())?;
+
+        let mut body = TypedModel::default();
+        macro_rules! wire {
+            ($name: ident = $op: expr, $($param: expr),*) => {
+                let $name = body.wire_node(
+                    format!("{}-{}", node.name, stringify!($name)),
+                    $op, [$($param),*].as_ref())?[0];
+            }
+        };
+
+        let x_source = body.add_source("x_source", chunk_fact.clone())?;
+        let cs_source = body.add_source("cs_source", chunk_fact.clone())?;
+        let h_source = body.add_source("h_source", chunk_fact.clone())?;
+        let W = body.add_const("w", w)?;
+        let bias = body.add_const("bias", bias)?;
+        let forget_bias = body.add_const("forget_bias", rctensor0(self.forget_bias))?;
+
+        wire!(x = AxisOp::Rm(0), x_source);
+        wire!(cs_prev = AxisOp::Rm(0), cs_source);
+        wire!(h_prev = AxisOp::Rm(0), h_source);
+
+        let xh_slices = tvec!(array::ConcatSlice::Var, array::ConcatSlice::Var);
+        wire!(xh = array::TypedConcat::new(1, xh_slices), x, h_prev);
+        wire!(xh_w = matmul::MatMul::default(), xh, W);
+        wire!(i_ci_f_o = math::add::bin_typed(), xh_w, bias);
+
+        wire!(i0 = array::Slice::new(1, 0, c_size), i_ci_f_o);
+        wire!(ci0 = array::Slice::new(1, c_size, 2 * c_size), i_ci_f_o);
+        wire!(f0 = array::Slice::new(1, 2 * c_size, 3 * c_size), i_ci_f_o);
+        wire!(o0 = array::Slice::new(1, 3 * c_size, 4 * c_size), i_ci_f_o);
+
+        wire!(i = nn::sigmoid(), i0);
+        wire!(f_biased = math::add::bin_typed(), f0, forget_bias);
+        wire!(f = nn::sigmoid(), f_biased);
+        wire!(ci = math::tanh(), ci0);
+
+        // cs = cs_prev (.) f + ci (.) i
+        wire!(cs_prev_f = math::mul::bin_typed(), cs_prev, f);
+        wire!(ci_i = math::mul::bin_typed(), ci, i);
+        wire!(cs = math::add::bin_typed(), cs_prev_f, ci_i);
+
+        // h = tanh(cs) (.) o
+        wire!(o = nn::sigmoid(), o0);
+        wire!(co = math::tanh(), cs);
+        wire!(h = math::mul::bin_typed(), co, o);
+
+        let mut outputs = tvec!();
+        for (ix, wire) in [i, cs, f, o, ci, co, h].iter().enumerate() {
+            outputs.push(
+                body.wire_node(format!("{}-output-{}", node.name, ix), AxisOp::Add(0), &[*wire])?
+                    [0],
+            );
+        }
+        body.set_output_outlets(&outputs)?;
+
+        let mut patch = TypedModelPatch::default();
+        let mut outer_inputs = tvec!(patch.tap_model(model, node.inputs[1])?);
+        let mut input_mapping =
+            vec![scan::InputMapping::Scan { slot: 0, axis: 0, chunk: 1.to_dim() }];
+        for &(ix, name) in &[(2, "cs_prev"), (3, "h_prev")] {
+            let initializer = if let Some(konst) = &facts[ix].konst {
+                let value = konst.to_array_view::<f32>()?.into_shape((1, b_size, c_size))?;
+                scan::StateInitializer::Value(value.to_owned().into_arc_tensor())
+            } else {
+                let wire = patch.tap_model(model, node.inputs[ix])?;
+                let wire =
+                    patch.wire_node(format!("{}-{}", node.name, name), AxisOp::Add(0), &[wire])?;
+                outer_inputs.push(wire[0]);
+                scan::StateInitializer::FromInput(outer_inputs.len() - 1)
+            };
+            input_mapping.push(scan::InputMapping::State { initializer });
+        }
+        let output_mapping = (0..7)
+            .map(|ix| scan::OutputMapping {
+                state: ix == 1 || ix == 6,
+                axis: 0,
+                chunk: 1.to_dim(),
+                full_dim_hint: None,
+                last_value_slot: None,
+                full_slot: Some(ix),
+            })
+            .collect();
+        let scan = scan::TypedScan::new(body, input_mapping, output_mapping, None)?;
+        let outputs = patch.wire_node(&*node.name, scan, &outer_inputs)?;
+        for (ix, output) in outputs.into_iter().enumerate() {
+            patch.shunt_outside(model, OutletId::new(node.id, ix), output)?;
+        }
+        Ok(Some(patch))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tract_hir::tract_core::pulse::{PulsedModel, StreamingSession};
+
+    fn model(len: TDim, seq_len_max: i64) -> TractResult<TypedModel> {
+        let mut model = TypedModel::default();
+        let shape = [len, 1.to_dim(), 3.to_dim()];
+        let x = model.add_source("x", TypedFact::dt_shape(f32::datum_type(), shape.as_ref())?)?;
+        let w = Array2::from_shape_fn((6, 12), |(i, j)| ((i * 12 + j) * 7 % 11) as f32 / 10. - 0.5);
+        let bias = Array1::from_shape_fn(12, |i| (i % 5) as f32 / 10.);
+        let cs = Array2::from_shape_fn((1, 3), |(_, i)| i as f32 / 5.);
+        let mut inputs = tvec!(model.add_const("seq_len_max", rctensor0(seq_len_max))?, x);
+        inputs.push(model.add_const("cs_prev", cs.into_arc_tensor())?);
+        inputs.push(model.add_const("h_prev", Array2::<f32>::zeros((1, 3)).into_arc_tensor())?);
+        inputs.push(model.add_const("w", w.into_arc_tensor())?);
+        for name in &["wci", "wcf", "wco"] {
+            inputs.push(model.add_const(*name, Array1::<f32>::zeros(3).into_arc_tensor())?);
+        }
+        inputs.push(model.add_const("bias", bias.into_arc_tensor())?);
+        let lstm = BlockLSTM::new(1.0, 3.0, f32::datum_type(), false);
+        let outputs = model.wire_node("lstm", lstm, &inputs)?;
+        model.set_output_outlets(&outputs[6..])?;
+        Ok(model)
+    }
+
+    fn has_scan(model: &TypedModel) -> bool {
+        model.nodes().iter().any(|n| n.op_is::<tract_hir::ops::scan::TypedScan>())
+    }
+
+    #[test]
+    fn lowered_to_scan() -> TractResult<()> {
+        let x = Array3::from_shape_fn((9, 1, 3), |(t, _, c)| (t as f32 - c as f32) / 3.);
+        let expected =
+            SimplePlan::new(model(9.to_dim(), 9)?)?.run(tvec!(x.clone().into_tensor()))?.remove(0);
+        assert!(!has_scan(&model(9.to_dim(), 3)?.declutter()?));
+        let decluttered = model(9.to_dim(), 9)?.declutter()?;
+        assert!(has_scan(&decluttered));
+        let found = SimplePlan::new(decluttered)?.run(tvec!(x.clone().into_tensor()))?.remove(0);
+        found.close_enough(&expected, true)?;
+
+        assert!(!has_scan(&model(TDim::s(), 9)?.declutter()?));
+        let streaming = model(TDim::s(), i32::MAX as i64)?.declutter()?.into_normalized()?;
+        let mut session = StreamingSession::new(PulsedModel::new(&streaming, 4)?)?;
+        let mut outputs = vec![session.push(x.slice(s![..5, .., ..]).to_owned().into_tensor())?];
+        outputs.push(session.push(x.slice(s![5.., .., ..]).to_owned().into_tensor())?);
+        outputs.push(session.finish()?);
+        Tensor::stack_tensors(0, &outputs)?.close_enough(&expected, true)?;
+        Ok(())
+    }
 }
@@ -6,6 +6,8 @@ use crate::tfpb::tensorflow::NodeDef;
 pub fn register_all_ops(reg: &mut TfOpRegister) {
     reg.insert("Assign", |_, _| Ok(Box::new(Assign::default())));
     reg.insert("VariableV2", variable_v2);
+    // resource variables are bound to constants when a SavedModel is loaded
+    reg.insert("ReadVariableOp", |_, _| Ok(Box::new(tract_hir::ops::identity::Identity)));
 }
 
 fn variable_v2(_ctx: &ParsingContext, node: &NodeDef) -> TractResult<Box<dyn InferenceOp>> {
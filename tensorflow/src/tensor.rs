@@ -1,10 +1,129 @@
 use tract_hir::internal::*;
+use tract_hir::tract_core::tensor::mmap::Mmap;
 
+use crate::tfpb::tensorflow::attr_value::{ListValue, Value};
 use crate::tfpb::tensorflow::tensor_shape_proto::Dim;
-use crate::tfpb::tensorflow::{TensorProto, TensorShapeProto};
+use crate::tfpb::tensorflow::{AttrValue, NodeDef, TensorProto, TensorShapeProto};
 
 use crate::tfpb::tensorflow::DataType;
-use std::convert::TryFrom;
+use prost::Message;
+use std::convert::{TryFrom, TryInto};
+use std::{fs, path};
+
+/// Node attribute telling that the content of the "value" tensor of a node
+/// is left in a file, to be memory mapped when the tensor is loaded.
+pub const EXTERNAL_DATA_ATTR: &str = "_tract_external_data";
+
+/// Tensor content is left in the model file when it is at least this big.
+pub const MMAP_THRESHOLD: usize = 4096;
+
+/// Location of the content of a tensor in a file.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ExternalData {
+    pub path: path::PathBuf,
+    pub offset: u64,
+    pub length: usize,
+}
+
+impl ExternalData {
+    pub fn from_attr(attr: &AttrValue) -> TractResult<ExternalData> {
+        match &attr.value {
+            Some(Value::List(ListValue { s, i, .. })) if s.len() == 1 && i.len() == 2 => {
+                let path = String::from_utf8(s[0].clone())
+                    .map_err(|_| "Non UTF-8 path in external data")?;
+                Ok(ExternalData { path: path.into(), offset: i[0] as u64, length: i[1] as usize })
+            }
+            _ => bail!("Invalid external data attribute {:?}", attr),
+        }
+    }
+
+    pub fn to_attr(&self) -> TractResult<AttrValue> {
+        let path = self.path.to_str().ok_or("Non UTF-8 path in external data")?;
+        let list = ListValue {
+            s: vec![path.as_bytes().to_vec()],
+            i: vec![self.offset as i64, self.length as i64],
+            ..ListValue::default()
+        };
+        Ok(AttrValue { value: Some(Value::List(list)) })
+    }
+
+    /// Map the data as a plain tensor.
+    pub fn load(&self, dt: DatumType, shape: &[usize]) -> TractResult<Tensor> {
+        if shape.iter().product::<usize>() * dt.size_of() != self.length {
+            bail!("External data size does not match tensor shape {:?}", shape)
+        }
+        let file = fs::File::open(&self.path)
+            .map_err(|e| format!("Opening external data {:?}: {}", self.path, e))?;
+        let mmap = Mmap::map(&file, self.offset, self.length)?;
+        unsafe { Tensor::from_mmap(dt, shape, Arc::new(mmap), 0) }
+    }
+}
+
+/// Load a tensor whose content is described by an `EXTERNAL_DATA_ATTR`.
+pub fn load_external_tensor(t: &TensorProto, external: &AttrValue) -> TractResult<Tensor> {
+    let dt: DatumType = DataType::from_i32(t.dtype)
+        .ok_or_else(|| format!("Unknown DataType {}", t.dtype))?
+        .try_into()?;
+    let shape: TVec<usize> = match &t.tensor_shape {
+        Some(shape) => shape.try_into()?,
+        None => tvec!(),
+    };
+    ExternalData::from_attr(external)?.load(dt, &shape)
+}
+
+/// A TensorProto with a type and a shape but no content, for a tensor with
+/// external data.
+pub fn tensor_proto_for_external_data(dt: DatumType, shape: &[usize]) -> TractResult<TensorProto> {
+    let dt: DataType = dt.try_into()?;
+    let mut tensor = empty_tensor_proto();
+    tensor.dtype = dt.into();
+    tensor.tensor_shape = Some(TensorShapeProto {
+        dim: shape.iter().map(|d| Dim { size: *d as _, name: String::new() }).collect(),
+        unknown_rank: false,
+    });
+    Ok(tensor)
+}
+
+/// Rewrite a serialized GraphDef so that the big contents of Const nodes are
+/// left in the model file, `location`, and referred to as external data.
+pub fn externalize_constants(graph: &[u8], location: &path::Path) -> TractResult<Vec<u8>> {
+    use tract_hir::pb_wire::*;
+    map_len_fields(graph, 1, |node| {
+        if !fields(node)?.iter().any(|f| f.tag == 2 && f.payload == b"Const") {
+            return Ok(node.to_vec());
+        }
+        let mut content = None;
+        let mut rewritten = map_len_fields(node, 5, |entry| {
+            if !fields(entry)?.iter().any(|f| f.tag == 1 && f.payload == b"value") {
+                return Ok(entry.to_vec());
+            }
+            map_len_fields(entry, 2, |attr| {
+                map_len_fields(attr, 8, |tensor| {
+                    let mut stripped = vec![];
+                    for field in fields(tensor)? {
+                        if field.tag == 4 && field.payload.len() >= MMAP_THRESHOLD {
+                            let offset = field.payload.as_ptr() as usize - graph.as_ptr() as usize;
+                            content = Some((offset as u64, field.payload.len()));
+                        } else {
+                            stripped.extend_from_slice(field.raw);
+                        }
+                    }
+                    Ok(stripped)
+                })
+            })
+        })?;
+        if let Some((offset, length)) = content {
+            let external = ExternalData { path: location.to_path_buf(), offset, length };
+            let mut attr = HashMap::new();
+            attr.insert(EXTERNAL_DATA_ATTR.to_string(), external.to_attr()?);
+            // serialized messages concatenation merges them
+            NodeDef { attr, ..NodeDef::default() }
+                .encode(&mut rewritten)
+                .map_err(|e| format!("{:?}", e))?;
+        }
+        Ok(rewritten)
+    })
+}
 
 impl TryFrom<DataType> for DatumType {
     type Error = TractError;
@@ -0,0 +1,334 @@
+//! Reader for the tensor bundle format, in which TensorFlow stores variables
+//! checkpoints.
+//!
+//! A bundle is made of an index file, `{prefix}.index`, and of one or more
+//! data files, `{prefix}.data-{shard:05}-of-{shards:05}`. The index is a
+//! LevelDB-style sorted table mapping tensor names to a `BundleEntryProto`
+//! telling where the tensor bytes are in the data files. The empty key holds
+//! the `BundleHeaderProto`.
+//!
+//! Checksums are not verified.
+
+use crate::tensor::ExternalData;
+use crate::tfpb::tensorflow::bundle_header_proto::Endianness;
+use crate::tfpb::tensorflow::{BundleEntryProto, BundleHeaderProto, DataType};
+use prost::Message;
+use std::convert::TryInto;
+use std::{fs, path};
+use tract_hir::internal::*;
+use tract_hir::pb_wire::read_varint;
+
+const TABLE_MAGIC: u64 = 0xdb4775248b80fb57;
+const FOOTER_LEN: usize = 48;
+const BLOCK_TRAILER_LEN: usize = 5;
+
+pub struct TensorBundle {
+    entries: HashMap<String, BundleEntryProto>,
+    shards: Vec<path::PathBuf>,
+}
+
+impl TensorBundle {
+    /// Open the bundle with the given prefix, like `variables/variables` in
+    /// a SavedModel directory.
+    pub fn open(prefix: impl AsRef<path::Path>) -> TractResult<TensorBundle> {
+        let prefix = prefix.as_ref().to_str().ok_or("Non UTF-8 bundle prefix")?;
+        let index = fs::read(format!("{}.index", prefix))?;
+        let mut header = None;
+        let mut entries = HashMap::new();
+        for (key, value) in read_table(&index)? {
+            let value = bytes::Bytes::from(value);
+            if key.is_empty() {
+                header = Some(BundleHeaderProto::decode(value).map_err(|e| format!("{:?}", e))?);
+            } else {
+                let key = String::from_utf8(key).map_err(|_| "Non UTF-8 key in tensor bundle")?;
+                let entry = BundleEntryProto::decode(value).map_err(|e| format!("{:?}", e))?;
+                entries.insert(key, entry);
+            }
+        }
+        let header = header.ok_or("Tensor bundle index has no header")?;
+        if header.endianness != Endianness::Little as i32 {
+            bail!("Only little endian tensor bundles are supported")
+        }
+        let shards = (0..header.num_shards)
+            .map(|shard| format!("{}.data-{:05}-of-{:05}", prefix, shard, header.num_shards).into())
+            .collect();
+        Ok(TensorBundle { entries, shards })
+    }
+
+    pub fn keys(&self) -> impl Iterator<Item = &str> {
+        self.entries.keys().map(|k| &**k)
+    }
+
+    pub fn contains(&self, key: &str) -> bool {
+        self.entries.contains_key(key)
+    }
+
+    fn entry(&self, key: &str) -> TractResult<&BundleEntryProto> {
+        self.entries.get(key).ok_or_else(|| format!("No tensor {} in bundle", key).into())
+    }
+
+    fn external_data(&self, entry: &BundleEntryProto) -> TractResult<ExternalData> {
+        let shard = self
+            .shards
+            .get(entry.shard_id as usize)
+            .ok_or_else(|| format!("Tensor bundle has no shard {}", entry.shard_id))?;
+        Ok(ExternalData {
+            path: shard.clone(),
+            offset: entry.offset as u64,
+            length: entry.size as usize,
+        })
+    }
+
+    fn raw(&self, entry: &BundleEntryProto) -> TractResult<Vec<u8>> {
+        use std::io::{Read, Seek, SeekFrom};
+        let external = self.external_data(entry)?;
+        let mut file = fs::File::open(&external.path)?;
+        file.seek(SeekFrom::Start(external.offset))?;
+        let mut bytes = vec![0u8; external.length];
+        file.read_exact(&mut bytes)?;
+        Ok(bytes)
+    }
+
+    /// Describe a numeric tensor, for loading it later.
+    ///
+    /// Returns its datum type, its shape and the location of its data.
+    pub fn tensor_location(
+        &self,
+        key: &str,
+    ) -> TractResult<(DatumType, TVec<usize>, ExternalData)> {
+        let entry = self.entry(key)?;
+        if entry.slices.len() > 0 {
+            bail!("Partitioned tensor {} is not supported", key)
+        }
+        let dt: DatumType = DataType::from_i32(entry.dtype)
+            .ok_or_else(|| format!("Unknown type for tensor {}", key))?
+            .try_into()?;
+        if dt == DatumType::String || dt == DatumType::Blob || dt == DatumType::TDim {
+            bail!("Tensor {} is not numeric ({:?})", key, dt)
+        }
+        let shape: TVec<usize> = match &entry.shape {
+            Some(shape) => shape.dim.iter().map(|d| d.size as usize).collect(),
+            None => tvec!(),
+        };
+        if entry.size as usize != shape.iter().product::<usize>() * dt.size_of() {
+            bail!("Tensor {} size does not match its shape {:?}", key, shape)
+        }
+        Ok((dt, shape, self.external_data(entry)?))
+    }
+
+    /// Read a numeric tensor. Its data is memory mapped from the bundle.
+    pub fn tensor(&self, key: &str) -> TractResult<Tensor> {
+        let (dt, shape, external) = self.tensor_location(key)?;
+        external.load(dt, &shape)
+    }
+
+    /// Read the elements of a string tensor, as raw bytes.
+    pub fn strings(&self, key: &str) -> TractResult<Vec<Vec<u8>>> {
+        let entry = self.entry(key)?;
+        if entry.dtype != DataType::DtString as i32 {
+            bail!("Tensor {} is not a string tensor", key)
+        }
+        let len: usize = match &entry.shape {
+            Some(shape) => shape.dim.iter().map(|d| d.size as usize).product(),
+            None => 1,
+        };
+        // element lengths as varints, a checksum of the lengths, then the
+        // concatenated elements
+        let bytes = self.raw(entry)?;
+        let mut cursor = &*bytes;
+        let lengths =
+            (0..len).map(|_| read_varint(&mut cursor)).collect::<TractResult<Vec<_>>>()?;
+        cursor = cursor.get(4..).ok_or("Truncated string tensor")?;
+        let mut strings = vec![];
+        for l in lengths {
+            let l = l as usize;
+            strings.push(cursor.get(..l).ok_or("Truncated string tensor")?.to_vec());
+            cursor = &cursor[l..];
+        }
+        Ok(strings)
+    }
+}
+
+fn block<'t>(table: &'t [u8], handle: &mut &[u8]) -> TractResult<&'t [u8]> {
+    let offset = read_varint(handle)? as usize;
+    let size = read_varint(handle)? as usize;
+    let block = table.get(offset..offset + size + BLOCK_TRAILER_LEN).ok_or("Truncated table")?;
+    if block[size] != 0 {
+        bail!("Compressed table blocks are not supported")
+    }
+    Ok(&block[..size])
+}
+
+fn block_entries(block: &[u8]) -> TractResult<Vec<(Vec<u8>, Vec<u8>)>> {
+    let restarts_len = block.len().checked_sub(4).ok_or("Truncated block")?;
+    let restarts = u32::from_le_bytes(block[restarts_len..].try_into().unwrap()) as usize;
+    let entries_len = restarts_len.checked_sub(4 * restarts).ok_or("Invalid block restarts")?;
+    let mut cursor = &block[..entries_len];
+    let mut entries: Vec<(Vec<u8>, Vec<u8>)> = vec![];
+    while cursor.len() > 0 {
+        let shared = read_varint(&mut cursor)? as usize;
+        let non_shared = read_varint(&mut cursor)? as usize;
+        let value_len = read_varint(&mut cursor)? as usize;
+        let mut key = match entries.last() {
+            Some((previous, _)) => previous.get(..shared).ok_or("Invalid block key")?.to_vec(),
+            None => vec![],
+        };
+        let bytes = cursor.get(..non_shared + value_len).ok_or("Truncated block")?;
+        key.extend_from_slice(&bytes[..non_shared]);
+        entries.push((key, bytes[non_shared..].to_vec()));
+        cursor = &cursor[non_shared + value_len..];
+    }
+    Ok(entries)
+}
+
+/// All key/value pairs of a sorted table, in order.
+fn read_table(table: &[u8]) -> TractResult<Vec<(Vec<u8>, Vec<u8>)>> {
+    let footer = table
+        .len()
+        .checked_sub(FOOTER_LEN)
+        .map(|start| &table[start..])
+        .ok_or("Truncated table")?;
+    if u64::from_le_bytes(footer[FOOTER_LEN - 8..].try_into().unwrap()) != TABLE_MAGIC {
+        bail!("Not a tensor bundle index (bad magic)")
+    }
+    let mut handles = footer;
+    let _metaindex = block(table, &mut handles)?;
+    let index = block(table, &mut handles)?;
+    let mut entries = vec![];
+    for (_, handle) in block_entries(index)? {
+        entries.extend(block_entries(block(table, &mut &*handle)?)?);
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+pub mod test {
+    use super::*;
+    use tract_hir::pb_wire::write_varint;
+
+    // one data block, without key prefix compression
+    fn write_table(entries: &[(Vec<u8>, Vec<u8>)]) -> Vec<u8> {
+        fn write_block(table: &mut Vec<u8>, entries: &[(Vec<u8>, Vec<u8>)]) -> Vec<u8> {
+            let offset = table.len();
+            for (k, v) in entries {
+                write_varint(table, 0);
+                write_varint(table, k.len() as u64);
+                write_varint(table, v.len() as u64);
+                table.extend_from_slice(k);
+                table.extend_from_slice(v);
+            }
+            table.extend_from_slice(&0u32.to_le_bytes());
+            table.extend_from_slice(&1u32.to_le_bytes());
+            let size = table.len() - offset;
+            table.extend_from_slice(&[0; BLOCK_TRAILER_LEN]);
+            let mut handle = vec![];
+            write_varint(&mut handle, offset as u64);
+            write_varint(&mut handle, size as u64);
+            handle
+        }
+        let mut table = vec![];
+        let data = write_block(&mut table, entries);
+        let metaindex = write_block(&mut table, &[]);
+        let last_key = entries.last().map(|e| e.0.clone()).unwrap_or_default();
+        let index = write_block(&mut table, &[(last_key, data)]);
+        let mut footer = metaindex;
+        footer.extend(index);
+        footer.resize(FOOTER_LEN - 8, 0);
+        footer.extend_from_slice(&TABLE_MAGIC.to_le_bytes());
+        table.extend(footer);
+        table
+    }
+
+    /// Write a single shard bundle. Tensors are either f32, i64, or rank 0
+    /// strings given as Blob.
+    pub fn write_bundle(prefix: &path::Path, tensors: &[(&str, Tensor)]) -> TractResult<()> {
+        use crate::tfpb::tensorflow::tensor_shape_proto::Dim;
+        use crate::tfpb::tensorflow::TensorShapeProto;
+        let mut tensors = tensors.iter().collect::<Vec<_>>();
+        tensors.sort_by(|a, b| a.0.cmp(b.0));
+        let mut data = vec![];
+        let header = BundleHeaderProto { num_shards: 1, endianness: 0, version: None };
+        let mut entries = vec![(vec![], encode(&header))];
+        for (name, t) in tensors {
+            let offset = data.len();
+            let dtype = if t.datum_type() == DatumType::Blob {
+                let blob = t.to_scalar::<Blob>()?;
+                write_varint(&mut data, blob.len() as u64);
+                data.extend_from_slice(&[0; 4]);
+                data.extend_from_slice(&blob);
+                DataType::DtString
+            } else if t.datum_type() == DatumType::F32 {
+                data.extend(t.as_slice::<f32>()?.iter().flat_map(|x| x.to_le_bytes().to_vec()));
+                DataType::DtFloat
+            } else {
+                data.extend(t.as_slice::<i64>()?.iter().flat_map(|x| x.to_le_bytes().to_vec()));
+                DataType::DtInt64
+            };
+            let dim = t.shape().iter().map(|d| Dim { size: *d as i64, name: String::new() });
+            let shape = TensorShapeProto { dim: dim.collect(), unknown_rank: false };
+            let entry = BundleEntryProto {
+                dtype: dtype as i32,
+                shape: Some(shape),
+                shard_id: 0,
+                offset: offset as i64,
+                size: (data.len() - offset) as i64,
+                crc32c: 0,
+                slices: vec![],
+            };
+            entries.push((name.as_bytes().to_vec(), encode(&entry)));
+        }
+        let prefix = prefix.to_str().unwrap();
+        fs::write(format!("{}.index", prefix), write_table(&entries))?;
+        fs::write(format!("{}.data-00000-of-00001", prefix), data)?;
+        Ok(())
+    }
+
+    fn encode(m: &impl Message) -> Vec<u8> {
+        let mut buf = vec![];
+        m.encode(&mut buf).unwrap();
+        buf
+    }
+
+    #[test]
+    fn bundle_roundtrip() {
+        let dir = std::env::temp_dir().join(format!("tract-bundle-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let prefix = dir.join("variables");
+        let name = Tensor::from(Blob(vec![1, 2]));
+        write_bundle(
+            &prefix,
+            &[
+                ("dense/kernel", tensor2(&[[1f32, 2.], [3., 4.]])),
+                ("dense/bias", tensor1(&[5i64, 6])),
+                ("name", name),
+            ],
+        )
+        .unwrap();
+        let bundle = TensorBundle::open(&prefix).unwrap();
+        let mut keys = bundle.keys().collect::<Vec<_>>();
+        keys.sort();
+        assert_eq!(keys, vec!["dense/bias", "dense/kernel", "name"]);
+        assert_eq!(bundle.tensor("dense/kernel").unwrap(), tensor2(&[[1f32, 2.], [3., 4.]]));
+        assert_eq!(bundle.tensor("dense/bias").unwrap(), tensor1(&[5i64, 6]));
+        assert_eq!(bundle.strings("name").unwrap(), vec![vec![1u8, 2]]);
+        assert!(bundle.tensor("name").is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    // Run tests/models/bundle/make_bundle.py with TensorFlow to produce the fixture.
+    #[test]
+    #[ignore]
+    fn bundle_from_tensorflow() {
+        let prefix =
+            path::Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/models/bundle/variables");
+        let bundle = TensorBundle::open(&prefix).unwrap();
+        assert_eq!(bundle.keys().count(), 43);
+        assert_eq!(bundle.tensor("dense/kernel").unwrap(), tensor2(&[[1f32, 2.], [3., 4.]]));
+        assert_eq!(bundle.tensor("dense/bias").unwrap(), tensor1(&[5i64, 6]));
+        assert_eq!(bundle.strings("name").unwrap(), vec![vec![1u8, 2]]);
+        for i in 0..40 {
+            let weights = bundle.tensor(&format!("layer/{:02}/weights", i)).unwrap();
+            assert_eq!(weights, tensor1(&[i as f32; 3]));
+        }
+    }
+}
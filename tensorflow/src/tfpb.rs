@@ -119,6 +119,21 @@ impl NodeDef {
         Ok(None)
     }
 
+    pub fn get_attr_func_name(&self, name: &str) -> TractResult<&str> {
+        Ok(self.get_attr_opt_func_name(name)?.ok_or_else(|| {
+            format!("Node {} ({}) expected function attribute '{}'", self.name, self.op, name)
+        })?)
+    }
+
+    pub fn get_attr_opt_func_name(&self, name: &str) -> TractResult<Option<&str>> {
+        if let Some(a) = self.attr.get(name) {
+            if let Value::Func(f) = a.value.as_ref().unwrap() {
+                return Ok(Some(&f.name));
+            }
+        };
+        Ok(None)
+    }
+
     pub fn get_attr_datum_type(&self, name: &str) -> TractResult<DatumType> {
         Ok(self.get_attr_opt_datum_type(name)?.ok_or_else(|| {
             format!("Node {} ({}) expected datum_type attribute '{}'", self.name, self.op, name)
@@ -155,9 +170,15 @@ impl NodeDef {
         })?)
     }
 
+    /// The "value" tensor content may be left in a file, see `EXTERNAL_DATA_ATTR`.
     pub fn get_attr_opt_tensor(&self, name: &str) -> TractResult<Option<Tensor>> {
         if let Some(a) = self.attr.get(name) {
             if let Value::Tensor(ref t) = a.value.as_ref().unwrap() {
+                if let Some(external) =
+                    self.attr.get(crate::tensor::EXTERNAL_DATA_ATTR).filter(|_| name == "value")
+                {
+                    return Ok(Some(crate::tensor::load_external_tensor(t, external)?));
+                }
                 return Ok(Some(t.try_into()?));
             }
         };
@@ -0,0 +1,26 @@
+# Writes variables.index and variables.data-00000-of-00001 next to this file,
+# with TensorFlow's own SaveV2 op. Checked by tensor_bundle::test::bundle_from_tensorflow.
+#
+#     python3 make_bundle.py
+
+import os
+import numpy
+import tensorflow as tf
+
+tensors = {
+    "dense/kernel": numpy.array([[1, 2], [3, 4]], dtype=numpy.float32),
+    "dense/bias": numpy.array([5, 6], dtype=numpy.int64),
+    "name": numpy.array([b"\x01\x02"]),
+}
+# enough keys sharing a prefix to get restart points and several index blocks
+for i in range(40):
+    tensors["layer/%02d/weights" % i] = numpy.full([3], i, dtype=numpy.float32)
+
+names = sorted(tensors)
+prefix = os.path.join(os.path.dirname(os.path.abspath(__file__)), "variables")
+tf.raw_ops.SaveV2(
+    prefix=prefix,
+    tensor_names=names,
+    shape_and_slices=[""] * len(names),
+    tensors=[tf.constant(tensors[n]) for n in names],
+)